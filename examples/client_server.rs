@@ -1,9 +1,10 @@
 //! End-to-end example: start a timer from a client connected to a
-//! server over a Unix socket pair.
+//! server over a Unix socket pair, then get it from a second client.
 //!
-//! Both sides run in separate threads over an in-process socket pair.
-//! The server drives [`TimerRequestHandle`] and the client drives
-//! [`TimerRequestSend`].
+//! Both sides run in separate threads over in-process socket pairs.
+//! The server drives one [`TimerRequestHandle`] per connection using
+//! a [`TimerSessionManager`], and the clients drive
+//! [`TimerRequestSend`] using the [`run`] helper.
 
 use std::{
     io::{Read, Write},
@@ -13,9 +14,13 @@ use std::{
 
 use io_socket::io::{SocketInput, SocketOutput};
 use io_time::{
+    client::std::run,
     coroutines::{
-        client::{TimerRequestSend, TimerRequestSendResult},
-        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+        client::TimerRequestSend,
+        server::{
+            TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult,
+            TimerSessionManager,
+        },
     },
     runtimes::std::handle,
     timer::{Timer, TimerConfig, TimerCycle, TimerCycles, TimerLoop},
@@ -43,48 +48,63 @@ fn main() {
         cycles_count: TimerLoop::Fixed(4),
     };
     let timer = Timer::new(config);
-    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+    let mut client_streams = Vec::new();
+    let mut server_streams = Vec::new();
 
-    // Server thread: handles one request and returns the resulting events.
+    for _ in 0..2 {
+        let (client_stream, server_stream) = UnixStream::pair().unwrap();
+        client_streams.push(client_stream);
+        server_streams.push(server_stream);
+    }
+
+    // Server thread: handles one request per connection, and returns
+    // the resulting events.
     let server = thread::spawn(move || {
         let mut timer = timer;
-        let mut server = TimerRequestHandle::new();
-        let mut arg: Option<TimerRequestHandleArg> = None;
+        let mut connections = TimerSessionManager::new();
+        let mut events = Vec::new();
 
-        loop {
-            match server.resume(&mut timer, arg.take()) {
-                TimerRequestHandleResult::Ok { events } => return events,
-                TimerRequestHandleResult::Io { input } => {
-                    arg = Some(TimerRequestHandleArg::Socket(socket_io(
-                        &mut server_stream,
-                        input,
-                    )));
-                }
-                TimerRequestHandleResult::TimeIo { input } => {
-                    arg = Some(TimerRequestHandleArg::Time(handle(input).unwrap()));
-                }
-                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
-            }
+        for id in 0..server_streams.len() {
+            connections.insert(id, TimerRequestHandle::new());
         }
-    });
 
-    // Client: send a Start request and wait for the response.
-    let mut client = TimerRequestSend::start();
-    let mut arg = None;
+        for (id, server_stream) in server_streams.iter_mut().enumerate() {
+            let mut arg: Option<TimerRequestHandleArg> = None;
 
-    let response = loop {
-        match client.resume(arg.take()) {
-            TimerRequestSendResult::Ok { response } => break response,
-            TimerRequestSendResult::Io { input } => {
-                arg = Some(socket_io(&mut client_stream, input))
+            loop {
+                match connections.resume(&id, &mut timer, arg.take()) {
+                    Some(TimerRequestHandleResult::Ok { events: e }) => {
+                        events.extend(e);
+                        break;
+                    }
+                    Some(TimerRequestHandleResult::Io { input }) => {
+                        arg = Some(TimerRequestHandleArg::Socket(socket_io(
+                            server_stream,
+                            input,
+                        )));
+                    }
+                    Some(TimerRequestHandleResult::TimeIo { input }) => {
+                        arg = Some(TimerRequestHandleArg::Time(handle(input).unwrap()));
+                    }
+                    Some(TimerRequestHandleResult::Disconnected) => break,
+                    Some(TimerRequestHandleResult::Err { err }) => panic!("server error: {err}"),
+                    None => unreachable!("connection {id} not registered"),
+                }
             }
-            TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
         }
-    };
+
+        events
+    });
+
+    // Clients: the first one starts the timer, the second one gets
+    // the started timer.
+    let response = run(&mut client_streams[0], TimerRequestSend::start()).unwrap();
+    let snapshot = run(&mut client_streams[1], TimerRequestSend::get()).unwrap();
 
     let events = server.join().unwrap();
 
     println!("Response:  {response:?}");
+    println!("Snapshot:  {snapshot:?}");
     println!("Events ({}):", events.len());
     for event in &events {
         println!("  {event:?}");
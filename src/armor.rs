@@ -0,0 +1,134 @@
+//! ASCII armor of frame payloads.
+//!
+//! Some text-only transports (like terminal multiplexers or chat-ops
+//! bridges) mangle binary bytes, or rewrite line endings. Armored
+//! payloads are encoded using the standard base64 alphabet with
+//! padding, so that they only contain printable ASCII characters and
+//! can travel one per line (see [`Framing::Armored`]).
+//!
+//! [`Framing::Armored`]: crate::frame::Framing::Armored
+
+use alloc::vec::Vec;
+
+use thiserror::Error;
+
+/// The standard base64 alphabet.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The base64 padding character.
+const PADDING: u8 = b'=';
+
+/// Error emitted when removing the armor of a payload.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum ArmorError {
+    #[error("Invalid armored payload length {0}")]
+    InvalidLength(usize),
+    #[error("Invalid armored byte {0:#04x}")]
+    InvalidByte(u8),
+}
+
+/// Armors the given payload.
+pub fn encode(payload: &[u8]) -> Vec<u8> {
+    let mut armored = Vec::with_capacity(payload.len().div_ceil(3) * 4);
+
+    for chunk in payload.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = u32::from_be_bytes([0, b[0], b[1], b[2]]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (n >> (18 - 6 * i)) & 0x3F;
+                armored.push(ALPHABET[index as usize]);
+            } else {
+                armored.push(PADDING);
+            }
+        }
+    }
+
+    armored
+}
+
+/// Removes the armor of the given payload.
+pub fn decode(armored: &[u8]) -> Result<Vec<u8>, ArmorError> {
+    if !armored.len().is_multiple_of(4) {
+        return Err(ArmorError::InvalidLength(armored.len()));
+    }
+
+    let mut payload = Vec::with_capacity(armored.len() / 4 * 3);
+    let chunks = armored.chunks(4);
+    let last = chunks.len().saturating_sub(1);
+
+    for (i, chunk) in chunks.enumerate() {
+        // padding is only allowed at the end of the last chunk
+        let padding = match chunk {
+            [_, _, PADDING, PADDING] if i == last => 2,
+            [_, _, _, PADDING] if i == last => 1,
+            _ => 0,
+        };
+
+        let mut n = 0u32;
+        for &byte in &chunk[..4 - padding] {
+            n = (n << 6) | sextet(byte)?;
+        }
+        n <<= 6 * padding as u32;
+
+        let bytes = n.to_be_bytes();
+        payload.extend_from_slice(&bytes[1..4 - padding]);
+    }
+
+    Ok(payload)
+}
+
+fn sextet(byte: u8) -> Result<u32, ArmorError> {
+    match byte {
+        b'A'..=b'Z' => Ok((byte - b'A') as u32),
+        b'a'..=b'z' => Ok((byte - b'a' + 26) as u32),
+        b'0'..=b'9' => Ok((byte - b'0' + 52) as u32),
+        b'+' => Ok(62),
+        b'/' => Ok(63),
+        _ => Err(ArmorError::InvalidByte(byte)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn armor_matches_rfc4648_vectors() {
+        let vectors: [(&[u8], &[u8]); 7] = [
+            (b"", b""),
+            (b"f", b"Zg=="),
+            (b"fo", b"Zm8="),
+            (b"foo", b"Zm9v"),
+            (b"foob", b"Zm9vYg=="),
+            (b"fooba", b"Zm9vYmE="),
+            (b"foobar", b"Zm9vYmFy"),
+        ];
+
+        for (payload, armored) in vectors {
+            assert_eq!(encode(payload), armored);
+            assert_eq!(decode(armored).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn binary_payload_round_trip() {
+        let payload: Vec<u8> = (0..=255).collect();
+        let armored = encode(&payload);
+
+        assert!(armored.iter().all(u8::is_ascii_graphic));
+        assert_eq!(decode(&armored).unwrap(), payload);
+    }
+
+    #[test]
+    fn invalid_armor_rejected() {
+        assert_eq!(decode(b"Zm9"), Err(ArmorError::InvalidLength(3)));
+        assert_eq!(decode(b"Zm9\n"), Err(ArmorError::InvalidByte(b'\n')));
+        assert_eq!(decode(b"Zg==Zm9v"), Err(ArmorError::InvalidByte(PADDING)));
+    }
+}
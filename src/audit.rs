@@ -0,0 +1,161 @@
+//! Audit log of the requests handled by a server.
+//!
+//! Deployments with many clients may need to know who did what to a
+//! timer. Once given a [`TimerAuditSink`] with
+//! [`TimerRequestHandle::with_audit`], the server records every
+//! request it handles, with its time, the id of its connection and
+//! its outcome.
+//!
+//! [`TimerAuditLog`] is a sink keeping the most recent entries in
+//! memory. Since each connection owns its sink, a log shared between
+//! connections needs to be wrapped in an `Arc<Mutex<_>>` (requires
+//! the `std` cargo feature):
+//!
+//! ```rust,ignore
+//! let log = Arc::new(Mutex::new(TimerAuditLog::new()));
+//! let handle = TimerRequestHandle::new().with_audit(id, log.clone());
+//!
+//! // later on, for example on behalf of an administrator
+//! for entry in log.lock().unwrap().session(&id) {
+//!     println!("{} {} {:?}", entry.at, entry.request, entry.outcome);
+//! }
+//! ```
+//!
+//! [`TimerRequestHandle::with_audit`]: crate::coroutines::server::TimerRequestHandle::with_audit
+
+use alloc::{collections::VecDeque, string::String};
+use core::fmt;
+
+use crate::timer::{TimerError, TimerResponse};
+
+/// The default maximum number of entries of a [`TimerAuditLog`].
+pub const DEFAULT_AUDIT_CAPACITY: usize = 1024;
+
+/// The outcome of an audited request.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TimerAuditOutcome {
+    /// The request was applied.
+    Ok,
+    /// The request was not applied because the server was busy, see
+    /// [`TimerResponse::Busy`].
+    Busy,
+    /// The request was rejected with the given error.
+    Rejected(TimerError),
+}
+
+impl From<&TimerResponse> for TimerAuditOutcome {
+    fn from(response: &TimerResponse) -> Self {
+        match response {
+            TimerResponse::Busy { .. } => Self::Busy,
+            TimerResponse::Err(err) => Self::Rejected(err.clone()),
+            _ => Self::Ok,
+        }
+    }
+}
+
+/// A request handled by a server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimerAuditEntry {
+    /// The time the request was handled at, in Unix epoch seconds.
+    pub at: u64,
+    /// The id of the connection the request was received from.
+    pub session: String,
+    /// The name of the request, see [`TimerRequest::name`].
+    ///
+    /// [`TimerRequest::name`]: crate::timer::TimerRequest::name
+    pub request: &'static str,
+    /// The name of the targeted timer, `None` for the default one.
+    pub timer: Option<String>,
+    /// The outcome of the request.
+    pub outcome: TimerAuditOutcome,
+}
+
+/// Destination of the entries of the audit log.
+pub trait TimerAuditSink: fmt::Debug + Send {
+    /// Records the given entry.
+    fn record(&mut self, entry: TimerAuditEntry);
+}
+
+#[cfg(feature = "std")]
+impl<T: TimerAuditSink> TimerAuditSink for std::sync::Arc<std::sync::Mutex<T>> {
+    fn record(&mut self, entry: TimerAuditEntry) {
+        // a poisoned log is still worth recording to
+        match self.lock() {
+            Ok(mut sink) => sink.record(entry),
+            Err(poisoned) => poisoned.into_inner().record(entry),
+        }
+    }
+}
+
+/// In-memory audit log, bounded to its most recent entries.
+#[derive(Clone, Debug)]
+pub struct TimerAuditLog {
+    capacity: usize,
+    entries: VecDeque<TimerAuditEntry>,
+}
+
+impl Default for TimerAuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerAuditLog {
+    /// Creates a new log, keeping at most
+    /// [`DEFAULT_AUDIT_CAPACITY`] entries.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_AUDIT_CAPACITY)
+    }
+
+    /// Creates a new log, keeping at most the given number of
+    /// entries.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Returns the entries of the log, from the oldest to the most
+    /// recent one.
+    pub fn entries(&self) -> impl Iterator<Item = &TimerAuditEntry> {
+        self.entries.iter()
+    }
+
+    /// Returns the entries of the connection with the given id, from
+    /// the oldest to the most recent one.
+    pub fn session<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a TimerAuditEntry> {
+        self.entries.iter().filter(move |entry| entry.session == id)
+    }
+
+    /// Returns the entries recorded since the given time, in Unix
+    /// epoch seconds.
+    pub fn since(&self, at: u64) -> impl Iterator<Item = &TimerAuditEntry> {
+        self.entries.iter().filter(move |entry| entry.at >= at)
+    }
+
+    /// Returns the number of entries of the log.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the log has no entry.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes every entry of the log.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl TimerAuditSink for TimerAuditLog {
+    fn record(&mut self, entry: TimerAuditEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(entry);
+    }
+}
@@ -0,0 +1,71 @@
+//! Authentication of the clients of a server.
+//!
+//! Clients present a [`TimerAuthToken`] in their [`ClientHello`].
+//! The server verifies it during the handshake with the
+//! [`TimerAuthenticator`] given to [`TimerAccept::with_authenticator`],
+//! which returns the [`Permission`] of the client, or rejects it.
+//!
+//! The crate does not dictate where tokens are stored nor what they
+//! look like: authenticators can be backed by a keyring, a file, PAM…
+//!
+//! ```rust,ignore
+//! #[derive(Debug)]
+//! struct Tokens(BTreeMap<String, Permission>);
+//!
+//! impl TimerAuthenticator for Tokens {
+//!     fn authenticate(&self, token: Option<&TimerAuthToken>) -> Option<Permission> {
+//!         self.0.get(token?.expose()).copied()
+//!     }
+//! }
+//!
+//! let accept = TimerAccept::new().with_authenticator(Arc::new(Tokens(tokens)));
+//! ```
+//!
+//! [`ClientHello`]: crate::session::ClientHello
+//! [`TimerAccept::with_authenticator`]: crate::coroutines::accept::TimerAccept::with_authenticator
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::session::Permission;
+
+/// A secret presented by a client to authenticate.
+///
+/// The token is never printed, even in debug logs.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct TimerAuthToken(String);
+
+impl TimerAuthToken {
+    /// Creates a new token with the given secret.
+    pub fn new(secret: impl ToString) -> Self {
+        Self(secret.to_string())
+    }
+
+    /// Returns the secret of the token.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for TimerAuthToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TimerAuthToken(***)")
+    }
+}
+
+/// Verifier of the tokens presented by clients.
+///
+/// A single authenticator is typically shared by every connection
+/// of a server, hence the shared reference: authenticators with a
+/// mutable state (like a cache) need interior mutability.
+pub trait TimerAuthenticator: fmt::Debug + Send + Sync {
+    /// Verifies the given token, `None` if the client did not present
+    /// any.
+    ///
+    /// Returns the permission granted to the client, or `None` to
+    /// reject it.
+    fn authenticate(&self, token: Option<&TimerAuthToken>) -> Option<Permission>;
+}
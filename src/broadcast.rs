@@ -0,0 +1,446 @@
+//! Server-side fan-out of timer events to subscribed connections.
+//!
+//! Servers handling many connections (see [`TimerSessionManager`])
+//! need to push the events of a timer to every connection subscribed
+//! to it, whatever connection triggered them. A
+//! [`TimerEventBroadcast`] keeps the events not pushed yet per
+//! connection, and hands out the [`TimerEventPush`] coroutines to
+//! drive over each connection:
+//!
+//! ```rust,ignore
+//! let mut broadcast = TimerEventBroadcast::new();
+//!
+//! // after each handled request or timer tick
+//! broadcast.publish(&connections, name, &timer);
+//!
+//! // once the connection `id` is writable
+//! if let Some(push) = broadcast.take(&connections, &id) {
+//!     // drive push over the connection
+//! }
+//! ```
+//!
+//! Subscribers rendering the timer state rather than its events can
+//! receive [`TimerDelta`]s instead, see
+//! [`TimerEventBroadcast::with_deltas`].
+//!
+//! Events can be rewritten or dropped before being queued, by
+//! registering [`TimerEventInterceptor`]s with
+//! [`TimerEventBroadcast::with_interceptor`] (or
+//! [`TimerServe::with_interceptor`] for single connections).
+//!
+//! [`TimerSessionManager`]: crate::coroutines::server::TimerSessionManager
+//! [`TimerServe::with_interceptor`]: crate::coroutines::serve::TimerServe::with_interceptor
+
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use log::trace;
+
+use crate::{
+    coroutines::{push::TimerEventPush, server::TimerSessionManager},
+    delta::TimerDelta,
+    history::{TimerHistory, TimerHistoryEntry},
+    timer::{Timer, TimerEvent},
+};
+
+/// The default maximum number of events waiting to be pushed to a
+/// single connection.
+pub const DEFAULT_BROADCAST_CAPACITY: usize = 256;
+
+/// Hook called on each event of a timer before it is pushed to
+/// subscribers.
+///
+/// Interceptors can rewrite events (for example to localize the
+/// names of cycles) or drop them, without modifying the timer nor
+/// its history. They are not called on the events returned in
+/// responses to requests.
+pub trait TimerEventInterceptor: fmt::Debug + Send {
+    /// Intercepts the given event of the timer with the given name.
+    ///
+    /// Returns the event to push, or `None` to drop it.
+    fn intercept(&mut self, timer: &str, entry: TimerHistoryEntry) -> Option<TimerHistoryEntry>;
+}
+
+/// Passes the given events through the given interceptors, in
+/// registration order.
+pub(crate) fn intercept(
+    interceptors: &mut [Box<dyn TimerEventInterceptor>],
+    timer: &str,
+    entries: impl IntoIterator<Item = TimerHistoryEntry>,
+) -> Vec<TimerHistoryEntry> {
+    entries
+        .into_iter()
+        .filter_map(|entry| {
+            interceptors
+                .iter_mut()
+                .try_fold(entry, |entry, interceptor| {
+                    interceptor.intercept(timer, entry)
+                })
+        })
+        .collect()
+}
+
+/// Events of timers waiting to be pushed to subscribed connections,
+/// keyed by connection id.
+#[derive(Debug)]
+pub struct TimerEventBroadcast<K> {
+    cursors: BTreeMap<String, u64>,
+    pending: BTreeMap<K, VecDeque<(String, TimerHistoryEntry)>>,
+    capacity: usize,
+    interceptors: Vec<Box<dyn TimerEventInterceptor>>,
+    deltas: bool,
+    replicas: BTreeMap<String, Timer>,
+    changes: BTreeMap<K, BTreeMap<String, TimerDelta>>,
+    synced: BTreeMap<K, BTreeSet<String>>,
+}
+
+impl<K: Clone + Ord> Default for TimerEventBroadcast<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Clone + Ord> TimerEventBroadcast<K> {
+    /// Creates a new broadcast, without any pending event.
+    pub fn new() -> Self {
+        Self {
+            cursors: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            capacity: DEFAULT_BROADCAST_CAPACITY,
+            interceptors: Vec::new(),
+            deltas: false,
+            replicas: BTreeMap::new(),
+            changes: BTreeMap::new(),
+            synced: BTreeMap::new(),
+        }
+    }
+
+    /// Keeps at most the given number of events per connection,
+    /// instead of [`DEFAULT_BROADCAST_CAPACITY`].
+    ///
+    /// Once the events of a slow connection exceed the capacity, the
+    /// oldest ones are dropped and replaced by a single
+    /// [`TimerEvent::Lagged`] marker, pushed first with the number of
+    /// dropped events.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Passes the published events through the given interceptor,
+    /// after the previously registered ones.
+    pub fn with_interceptor(mut self, interceptor: impl TimerEventInterceptor + 'static) -> Self {
+        self.interceptors.push(Box::new(interceptor));
+        self
+    }
+
+    /// Queues the changes of timers instead of their events, pushed
+    /// as [`TimerResponse::Delta`] frames.
+    ///
+    /// Each delta covers the events published at once by
+    /// [`publish`], and is keyed by their sequence numbers: its
+    /// `seq` is the sequence number following the last covered
+    /// event. The changes not pushed yet to a connection are merged
+    /// together, so that slow connections never lag. The first delta
+    /// pushed to a connection is a keyframe (see
+    /// [`TimerDelta::keyframe`]), applying on top of a fresh timer
+    /// (see [`TimerMirror::apply_delta`]).
+    ///
+    /// Interceptors are not called on deltas.
+    ///
+    /// [`TimerResponse::Delta`]: crate::timer::TimerResponse::Delta
+    /// [`publish`]: TimerEventBroadcast::publish
+    /// [`TimerMirror::apply_delta`]: crate::mirror::TimerMirror::apply_delta
+    pub fn with_deltas(mut self) -> Self {
+        self.deltas = true;
+        self
+    }
+
+    /// Queues the events of the timer with the given name which were
+    /// not published yet, for every connection subscribed to it.
+    ///
+    /// Events are read from the history of the timer, where the
+    /// server records the events of every request (ticks included,
+    /// see [`TimerRequest::Update`]), so that they carry their
+    /// sequence number. The first publication of a timer covers its
+    /// whole remembered history, as well as the first publication
+    /// after its history restarted (for example once deleted then
+    /// created again, see [`forget`]).
+    ///
+    /// With [`with_deltas`], the changes of the timer since the
+    /// previous publication are queued instead.
+    ///
+    /// Returns the number of published events.
+    ///
+    /// [`TimerRequest::Update`]: crate::timer::TimerRequest::Update
+    /// [`with_deltas`]: TimerEventBroadcast::with_deltas
+    /// [`forget`]: TimerEventBroadcast::forget
+    pub fn publish(
+        &mut self,
+        connections: &TimerSessionManager<K>,
+        name: &str,
+        timer: &Timer,
+    ) -> usize {
+        let next = timer.history.next_seq();
+        if self.cursors.get(name).is_some_and(|cursor| next < *cursor) {
+            trace!("history of timer {name} restarted");
+            self.forget(name);
+        }
+
+        let base = self.cursors.get(name).copied();
+        let mut cursor = base;
+        let mut entries = Vec::new();
+
+        loop {
+            let page = timer.history.page(cursor, None);
+            entries.extend(page.events);
+
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        let Some(last) = entries.last() else {
+            return 0;
+        };
+
+        let seq = last.seq + 1;
+        self.cursors.insert(name.to_string(), seq);
+
+        if !self.deltas {
+            return self.publish_entries(connections, name, entries);
+        }
+
+        self.publish_delta(connections, name, timer, base, seq);
+        entries.len()
+    }
+
+    /// Forgets what was published of the timer with the given name,
+    /// typically once deleted.
+    ///
+    /// The next publication of a timer with the same name then covers
+    /// its whole history, and starts with a keyframe for connections
+    /// receiving deltas. Restarted histories are detected by
+    /// [`publish`], unless they already outgrew the previous one.
+    ///
+    /// [`publish`]: TimerEventBroadcast::publish
+    pub fn forget(&mut self, name: &str) {
+        self.cursors.remove(name);
+        self.replicas.remove(name);
+
+        for synced in self.synced.values_mut() {
+            synced.remove(name);
+        }
+    }
+
+    /// Queues the changes of the timer with the given name since the
+    /// sequence number `base`, for every connection subscribed to it.
+    fn publish_delta(
+        &mut self,
+        connections: &TimerSessionManager<K>,
+        name: &str,
+        timer: &Timer,
+        base: Option<u64>,
+        seq: u64,
+    ) {
+        let fresh = Timer::new(timer.config.clone());
+        let prev = self.replicas.get(name).unwrap_or(&fresh);
+        let delta = TimerDelta {
+            seq,
+            ..TimerDelta::diff(base.unwrap_or_default(), prev, timer)
+        };
+
+        let keyframe = TimerDelta::keyframe(seq, timer);
+
+        for id in connections.subscribers(name) {
+            let synced = self.synced.entry(id.clone()).or_default();
+            let changes = self.changes.entry(id.clone()).or_default();
+
+            if synced.insert(name.to_string()) {
+                trace!("queue keyframe of timer {name}");
+                changes.insert(name.to_string(), keyframe.clone());
+                continue;
+            }
+
+            trace!("queue delta of timer {name}");
+            match changes.get_mut(name) {
+                Some(pending) => {
+                    if pending.merge(delta.clone()).is_err() {
+                        *pending = keyframe.clone();
+                    }
+                }
+                None => {
+                    changes.insert(name.to_string(), delta.clone());
+                }
+            }
+        }
+
+        let mut replica = timer.clone();
+        // the history is not part of deltas
+        replica.history = TimerHistory::default();
+        self.replicas.insert(name.to_string(), replica);
+    }
+
+    /// Queues the given events of the timer with the given name, for
+    /// every connection subscribed to it.
+    ///
+    /// Returns the number of published events, interceptors
+    /// excluded.
+    pub fn publish_entries(
+        &mut self,
+        connections: &TimerSessionManager<K>,
+        name: &str,
+        entries: impl IntoIterator<Item = TimerHistoryEntry>,
+    ) -> usize {
+        let entries = intercept(&mut self.interceptors, name, entries);
+
+        for id in connections.subscribers(name) {
+            trace!("queue {} events of timer {name}", entries.len());
+            let pending = self.pending.entry(id.clone()).or_default();
+
+            for entry in &entries {
+                enqueue(pending, self.capacity, name, entry.clone());
+            }
+        }
+
+        entries.len()
+    }
+
+    /// Takes the pending events of the connection with the given id,
+    /// and returns the coroutine pushing them.
+    ///
+    /// Events are pushed using the session of the connection, and
+    /// tagged with the name of their timer for connections subscribed
+    /// to every timer. Each coroutine pushes the events (or the
+    /// delta) of a single timer: the function needs to be called
+    /// until it returns `None`.
+    pub fn take(&mut self, connections: &TimerSessionManager<K>, id: &K) -> Option<TimerEventPush> {
+        let Some(handle) = connections.get(id) else {
+            // the connection is gone
+            self.remove(id);
+            return None;
+        };
+
+        let (name, push) = match self.pending.get_mut(id) {
+            Some(pending) => {
+                let (name, entry) = pending.pop_front()?;
+                let mut entries = Vec::from([entry]);
+
+                while pending.front().is_some_and(|(next, _)| *next == name) {
+                    if let Some((_, entry)) = pending.pop_front() {
+                        entries.push(entry);
+                    }
+                }
+
+                if pending.is_empty() {
+                    self.pending.remove(id);
+                }
+
+                (name, TimerEventPush::new(entries))
+            }
+            None => {
+                let changes = self.changes.get_mut(id)?;
+                let (name, delta) = changes.pop_first()?;
+
+                if changes.is_empty() {
+                    self.changes.remove(id);
+                }
+
+                (name, TimerEventPush::deltas([delta]))
+            }
+        };
+
+        let mut push = push.with_session(*handle.session());
+        if handle.subscription().is_some_and(|s| s.is_wildcard()) {
+            push = push.with_timer(name);
+        }
+
+        Some(push)
+    }
+
+    /// Returns the number of events waiting to be pushed to the
+    /// connection with the given id, [`TimerEvent::Lagged`] marker
+    /// and deltas included.
+    pub fn pending(&self, id: &K) -> usize {
+        let events = self.pending.get(id).map(VecDeque::len);
+        let deltas = self.changes.get(id).map(BTreeMap::len);
+        events.unwrap_or_default() + deltas.unwrap_or_default()
+    }
+
+    /// Returns the number of events dropped since the last push to
+    /// the connection with the given id, because it was too slow.
+    pub fn missed(&self, id: &K) -> u64 {
+        self.pending.get(id).and_then(lagged).unwrap_or_default()
+    }
+
+    /// Returns the ids of the connections with events waiting to be
+    /// pushed, in order.
+    pub fn ready(&self) -> impl Iterator<Item = &K> {
+        let ids: BTreeSet<&K> = self.pending.keys().chain(self.changes.keys()).collect();
+        ids.into_iter()
+    }
+
+    /// Drops the pending events of the connection with the given id,
+    /// typically once closed.
+    pub fn remove(&mut self, id: &K) {
+        self.pending.remove(id);
+        self.changes.remove(id);
+        self.synced.remove(id);
+    }
+}
+
+/// Queues the given event of the timer with the given name, dropping
+/// the oldest event of the queue once it holds `capacity` events.
+pub(crate) fn enqueue(
+    pending: &mut VecDeque<(String, TimerHistoryEntry)>,
+    capacity: usize,
+    name: &str,
+    entry: TimerHistoryEntry,
+) {
+    let queued = pending.len() - lagged(pending).is_some() as usize;
+    if queued >= capacity {
+        drop_oldest(pending);
+    }
+    pending.push_back((name.to_string(), entry));
+}
+
+/// Returns the number of dropped events of the given queue, if it
+/// starts with a [`TimerEvent::Lagged`] marker.
+fn lagged(pending: &VecDeque<(String, TimerHistoryEntry)>) -> Option<u64> {
+    match pending.front() {
+        Some((_, entry)) => match entry.event {
+            TimerEvent::Lagged { missed } => Some(missed),
+            _ => None,
+        },
+        None => None,
+    }
+}
+
+/// Drops the oldest event of the given queue, and accounts it in the
+/// [`TimerEvent::Lagged`] marker starting the queue.
+fn drop_oldest(pending: &mut VecDeque<(String, TimerHistoryEntry)>) {
+    let missed = lagged(pending);
+    let index = missed.is_some() as usize;
+    let Some((name, dropped)) = pending.remove(index) else {
+        return;
+    };
+
+    // the marker takes the sequence number of the last dropped event
+    let marker = TimerHistoryEntry {
+        event: TimerEvent::Lagged {
+            missed: missed.unwrap_or_default() + 1,
+        },
+        ..dropped
+    };
+
+    match missed {
+        Some(_) => pending[0] = (name, marker),
+        None => pending.push_front((name, marker)),
+    }
+}
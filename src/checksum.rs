@@ -0,0 +1,133 @@
+//! Checksums of request and response frames.
+//!
+//! Checksums are negotiated per connection during the handshake (see
+//! [`TimerConnect`] and [`TimerAccept`]). They are meant for
+//! unreliable transports like serial links or lossy tunnels, where
+//! corrupted frames would otherwise be decoded as garbage.
+//!
+//! [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+//! [`TimerAccept`]: crate::coroutines::accept::TimerAccept
+
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The size of a CRC32 checksum.
+const CRC32_SIZE: usize = 4;
+
+/// The reversed polynomial of the IEEE CRC32.
+const CRC32_POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// Error emitted when verifying the checksum of a frame.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum ChecksumError {
+    #[error("Frame is too short to contain a checksum")]
+    Truncated,
+    #[error("Invalid frame checksum {got:#010x}, expected {expected:#010x}")]
+    Mismatch { expected: u32, got: u32 },
+}
+
+/// The checksum appended to request and response frames.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Checksum {
+    /// Frames are sent without checksum.
+    #[default]
+    None,
+    /// Frames are followed by their IEEE CRC32, encoded as a
+    /// big-endian `u32`.
+    Crc32,
+}
+
+impl Checksum {
+    /// Returns the checksums supported by this build, by order of
+    /// preference.
+    ///
+    /// Checksums are useless over reliable transports, so they are
+    /// only used when explicitly preferred by the client.
+    pub fn supported() -> Vec<Self> {
+        Vec::from([Self::None, Self::Crc32])
+    }
+
+    /// Appends the checksum of the given payload to it.
+    pub fn append(self, mut payload: Vec<u8>) -> Vec<u8> {
+        match self {
+            Self::None => payload,
+            Self::Crc32 => {
+                let crc = crc32(&payload);
+                payload.extend_from_slice(&crc.to_be_bytes());
+                payload
+            }
+        }
+    }
+
+    /// Verifies and removes the checksum ending the given frame.
+    pub fn verify(self, mut frame: Vec<u8>) -> Result<Vec<u8>, ChecksumError> {
+        match self {
+            Self::None => Ok(frame),
+            Self::Crc32 => {
+                let Some(len) = frame.len().checked_sub(CRC32_SIZE) else {
+                    return Err(ChecksumError::Truncated);
+                };
+
+                let mut got = [0; CRC32_SIZE];
+                got.copy_from_slice(&frame[len..]);
+                let got = u32::from_be_bytes(got);
+
+                frame.truncate(len);
+                let expected = crc32(&frame);
+
+                if got != expected {
+                    return Err(ChecksumError::Mismatch { expected, got });
+                }
+
+                Ok(frame)
+            }
+        }
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (CRC32_POLYNOMIAL & mask);
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn corrupted_frame_rejected() {
+        let frame = Checksum::Crc32.append(b"{\"request\":\"Get\"}".to_vec());
+        assert_eq!(
+            Checksum::Crc32.verify(frame.clone()),
+            Ok(b"{\"request\":\"Get\"}".to_vec())
+        );
+
+        let mut corrupted = frame;
+        corrupted[3] ^= 0x20;
+        assert!(matches!(
+            Checksum::Crc32.verify(corrupted),
+            Err(ChecksumError::Mismatch { .. })
+        ));
+
+        assert_eq!(
+            Checksum::Crc32.verify(b"abc".to_vec()),
+            Err(ChecksumError::Truncated)
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Collection of high-level timer clients.
+//!
+//! Clients own a stream, and expose timer requests as plain methods
+//! by driving the [coroutines] against it. Use the coroutines
+//! directly if you need a finer control over the I/O.
+//!
+//! [coroutines]: crate::coroutines
+
+#[cfg(feature = "std")]
+pub mod std;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+use alloc::boxed::Box;
+
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        client::TimerRequestSendError, connect::TimerConnectError, request::TimerRequestError,
+        stream::TimerEventStreamError,
+    },
+    timer::{TimerError, TimerResponse},
+};
+
+/// Error emitted by the high-level clients.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Timer request rejected by the server")]
+    Rejected(#[source] TimerError),
+    #[error("Timer server busy, retry after {0}ms")]
+    Busy(u64),
+    #[error("Unexpected timer response {0:?}")]
+    UnexpectedResponse(Box<TimerResponse>),
+    #[error(
+        "Previous timer request cancelled before completion, the connection needs to be reopened"
+    )]
+    Cancelled,
+
+    #[error(transparent)]
+    Connect(#[from] TimerConnectError),
+    #[error(transparent)]
+    Send(#[from] TimerRequestSendError),
+    #[error(transparent)]
+    Stream(#[from] TimerEventStreamError),
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    Io(#[from] ::std::io::Error),
+}
+
+impl From<TimerRequestError> for ClientError {
+    fn from(err: TimerRequestError) -> Self {
+        match err {
+            TimerRequestError::Rejected(err) => Self::Rejected(err),
+            TimerRequestError::Busy(retry_after_ms) => Self::Busy(retry_after_ms),
+            TimerRequestError::UnexpectedResponse(response) => Self::UnexpectedResponse(response),
+            TimerRequestError::Send(err) => Self::Send(err),
+        }
+    }
+}
+
+/// Turns [`TimerResponse::Err`] and [`TimerResponse::Busy`]
+/// responses into errors.
+#[cfg(feature = "std")]
+pub(crate) fn expect(response: TimerResponse) -> Result<TimerResponse, ClientError> {
+    match response {
+        TimerResponse::Err(err) => Err(ClientError::Rejected(err)),
+        TimerResponse::Busy { retry_after_ms } => Err(ClientError::Busy(retry_after_ms)),
+        response => Ok(response),
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn unexpected(response: TimerResponse) -> ClientError {
+    ClientError::UnexpectedResponse(Box::new(response))
+}
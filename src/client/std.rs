@@ -0,0 +1,311 @@
+//! Standard blocking timer client.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+#[cfg(unix)]
+use std::{os::unix::net::UnixStream, path::Path};
+
+use io_socket::io::{SocketInput, SocketOutput};
+
+use crate::{
+    client::{ClientError, expect, unexpected},
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::TimerRequestSend,
+        connect::TimerConnect,
+        pipeline::{TimerBatch, TimerRequestPipeline},
+        request::TimerStatsExport,
+    },
+    session::{ClientHello, Session},
+    stats::TimerStats,
+    timer::{
+        TimerConfig, TimerRequest, TimerResponse, TimerSnapshot, TimerStampedEvent, TimerState,
+        TimerStatusLine,
+    },
+};
+
+/// Standard blocking timer client.
+///
+/// The client owns a stream (typically a [`TcpStream`] or a
+/// [`UnixStream`]), and sends each request by driving a
+/// [`TimerRequestSend`] coroutine against it:
+///
+/// ```rust,ignore
+/// use io_time::client::std::StdClient;
+///
+/// let mut client = StdClient::connect_tcp("localhost:1234")?;
+/// client.start()?;
+/// println!("{}", client.status()?.text);
+/// ```
+#[derive(Debug)]
+pub struct StdClient<S> {
+    stream: S,
+    session: Session,
+    timer: Option<String>,
+}
+
+impl StdClient<TcpStream> {
+    /// Connects to the timer server at the given TCP address.
+    pub fn connect_tcp(addr: impl ToSocketAddrs) -> Result<Self, ClientError> {
+        Ok(Self::new(TcpStream::connect(addr)?))
+    }
+}
+
+#[cfg(unix)]
+impl StdClient<UnixStream> {
+    /// Connects to the timer server at the given Unix socket path.
+    pub fn connect_unix(path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        Ok(Self::new(UnixStream::connect(path)?))
+    }
+}
+
+impl<S: Read + Write> StdClient<S> {
+    /// Creates a new client over the given stream, using the default
+    /// session.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            session: Session::default(),
+            timer: None,
+        }
+    }
+
+    /// Performs the handshake with the given hello, then uses the
+    /// negotiated session for the next requests.
+    ///
+    /// The server needs to accept connections with [`TimerAccept`].
+    ///
+    /// [`TimerAccept`]: crate::coroutines::accept::TimerAccept
+    pub fn handshake(mut self, hello: ClientHello) -> Result<Self, ClientError> {
+        self.session = self.run(TimerConnect::new(hello))?;
+        Ok(self)
+    }
+
+    /// Targets the timer with the given name, instead of the default
+    /// one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.timer = Some(name.to_string());
+        self
+    }
+
+    /// Returns the session used by the client.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Sends the given request, and returns the response.
+    ///
+    /// [`TimerResponse::Err`] and [`TimerResponse::Busy`] responses
+    /// are returned as errors.
+    pub fn send(&mut self, request: TimerRequest) -> Result<TimerResponse, ClientError> {
+        let mut client = TimerRequestSend::new(request).with_session(self.session);
+        if let Some(name) = &self.timer {
+            client = client.with_timer(name);
+        }
+
+        expect(self.run(client)?)
+    }
+
+    /// Returns the current state of the timer.
+    pub fn get(&mut self) -> Result<TimerSnapshot, ClientError> {
+        match self.send(TimerRequest::Get)? {
+            TimerResponse::Snapshot(snapshot) => Ok(snapshot),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Returns the compact status of the timer.
+    pub fn status(&mut self) -> Result<TimerStatusLine, ClientError> {
+        match self.send(TimerRequest::Status)? {
+            TimerResponse::StatusLine(status) => Ok(status),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Returns the statistics of the timer.
+    pub fn stats(&mut self) -> Result<TimerStats, ClientError> {
+        match self.send(TimerRequest::GetStats)? {
+            TimerResponse::Stats(stats) => Ok(stats),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Returns the statistics of every timer of the server, by name.
+    ///
+    /// The statistics are streamed by the server, see
+    /// [`TimerStatsExport`].
+    pub fn export_stats(&mut self) -> Result<BTreeMap<String, TimerStats>, ClientError> {
+        let coroutine = TimerStatsExport::new().with_session(self.session);
+        self.run(coroutine)
+    }
+
+    /// Starts the timer.
+    pub fn start(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Start)
+    }
+
+    /// Stops the timer.
+    pub fn stop(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Stop)
+    }
+
+    /// Stops the timer, even if paused, and resets it.
+    pub fn reset(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Reset)
+    }
+
+    /// Pauses the timer.
+    pub fn pause(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Pause)
+    }
+
+    /// Resumes the paused timer.
+    pub fn resume(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Resume)
+    }
+
+    /// Updates the timer.
+    pub fn update(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Update)
+    }
+
+    /// Reports that the user is active.
+    pub fn activity(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Activity)
+    }
+
+    /// Sets the remaining seconds of the current cycle.
+    pub fn set_remaining(&mut self, secs: usize) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::SetRemaining(secs))
+    }
+
+    /// Sets the duration of the given cycle.
+    pub fn set_cycle_duration(
+        &mut self,
+        cycle: impl ToString,
+        secs: usize,
+    ) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        let cycle = cycle.to_string();
+        self.send_command(TimerRequest::SetCycleDuration { cycle, secs })
+    }
+
+    /// Replaces the configuration of the timer.
+    pub fn set_config(
+        &mut self,
+        config: TimerConfig,
+    ) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::SetConfig(config))
+    }
+
+    /// Pauses the running timer, or resumes (or starts) it otherwise,
+    /// and returns its new state.
+    pub fn toggle(&mut self) -> Result<TimerState, ClientError> {
+        match self.send(TimerRequest::Toggle)? {
+            TimerResponse::State(state) => Ok(state),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Starts a batch of requests, sent at once by
+    /// [`TimerBatch::commit`] over the connection of the client.
+    pub fn batch(&mut self) -> TimerBatch<&mut Self> {
+        TimerBatch::on(self)
+    }
+
+    /// Checks that the connection is alive.
+    pub fn ping(&mut self) -> Result<(), ClientError> {
+        match self.send(TimerRequest::Ping)? {
+            TimerResponse::Pong => Ok(()),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    fn send_command(
+        &mut self,
+        request: TimerRequest,
+    ) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        match self.send(request)? {
+            TimerResponse::Events(events) => Ok(events),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    fn run<C>(&mut self, coroutine: C) -> Result<C::Ok, ClientError>
+    where
+        C: Coroutine<Input = SocketInput, Output = SocketOutput>,
+        ClientError: From<C::Error>,
+    {
+        run(&mut self.stream, coroutine)
+    }
+}
+
+impl<S: Read + Write> TimerBatch<&mut StdClient<S>> {
+    /// Sends every request of the batch at once, then returns their
+    /// responses in order.
+    ///
+    /// Requests without timer target the timer of the client.
+    /// Requests are all applied even if one of them fails, but the
+    /// first [`TimerResponse::Err`] or [`TimerResponse::Busy`]
+    /// response is returned as error.
+    pub fn commit(self) -> Result<Vec<TimerResponse>, ClientError> {
+        let (client, mut envelopes) = self.into_parts();
+
+        for envelope in &mut envelopes {
+            if envelope.timer.is_none() {
+                envelope.timer.clone_from(&client.timer);
+            }
+        }
+
+        let pipeline = TimerRequestPipeline::new(envelopes).with_session(client.session);
+        let responses = client.run(pipeline)?;
+        responses.into_iter().map(expect).collect()
+    }
+}
+
+/// Drives the given coroutine against the given stream until it
+/// terminates.
+///
+/// This is the loop used by [`StdClient`], exposed for coroutines
+/// the client does not wrap:
+///
+/// ```rust,ignore
+/// use io_time::{client::std::run, coroutines::request::TimerGet};
+///
+/// let snapshot = run(&mut stream, TimerGet::new())?;
+/// ```
+pub fn run<C>(stream: &mut (impl Read + Write), mut coroutine: C) -> Result<C::Ok, ClientError>
+where
+    C: Coroutine<Input = SocketInput, Output = SocketOutput>,
+    ClientError: From<C::Error>,
+{
+    let mut arg = None;
+
+    loop {
+        match Coroutine::resume(&mut coroutine, arg.take()) {
+            CoroutineResult::Ok(ok) => return Ok(ok),
+            CoroutineResult::Io(input) => arg = Some(io(stream, input)?),
+            CoroutineResult::Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+fn io(stream: &mut (impl Read + Write), input: SocketInput) -> io::Result<SocketOutput> {
+    match input {
+        SocketInput::Read { mut buf } => {
+            let n = stream.read(&mut buf)?;
+            Ok(SocketOutput::Read { buf, n })
+        }
+        SocketInput::Write { buf } => {
+            let n = stream.write(&buf)?;
+            Ok(SocketOutput::Wrote { buf, n })
+        }
+    }
+}
@@ -0,0 +1,507 @@
+//! Asynchronous timer client backed by [`tokio`].
+
+#[cfg(unix)]
+use std::path::Path;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use io_socket::io::{SocketInput, SocketOutput};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use crate::{
+    client::{ClientError, expect, unexpected},
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::TimerRequestSend,
+        connect::TimerConnect,
+        pipeline::{TimerBatch, TimerRequestPipeline},
+        request::TimerStatsExport,
+        stream::{TimerEventStream, TimerEventStreamResult},
+    },
+    history::TimerHistoryEntry,
+    session::{ClientHello, Session},
+    stats::TimerStats,
+    timer::{
+        TimerConfig, TimerRequest, TimerResponse, TimerSnapshot, TimerStampedEvent, TimerState,
+        TimerStatusLine,
+    },
+};
+
+/// Asynchronous timer client.
+///
+/// The async counterpart of the [`StdClient`], owning a tokio stream
+/// (typically a [`TcpStream`] or a [`UnixStream`]):
+///
+/// ```rust,ignore
+/// use io_time::client::tokio::TokioClient;
+///
+/// let mut client = TokioClient::connect_tcp("localhost:1234").await?;
+/// client.start().await?;
+///
+/// let mut events = client.subscribe(None).await?;
+/// while let Some((_, event)) = events.next().await? {
+///     println!("{:?}", event.event);
+/// }
+/// ```
+///
+/// # Cancellation
+///
+/// Futures returned by the client can be dropped at any point (for
+/// example by `tokio::select!` or a timeout), but a request
+/// cancelled before completion leaves the connection in an
+/// undefined state: the request may be partially written, and its
+/// response may still arrive. The next requests then fail with
+/// [`ClientError::Cancelled`], and a new client needs to be
+/// connected. Mutating requests cancelled this way may or may not
+/// have been applied by the server.
+///
+/// [`StdClient`]: crate::client::std::StdClient
+#[derive(Debug)]
+pub struct TokioClient<S> {
+    stream: S,
+    session: Session,
+    timer: Option<String>,
+    in_flight: bool,
+}
+
+impl TokioClient<TcpStream> {
+    /// Connects to the timer server at the given TCP address.
+    pub async fn connect_tcp(addr: impl ToSocketAddrs) -> Result<Self, ClientError> {
+        Ok(Self::new(TcpStream::connect(addr).await?))
+    }
+}
+
+#[cfg(unix)]
+impl TokioClient<UnixStream> {
+    /// Connects to the timer server at the given Unix socket path.
+    pub async fn connect_unix(path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        Ok(Self::new(UnixStream::connect(path).await?))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> TokioClient<S> {
+    /// Creates a new client over the given stream, using the default
+    /// session.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            session: Session::default(),
+            timer: None,
+            in_flight: false,
+        }
+    }
+
+    /// Performs the handshake with the given hello, then uses the
+    /// negotiated session for the next requests.
+    ///
+    /// The server needs to accept connections with [`TimerAccept`].
+    ///
+    /// [`TimerAccept`]: crate::coroutines::accept::TimerAccept
+    pub async fn handshake(mut self, hello: ClientHello) -> Result<Self, ClientError> {
+        self.session = self.run(TimerConnect::new(hello)).await?;
+        Ok(self)
+    }
+
+    /// Targets the timer with the given name, instead of the default
+    /// one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.timer = Some(name.to_string());
+        self
+    }
+
+    /// Returns the session used by the client.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Returns `true` if a previous request was cancelled before
+    /// completion, in which case the connection cannot be used
+    /// anymore.
+    pub fn is_cancelled(&self) -> bool {
+        self.in_flight
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Sends the given request, and returns the response.
+    ///
+    /// [`TimerResponse::Err`] and [`TimerResponse::Busy`] responses
+    /// are returned as errors.
+    pub async fn send(&mut self, request: TimerRequest) -> Result<TimerResponse, ClientError> {
+        let mut client = TimerRequestSend::new(request).with_session(self.session);
+        if let Some(name) = &self.timer {
+            client = client.with_timer(name);
+        }
+
+        expect(self.run(client).await?)
+    }
+
+    /// Returns the current state of the timer.
+    pub async fn get(&mut self) -> Result<TimerSnapshot, ClientError> {
+        match self.send(TimerRequest::Get).await? {
+            TimerResponse::Snapshot(snapshot) => Ok(snapshot),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Returns the compact status of the timer.
+    pub async fn status(&mut self) -> Result<TimerStatusLine, ClientError> {
+        match self.send(TimerRequest::Status).await? {
+            TimerResponse::StatusLine(status) => Ok(status),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Returns the statistics of the timer.
+    pub async fn stats(&mut self) -> Result<TimerStats, ClientError> {
+        match self.send(TimerRequest::GetStats).await? {
+            TimerResponse::Stats(stats) => Ok(stats),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Returns the statistics of every timer of the server, by name.
+    ///
+    /// The statistics are streamed by the server, see
+    /// [`TimerStatsExport`].
+    pub async fn export_stats(&mut self) -> Result<BTreeMap<String, TimerStats>, ClientError> {
+        let coroutine = TimerStatsExport::new().with_session(self.session);
+        self.run(coroutine).await
+    }
+
+    /// Starts the timer.
+    pub async fn start(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Start).await
+    }
+
+    /// Stops the timer.
+    pub async fn stop(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Stop).await
+    }
+
+    /// Stops the timer, even if paused, and resets it.
+    pub async fn reset(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Reset).await
+    }
+
+    /// Pauses the timer.
+    pub async fn pause(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Pause).await
+    }
+
+    /// Resumes the paused timer.
+    pub async fn resume(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Resume).await
+    }
+
+    /// Updates the timer.
+    pub async fn update(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Update).await
+    }
+
+    /// Reports that the user is active.
+    pub async fn activity(&mut self) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::Activity).await
+    }
+
+    /// Sets the remaining seconds of the current cycle.
+    pub async fn set_remaining(
+        &mut self,
+        secs: usize,
+    ) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::SetRemaining(secs)).await
+    }
+
+    /// Sets the duration of the given cycle.
+    pub async fn set_cycle_duration(
+        &mut self,
+        cycle: impl ToString,
+        secs: usize,
+    ) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        let cycle = cycle.to_string();
+        self.send_command(TimerRequest::SetCycleDuration { cycle, secs })
+            .await
+    }
+
+    /// Replaces the configuration of the timer.
+    pub async fn set_config(
+        &mut self,
+        config: TimerConfig,
+    ) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        self.send_command(TimerRequest::SetConfig(config)).await
+    }
+
+    /// Pauses the running timer, or resumes (or starts) it otherwise,
+    /// and returns its new state.
+    pub async fn toggle(&mut self) -> Result<TimerState, ClientError> {
+        match self.send(TimerRequest::Toggle).await? {
+            TimerResponse::State(state) => Ok(state),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Starts a batch of requests, sent at once by
+    /// [`TimerBatch::commit`] over the connection of the client.
+    pub fn batch(&mut self) -> TimerBatch<&mut Self> {
+        TimerBatch::on(self)
+    }
+
+    /// Checks that the connection is alive.
+    pub async fn ping(&mut self) -> Result<(), ClientError> {
+        match self.send(TimerRequest::Ping).await? {
+            TimerResponse::Pong => Ok(()),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Subscribes to the events of the timer, with the given durable
+    /// subscription id if any.
+    ///
+    /// The connection is dedicated to the subscription from now on.
+    /// A rejected subscription is reported by the first call to
+    /// [`TokioEventStream::next`].
+    pub async fn subscribe(self, id: Option<String>) -> Result<TokioEventStream<S>, ClientError> {
+        if self.in_flight {
+            return Err(ClientError::Cancelled);
+        }
+
+        let mut coroutine = TimerEventStream::new(id).with_session(self.session);
+        if let Some(name) = &self.timer {
+            coroutine = coroutine.with_timer(name);
+        }
+
+        Ok(TokioEventStream {
+            stream: self.stream,
+            coroutine,
+        })
+    }
+
+    async fn send_command(
+        &mut self,
+        request: TimerRequest,
+    ) -> Result<Vec<TimerStampedEvent>, ClientError> {
+        match self.send(request).await? {
+            TimerResponse::Events(events) => Ok(events),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    async fn run<C>(&mut self, coroutine: C) -> Result<C::Ok, ClientError>
+    where
+        C: Coroutine<Input = SocketInput, Output = SocketOutput> + Unpin,
+        ClientError: From<C::Error>,
+    {
+        if self.in_flight {
+            return Err(ClientError::Cancelled);
+        }
+
+        // cleared only once the coroutine terminates, so that it
+        // remains set if the future is dropped in between
+        self.in_flight = true;
+        let result = run(&mut self.stream, coroutine).await;
+        self.in_flight = false;
+        result
+    }
+}
+
+/// Stream of timer events, see [`TokioClient::subscribe`].
+#[derive(Debug)]
+pub struct TokioEventStream<S> {
+    stream: S,
+    coroutine: TimerEventStream,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> TokioEventStream<S> {
+    /// Waits for the next event, along with the name of its timer
+    /// unless subscribed to the default timer.
+    ///
+    /// Pushed deltas (see [`TimerResponse::Delta`]) are skipped.
+    /// Returns `None` once the server closed the connection.
+    ///
+    /// [`TimerResponse::Delta`]: crate::timer::TimerResponse::Delta
+    pub async fn next(
+        &mut self,
+    ) -> Result<Option<(Option<String>, TimerHistoryEntry)>, ClientError> {
+        let mut arg = None;
+
+        loop {
+            match self.coroutine.resume(arg.take()) {
+                TimerEventStreamResult::Ok => return Ok(None),
+                TimerEventStreamResult::Event { timer, event } => return Ok(Some((timer, event))),
+                TimerEventStreamResult::Delta { .. } => (),
+                TimerEventStreamResult::Io { input } => {
+                    arg = Some(io(&mut self.stream, input).await?);
+                }
+                TimerEventStreamResult::Err { err } => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Acknowledges the event with the given sequence number, sent
+    /// along with the next call to [`next`].
+    ///
+    /// [`next`]: TokioEventStream::next
+    pub fn ack(&mut self, seq: u64) {
+        self.coroutine.ack(seq);
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> TimerBatch<&mut TokioClient<S>> {
+    /// Sends every request of the batch at once, then returns their
+    /// responses in order.
+    ///
+    /// Requests without timer target the timer of the client.
+    /// Requests are all applied even if one of them fails, but the
+    /// first [`TimerResponse::Err`] or [`TimerResponse::Busy`]
+    /// response is returned as error.
+    pub async fn commit(self) -> Result<Vec<TimerResponse>, ClientError> {
+        let (client, mut envelopes) = self.into_parts();
+
+        for envelope in &mut envelopes {
+            if envelope.timer.is_none() {
+                envelope.timer.clone_from(&client.timer);
+            }
+        }
+
+        let pipeline = TimerRequestPipeline::new(envelopes).with_session(client.session);
+        let responses = client.run(pipeline).await?;
+        responses.into_iter().map(expect).collect()
+    }
+}
+
+/// Drives the given coroutine against the given stream until it
+/// terminates.
+///
+/// This is the loop used by [`TokioClient`], exposed for coroutines
+/// the client does not wrap:
+///
+/// ```rust,ignore
+/// use io_time::{client::tokio::run, coroutines::request::TimerGet};
+///
+/// let snapshot = run(&mut stream, TimerGet::new()).await?;
+/// ```
+///
+/// Unlike the client, nothing prevents the stream from being reused
+/// after the returned future is dropped before completion, see
+/// [`TokioClient#cancellation`].
+pub fn run<S, C>(stream: &mut S, coroutine: C) -> CoroutineFuture<'_, S, C>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    C: Coroutine<Input = SocketInput, Output = SocketOutput> + Unpin,
+    ClientError: From<C::Error>,
+{
+    CoroutineFuture::new(stream, coroutine)
+}
+
+/// Future driving a coroutine against a stream until it terminates,
+/// see [`run`].
+///
+/// Unlike an `async` block, the future can be named, for example to
+/// be stored in a struct, and it is [`Unpin`] so that it can be
+/// polled from `select!` without being pinned first.
+#[derive(Debug)]
+pub struct CoroutineFuture<'a, S, C> {
+    stream: &'a mut S,
+    coroutine: C,
+    input: Option<SocketInput>,
+    arg: Option<SocketOutput>,
+}
+
+impl<'a, S, C> CoroutineFuture<'a, S, C> {
+    /// Wraps the given coroutine, to be driven against the given
+    /// stream once awaited.
+    pub fn new(stream: &'a mut S, coroutine: C) -> Self {
+        Self {
+            stream,
+            coroutine,
+            input: None,
+            arg: None,
+        }
+    }
+
+    /// Returns the coroutine, for example to inspect it once the
+    /// future was dropped before completion.
+    pub fn into_inner(self) -> C {
+        self.coroutine
+    }
+}
+
+impl<S, C> Future for CoroutineFuture<'_, S, C>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    C: Coroutine<Input = SocketInput, Output = SocketOutput> + Unpin,
+    ClientError: From<C::Error>,
+{
+    type Output = Result<C::Ok, ClientError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        loop {
+            match this.input.take() {
+                None => match Coroutine::resume(&mut this.coroutine, this.arg.take()) {
+                    CoroutineResult::Ok(ok) => return Poll::Ready(Ok(ok)),
+                    CoroutineResult::Io(input) => this.input = Some(input),
+                    CoroutineResult::Err(err) => return Poll::Ready(Err(err.into())),
+                },
+                Some(SocketInput::Read { mut buf }) => {
+                    let mut read_buf = ReadBuf::new(&mut buf);
+                    let poll = Pin::new(&mut *this.stream).poll_read(cx, &mut read_buf);
+                    let n = read_buf.filled().len();
+
+                    match poll {
+                        Poll::Ready(Ok(())) => this.arg = Some(SocketOutput::Read { buf, n }),
+                        Poll::Ready(Err(err)) => return Poll::Ready(Err(err.into())),
+                        Poll::Pending => {
+                            this.input = Some(SocketInput::Read { buf });
+                            return Poll::Pending;
+                        }
+                    }
+                }
+                Some(SocketInput::Write { buf }) => {
+                    match Pin::new(&mut *this.stream).poll_write(cx, &buf) {
+                        Poll::Ready(Ok(n)) => this.arg = Some(SocketOutput::Wrote { buf, n }),
+                        Poll::Ready(Err(err)) => return Poll::Ready(Err(err.into())),
+                        Poll::Pending => {
+                            this.input = Some(SocketInput::Write { buf });
+                            return Poll::Pending;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn io(
+    stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+    input: SocketInput,
+) -> std::io::Result<SocketOutput> {
+    match input {
+        SocketInput::Read { mut buf } => {
+            let n = stream.read(&mut buf).await?;
+            Ok(SocketOutput::Read { buf, n })
+        }
+        SocketInput::Write { buf } => {
+            let n = stream.write(&buf).await?;
+            Ok(SocketOutput::Wrote { buf, n })
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Serialization formats of timer requests and responses.
+//!
+//! The codec is negotiated per connection during the handshake (see
+//! [`TimerConnect`] and [`TimerAccept`]), so that clients supporting
+//! different codecs can share the same server socket. Connections
+//! without handshake use [`Codec::Json`]. Note that the handshake
+//! itself is always JSON-encoded.
+//!
+//! [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+//! [`TimerAccept`]: crate::coroutines::accept::TimerAccept
+
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+/// Error emitted when encoding or decoding a payload.
+#[derive(Debug, Error)]
+pub enum CodecError {
+    #[error("Failed to encode JSON payload")]
+    EncodeJson(#[source] serde_json::Error),
+    #[error("Failed to decode JSON payload")]
+    DecodeJson(#[source] serde_json::Error),
+    #[cfg(feature = "postcard")]
+    #[error("Failed to encode postcard payload")]
+    EncodePostcard(#[source] postcard::Error),
+    #[cfg(feature = "postcard")]
+    #[error("Failed to decode postcard payload")]
+    DecodePostcard(#[source] postcard::Error),
+    #[error("Codec {0:?} is not supported")]
+    Unsupported(Codec),
+}
+
+/// The serialization format of timer requests and responses.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Codec {
+    /// Compact JSON.
+    #[default]
+    Json,
+    /// Compact binary [postcard] format, suited to constrained
+    /// clients.
+    ///
+    /// Requires the `postcard` cargo feature.
+    ///
+    /// [postcard]: https://docs.rs/postcard
+    Postcard,
+}
+
+impl Codec {
+    /// Returns the codecs supported by this build, by order of
+    /// preference.
+    pub fn supported() -> Vec<Self> {
+        [Self::Json, Self::Postcard]
+            .into_iter()
+            .filter(|c| c.is_supported())
+            .collect()
+    }
+
+    /// Returns `true` if the codec is supported by this build.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Self::Json => true,
+            Self::Postcard => cfg!(feature = "postcard"),
+        }
+    }
+
+    /// Returns `true` if encoded payloads may contain any byte,
+    /// including the line delimiter.
+    pub fn is_binary(self) -> bool {
+        match self {
+            Self::Json => false,
+            Self::Postcard => true,
+        }
+    }
+
+    /// Encodes the given value.
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, CodecError> {
+        match self {
+            Self::Json => serde_json::to_vec(value).map_err(CodecError::EncodeJson),
+            #[cfg(feature = "postcard")]
+            Self::Postcard => postcard::to_allocvec(value).map_err(CodecError::EncodePostcard),
+            #[cfg(not(feature = "postcard"))]
+            Self::Postcard => Err(CodecError::Unsupported(self)),
+        }
+    }
+
+    /// Decodes a value from the given bytes.
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, CodecError> {
+        match self {
+            Self::Json => serde_json::from_slice(bytes).map_err(CodecError::DecodeJson),
+            #[cfg(feature = "postcard")]
+            Self::Postcard => postcard::from_bytes(bytes).map_err(CodecError::DecodePostcard),
+            #[cfg(not(feature = "postcard"))]
+            Self::Postcard => Err(CodecError::Unsupported(self)),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "postcard"))]
+mod tests {
+    use super::*;
+    use crate::timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerEnvelope, TimerRequest, TimerResponse,
+        TimerSnapshot,
+    };
+
+    #[test]
+    fn postcard_round_trip() {
+        let envelope = TimerEnvelope {
+            request: TimerRequest::SetRemaining(42),
+            key: Some("key".into()),
+            timer: Some("work".into()),
+        };
+        let bytes = Codec::Postcard.encode(&envelope).unwrap();
+        assert_eq!(
+            Codec::Postcard.decode::<TimerEnvelope>(&bytes).unwrap(),
+            envelope
+        );
+
+        let mut timer = Timer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("a", 3)]),
+            ..Default::default()
+        });
+        timer.start(10).into_iter().for_each(drop);
+
+        let response = TimerResponse::Snapshot(TimerSnapshot::from(&timer));
+        let bytes = Codec::Postcard.encode(&response).unwrap();
+        assert_eq!(
+            Codec::Postcard.decode::<TimerResponse>(&bytes).unwrap(),
+            response
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Compression of response frames.
+//!
+//! Compression is negotiated per connection during the handshake
+//! (see [`TimerConnect`] and [`TimerAccept`]). Only the algorithms
+//! enabled at compile time are advertised.
+//!
+//! [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+//! [`TimerAccept`]: crate::coroutines::accept::TimerAccept
+
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The deflate compression level, from 0 (none) to 10 (best).
+#[cfg(feature = "deflate")]
+const DEFLATE_LEVEL: u8 = 6;
+
+/// Error emitted when compressing or decompressing a frame.
+#[derive(Clone, Debug, Error)]
+pub enum CompressionError {
+    #[error("Compression {0:?} is not supported")]
+    Unsupported(Compression),
+    #[error("Failed to inflate deflate frame")]
+    Inflate,
+}
+
+/// The compression algorithm applied to response frames.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Compression {
+    /// Frames are sent as is.
+    #[default]
+    None,
+    /// Frames are compressed using raw deflate.
+    ///
+    /// Requires the `deflate` cargo feature.
+    Deflate,
+}
+
+impl Compression {
+    /// Returns the compression algorithms supported by this build,
+    /// by order of preference.
+    pub fn supported() -> Vec<Self> {
+        [Self::Deflate, Self::None]
+            .into_iter()
+            .filter(|c| c.is_supported())
+            .collect()
+    }
+
+    /// Returns `true` if the algorithm is supported by this build.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Self::None => true,
+            Self::Deflate => cfg!(feature = "deflate"),
+        }
+    }
+
+    /// Compresses the given bytes.
+    pub fn compress(self, bytes: Vec<u8>) -> Result<Vec<u8>, CompressionError> {
+        match self {
+            Self::None => Ok(bytes),
+            #[cfg(feature = "deflate")]
+            Self::Deflate => Ok(miniz_oxide::deflate::compress_to_vec(&bytes, DEFLATE_LEVEL)),
+            #[cfg(not(feature = "deflate"))]
+            Self::Deflate => Err(CompressionError::Unsupported(self)),
+        }
+    }
+
+    /// Decompresses the given bytes.
+    pub fn decompress(self, bytes: Vec<u8>) -> Result<Vec<u8>, CompressionError> {
+        match self {
+            Self::None => Ok(bytes),
+            #[cfg(feature = "deflate")]
+            Self::Deflate => miniz_oxide::inflate::decompress_to_vec(&bytes)
+                .map_err(|_| CompressionError::Inflate),
+            #[cfg(not(feature = "deflate"))]
+            Self::Deflate => Err(CompressionError::Unsupported(self)),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "deflate"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deflate_round_trip() {
+        let bytes = b"{\"cycles\":[\"Focus\",\"Focus\",\"Focus\",\"Focus\"]}".to_vec();
+        let compressed = Compression::Deflate.compress(bytes.clone()).unwrap();
+
+        assert!(compressed.len() < bytes.len());
+        assert_eq!(Compression::Deflate.decompress(compressed).unwrap(), bytes);
+    }
+}
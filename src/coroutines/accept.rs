@@ -0,0 +1,272 @@
+//! I/O-free coroutine to negotiate a session with a timer client.
+
+use alloc::{sync::Arc, vec::Vec};
+
+use io_socket::{
+    coroutines::{read::*, write::*},
+    io::{SocketInput, SocketOutput},
+};
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::{
+    auth::TimerAuthenticator,
+    checksum::Checksum,
+    codec::Codec,
+    compression::Compression,
+    coroutines::{Coroutine, CoroutineResult},
+    frame::{FrameDecoder, FrameError, Framing},
+    session::{ClientHello, Keepalive, Permission, ServerHello, Session},
+};
+
+/// Error emitted by the [`TimerAccept`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimerAcceptError {
+    #[error("Failed to serialize server hello")]
+    Serialize(#[source] serde_json::Error),
+    #[error("Failed to deserialize client hello")]
+    Deserialize(#[source] serde_json::Error),
+
+    #[error("Failed to decode hello frame")]
+    Frame(#[source] FrameError),
+    #[error("Client rejected by the authenticator")]
+    Unauthenticated,
+
+    #[error("Reached unexpected EOF while reading client hello")]
+    ReadEof,
+    #[error(transparent)]
+    Read(SocketReadError),
+
+    #[error("Reached unexpected EOF while writing server hello")]
+    WriteEof,
+    #[error(transparent)]
+    Write(SocketWriteError),
+}
+
+/// Result emitted on each step of the [`TimerAccept`] coroutine.
+#[derive(Debug)]
+pub enum TimerAcceptResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok { session: Session },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerAcceptError },
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    Read(SocketRead),
+    Deserialize(Vec<u8>),
+    Write(SocketWrite),
+}
+
+/// I/O-free coroutine to perform the server side of the handshake.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit [`SocketInput::Read`] until the JSON-encoded
+///    [`ClientHello`] line is fully received.
+/// 2. Negotiate the session against the server preferences, and
+///    authenticate the client (see [`with_authenticator`]).
+/// 3. Emit [`SocketInput::Write`] with the JSON-encoded
+///    [`ServerHello`] line.
+/// 4. Return `Ok { session }` once the write completes.
+///
+/// [`resume`]: TimerAccept::resume
+/// [`with_authenticator`]: TimerAccept::with_authenticator
+#[derive(Clone, Debug)]
+pub struct TimerAccept {
+    codecs: Vec<Codec>,
+    compressions: Vec<Compression>,
+    checksums: Vec<Checksum>,
+    idle_timeout: Option<u64>,
+    permission: Permission,
+    authenticator: Option<Arc<dyn TimerAuthenticator>>,
+    state: State,
+    decoder: FrameDecoder,
+    session: Session,
+}
+
+impl Default for TimerAccept {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerAccept {
+    /// Creates a new coroutine accepting everything supported by
+    /// this build.
+    pub fn new() -> Self {
+        Self {
+            codecs: Codec::supported(),
+            compressions: Compression::supported(),
+            checksums: Checksum::supported(),
+            idle_timeout: None,
+            permission: Permission::Controller,
+            authenticator: None,
+            state: State::Read(SocketRead::default()),
+            decoder: FrameDecoder::new(),
+            session: Session::default(),
+        }
+    }
+
+    /// Accepts only the given codecs.
+    pub fn with_codecs(mut self, codecs: impl IntoIterator<Item = Codec>) -> Self {
+        self.codecs = codecs.into_iter().collect();
+        self
+    }
+
+    /// Accepts only the given compression algorithms.
+    pub fn with_compressions(
+        mut self,
+        compressions: impl IntoIterator<Item = Compression>,
+    ) -> Self {
+        self.compressions = compressions.into_iter().collect();
+        self
+    }
+
+    /// Accepts only the given checksums.
+    pub fn with_checksums(mut self, checksums: impl IntoIterator<Item = Checksum>) -> Self {
+        self.checksums = checksums.into_iter().collect();
+        self
+    }
+
+    /// Closes connections idle for more than the given number of
+    /// seconds, even if the client proposes a longer timeout.
+    ///
+    /// See [`Keepalive`].
+    pub fn with_idle_timeout(mut self, secs: u64) -> Self {
+        self.idle_timeout = Some(secs);
+        self
+    }
+
+    /// Grants at most the given permission, even if the client
+    /// requests more (for example on a read-only socket).
+    pub fn with_permission(mut self, permission: Permission) -> Self {
+        self.permission = permission;
+        self
+    }
+
+    /// Authenticates clients with the given authenticator, and grants
+    /// them at most the returned permission.
+    ///
+    /// Rejected clients make the coroutine fail with
+    /// [`TimerAcceptError::Unauthenticated`] without answering them:
+    /// the connection should then be closed.
+    pub fn with_authenticator(mut self, authenticator: Arc<dyn TimerAuthenticator>) -> Self {
+        self.authenticator = Some(authenticator);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, mut arg: Option<SocketOutput>) -> TimerAcceptResult {
+        loop {
+            match &mut self.state {
+                State::Read(r) => match r.resume(arg.take()) {
+                    SocketReadResult::Ok { buf, n } => {
+                        self.decoder.push(&buf[..n]);
+                        self.state = match self.decoder.next_frame() {
+                            Ok(Some(frame)) => State::Deserialize(frame),
+                            Ok(None) => State::Read(SocketRead::default()),
+                            Err(err) => {
+                                let err = TimerAcceptError::Frame(err);
+                                return TimerAcceptResult::Err { err };
+                            }
+                        };
+                    }
+                    SocketReadResult::Io { input } => {
+                        return TimerAcceptResult::Io { input };
+                    }
+                    SocketReadResult::Eof => {
+                        let err = TimerAcceptError::ReadEof;
+                        return TimerAcceptResult::Err { err };
+                    }
+                    SocketReadResult::Err { err } => {
+                        let err = TimerAcceptError::Read(err);
+                        return TimerAcceptResult::Err { err };
+                    }
+                },
+                State::Deserialize(bytes) => {
+                    let client: ClientHello = match serde_json::from_slice(bytes) {
+                        Ok(hello) => hello,
+                        Err(err) => {
+                            let err = TimerAcceptError::Deserialize(err);
+                            return TimerAcceptResult::Err { err };
+                        }
+                    };
+                    debug!("client hello received: {client:?}");
+
+                    let mut permission = self.permission;
+
+                    if let Some(authenticator) = &self.authenticator {
+                        match authenticator.authenticate(client.token.as_ref()) {
+                            Some(granted) => permission = permission.min(granted),
+                            None => {
+                                debug!("client rejected by the authenticator");
+                                let err = TimerAcceptError::Unauthenticated;
+                                return TimerAcceptResult::Err { err };
+                            }
+                        }
+                    }
+
+                    let mut server = ServerHello::negotiate(
+                        &client,
+                        &self.codecs,
+                        &self.compressions,
+                        &self.checksums,
+                    );
+                    server.keepalive = Keepalive::negotiate(client.keepalive, self.idle_timeout);
+                    server.permission = client.permission.min(permission);
+                    trace!("server hello to send: {server:?}");
+
+                    let bytes = match serde_json::to_vec(&server) {
+                        Ok(bytes) => Framing::Line.encode(bytes),
+                        Err(err) => {
+                            let err = TimerAcceptError::Serialize(err);
+                            return TimerAcceptResult::Err { err };
+                        }
+                    };
+
+                    self.session = Session::from(server);
+                    self.state = State::Write(SocketWrite::new(bytes));
+                }
+                State::Write(w) => {
+                    return match w.resume(arg.take()) {
+                        SocketWriteResult::Ok { .. } => TimerAcceptResult::Ok {
+                            session: self.session,
+                        },
+                        SocketWriteResult::Io { input } => TimerAcceptResult::Io { input },
+                        SocketWriteResult::Eof => {
+                            let err = TimerAcceptError::WriteEof;
+                            TimerAcceptResult::Err { err }
+                        }
+                        SocketWriteResult::Err { err } => {
+                            let err = TimerAcceptError::Write(err);
+                            TimerAcceptResult::Err { err }
+                        }
+                    };
+                }
+            }
+        }
+    }
+}
+
+impl Coroutine for TimerAccept {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = Session;
+    type Error = TimerAcceptError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<Session, SocketInput, TimerAcceptError> {
+        match TimerAccept::resume(self, arg) {
+            TimerAcceptResult::Ok { session } => CoroutineResult::Ok(session),
+            TimerAcceptResult::Io { input } => CoroutineResult::Io(input),
+            TimerAcceptResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
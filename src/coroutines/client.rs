@@ -1,21 +1,49 @@
 //! I/O-free coroutine to send a timer request and receive a response.
 
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
 use io_socket::{
     coroutines::{read::*, write::*},
     io::{SocketInput, SocketOutput},
 };
 use log::trace;
+use serde_json::Value;
 use thiserror::Error;
 
-use crate::timer::{TimerRequest, TimerResponse};
+use crate::{
+    checksum::ChecksumError,
+    codec::CodecError,
+    compression::CompressionError,
+    coroutines::{Coroutine, CoroutineResult},
+    frame::{FrameDecoder, FrameError},
+    metrics::{TimerRequestEvent, TimerRequestHook, TimerRequestMetrics},
+    session::Session,
+    timer::{TimerConfig, TimerEnvelope, TimerRequest, TimerResponse},
+};
 
-/// Error emitted by the [`TimerRequestSend`] coroutine.
+/// Error emitted by the [`TimerRequestSend`] and
+/// [`TimerRequestPipeline`] coroutines.
+///
+/// [`TimerRequestPipeline`]: crate::coroutines::pipeline::TimerRequestPipeline
 #[derive(Debug, Error)]
 pub enum TimerRequestSendError {
+    #[error("Invalid timer request send arg: {0:?}")]
+    InvalidArg(SocketOutput),
+
     #[error("Failed to serialize timer request")]
-    Serialize(#[source] serde_json::Error),
+    Serialize(#[source] CodecError),
     #[error("Failed to deserialize timer response")]
-    Deserialize(#[source] serde_json::Error),
+    Deserialize(#[source] CodecError),
+    #[error("Failed to decompress timer response")]
+    Decompress(#[source] CompressionError),
+    #[error("Failed to verify timer response checksum")]
+    Checksum(#[source] ChecksumError),
+    #[error("Failed to decode timer response frame")]
+    Frame(#[source] FrameError),
 
     #[error("Reached EOF while receiving response")]
     ReadEof,
@@ -28,6 +56,9 @@ pub enum TimerRequestSendError {
     Write(SocketWriteError),
 }
 
+/// The default number of bytes read at once by [`TimerRequestSend`].
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 1024;
+
 /// Result emitted on each step of the [`TimerRequestSend`] coroutine.
 #[derive(Debug)]
 pub enum TimerRequestSendResult {
@@ -40,49 +71,339 @@ pub enum TimerRequestSendResult {
     Err { err: TimerRequestSendError },
 }
 
+/// Progress of the request of a [`TimerRequestSend`] coroutine, see
+/// [`TimerRequestSend::progress`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TimerRequestProgress {
+    /// The request was not handed to the runtime yet.
+    #[default]
+    Pending,
+    /// The request was handed to the runtime, but its response was
+    /// not fully received yet.
+    Sent,
+    /// The response was received.
+    Received,
+}
+
 #[derive(Clone, Debug)]
 enum State {
     Serialize,
     Write(SocketWrite),
-    Read(SocketRead),
+    Read,
     Deserialize(Vec<u8>),
 }
 
 /// I/O-free coroutine to send a [`TimerRequest`] to a server and
-/// receive the [`TimerResponse`] over NDJSON.
+/// receive the [`TimerResponse`].
 ///
 /// Each call to [`resume`] advances one step:
 ///
-/// 1. Emit [`SocketInput::Write`] with the JSON-encoded request line.
-/// 2. Emit [`SocketInput::Read`] to receive the JSON-encoded response
-///    line.
+/// 1. Emit [`SocketInput::Write`] with the encoded request frame.
+/// 2. Emit [`SocketInput::Read`] until the encoded response frame is
+///    fully received.
 /// 3. Return `Ok { response }`.
 ///
+/// A malformed response (for example a response unknown to this
+/// build, or a corrupted one) fails with a typed error instead of
+/// aborting. The coroutine can then be resumed to send the request
+/// again, which should carry an idempotency key if it modifies the
+/// timer (see [`with_idempotency_key`]).
+///
 /// Use the named constructors ([`TimerRequestSend::get`],
-/// [`TimerRequestSend::start`], …) rather than constructing directly.
+/// [`TimerRequestSend::start`], …) rather than constructing directly,
+/// then [`send`] to reuse the coroutine for the next requests of the
+/// same connection. The coroutines of the [`request`] module wrap it
+/// to return typed values instead of raw responses.
+///
+/// # Cancellation
+///
+/// The coroutine can be dropped at any point, for example when the
+/// future driving it is cancelled. What happens to the connection
+/// then depends on the [`progress`] of the request:
 ///
+/// - [`Pending`]: nothing was sent, the connection can be reused
+///   as is.
+/// - [`Sent`]: the request frame may be partially written, and its
+///   response may still arrive. The connection is left in an
+///   undefined state and must be closed. See [`may_be_applied`] to
+///   know whether the request needs to be replayed with care.
+/// - [`Received`]: the request is complete, the connection can be
+///   reused as is.
+///
+/// [`Pending`]: TimerRequestProgress::Pending
+/// [`Received`]: TimerRequestProgress::Received
+/// [`Sent`]: TimerRequestProgress::Sent
+/// [`may_be_applied`]: TimerRequestSend::may_be_applied
+/// [`progress`]: TimerRequestSend::progress
+/// [`request`]: crate::coroutines::request
 /// [`resume`]: TimerRequestSend::resume
+/// [`send`]: TimerRequestSend::send
+/// [`with_idempotency_key`]: TimerRequestSend::with_idempotency_key
 #[derive(Clone, Debug)]
 pub struct TimerRequestSend {
-    request: TimerRequest,
+    envelope: TimerEnvelope,
+    session: Session,
     state: State,
+    decoder: FrameDecoder,
+    buf: Vec<u8>,
+    progress: TimerRequestProgress,
+    metrics: TimerRequestMetrics,
+    hook: Option<TimerRequestHook>,
 }
 
 impl TimerRequestSend {
-    pub fn new(request: TimerRequest) -> Self {
-        trace!("timer request to send: {request:?}");
+    /// Creates a new coroutine sending the given request, or request
+    /// envelope built with [`TimerEnvelope::new`].
+    pub fn new(request: impl Into<TimerEnvelope>) -> Self {
+        let envelope = request.into();
+        trace!("timer request to send: {envelope:?}");
 
         Self {
-            request,
+            envelope,
+            session: Session::default(),
             state: State::Serialize,
+            decoder: FrameDecoder::new(),
+            buf: Vec::new(),
+            progress: TimerRequestProgress::Pending,
+            metrics: TimerRequestMetrics::default(),
+            hook: None,
         }
     }
 
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.decoder.set_framing(session.framing());
+        self.session = session;
+        self
+    }
+
+    /// Rejects response frames larger than the given number of
+    /// bytes, instead of [`DEFAULT_MAX_FRAME_SIZE`].
+    ///
+    /// [`DEFAULT_MAX_FRAME_SIZE`]: crate::frame::DEFAULT_MAX_FRAME_SIZE
+    pub fn with_max_frame_size(mut self, max: usize) -> Self {
+        self.decoder.set_max_frame_size(max);
+        self
+    }
+
+    /// Reserves a receive buffer of the given number of bytes.
+    ///
+    /// The buffer is kept and reused by the next responses when the
+    /// coroutine is reused with [`send`], so that high-frequency
+    /// clients do not reallocate it for every response.
+    ///
+    /// [`send`]: TimerRequestSend::send
+    pub fn with_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.decoder.reserve(capacity);
+        self
+    }
+
+    /// Reads up to the given number of bytes at once, instead of
+    /// [`DEFAULT_READ_BUFFER_SIZE`].
+    ///
+    /// Larger reads save I/O round trips for large responses, like
+    /// long histories.
+    pub fn with_read_buffer_size(self, size: usize) -> Self {
+        self.with_read_buffer(vec![0; size])
+    }
+
+    /// Reads into the given buffer, up to its length.
+    ///
+    /// The buffer is handed to the runtime on each read, then kept
+    /// for the next reads and the next requests sent with [`send`].
+    /// It can be taken back with [`take_read_buffer`], to be reused
+    /// by another coroutine.
+    ///
+    /// [`send`]: TimerRequestSend::send
+    /// [`take_read_buffer`]: TimerRequestSend::take_read_buffer
+    pub fn with_read_buffer(mut self, buf: Vec<u8>) -> Self {
+        self.buf = buf;
+        self
+    }
+
+    /// Takes the read buffer back, leaving the default one in place.
+    ///
+    /// The buffer is only available between two reads, for example
+    /// once the response is received.
+    pub fn take_read_buffer(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.buf)
+    }
+
+    /// Attaches the given idempotency key to the request, so that it
+    /// can be safely sent again after a connection failure.
+    ///
+    /// See [`TimerEnvelope::key`].
+    pub fn with_idempotency_key(mut self, key: impl ToString) -> Self {
+        self.envelope.key = Some(key.to_string());
+        self
+    }
+
+    /// Targets the timer with the given name, instead of the default
+    /// one.
+    ///
+    /// See [`TimerEnvelope::timer`].
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.envelope.timer = Some(name.to_string());
+        self
+    }
+
+    /// Sends the given request, or request envelope, once the
+    /// previous one terminated.
+    ///
+    /// The session, the maximum frame size and the buffered bytes of
+    /// the coroutine are kept, so that long-lived clients can reuse a
+    /// single coroutine for every request of a connection. The
+    /// previous envelope is replaced, including its metadata.
+    pub fn send(&mut self, request: impl Into<TimerEnvelope>) {
+        self.envelope = request.into();
+        trace!("timer request to send: {:?}", self.envelope);
+        self.state = State::Serialize;
+        self.progress = TimerRequestProgress::Pending;
+    }
+
+    /// Receives the next response frame of the previous request,
+    /// without sending any request, once the previous response was
+    /// received.
+    ///
+    /// Used for requests answered with several frames, like
+    /// [`TimerRequest::StreamHistory`].
+    pub fn receive(&mut self) {
+        trace!("timer response to receive: {:?}", self.envelope);
+        self.state = State::Read;
+        self.progress = TimerRequestProgress::Sent;
+    }
+
+    /// Calls the given hook on each step of the requests sent by the
+    /// coroutine, see [`TimerRequestEvent`].
+    pub fn with_hook(mut self, hook: TimerRequestHook) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+
+    /// Returns the counters of the requests sent by the coroutine so
+    /// far, including the ones sent before [`send`].
+    ///
+    /// [`send`]: TimerRequestSend::send
+    pub fn metrics(&self) -> &TimerRequestMetrics {
+        &self.metrics
+    }
+
+    /// Returns the progress of the request, to know whether the
+    /// coroutine can be abandoned without closing the connection.
+    pub fn progress(&self) -> TimerRequestProgress {
+        self.progress
+    }
+
+    /// Returns `true` if the request may modify the timer and was
+    /// sent, without its response being received yet.
+    ///
+    /// If the coroutine is abandoned in this state, the request may
+    /// or may not have been applied by the server: it should only be
+    /// replayed with an idempotency key (see
+    /// [`with_idempotency_key`]).
+    ///
+    /// [`with_idempotency_key`]: TimerRequestSend::with_idempotency_key
+    pub fn may_be_applied(&self) -> bool {
+        self.progress == TimerRequestProgress::Sent && self.envelope.request.is_mutating()
+    }
+
     /// Creates a coroutine that sends a [`TimerRequest::Get`].
     pub fn get() -> Self {
         Self::new(TimerRequest::Get)
     }
 
+    /// Creates a coroutine that sends a [`TimerRequest::Status`].
+    pub fn status() -> Self {
+        Self::new(TimerRequest::Status)
+    }
+
+    /// Creates a coroutine that sends a
+    /// [`TimerRequest::Capabilities`].
+    pub fn capabilities() -> Self {
+        Self::new(TimerRequest::Capabilities)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Version`].
+    pub fn version() -> Self {
+        Self::new(TimerRequest::Version)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::List`].
+    pub fn list() -> Self {
+        Self::new(TimerRequest::List)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Create`].
+    pub fn create(name: impl ToString, config: TimerConfig) -> Self {
+        Self::new(TimerRequest::Create {
+            name: name.to_string(),
+            config,
+        })
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Delete`].
+    pub fn delete(name: impl ToString) -> Self {
+        Self::new(TimerRequest::Delete {
+            name: name.to_string(),
+        })
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::GetHistory`].
+    pub fn get_history(cursor: Option<u64>, limit: Option<usize>) -> Self {
+        Self::new(TimerRequest::GetHistory { cursor, limit })
+    }
+
+    /// Creates a coroutine that sends a
+    /// [`TimerRequest::StreamHistory`].
+    ///
+    /// Only the first page is returned: the next ones are received
+    /// with [`receive`](TimerRequestSend::receive), until a page has no
+    /// next cursor.
+    pub fn stream_history(cursor: Option<u64>) -> Self {
+        Self::new(TimerRequest::StreamHistory { cursor })
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::GetStats`].
+    pub fn get_stats() -> Self {
+        Self::new(TimerRequest::GetStats)
+    }
+
+    /// Creates a coroutine that sends a
+    /// [`TimerRequest::ExportStats`].
+    ///
+    /// Only the first page is returned: the next ones are received
+    /// with [`receive`](TimerRequestSend::receive), until a page has no
+    /// next cursor.
+    pub fn export_stats(cursor: Option<String>) -> Self {
+        Self::new(TimerRequest::ExportStats { cursor })
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Subscribe`].
+    pub fn subscribe(id: Option<String>) -> Self {
+        Self::new(TimerRequest::Subscribe { id })
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Ack`].
+    pub fn ack(seq: u64) -> Self {
+        Self::new(TimerRequest::Ack { seq })
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Extension`].
+    pub fn extension(name: impl ToString, payload: Value) -> Self {
+        Self::new(TimerRequest::Extension {
+            name: name.to_string(),
+            payload,
+        })
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Ping`].
+    pub fn ping() -> Self {
+        Self::new(TimerRequest::Ping)
+    }
+
     /// Creates a coroutine that sends a [`TimerRequest::Start`].
     pub fn start() -> Self {
         Self::new(TimerRequest::Start)
@@ -108,30 +429,75 @@ impl TimerRequestSend {
         Self::new(TimerRequest::Update)
     }
 
-    /// Creates a coroutine that sends a [`TimerRequest::Set`].
-    pub fn set(duration: usize) -> Self {
-        Self::new(TimerRequest::Set(duration))
+    /// Creates a coroutine that sends a [`TimerRequest::SetRemaining`].
+    pub fn set_remaining(secs: usize) -> Self {
+        Self::new(TimerRequest::SetRemaining(secs))
+    }
+
+    /// Creates a coroutine that sends a
+    /// [`TimerRequest::SetCycleDuration`].
+    pub fn set_cycle_duration(cycle: impl ToString, secs: usize) -> Self {
+        let cycle = cycle.to_string();
+        Self::new(TimerRequest::SetCycleDuration { cycle, secs })
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::SetConfig`].
+    pub fn set_config(config: TimerConfig) -> Self {
+        Self::new(TimerRequest::SetConfig(config))
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Reset`].
+    pub fn reset() -> Self {
+        Self::new(TimerRequest::Reset)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Toggle`].
+    pub fn toggle() -> Self {
+        Self::new(TimerRequest::Toggle)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Activity`].
+    pub fn activity() -> Self {
+        Self::new(TimerRequest::Activity)
     }
 
     /// Advances the coroutine by one step.
-    pub fn resume(&mut self, mut arg: Option<SocketOutput>) -> TimerRequestSendResult {
+    pub fn resume(&mut self, arg: Option<SocketOutput>) -> TimerRequestSendResult {
+        let result = self.step(arg);
+
+        if let TimerRequestSendResult::Err { err } = &result {
+            let event = match err {
+                TimerRequestSendError::Deserialize(_)
+                | TimerRequestSendError::Decompress(_)
+                | TimerRequestSendError::Checksum(_)
+                | TimerRequestSendError::Frame(_) => TimerRequestEvent::DecodeFailed,
+                _ => TimerRequestEvent::Failed,
+            };
+            self.record(event);
+        }
+
+        result
+    }
+
+    fn step(&mut self, mut arg: Option<SocketOutput>) -> TimerRequestSendResult {
         loop {
             match &mut self.state {
-                State::Serialize => match serde_json::to_vec(&self.request) {
-                    Ok(mut bytes) => {
-                        bytes.push(b'\n');
-                        self.state = State::Write(SocketWrite::new(bytes));
-                    }
-                    Err(err) => {
-                        let err = TimerRequestSendError::Serialize(err);
-                        return TimerRequestSendResult::Err { err };
+                State::Serialize => {
+                    self.record(TimerRequestEvent::Started);
+                    self.progress = TimerRequestProgress::Pending;
+
+                    match encode_request(&self.session, &self.envelope) {
+                        Ok(bytes) => self.state = State::Write(SocketWrite::new(bytes)),
+                        Err(err) => return TimerRequestSendResult::Err { err },
                     }
-                },
+                }
                 State::Write(w) => match w.resume(arg.take()) {
-                    SocketWriteResult::Ok { .. } => {
-                        self.state = State::Read(SocketRead::default());
+                    SocketWriteResult::Ok { n, .. } => {
+                        self.state = State::Read;
+                        self.record(TimerRequestEvent::Wrote(n));
                     }
                     SocketWriteResult::Io { input } => {
+                        self.progress = TimerRequestProgress::Sent;
                         return TimerRequestSendResult::Io { input };
                     }
                     SocketWriteResult::Eof => {
@@ -143,36 +509,136 @@ impl TimerRequestSend {
                         return TimerRequestSendResult::Err { err };
                     }
                 },
-                State::Read(r) => match r.resume(arg.take()) {
-                    SocketReadResult::Ok { mut buf, n } => {
-                        buf.truncate(n);
-                        self.state = State::Deserialize(buf);
-                    }
-                    SocketReadResult::Io { input } => {
+                State::Read => match arg.take() {
+                    None => {
+                        // the response may have been received along
+                        // with the previous one
+                        match self.decoder.next_frame() {
+                            Ok(Some(frame)) => {
+                                self.state = State::Deserialize(frame);
+                                continue;
+                            }
+                            Ok(None) => (),
+                            Err(err) => {
+                                let err = TimerRequestSendError::Frame(err);
+                                return TimerRequestSendResult::Err { err };
+                            }
+                        }
+
+                        let mut buf = core::mem::take(&mut self.buf);
+                        if buf.is_empty() {
+                            buf.resize(DEFAULT_READ_BUFFER_SIZE, 0);
+                        }
+                        let input = SocketInput::Read { buf };
                         return TimerRequestSendResult::Io { input };
                     }
-                    SocketReadResult::Eof => {
+                    Some(SocketOutput::Read { n: 0, buf }) => {
+                        self.buf = buf;
                         let err = TimerRequestSendError::ReadEof;
                         return TimerRequestSendResult::Err { err };
                     }
-                    SocketReadResult::Err { err } => {
-                        let err = TimerRequestSendError::Read(err);
+                    Some(SocketOutput::Read { buf, n }) => {
+                        self.decoder.push(&buf[..n]);
+                        self.buf = buf;
+                        self.record(TimerRequestEvent::Read(n));
+                        self.state = match self.decoder.next_frame() {
+                            Ok(Some(frame)) => State::Deserialize(frame),
+                            Ok(None) => State::Read,
+                            Err(err) => {
+                                let err = TimerRequestSendError::Frame(err);
+                                return TimerRequestSendResult::Err { err };
+                            }
+                        };
+                    }
+                    Some(output) => {
+                        let err = TimerRequestSendError::InvalidArg(output);
                         return TimerRequestSendResult::Err { err };
                     }
                 },
                 State::Deserialize(bytes) => {
-                    let bytes = bytes.trim_ascii_end();
-                    return match serde_json::from_slice(bytes) {
+                    return match decode_response(&self.session, core::mem::take(bytes)) {
                         Ok(response) => {
-                            trace!("timer response received: {response:?}");
+                            self.progress = TimerRequestProgress::Received;
+                            self.record(TimerRequestEvent::Received);
                             TimerRequestSendResult::Ok { response }
                         }
-                        Err(err) => TimerRequestSendResult::Err {
-                            err: TimerRequestSendError::Deserialize(err),
-                        },
+                        Err(err) => {
+                            // the malformed frame is consumed, so the
+                            // connection is still usable
+                            self.state = State::Serialize;
+                            TimerRequestSendResult::Err { err }
+                        }
                     };
                 }
             }
         }
     }
+
+    fn record(&mut self, event: TimerRequestEvent) {
+        self.metrics.record(event);
+
+        if let Some(hook) = self.hook {
+            hook(event);
+        }
+    }
+}
+
+/// Encodes the given request envelope into a frame of the given
+/// session.
+pub(crate) fn encode_request(
+    session: &Session,
+    envelope: &TimerEnvelope,
+) -> Result<Vec<u8>, TimerRequestSendError> {
+    let bytes = session
+        .codec
+        .encode(envelope)
+        .map_err(TimerRequestSendError::Serialize)?;
+    #[cfg(feature = "signing")]
+    let bytes = match &session.signing_key {
+        Some(key) => key.sign(bytes),
+        None => bytes,
+    };
+    let bytes = session.checksum.append(bytes);
+
+    Ok(session.framing().encode(bytes))
+}
+
+/// Decodes the given response frame of the given session.
+pub(crate) fn decode_response(
+    session: &Session,
+    bytes: Vec<u8>,
+) -> Result<TimerResponse, TimerRequestSendError> {
+    let bytes = session
+        .checksum
+        .verify(bytes)
+        .map_err(TimerRequestSendError::Checksum)?;
+    let bytes = session
+        .compression
+        .decompress(bytes)
+        .map_err(TimerRequestSendError::Decompress)?;
+    let response = session
+        .codec
+        .decode(&bytes)
+        .map_err(TimerRequestSendError::Deserialize)?;
+
+    trace!("timer response received: {response:?}");
+    Ok(response)
+}
+
+impl Coroutine for TimerRequestSend {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = TimerResponse;
+    type Error = TimerRequestSendError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<TimerResponse, SocketInput, TimerRequestSendError> {
+        match TimerRequestSend::resume(self, arg) {
+            TimerRequestSendResult::Ok { response } => CoroutineResult::Ok(response),
+            TimerRequestSendResult::Io { input } => CoroutineResult::Io(input),
+            TimerRequestSendResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
 }
@@ -0,0 +1,191 @@
+//! I/O-free coroutine to negotiate a session with a timer server.
+
+use alloc::vec::Vec;
+
+use io_socket::{
+    coroutines::{read::*, write::*},
+    io::{SocketInput, SocketOutput},
+};
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::{
+    coroutines::{Coroutine, CoroutineResult},
+    frame::{FrameDecoder, FrameError, Framing},
+    session::{ClientHello, ServerHello, Session},
+    timer::PROTOCOL_VERSION,
+};
+
+/// Error emitted by the [`TimerConnect`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimerConnectError {
+    #[error("Failed to serialize client hello")]
+    Serialize(#[source] serde_json::Error),
+    #[error("Failed to deserialize server hello")]
+    Deserialize(#[source] serde_json::Error),
+
+    #[error("Unsupported timer protocol revision {0}, expected {PROTOCOL_VERSION}")]
+    UnsupportedProtocol(u32),
+
+    #[error("Failed to decode hello frame")]
+    Frame(#[source] FrameError),
+
+    #[error("Reached EOF while receiving server hello")]
+    ReadEof,
+    #[error(transparent)]
+    Read(SocketReadError),
+
+    #[error("Reached unexpected EOF while sending client hello")]
+    WriteEof,
+    #[error(transparent)]
+    Write(SocketWriteError),
+}
+
+/// Result emitted on each step of the [`TimerConnect`] coroutine.
+#[derive(Debug)]
+pub enum TimerConnectResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok { session: Session },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerConnectError },
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    Serialize,
+    Write(SocketWrite),
+    Read(SocketRead),
+    Deserialize(Vec<u8>),
+}
+
+/// I/O-free coroutine to perform the client side of the handshake.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit [`SocketInput::Write`] with the JSON-encoded
+///    [`ClientHello`] line.
+/// 2. Emit [`SocketInput::Read`] until the JSON-encoded
+///    [`ServerHello`] line is fully received.
+/// 3. Return `Ok { session }`, unless the server speaks another
+///    revision of the protocol (see [`PROTOCOL_VERSION`]).
+///
+/// The returned session is meant to be given to the other client
+/// coroutines of the connection, through their `with_session`
+/// builder. The handshake is always line-delimited JSON, whatever the
+/// negotiated session.
+///
+/// [`resume`]: TimerConnect::resume
+#[derive(Clone, Debug)]
+pub struct TimerConnect {
+    hello: ClientHello,
+    state: State,
+    decoder: FrameDecoder,
+}
+
+impl TimerConnect {
+    /// Creates a new coroutine sending the given hello.
+    pub fn new(hello: ClientHello) -> Self {
+        trace!("client hello to send: {hello:?}");
+
+        Self {
+            hello,
+            state: State::Serialize,
+            decoder: FrameDecoder::new(),
+        }
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, mut arg: Option<SocketOutput>) -> TimerConnectResult {
+        loop {
+            match &mut self.state {
+                State::Serialize => match serde_json::to_vec(&self.hello) {
+                    Ok(bytes) => {
+                        let bytes = Framing::Line.encode(bytes);
+                        self.state = State::Write(SocketWrite::new(bytes));
+                    }
+                    Err(err) => {
+                        let err = TimerConnectError::Serialize(err);
+                        return TimerConnectResult::Err { err };
+                    }
+                },
+                State::Write(w) => match w.resume(arg.take()) {
+                    SocketWriteResult::Ok { .. } => {
+                        self.state = State::Read(SocketRead::default());
+                    }
+                    SocketWriteResult::Io { input } => {
+                        return TimerConnectResult::Io { input };
+                    }
+                    SocketWriteResult::Eof => {
+                        let err = TimerConnectError::WriteEof;
+                        return TimerConnectResult::Err { err };
+                    }
+                    SocketWriteResult::Err { err } => {
+                        let err = TimerConnectError::Write(err);
+                        return TimerConnectResult::Err { err };
+                    }
+                },
+                State::Read(r) => match r.resume(arg.take()) {
+                    SocketReadResult::Ok { buf, n } => {
+                        self.decoder.push(&buf[..n]);
+                        self.state = match self.decoder.next_frame() {
+                            Ok(Some(frame)) => State::Deserialize(frame),
+                            Ok(None) => State::Read(SocketRead::default()),
+                            Err(err) => {
+                                let err = TimerConnectError::Frame(err);
+                                return TimerConnectResult::Err { err };
+                            }
+                        };
+                    }
+                    SocketReadResult::Io { input } => {
+                        return TimerConnectResult::Io { input };
+                    }
+                    SocketReadResult::Eof => {
+                        let err = TimerConnectError::ReadEof;
+                        return TimerConnectResult::Err { err };
+                    }
+                    SocketReadResult::Err { err } => {
+                        let err = TimerConnectError::Read(err);
+                        return TimerConnectResult::Err { err };
+                    }
+                },
+                State::Deserialize(bytes) => {
+                    return match serde_json::from_slice::<ServerHello>(bytes) {
+                        Ok(hello) if hello.protocol != PROTOCOL_VERSION => {
+                            let err = TimerConnectError::UnsupportedProtocol(hello.protocol);
+                            TimerConnectResult::Err { err }
+                        }
+                        Ok(hello) => {
+                            debug!("server hello received: {hello:?}");
+                            let session = Session::from(hello);
+                            TimerConnectResult::Ok { session }
+                        }
+                        Err(err) => TimerConnectResult::Err {
+                            err: TimerConnectError::Deserialize(err),
+                        },
+                    };
+                }
+            }
+        }
+    }
+}
+
+impl Coroutine for TimerConnect {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = Session;
+    type Error = TimerConnectError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<Session, SocketInput, TimerConnectError> {
+        match TimerConnect::resume(self, arg) {
+            TimerConnectResult::Ok { session } => CoroutineResult::Ok(session),
+            TimerConnectResult::Io { input } => CoroutineResult::Io(input),
+            TimerConnectResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! I/O-free coroutine to bound a socket coroutine with a deadline.
+
+use io_socket::io::{SocketInput, SocketOutput};
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        now::{TimeNow, TimeNowError, TimeNowResult},
+    },
+    io::{TimeInput, TimeOutput},
+};
+
+/// I/O request emitted by the [`TimeDeadline`] coroutine.
+#[derive(Clone, Debug)]
+pub enum TimeDeadlineInput {
+    /// A time I/O request, to check the deadline.
+    Time(TimeInput),
+    /// A socket I/O request of the wrapped coroutine, along with the
+    /// number of seconds left before the deadline.
+    ///
+    /// Runtimes are expected to give up the I/O once the timeout
+    /// elapsed (for example using socket read and write timeouts),
+    /// then to resume with [`TimeDeadlineArg::TimedOut`].
+    Socket { input: SocketInput, timeout: u64 },
+}
+
+/// Argument passed back to [`TimeDeadline`] after any I/O.
+#[derive(Clone, Debug)]
+pub enum TimeDeadlineArg {
+    /// Response to a [`TimeInput`] request.
+    Time(TimeOutput),
+    /// Response to a [`SocketInput`] request.
+    Socket(SocketOutput),
+    /// The [`SocketInput`] request did not complete in time.
+    TimedOut,
+}
+
+impl From<TimeOutput> for TimeDeadlineArg {
+    fn from(output: TimeOutput) -> Self {
+        Self::Time(output)
+    }
+}
+
+impl From<SocketOutput> for TimeDeadlineArg {
+    fn from(output: SocketOutput) -> Self {
+        Self::Socket(output)
+    }
+}
+
+/// Error emitted by the [`TimeDeadline`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimeDeadlineError<E: core::error::Error + 'static> {
+    #[error("Invalid time deadline arg: {0:?}")]
+    InvalidArg(TimeDeadlineArg),
+    #[error("Reached deadline {0} before I/O completed")]
+    Timeout(u64),
+
+    #[error(transparent)]
+    TimeNow(TimeNowError),
+    #[error(transparent)]
+    Inner(E),
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    Resume,
+    Check(SocketInput, TimeNow),
+}
+
+/// I/O-free coroutine wrapping a socket coroutine (like
+/// [`TimerRequestSend`]), failing with
+/// [`TimeDeadlineError::Timeout`] instead of waiting forever for a
+/// dead peer.
+///
+/// Each call to [`Coroutine::resume`] advances one step:
+///
+/// 1. Resume the wrapped coroutine, and return its result unless it
+///    emits a [`SocketInput`] request.
+/// 2. Emit [`TimeInput::Now`] to check the deadline, and fail with
+///    [`TimeDeadlineError::Timeout`] if it is reached.
+/// 3. Emit [`TimeDeadlineInput::Socket`] with the time left, then
+///    resume the wrapped coroutine with the [`SocketOutput`].
+///
+/// [`TimerRequestSend`]: crate::coroutines::client::TimerRequestSend
+#[derive(Clone, Debug)]
+pub struct TimeDeadline<C> {
+    coroutine: C,
+    deadline: u64,
+    state: State,
+}
+
+impl<C> TimeDeadline<C> {
+    /// Wraps the given coroutine, which needs to terminate before
+    /// `deadline` (Unix epoch seconds).
+    pub fn new(coroutine: C, deadline: u64) -> Self {
+        Self {
+            coroutine,
+            deadline,
+            state: State::Resume,
+        }
+    }
+
+    /// Returns the deadline, in Unix epoch seconds.
+    pub fn deadline(&self) -> u64 {
+        self.deadline
+    }
+
+    /// Returns the wrapped coroutine.
+    pub fn into_inner(self) -> C {
+        self.coroutine
+    }
+}
+
+impl<C> Coroutine for TimeDeadline<C>
+where
+    C: Coroutine<Input = SocketInput, Output = SocketOutput>,
+    C::Error: core::error::Error + 'static,
+{
+    type Input = TimeDeadlineInput;
+    type Output = TimeDeadlineArg;
+    type Ok = C::Ok;
+    type Error = TimeDeadlineError<C::Error>;
+
+    fn resume(
+        &mut self,
+        mut arg: Option<TimeDeadlineArg>,
+    ) -> CoroutineResult<C::Ok, TimeDeadlineInput, TimeDeadlineError<C::Error>> {
+        loop {
+            match &mut self.state {
+                State::Resume => {
+                    let socket_arg = match arg.take() {
+                        None => None,
+                        Some(TimeDeadlineArg::Socket(output)) => Some(output),
+                        Some(TimeDeadlineArg::TimedOut) => {
+                            debug!("socket I/O timed out");
+                            let err = TimeDeadlineError::Timeout(self.deadline);
+                            return CoroutineResult::Err(err);
+                        }
+                        Some(a) => {
+                            let err = TimeDeadlineError::InvalidArg(a);
+                            return CoroutineResult::Err(err);
+                        }
+                    };
+
+                    match self.coroutine.resume(socket_arg) {
+                        CoroutineResult::Ok(ok) => return CoroutineResult::Ok(ok),
+                        CoroutineResult::Io(input) => {
+                            self.state = State::Check(input, TimeNow::new());
+                        }
+                        CoroutineResult::Err(err) => {
+                            return CoroutineResult::Err(TimeDeadlineError::Inner(err));
+                        }
+                    }
+                }
+                State::Check(input, now) => {
+                    let time_arg = match arg.take() {
+                        None => None,
+                        Some(TimeDeadlineArg::Time(output)) => Some(output),
+                        Some(a) => {
+                            let err = TimeDeadlineError::InvalidArg(a);
+                            return CoroutineResult::Err(err);
+                        }
+                    };
+
+                    match now.resume(time_arg) {
+                        TimeNowResult::Ok { secs, .. } if secs >= self.deadline => {
+                            debug!("deadline {} reached at {secs}", self.deadline);
+                            let err = TimeDeadlineError::Timeout(self.deadline);
+                            return CoroutineResult::Err(err);
+                        }
+                        TimeNowResult::Ok { secs, .. } => {
+                            let timeout = self.deadline - secs;
+                            trace!("wants socket I/O within {timeout}s");
+                            let input = input.clone();
+                            self.state = State::Resume;
+                            let input = TimeDeadlineInput::Socket { input, timeout };
+                            return CoroutineResult::Io(input);
+                        }
+                        TimeNowResult::Io { input } => {
+                            return CoroutineResult::Io(TimeDeadlineInput::Time(input));
+                        }
+                        TimeNowResult::Err { err } => {
+                            return CoroutineResult::Err(TimeDeadlineError::TimeNow(err));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! I/O-free coroutine to send the requests of a
+//! [`TimerRequestQueue`].
+
+use alloc::vec::Vec;
+
+use io_socket::io::{SocketInput, SocketOutput};
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::{TimerRequestSend, TimerRequestSendError, TimerRequestSendResult},
+    },
+    queue::TimerRequestQueue,
+    session::Session,
+    timer::TimerResponse,
+};
+
+/// Error emitted by the [`TimerQueueFlush`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimerQueueFlushError {
+    #[error("Timer server busy, retry after {0}ms")]
+    Busy(u64),
+
+    #[error(transparent)]
+    Send(TimerRequestSendError),
+}
+
+/// Result emitted on each step of the [`TimerQueueFlush`] coroutine.
+#[derive(Debug)]
+pub enum TimerQueueFlushResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok { responses: Vec<TimerResponse> },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerQueueFlushError },
+}
+
+/// I/O-free coroutine to send the queued requests of a
+/// [`TimerRequestQueue`] one by one, over a freshly established
+/// connection.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit the [`SocketInput`] requests to send the next queued
+///    request.
+/// 2. Remove the request from the queue once its response is
+///    received, then go back to 1.
+/// 3. Return `Ok { responses }` once the queue is empty, in the order
+///    of the requests.
+///
+/// Requests rejected by the server are removed from the queue as
+/// well, their [`TimerResponse::Err`] being part of the responses.
+/// On error (including [`TimerResponse::Busy`]), the request being
+/// sent and the following ones stay queued, so that they can be
+/// flushed again once reconnected.
+///
+/// [`resume`]: TimerQueueFlush::resume
+#[derive(Clone, Debug)]
+pub struct TimerQueueFlush {
+    session: Session,
+    send: Option<TimerRequestSend>,
+    responses: Vec<TimerResponse>,
+}
+
+impl Default for TimerQueueFlush {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerQueueFlush {
+    /// Creates a new coroutine.
+    pub fn new() -> Self {
+        Self {
+            session: Session::default(),
+            send: None,
+            responses: Vec::new(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = session;
+        self
+    }
+
+    /// Binds the coroutine to the given queue, so that it can be
+    /// driven through the [`Coroutine`] trait.
+    pub fn bind<'a>(&'a mut self, queue: &'a mut TimerRequestQueue) -> BoundTimerQueueFlush<'a> {
+        BoundTimerQueueFlush { flush: self, queue }
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(
+        &mut self,
+        queue: &mut TimerRequestQueue,
+        mut arg: Option<SocketOutput>,
+    ) -> TimerQueueFlushResult {
+        loop {
+            let send = match &mut self.send {
+                Some(send) => send,
+                None => {
+                    let Some(envelope) = queue.front() else {
+                        debug!("timer request queue flushed");
+                        let responses = core::mem::take(&mut self.responses);
+                        return TimerQueueFlushResult::Ok { responses };
+                    };
+
+                    trace!("flushing queued timer request {envelope:?}");
+                    let send = TimerRequestSend::new(envelope.clone()).with_session(self.session);
+                    self.send.insert(send)
+                }
+            };
+
+            let response = match send.resume(arg.take()) {
+                TimerRequestSendResult::Ok { response } => response,
+                TimerRequestSendResult::Io { input } => {
+                    return TimerQueueFlushResult::Io { input };
+                }
+                TimerRequestSendResult::Err { err } => {
+                    self.send = None;
+                    let err = TimerQueueFlushError::Send(err);
+                    return TimerQueueFlushResult::Err { err };
+                }
+            };
+
+            self.send = None;
+
+            if let TimerResponse::Busy { retry_after_ms } = response {
+                let err = TimerQueueFlushError::Busy(retry_after_ms);
+                return TimerQueueFlushResult::Err { err };
+            }
+
+            queue.pop();
+            self.responses.push(response);
+        }
+    }
+}
+
+/// A [`TimerQueueFlush`] bound to a queue, see
+/// [`TimerQueueFlush::bind`].
+#[derive(Debug)]
+pub struct BoundTimerQueueFlush<'a> {
+    flush: &'a mut TimerQueueFlush,
+    queue: &'a mut TimerRequestQueue,
+}
+
+impl Coroutine for BoundTimerQueueFlush<'_> {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = Vec<TimerResponse>;
+    type Error = TimerQueueFlushError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<Vec<TimerResponse>, SocketInput, TimerQueueFlushError> {
+        match self.flush.resume(self.queue, arg) {
+            TimerQueueFlushResult::Ok { responses } => CoroutineResult::Ok(responses),
+            TimerQueueFlushResult::Io { input } => CoroutineResult::Io(input),
+            TimerQueueFlushResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! I/O-free coroutine to fetch the whole history of a timer.
+
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use log::trace;
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::{TimerRequestSend, TimerRequestSendError, TimerRequestSendResult},
+    },
+    history::TimerHistoryEntry,
+    session::Session,
+    timer::{TimerError, TimerResponse},
+};
+
+/// Error emitted by the [`TimerHistoryFetch`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimerHistoryFetchError {
+    #[error("Timer history request rejected by the server")]
+    Rejected(#[source] TimerError),
+    #[error("Unexpected timer response {0:?}")]
+    UnexpectedResponse(Box<TimerResponse>),
+
+    #[error(transparent)]
+    Send(TimerRequestSendError),
+}
+
+/// Result emitted on each step of the [`TimerHistoryFetch`]
+/// coroutine.
+#[derive(Debug)]
+pub enum TimerHistoryFetchResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok { events: Vec<TimerHistoryEntry> },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerHistoryFetchError },
+}
+
+/// I/O-free coroutine to send [`TimerRequest::GetHistory`] requests,
+/// following the cursors of the returned pages over the same
+/// connection.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit the [`SocketInput`] requests to get the next history page.
+/// 2. Go back to 1 while the page has a next cursor.
+/// 3. Return `Ok { events }`, oldest first.
+///
+/// [`TimerRequest::GetHistory`]: crate::timer::TimerRequest::GetHistory
+/// [`resume`]: TimerHistoryFetch::resume
+#[derive(Clone, Debug)]
+pub struct TimerHistoryFetch {
+    since: Option<u64>,
+    session: Option<Session>,
+    timer: Option<String>,
+    limit: Option<usize>,
+    streamed: bool,
+    send: TimerRequestSend,
+    events: Vec<TimerHistoryEntry>,
+}
+
+impl TimerHistoryFetch {
+    /// Creates a new coroutine fetching the events with a sequence
+    /// number greater than or equal to `since`, or every remembered
+    /// event.
+    pub fn new(since: Option<u64>) -> Self {
+        Self {
+            since,
+            session: None,
+            timer: None,
+            limit: None,
+            streamed: false,
+            send: TimerRequestSend::get_history(since, None),
+            events: Vec::new(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self.send = self.request(self.since);
+        self
+    }
+
+    /// Fetches the history of the timer with the given name, instead
+    /// of the default one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.timer = Some(name.to_string());
+        self.send = self.request(self.since);
+        self
+    }
+
+    /// Requests pages of at most the given number of events, instead
+    /// of [`MAX_PAGE_SIZE`].
+    ///
+    /// [`MAX_PAGE_SIZE`]: crate::history::MAX_PAGE_SIZE
+    pub fn with_page_size(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self.send = self.request(self.since);
+        self
+    }
+
+    /// Fetches the whole history with a single
+    /// [`TimerRequest::StreamHistory`] request, instead of one
+    /// [`TimerRequest::GetHistory`] request per page.
+    ///
+    /// The server then streams the pages without waiting for the
+    /// client to request them. The page size is ignored.
+    ///
+    /// [`TimerRequest::StreamHistory`]: crate::timer::TimerRequest::StreamHistory
+    /// [`TimerRequest::GetHistory`]: crate::timer::TimerRequest::GetHistory
+    pub fn streamed(mut self) -> Self {
+        self.streamed = true;
+        self.send = self.request(self.since);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, mut arg: Option<SocketOutput>) -> TimerHistoryFetchResult {
+        loop {
+            let page = match self.send.resume(arg.take()) {
+                TimerRequestSendResult::Ok {
+                    response: TimerResponse::History(page),
+                } => page,
+                TimerRequestSendResult::Ok {
+                    response: TimerResponse::Err(err),
+                } => {
+                    let err = TimerHistoryFetchError::Rejected(err);
+                    return TimerHistoryFetchResult::Err { err };
+                }
+                TimerRequestSendResult::Ok { response } => {
+                    let err = TimerHistoryFetchError::UnexpectedResponse(Box::new(response));
+                    return TimerHistoryFetchResult::Err { err };
+                }
+                TimerRequestSendResult::Io { input } => {
+                    return TimerHistoryFetchResult::Io { input };
+                }
+                TimerRequestSendResult::Err { err } => {
+                    let err = TimerHistoryFetchError::Send(err);
+                    return TimerHistoryFetchResult::Err { err };
+                }
+            };
+
+            self.events.extend(page.events);
+
+            let Some(cursor) = page.next else {
+                let events = core::mem::take(&mut self.events);
+                return TimerHistoryFetchResult::Ok { events };
+            };
+
+            trace!("fetching next timer history page from {cursor}");
+
+            if self.streamed {
+                self.send.receive();
+            } else {
+                self.send = self.request(Some(cursor));
+            }
+        }
+    }
+
+    fn request(&self, cursor: Option<u64>) -> TimerRequestSend {
+        let mut send = if self.streamed {
+            TimerRequestSend::stream_history(cursor)
+        } else {
+            TimerRequestSend::get_history(cursor, self.limit)
+        };
+
+        if let Some(session) = self.session {
+            send = send.with_session(session);
+        }
+
+        if let Some(name) = &self.timer {
+            send = send.with_timer(name);
+        }
+
+        send
+    }
+}
+
+impl Coroutine for TimerHistoryFetch {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = Vec<TimerHistoryEntry>;
+    type Error = TimerHistoryFetchError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<Vec<TimerHistoryEntry>, SocketInput, TimerHistoryFetchError> {
+        match TimerHistoryFetch::resume(self, arg) {
+            TimerHistoryFetchResult::Ok { events } => CoroutineResult::Ok(events),
+            TimerHistoryFetchResult::Io { input } => CoroutineResult::Io(input),
+            TimerHistoryFetchResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
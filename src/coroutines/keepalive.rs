@@ -0,0 +1,242 @@
+//! I/O-free coroutine to send timer requests over a long-lived
+//! connection, pinging the server when idle.
+
+use alloc::boxed::Box;
+
+use io_socket::io::{SocketInput, SocketOutput};
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::{TimerRequestSend, TimerRequestSendError, TimerRequestSendResult},
+        sleep::{TimeSleep, TimeSleepError, TimeSleepResult},
+    },
+    io::{TimeInput, TimeOutput},
+    session::{Keepalive, Session},
+    timer::{TimerEnvelope, TimerRequest, TimerResponse},
+};
+
+/// I/O request emitted by the [`TimerKeepalive`] coroutine.
+#[derive(Clone, Debug)]
+pub enum TimerKeepaliveInput {
+    /// A time I/O request, to wait idle until the next heartbeat.
+    ///
+    /// Runtimes are expected to interrupt the sleep as soon as a
+    /// request needs to be sent, then to resume with
+    /// [`TimerKeepaliveArg::Request`].
+    Time(TimeInput),
+    /// A socket I/O request, along with the number of seconds after
+    /// which the server is considered dead.
+    ///
+    /// Runtimes are expected to give up the I/O once the timeout
+    /// elapsed (for example using socket read and write timeouts),
+    /// then to resume with [`TimerKeepaliveArg::TimedOut`].
+    Socket { input: SocketInput, timeout: u64 },
+}
+
+/// Argument passed back to [`TimerKeepalive`] after any I/O.
+#[derive(Clone, Debug)]
+pub enum TimerKeepaliveArg {
+    /// Response to a [`TimeInput`] request.
+    Time(TimeOutput),
+    /// Response to a [`SocketInput`] request.
+    Socket(SocketOutput),
+    /// The [`SocketInput`] request did not complete in time.
+    TimedOut,
+    /// A request to send, in place of the [`TimeInput`] response.
+    Request(TimerEnvelope),
+}
+
+impl From<TimeOutput> for TimerKeepaliveArg {
+    fn from(output: TimeOutput) -> Self {
+        Self::Time(output)
+    }
+}
+
+impl From<SocketOutput> for TimerKeepaliveArg {
+    fn from(output: SocketOutput) -> Self {
+        Self::Socket(output)
+    }
+}
+
+/// Error emitted by the [`TimerKeepalive`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimerKeepaliveError {
+    #[error("Invalid timer keepalive arg: {0:?}")]
+    InvalidArg(TimerKeepaliveArg),
+    #[error("Timer server did not answer within {0}s")]
+    Timeout(u64),
+    #[error("Unexpected timer heartbeat response {0:?}")]
+    UnexpectedResponse(Box<TimerResponse>),
+
+    #[error(transparent)]
+    Send(TimerRequestSendError),
+    #[error(transparent)]
+    Sleep(TimeSleepError),
+}
+
+/// Result emitted on each step of the [`TimerKeepalive`] coroutine.
+#[derive(Debug)]
+pub enum TimerKeepaliveResult {
+    /// A request was sent, and its response received. The coroutine
+    /// can be resumed without argument to wait idle again.
+    Ok { response: TimerResponse },
+    /// An I/O needs to be performed to make the coroutine progress.
+    Io { input: TimerKeepaliveInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerKeepaliveError },
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    Idle(TimeSleep),
+    Ping,
+    Request,
+}
+
+/// I/O-free coroutine to send requests over a long-lived connection,
+/// interleaved with [`TimerRequest::Ping`] heartbeats.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit [`TimeInput::Sleep`] for the heartbeat interval.
+/// 2. If resumed with [`TimerKeepaliveArg::Request`], emit the
+///    [`SocketInput`] requests to send it, then return
+///    `Ok { response }`.
+/// 3. Otherwise once the sleep completed, emit the [`SocketInput`]
+///    requests to ping the server, then go back to 1.
+///
+/// Every socket I/O comes with the keepalive timeout, so that dead
+/// servers are detected within the timeout, failing with
+/// [`TimerKeepaliveError::Timeout`].
+///
+/// [`resume`]: TimerKeepalive::resume
+#[derive(Clone, Debug)]
+pub struct TimerKeepalive {
+    keepalive: Keepalive,
+    send: TimerRequestSend,
+    state: State,
+}
+
+impl TimerKeepalive {
+    /// Creates a new coroutine using the given heartbeat settings,
+    /// typically the ones negotiated at handshake (see
+    /// [`Session::keepalive`]).
+    pub fn new(keepalive: Keepalive) -> Self {
+        Self {
+            keepalive,
+            send: TimerRequestSend::ping(),
+            state: State::Idle(TimeSleep::new(keepalive.interval)),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, mut arg: Option<TimerKeepaliveArg>) -> TimerKeepaliveResult {
+        loop {
+            match &mut self.state {
+                State::Idle(sleep) => {
+                    let time_arg = match arg.take() {
+                        None => None,
+                        Some(TimerKeepaliveArg::Time(output)) => Some(output),
+                        Some(TimerKeepaliveArg::Request(envelope)) => {
+                            trace!("interrupt idle wait to send {envelope:?}");
+                            self.send.send(envelope);
+                            self.state = State::Request;
+                            continue;
+                        }
+                        Some(a) => {
+                            let err = TimerKeepaliveError::InvalidArg(a);
+                            return TimerKeepaliveResult::Err { err };
+                        }
+                    };
+
+                    match sleep.resume(time_arg) {
+                        TimeSleepResult::Ok => {
+                            debug!("connection idle, sending heartbeat");
+                            self.send.send(TimerRequest::Ping);
+                            self.state = State::Ping;
+                        }
+                        TimeSleepResult::Io { input } => {
+                            let input = TimerKeepaliveInput::Time(input);
+                            return TimerKeepaliveResult::Io { input };
+                        }
+                        TimeSleepResult::Err { err } => {
+                            let err = TimerKeepaliveError::Sleep(err);
+                            return TimerKeepaliveResult::Err { err };
+                        }
+                    }
+                }
+                State::Ping | State::Request => {
+                    let socket_arg = match arg.take() {
+                        None => None,
+                        Some(TimerKeepaliveArg::Socket(output)) => Some(output),
+                        Some(TimerKeepaliveArg::TimedOut) => {
+                            let err = TimerKeepaliveError::Timeout(self.keepalive.timeout);
+                            return TimerKeepaliveResult::Err { err };
+                        }
+                        Some(a) => {
+                            let err = TimerKeepaliveError::InvalidArg(a);
+                            return TimerKeepaliveResult::Err { err };
+                        }
+                    };
+
+                    let response = match self.send.resume(socket_arg) {
+                        TimerRequestSendResult::Ok { response } => response,
+                        TimerRequestSendResult::Io { input } => {
+                            let timeout = self.keepalive.timeout;
+                            let input = TimerKeepaliveInput::Socket { input, timeout };
+                            return TimerKeepaliveResult::Io { input };
+                        }
+                        TimerRequestSendResult::Err { err } => {
+                            let err = TimerKeepaliveError::Send(err);
+                            return TimerKeepaliveResult::Err { err };
+                        }
+                    };
+
+                    let ping = matches!(self.state, State::Ping);
+                    self.state = State::Idle(TimeSleep::new(self.keepalive.interval));
+
+                    if !ping {
+                        return TimerKeepaliveResult::Ok { response };
+                    }
+
+                    if response != TimerResponse::Pong {
+                        let err = TimerKeepaliveError::UnexpectedResponse(Box::new(response));
+                        return TimerKeepaliveResult::Err { err };
+                    }
+
+                    trace!("heartbeat answered");
+                }
+            }
+        }
+    }
+}
+
+impl Coroutine for TimerKeepalive {
+    type Input = TimerKeepaliveInput;
+    type Output = TimerKeepaliveArg;
+    type Ok = TimerResponse;
+    type Error = TimerKeepaliveError;
+
+    fn resume(
+        &mut self,
+        arg: Option<TimerKeepaliveArg>,
+    ) -> CoroutineResult<TimerResponse, TimerKeepaliveInput, TimerKeepaliveError> {
+        match TimerKeepalive::resume(self, arg) {
+            TimerKeepaliveResult::Ok { response } => CoroutineResult::Ok(response),
+            TimerKeepaliveResult::Io { input } => CoroutineResult::Io(input),
+            TimerKeepaliveResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
@@ -5,13 +5,97 @@
 //! need to be processed by runtimes in order to continue their
 //! progression.
 //!
+//! Every coroutine implements the [`Coroutine`] trait, so that
+//! generic loops can drive any of them.
+//!
 //! [`TimeInput`]: crate::io::TimeInput
 //! [`SocketInput`]: io_socket::io::SocketInput
 
+#[cfg(feature = "timer")]
+pub mod accept;
 #[cfg(feature = "timer")]
 pub mod client;
+#[cfg(feature = "timer")]
+pub mod connect;
+#[cfg(feature = "timer")]
+pub mod deadline;
+#[cfg(feature = "timer")]
+pub mod flush;
+#[cfg(feature = "timer")]
+pub mod history;
+#[cfg(feature = "timer")]
+pub mod keepalive;
 pub mod now;
 #[cfg(feature = "timer")]
+pub mod pipeline;
+#[cfg(feature = "timer")]
+pub mod poll;
+#[cfg(feature = "timer")]
+pub mod push;
+#[cfg(feature = "timer")]
+pub mod raw;
+#[cfg(feature = "timer")]
+pub mod reconnect;
+#[cfg(feature = "timer")]
+pub mod registry;
+#[cfg(feature = "timer")]
+pub mod request;
+#[cfg(feature = "timer")]
+pub mod serve;
+#[cfg(feature = "timer")]
 pub mod server;
 pub mod sleep;
 pub mod sleep_until;
+#[cfg(feature = "timer")]
+pub mod stream;
+#[cfg(feature = "timer")]
+pub mod tick;
+#[cfg(feature = "timer")]
+pub mod toggle;
+
+/// Result emitted on each step of a [`Coroutine`].
+#[derive(Clone, Debug)]
+pub enum CoroutineResult<T, I, E> {
+    /// The coroutine has successfully terminated its progression.
+    Ok(T),
+    /// An I/O needs to be performed to make the coroutine progress.
+    Io(I),
+    /// The coroutine encountered an unrecoverable error.
+    Err(E),
+}
+
+/// Uniform interface of the I/O-free coroutines.
+///
+/// Coroutines also expose an inherent `resume` method returning their
+/// own result enum, which takes precedence over this trait method.
+/// The trait is meant for generic loops:
+///
+/// ```rust,ignore
+/// fn run<C: Coroutine>(mut coroutine: C, mut io: impl FnMut(C::Input) -> C::Output) -> Result<C::Ok, C::Error> {
+///     let mut arg = None;
+///
+///     loop {
+///         match Coroutine::resume(&mut coroutine, arg.take()) {
+///             CoroutineResult::Ok(ok) => return Ok(ok),
+///             CoroutineResult::Io(input) => arg = Some(io(input)),
+///             CoroutineResult::Err(err) => return Err(err),
+///         }
+///     }
+/// }
+/// ```
+pub trait Coroutine {
+    /// The I/O request emitted by the coroutine.
+    type Input;
+    /// The I/O response expected by the coroutine.
+    type Output;
+    /// The value returned once the coroutine terminates.
+    type Ok;
+    /// The error emitted by the coroutine.
+    type Error;
+
+    /// Advances the coroutine by one step.
+    fn resume(
+        &mut self,
+        arg: Option<Self::Output>,
+    ) -> CoroutineResult<Self::Ok, Self::Input, Self::Error>;
+}
@@ -3,7 +3,10 @@
 use log::{debug, trace};
 use thiserror::Error;
 
-use crate::io::{TimeInput, TimeOutput};
+use crate::{
+    coroutines::{Coroutine, CoroutineResult},
+    io::{TimeInput, TimeOutput},
+};
 
 /// Error emitted by the [`TimeNow`] coroutine.
 #[derive(Clone, Debug, Error)]
@@ -57,3 +60,21 @@ impl TimeNow {
         }
     }
 }
+
+impl Coroutine for TimeNow {
+    type Input = TimeInput;
+    type Output = TimeOutput;
+    type Ok = (u64, u32);
+    type Error = TimeNowError;
+
+    fn resume(
+        &mut self,
+        arg: Option<TimeOutput>,
+    ) -> CoroutineResult<(u64, u32), TimeInput, TimeNowError> {
+        match TimeNow::resume(self, arg) {
+            TimeNowResult::Ok { secs, nanos } => CoroutineResult::Ok((secs, nanos)),
+            TimeNowResult::Io { input } => CoroutineResult::Io(input),
+            TimeNowResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! I/O-free coroutine to send several timer requests at once and
+//! receive their responses.
+
+use alloc::{
+    collections::VecDeque,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_socket::{
+    coroutines::{read::*, write::*},
+    io::{SocketInput, SocketOutput},
+};
+use log::trace;
+
+use crate::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::{TimerRequestSendError, decode_response, encode_request},
+    },
+    frame::FrameDecoder,
+    session::Session,
+    timer::{TimerConfig, TimerEnvelope, TimerRequest, TimerResponse},
+};
+
+/// Result emitted on each step of the [`TimerRequestPipeline`]
+/// coroutine.
+#[derive(Debug)]
+pub enum TimerRequestPipelineResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok { responses: Vec<TimerResponse> },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerRequestSendError },
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    Serialize,
+    Write(SocketWrite),
+    Read(SocketRead),
+}
+
+/// I/O-free coroutine to send several [`TimerRequest`]s to a server
+/// in a single write, then receive their [`TimerResponse`]s in order
+/// over the same connection.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit [`SocketInput::Write`] with the encoded request frames.
+/// 2. Emit [`SocketInput::Read`] until one response frame per request
+///    is fully received.
+/// 3. Return `Ok { responses }`, in the order of the requests.
+///
+/// This saves round trips for sequences like stop, set config then
+/// start. Requests are applied one after the other by the server,
+/// which keeps answering the next ones even if a request fails.
+///
+/// Since every request is written at once, pipelines are not suited
+/// to datagram sessions.
+///
+/// [`TimerRequest`]: crate::timer::TimerRequest
+/// [`resume`]: TimerRequestPipeline::resume
+#[derive(Clone, Debug)]
+pub struct TimerRequestPipeline {
+    envelopes: Vec<TimerEnvelope>,
+    session: Session,
+    state: State,
+    decoder: FrameDecoder,
+    frames: VecDeque<Vec<u8>>,
+    responses: Vec<TimerResponse>,
+}
+
+impl TimerRequestPipeline {
+    /// Creates a new coroutine sending the given requests, or
+    /// request envelopes.
+    pub fn new(requests: impl IntoIterator<Item = impl Into<TimerEnvelope>>) -> Self {
+        let envelopes: Vec<TimerEnvelope> = requests.into_iter().map(Into::into).collect();
+        trace!("timer requests to pipeline: {envelopes:?}");
+
+        Self {
+            envelopes,
+            session: Session::default(),
+            state: State::Serialize,
+            decoder: FrameDecoder::new(),
+            frames: VecDeque::new(),
+            responses: Vec::new(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.decoder.set_framing(session.framing());
+        self.session = session;
+        self
+    }
+
+    /// Rejects response frames larger than the given number of
+    /// bytes, instead of [`DEFAULT_MAX_FRAME_SIZE`].
+    ///
+    /// [`DEFAULT_MAX_FRAME_SIZE`]: crate::frame::DEFAULT_MAX_FRAME_SIZE
+    pub fn with_max_frame_size(mut self, max: usize) -> Self {
+        self.decoder.set_max_frame_size(max);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, mut arg: Option<SocketOutput>) -> TimerRequestPipelineResult {
+        loop {
+            match &mut self.state {
+                State::Serialize if self.envelopes.is_empty() => {
+                    return TimerRequestPipelineResult::Ok {
+                        responses: Vec::new(),
+                    };
+                }
+                State::Serialize => {
+                    let mut bytes = Vec::new();
+
+                    for envelope in &self.envelopes {
+                        match encode_request(&self.session, envelope) {
+                            Ok(frame) => bytes.extend(frame),
+                            Err(err) => return TimerRequestPipelineResult::Err { err },
+                        }
+                    }
+
+                    self.state = State::Write(SocketWrite::new(bytes));
+                }
+                State::Write(w) => match w.resume(arg.take()) {
+                    SocketWriteResult::Ok { .. } => {
+                        self.state = State::Read(SocketRead::default());
+                    }
+                    SocketWriteResult::Io { input } => {
+                        return TimerRequestPipelineResult::Io { input };
+                    }
+                    SocketWriteResult::Eof => {
+                        let err = TimerRequestSendError::WriteEof;
+                        return TimerRequestPipelineResult::Err { err };
+                    }
+                    SocketWriteResult::Err { err } => {
+                        let err = TimerRequestSendError::Write(err);
+                        return TimerRequestPipelineResult::Err { err };
+                    }
+                },
+                State::Read(_) if self.responses.len() == self.envelopes.len() => {
+                    let responses = core::mem::take(&mut self.responses);
+                    return TimerRequestPipelineResult::Ok { responses };
+                }
+                State::Read(_) if !self.frames.is_empty() => {
+                    let frame = self.frames.pop_front().unwrap_or_default();
+                    match decode_response(&self.session, frame) {
+                        Ok(response) => self.responses.push(response),
+                        Err(err) => return TimerRequestPipelineResult::Err { err },
+                    }
+                }
+                State::Read(r) => match r.resume(arg.take()) {
+                    SocketReadResult::Ok { buf, n } => {
+                        self.decoder.push(&buf[..n]);
+                        loop {
+                            match self.decoder.next_frame() {
+                                Ok(Some(frame)) => self.frames.push_back(frame),
+                                Ok(None) => break,
+                                Err(err) => {
+                                    let err = TimerRequestSendError::Frame(err);
+                                    return TimerRequestPipelineResult::Err { err };
+                                }
+                            }
+                        }
+                        self.state = State::Read(SocketRead::default());
+                    }
+                    SocketReadResult::Io { input } => {
+                        return TimerRequestPipelineResult::Io { input };
+                    }
+                    SocketReadResult::Eof => {
+                        let err = TimerRequestSendError::ReadEof;
+                        return TimerRequestPipelineResult::Err { err };
+                    }
+                    SocketReadResult::Err { err } => {
+                        let err = TimerRequestSendError::Read(err);
+                        return TimerRequestPipelineResult::Err { err };
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl Coroutine for TimerRequestPipeline {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = Vec<TimerResponse>;
+    type Error = TimerRequestSendError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<Vec<TimerResponse>, SocketInput, TimerRequestSendError> {
+        match TimerRequestPipeline::resume(self, arg) {
+            TimerRequestPipelineResult::Ok { responses } => CoroutineResult::Ok(responses),
+            TimerRequestPipelineResult::Io { input } => CoroutineResult::Io(input),
+            TimerRequestPipelineResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
+
+/// Fluent builder of the requests of a [`TimerRequestPipeline`].
+///
+/// Requests are chained, then sent at once:
+///
+/// ```rust,ignore
+/// let responses = client
+///     .batch()
+///     .stop()
+///     .set_config(config)
+///     .start()
+///     .commit()?;
+/// ```
+///
+/// Batches built from a client (see [`StdClient::batch`] and
+/// [`TokioClient::batch`]) are committed over its connection, using
+/// its session and its timer. Batches built with [`TimerBatch::new`]
+/// are turned into a coroutine with [`into_pipeline`].
+///
+/// [`StdClient::batch`]: crate::client::std::StdClient::batch
+/// [`TokioClient::batch`]: crate::client::tokio::TokioClient::batch
+/// [`into_pipeline`]: TimerBatch::into_pipeline
+#[derive(Clone, Debug)]
+pub struct TimerBatch<C = ()> {
+    client: C,
+    timer: Option<String>,
+    envelopes: Vec<TimerEnvelope>,
+}
+
+impl Default for TimerBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerBatch {
+    /// Creates a new batch, without any request.
+    pub fn new() -> Self {
+        Self::on(())
+    }
+}
+
+impl<C> TimerBatch<C> {
+    pub(crate) fn on(client: C) -> Self {
+        Self {
+            client,
+            timer: None,
+            envelopes: Vec::new(),
+        }
+    }
+
+    /// Targets the timer with the given name with the next requests,
+    /// instead of the default one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.timer = Some(name.to_string());
+        self
+    }
+
+    /// Adds the given request, or request envelope.
+    pub fn request(mut self, request: impl Into<TimerEnvelope>) -> Self {
+        let mut envelope = request.into();
+        if envelope.timer.is_none() {
+            envelope.timer.clone_from(&self.timer);
+        }
+        self.envelopes.push(envelope);
+        self
+    }
+
+    /// Adds a [`TimerRequest::Get`].
+    pub fn get(self) -> Self {
+        self.request(TimerRequest::Get)
+    }
+
+    /// Adds a [`TimerRequest::Start`].
+    pub fn start(self) -> Self {
+        self.request(TimerRequest::Start)
+    }
+
+    /// Adds a [`TimerRequest::Stop`].
+    pub fn stop(self) -> Self {
+        self.request(TimerRequest::Stop)
+    }
+
+    /// Adds a [`TimerRequest::Pause`].
+    pub fn pause(self) -> Self {
+        self.request(TimerRequest::Pause)
+    }
+
+    /// Adds a [`TimerRequest::Resume`].
+    pub fn resume_timer(self) -> Self {
+        self.request(TimerRequest::Resume)
+    }
+
+    /// Adds a [`TimerRequest::Reset`].
+    pub fn reset(self) -> Self {
+        self.request(TimerRequest::Reset)
+    }
+
+    /// Adds a [`TimerRequest::Activity`].
+    pub fn activity(self) -> Self {
+        self.request(TimerRequest::Activity)
+    }
+
+    /// Adds a [`TimerRequest::SetRemaining`].
+    pub fn set_remaining(self, secs: usize) -> Self {
+        self.request(TimerRequest::SetRemaining(secs))
+    }
+
+    /// Adds a [`TimerRequest::SetCycleDuration`].
+    pub fn set_cycle_duration(self, cycle: impl ToString, secs: usize) -> Self {
+        let cycle = cycle.to_string();
+        self.request(TimerRequest::SetCycleDuration { cycle, secs })
+    }
+
+    /// Adds a [`TimerRequest::SetConfig`].
+    pub fn set_config(self, config: TimerConfig) -> Self {
+        self.request(TimerRequest::SetConfig(config))
+    }
+
+    /// Returns the requests added so far.
+    pub fn envelopes(&self) -> &[TimerEnvelope] {
+        &self.envelopes
+    }
+
+    /// Turns the batch into a coroutine sending its requests.
+    pub fn into_pipeline(self) -> TimerRequestPipeline {
+        let (_, envelopes) = self.into_parts();
+        TimerRequestPipeline::new(envelopes)
+    }
+
+    pub(crate) fn into_parts(self) -> (C, Vec<TimerEnvelope>) {
+        (self.client, self.envelopes)
+    }
+}
@@ -0,0 +1,265 @@
+//! I/O-free coroutine to poll a timer until it changes.
+
+use alloc::{boxed::Box, string::ToString};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::{TimerRequestSend, TimerRequestSendError, TimerRequestSendResult},
+        sleep::{TimeSleep, TimeSleepError, TimeSleepResult},
+    },
+    io::{TimeInput, TimeOutput},
+    session::Session,
+    timer::{TimerEnvelope, TimerError, TimerRequest, TimerResponse, TimerSnapshot},
+};
+
+/// The default number of seconds between two polls.
+pub const DEFAULT_POLL_INTERVAL: u64 = 1;
+
+/// I/O request emitted by the [`TimerPoll`] coroutine.
+#[derive(Clone, Debug)]
+pub enum TimerPollInput {
+    /// A socket I/O request, to get the timer.
+    Socket(SocketInput),
+    /// A time I/O request, to wait between two polls.
+    Time(TimeInput),
+}
+
+/// Argument passed back to [`TimerPoll`] after any I/O.
+#[derive(Clone, Debug)]
+pub enum TimerPollArg {
+    /// Response to a [`SocketInput`] request.
+    Socket(SocketOutput),
+    /// Response to a [`TimeInput`] request.
+    Time(TimeOutput),
+}
+
+impl From<SocketOutput> for TimerPollArg {
+    fn from(output: SocketOutput) -> Self {
+        Self::Socket(output)
+    }
+}
+
+impl From<TimeOutput> for TimerPollArg {
+    fn from(output: TimeOutput) -> Self {
+        Self::Time(output)
+    }
+}
+
+/// Error emitted by the [`TimerPoll`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimerPollError {
+    #[error("Invalid timer poll arg: {0:?}")]
+    InvalidArg(TimerPollArg),
+    #[error("Timer poll rejected by the server")]
+    Rejected(#[source] TimerError),
+    #[error("Unexpected timer response {0:?}")]
+    UnexpectedResponse(Box<TimerResponse>),
+
+    #[error(transparent)]
+    Send(TimerRequestSendError),
+    #[error(transparent)]
+    Sleep(TimeSleepError),
+}
+
+/// Result emitted on each step of the [`TimerPoll`] coroutine.
+#[derive(Debug)]
+pub enum TimerPollResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok { snapshot: TimerSnapshot },
+    /// An I/O needs to be performed to make the coroutine progress.
+    Io { input: TimerPollInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerPollError },
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    Get,
+    Sleep(TimeSleep),
+}
+
+/// I/O-free coroutine to poll a timer with [`TimerRequest::Get`]
+/// until its state or its cycle changes.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit the [`SocketInput`] requests to get the timer.
+/// 2. Return `Ok { snapshot }` if the timer state or cycle differs
+///    from the previous snapshot.
+/// 3. Otherwise emit [`TimeInput::Sleep`] for the poll interval, then
+///    go back to 1.
+///
+/// Without previous snapshot (see [`with_snapshot`]), the first poll
+/// is only used as reference. This is meant for clients which do not
+/// subscribe to timer events (see [`TimerRequest::Subscribe`]).
+///
+/// [`TimerRequest::Get`]: crate::timer::TimerRequest::Get
+/// [`TimerRequest::Subscribe`]: crate::timer::TimerRequest::Subscribe
+/// [`resume`]: TimerPoll::resume
+/// [`with_snapshot`]: TimerPoll::with_snapshot
+#[derive(Clone, Debug)]
+pub struct TimerPoll {
+    envelope: TimerEnvelope,
+    send: TimerRequestSend,
+    interval: u64,
+    snapshot: Option<TimerSnapshot>,
+    state: State,
+}
+
+impl Default for TimerPoll {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerPoll {
+    /// Creates a new coroutine.
+    pub fn new() -> Self {
+        let envelope = TimerEnvelope::new(TimerRequest::Get);
+
+        Self {
+            send: TimerRequestSend::new(envelope.clone()),
+            envelope,
+            interval: DEFAULT_POLL_INTERVAL,
+            snapshot: None,
+            state: State::Get,
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Polls the timer with the given name, instead of the default
+    /// one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.envelope = self.envelope.with_timer(name);
+        self.send.send(self.envelope.clone());
+        self
+    }
+
+    /// Waits the given number of seconds between two polls, instead
+    /// of [`DEFAULT_POLL_INTERVAL`].
+    pub fn with_interval(mut self, secs: u64) -> Self {
+        self.interval = secs;
+        self
+    }
+
+    /// Compares the first poll with the given snapshot, typically
+    /// returned by a previous [`TimerPoll`].
+    pub fn with_snapshot(mut self, snapshot: TimerSnapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, mut arg: Option<TimerPollArg>) -> TimerPollResult {
+        loop {
+            match &mut self.state {
+                State::Get => {
+                    let socket_arg = match arg.take() {
+                        None => None,
+                        Some(TimerPollArg::Socket(output)) => Some(output),
+                        Some(a) => {
+                            let err = TimerPollError::InvalidArg(a);
+                            return TimerPollResult::Err { err };
+                        }
+                    };
+
+                    let snapshot = match self.send.resume(socket_arg) {
+                        TimerRequestSendResult::Ok {
+                            response: TimerResponse::Snapshot(snapshot),
+                        } => snapshot,
+                        TimerRequestSendResult::Ok {
+                            response: TimerResponse::Err(err),
+                        } => {
+                            let err = TimerPollError::Rejected(err);
+                            return TimerPollResult::Err { err };
+                        }
+                        TimerRequestSendResult::Ok { response } => {
+                            let err = TimerPollError::UnexpectedResponse(Box::new(response));
+                            return TimerPollResult::Err { err };
+                        }
+                        TimerRequestSendResult::Io { input } => {
+                            let input = TimerPollInput::Socket(input);
+                            return TimerPollResult::Io { input };
+                        }
+                        TimerRequestSendResult::Err { err } => {
+                            let err = TimerPollError::Send(err);
+                            return TimerPollResult::Err { err };
+                        }
+                    };
+
+                    let changed = self.snapshot.as_ref().is_some_and(|previous| {
+                        previous.state != snapshot.state
+                            || previous.cycle_index != snapshot.cycle_index
+                            || previous.cycle != snapshot.cycle
+                    });
+
+                    if changed {
+                        debug!("timer changed: {snapshot:?}");
+                        self.snapshot = Some(snapshot.clone());
+                        return TimerPollResult::Ok { snapshot };
+                    }
+
+                    trace!("timer unchanged, polling again in {}s", self.interval);
+                    self.snapshot = Some(snapshot);
+                    self.state = State::Sleep(TimeSleep::new(self.interval));
+                }
+                State::Sleep(sleep) => {
+                    let time_arg = match arg.take() {
+                        None => None,
+                        Some(TimerPollArg::Time(output)) => Some(output),
+                        Some(a) => {
+                            let err = TimerPollError::InvalidArg(a);
+                            return TimerPollResult::Err { err };
+                        }
+                    };
+
+                    match sleep.resume(time_arg) {
+                        TimeSleepResult::Ok => {
+                            self.send.send(self.envelope.clone());
+                            self.state = State::Get;
+                        }
+                        TimeSleepResult::Io { input } => {
+                            let input = TimerPollInput::Time(input);
+                            return TimerPollResult::Io { input };
+                        }
+                        TimeSleepResult::Err { err } => {
+                            let err = TimerPollError::Sleep(err);
+                            return TimerPollResult::Err { err };
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Coroutine for TimerPoll {
+    type Input = TimerPollInput;
+    type Output = TimerPollArg;
+    type Ok = TimerSnapshot;
+    type Error = TimerPollError;
+
+    fn resume(
+        &mut self,
+        arg: Option<TimerPollArg>,
+    ) -> CoroutineResult<TimerSnapshot, TimerPollInput, TimerPollError> {
+        match TimerPoll::resume(self, arg) {
+            TimerPollResult::Ok { snapshot } => CoroutineResult::Ok(snapshot),
+            TimerPollResult::Io { input } => CoroutineResult::Io(input),
+            TimerPollResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! I/O-free coroutine to push timer events to a subscribed client.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_socket::{
+    coroutines::write::*,
+    io::{SocketInput, SocketOutput},
+};
+use log::trace;
+use thiserror::Error;
+
+use crate::{
+    codec::{Codec, CodecError},
+    compression::CompressionError,
+    coroutines::{Coroutine, CoroutineResult},
+    delta::TimerDelta,
+    history::TimerHistoryEntry,
+    session::Session,
+    timer::TimerResponse,
+};
+
+/// Error emitted by the [`TimerEventPush`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimerEventPushError {
+    #[error("Failed to serialize timer event")]
+    Serialize(#[source] CodecError),
+    #[error("Failed to compress timer event")]
+    Compress(#[source] CompressionError),
+
+    #[error("Reached unexpected EOF while pushing events")]
+    WriteEof,
+    #[error(transparent)]
+    Write(SocketWriteError),
+}
+
+/// Result emitted on each step of the [`TimerEventPush`] coroutine.
+#[derive(Debug)]
+pub enum TimerEventPushResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok,
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerEventPushError },
+}
+
+/// A message pushed to a subscribed connection.
+#[derive(Clone, Debug)]
+enum Pushed {
+    Event(TimerHistoryEntry),
+    Delta(TimerDelta),
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    Serialize(Vec<Pushed>),
+    Write(SocketWrite),
+}
+
+/// I/O-free coroutine to push events to a connection subscribed with
+/// [`TimerRequest::Subscribe`].
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit [`SocketInput::Write`] with one encoded
+///    [`TimerResponse::Event`] frame per event.
+/// 2. Return `Ok` once the write completes.
+///
+/// Events are typically taken from the history of the subscribed
+/// timer, so that they carry their sequence number. Timer changes can
+/// be pushed as [`TimerResponse::Delta`] frames instead, see
+/// [`TimerEventPush::deltas`]. Over datagram
+/// sessions, each write is sent as a single datagram: events should
+/// then be pushed one by one.
+///
+/// [`TimerRequest::Subscribe`]: crate::timer::TimerRequest::Subscribe
+/// [`resume`]: TimerEventPush::resume
+#[derive(Clone, Debug)]
+pub struct TimerEventPush {
+    session: Session,
+    timer: Option<String>,
+    sse: bool,
+    state: State,
+}
+
+impl TimerEventPush {
+    /// Creates a new coroutine pushing the given events.
+    pub fn new(events: impl IntoIterator<Item = TimerHistoryEntry>) -> Self {
+        Self {
+            session: Session::default(),
+            timer: None,
+            sse: false,
+            state: State::Serialize(events.into_iter().map(Pushed::Event).collect()),
+        }
+    }
+
+    /// Creates a new coroutine pushing the given timer changes.
+    pub fn deltas(deltas: impl IntoIterator<Item = TimerDelta>) -> Self {
+        Self {
+            session: Session::default(),
+            timer: None,
+            sse: false,
+            state: State::Serialize(deltas.into_iter().map(Pushed::Delta).collect()),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerAccept`].
+    ///
+    /// [`TimerAccept`]: crate::coroutines::accept::TimerAccept
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = session;
+        self
+    }
+
+    /// Tags the pushed events with the name of their timer, for
+    /// connections subscribed to every timer.
+    ///
+    /// Events are then pushed as [`TimerResponse::TaggedEvent`]
+    /// frames, and changes as [`TimerResponse::TaggedDelta`] frames.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.timer = Some(name.to_string());
+        self
+    }
+
+    /// Frames the pushed events as [Server-Sent Events], so that
+    /// they can be consumed directly by browsers or `curl` behind an
+    /// HTTP reverse proxy.
+    ///
+    /// Each event is sent with its sequence number as `id`, its name
+    /// (see [`TimerEvent::name`], or `Delta` for changes) as `event`
+    /// and its JSON-encoded frame as `data`, whatever the session
+    /// codec. The session compression and checksum are not applied.
+    ///
+    /// [Server-Sent Events]: https://html.spec.whatwg.org/multipage/server-sent-events.html
+    /// [`TimerEvent::name`]: crate::timer::TimerEvent::name
+    pub fn with_sse(mut self) -> Self {
+        self.sse = true;
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, mut arg: Option<SocketOutput>) -> TimerEventPushResult {
+        loop {
+            match &mut self.state {
+                State::Serialize(events) => {
+                    let mut bytes = Vec::new();
+
+                    for pushed in core::mem::take(events) {
+                        trace!("timer event to push: {pushed:?}");
+                        let timer = self.timer.clone();
+                        let (seq, name, response) = match (pushed, timer) {
+                            (Pushed::Event(event), Some(timer)) => {
+                                let (seq, name) = (event.seq, event.event.name());
+                                (seq, name, TimerResponse::TaggedEvent { timer, event })
+                            }
+                            (Pushed::Event(event), None) => {
+                                let (seq, name) = (event.seq, event.event.name());
+                                (seq, name, TimerResponse::Event(event))
+                            }
+                            (Pushed::Delta(delta), Some(timer)) => {
+                                let seq = delta.seq;
+                                (seq, "Delta", TimerResponse::TaggedDelta { timer, delta })
+                            }
+                            (Pushed::Delta(delta), None) => {
+                                (delta.seq, "Delta", TimerResponse::Delta(delta))
+                            }
+                        };
+                        let frame = if self.sse {
+                            self.encode_sse(seq, name, response)
+                        } else {
+                            self.encode(response)
+                        };
+                        match frame {
+                            Ok(frame) => bytes.extend(frame),
+                            Err(err) => return TimerEventPushResult::Err { err },
+                        }
+                    }
+
+                    self.state = State::Write(SocketWrite::new(bytes));
+                }
+                State::Write(w) => {
+                    return match w.resume(arg.take()) {
+                        SocketWriteResult::Ok { .. } => TimerEventPushResult::Ok,
+                        SocketWriteResult::Io { input } => TimerEventPushResult::Io { input },
+                        SocketWriteResult::Eof => {
+                            let err = TimerEventPushError::WriteEof;
+                            TimerEventPushResult::Err { err }
+                        }
+                        SocketWriteResult::Err { err } => {
+                            let err = TimerEventPushError::Write(err);
+                            TimerEventPushResult::Err { err }
+                        }
+                    };
+                }
+            }
+        }
+    }
+
+    fn encode(&self, response: TimerResponse) -> Result<Vec<u8>, TimerEventPushError> {
+        let bytes = self
+            .session
+            .codec
+            .encode(&response)
+            .map_err(TimerEventPushError::Serialize)?;
+        let bytes = self
+            .session
+            .compression
+            .compress(bytes)
+            .map_err(TimerEventPushError::Compress)?;
+        let bytes = self.session.checksum.append(bytes);
+
+        Ok(self.session.framing().encode(bytes))
+    }
+
+    fn encode_sse(
+        &self,
+        seq: u64,
+        name: &str,
+        response: TimerResponse,
+    ) -> Result<Vec<u8>, TimerEventPushError> {
+        // compact JSON never contains raw line feeds, so the data
+        // fits on a single line
+        let data = Codec::Json
+            .encode(&response)
+            .map_err(TimerEventPushError::Serialize)?;
+
+        let mut frame = format!("id: {seq}\nevent: {name}\ndata: ").into_bytes();
+        frame.extend(data);
+        frame.extend_from_slice(b"\n\n");
+        Ok(frame)
+    }
+}
+
+impl Coroutine for TimerEventPush {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = ();
+    type Error = TimerEventPushError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<(), SocketInput, TimerEventPushError> {
+        match TimerEventPush::resume(self, arg) {
+            TimerEventPushResult::Ok => CoroutineResult::Ok(()),
+            TimerEventPushResult::Io { input } => CoroutineResult::Io(input),
+            TimerEventPushResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! I/O-free coroutine to send an encoded request frame and receive
+//! the raw response frame.
+
+use alloc::vec::Vec;
+
+use io_socket::{
+    coroutines::{read::*, write::*},
+    io::{SocketInput, SocketOutput},
+};
+use log::trace;
+
+use crate::{
+    coroutines::{Coroutine, CoroutineResult, client::TimerRequestSendError},
+    frame::FrameDecoder,
+    session::Session,
+};
+
+/// Result emitted on each step of the [`TimerFrameSend`] coroutine.
+#[derive(Debug)]
+pub enum TimerFrameSendResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok { frame: Vec<u8> },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerRequestSendError },
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    Write(SocketWrite),
+    Read(SocketRead),
+}
+
+/// I/O-free coroutine to send an already encoded request frame to a
+/// server, and receive the raw response frame.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit [`SocketInput::Write`] with the request frame, as is.
+/// 2. Emit [`SocketInput::Read`] until the response frame is fully
+///    received.
+/// 3. Return `Ok { frame }`, without its delimiters.
+///
+/// Unlike [`TimerRequestSend`], neither the request nor the response
+/// go through the codec, the checksum or the compression of the
+/// session: only its framing is used to delimit the response. This
+/// is meant for applications tunneling the protocol inside their own
+/// transport, which forward frames without decoding them.
+///
+/// [`TimerRequestSend`]: crate::coroutines::client::TimerRequestSend
+/// [`resume`]: TimerFrameSend::resume
+#[derive(Clone, Debug)]
+pub struct TimerFrameSend {
+    state: State,
+    decoder: FrameDecoder,
+}
+
+impl TimerFrameSend {
+    /// Creates a new coroutine sending the given request frame,
+    /// delimiters included.
+    pub fn new(frame: impl Into<Vec<u8>>) -> Self {
+        Self {
+            state: State::Write(SocketWrite::new(frame.into())),
+            decoder: FrameDecoder::new(),
+        }
+    }
+
+    /// Delimits response frames using the framing of the given
+    /// session, previously negotiated with [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.decoder.set_framing(session.framing());
+        self
+    }
+
+    /// Rejects response frames larger than the given number of
+    /// bytes, instead of [`DEFAULT_MAX_FRAME_SIZE`].
+    ///
+    /// [`DEFAULT_MAX_FRAME_SIZE`]: crate::frame::DEFAULT_MAX_FRAME_SIZE
+    pub fn with_max_frame_size(mut self, max: usize) -> Self {
+        self.decoder.set_max_frame_size(max);
+        self
+    }
+
+    /// Sends the given request frame once the previous one
+    /// terminated, keeping the buffered bytes of the connection.
+    pub fn send(&mut self, frame: impl Into<Vec<u8>>) {
+        self.state = State::Write(SocketWrite::new(frame.into()));
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, mut arg: Option<SocketOutput>) -> TimerFrameSendResult {
+        loop {
+            match &mut self.state {
+                State::Write(w) => match w.resume(arg.take()) {
+                    SocketWriteResult::Ok { n, .. } => {
+                        trace!("wrote request frame of {n} bytes");
+                        self.state = State::Read(SocketRead::default());
+                    }
+                    SocketWriteResult::Io { input } => {
+                        return TimerFrameSendResult::Io { input };
+                    }
+                    SocketWriteResult::Eof => {
+                        let err = TimerRequestSendError::WriteEof;
+                        return TimerFrameSendResult::Err { err };
+                    }
+                    SocketWriteResult::Err { err } => {
+                        let err = TimerRequestSendError::Write(err);
+                        return TimerFrameSendResult::Err { err };
+                    }
+                },
+                State::Read(r) => {
+                    // frames already buffered by a previous read are
+                    // returned without reading again
+                    if arg.is_none() {
+                        match self.decoder.next_frame() {
+                            Ok(Some(frame)) => return TimerFrameSendResult::Ok { frame },
+                            Ok(None) => (),
+                            Err(err) => {
+                                let err = TimerRequestSendError::Frame(err);
+                                return TimerFrameSendResult::Err { err };
+                            }
+                        }
+                    }
+
+                    match r.resume(arg.take()) {
+                        SocketReadResult::Ok { buf, n } => {
+                            self.decoder.push(&buf[..n]);
+                            self.state = State::Read(SocketRead::default());
+                        }
+                        SocketReadResult::Io { input } => {
+                            return TimerFrameSendResult::Io { input };
+                        }
+                        SocketReadResult::Eof => {
+                            let err = TimerRequestSendError::ReadEof;
+                            return TimerFrameSendResult::Err { err };
+                        }
+                        SocketReadResult::Err { err } => {
+                            let err = TimerRequestSendError::Read(err);
+                            return TimerFrameSendResult::Err { err };
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Coroutine for TimerFrameSend {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = Vec<u8>;
+    type Error = TimerRequestSendError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<Vec<u8>, SocketInput, TimerRequestSendError> {
+        match TimerFrameSend::resume(self, arg) {
+            TimerFrameSendResult::Ok { frame } => CoroutineResult::Ok(frame),
+            TimerFrameSendResult::Io { input } => CoroutineResult::Io(input),
+            TimerFrameSendResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
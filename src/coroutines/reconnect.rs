@@ -0,0 +1,296 @@
+//! I/O-free coroutine to send a timer request, reconnecting on
+//! connection failures.
+
+use io_socket::io::{SocketInput, SocketOutput};
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::{TimerRequestSend, TimerRequestSendError, TimerRequestSendResult},
+        sleep::{TimeSleep, TimeSleepError, TimeSleepResult},
+    },
+    io::{TimeInput, TimeOutput},
+    timer::TimerResponse,
+};
+
+/// The default maximum number of reconnections.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// The default delay before the first reconnection, in seconds.
+pub const DEFAULT_INITIAL_BACKOFF: u64 = 1;
+
+/// The default maximum delay between two reconnections, in seconds.
+pub const DEFAULT_MAX_BACKOFF: u64 = 30;
+
+/// I/O request emitted by the [`TimerRequestReconnect`] coroutine.
+#[derive(Clone, Debug)]
+pub enum TimerRequestReconnectInput {
+    /// A socket I/O request on the current connection.
+    Socket(SocketInput),
+    /// A time I/O request, to wait before reconnecting.
+    Time(TimeInput),
+    /// The current connection failed: the runtime needs to open a
+    /// new one, then resume with
+    /// [`TimerRequestReconnectArg::Reconnected`].
+    Reconnect,
+}
+
+/// Argument passed back to [`TimerRequestReconnect`] after any I/O.
+#[derive(Clone, Debug)]
+pub enum TimerRequestReconnectArg {
+    /// Response to a [`SocketInput`] request.
+    Socket(SocketOutput),
+    /// The runtime failed to process the [`SocketInput`] request, for
+    /// example because the connection was reset.
+    SocketFailed,
+    /// Response to a [`TimeInput`] request.
+    Time(TimeOutput),
+    /// Response to a [`TimerRequestReconnectInput::Reconnect`]
+    /// request, once the new connection is open.
+    Reconnected,
+}
+
+/// Error emitted by the [`TimerRequestReconnect`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimerRequestReconnectError {
+    #[error("Invalid timer request reconnect arg: {0:?}")]
+    InvalidArg(TimerRequestReconnectArg),
+    #[error("Failed to send timer request after {0} reconnections")]
+    RetriesExhausted(u32),
+
+    #[error(transparent)]
+    Send(TimerRequestSendError),
+    #[error(transparent)]
+    Sleep(TimeSleepError),
+}
+
+/// Result emitted on each step of the [`TimerRequestReconnect`]
+/// coroutine.
+#[derive(Debug)]
+pub enum TimerRequestReconnectResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok { response: TimerResponse },
+    /// An I/O needs to be performed to make the coroutine progress.
+    Io { input: TimerRequestReconnectInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerRequestReconnectError },
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    Send,
+    Backoff(TimeSleep),
+    Reconnect,
+}
+
+/// I/O-free coroutine wrapping [`TimerRequestSend`], replaying the
+/// request on a new connection when the current one fails.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit the [`SocketInput`] requests of the wrapped coroutine.
+/// 2. On connection failure (unexpected EOF, socket error, or
+///    [`TimerRequestReconnectArg::SocketFailed`]), emit
+///    [`TimeInput::Sleep`] for the backoff delay, then
+///    [`TimerRequestReconnectInput::Reconnect`].
+/// 3. Replay the request from scratch on the new connection.
+/// 4. Return `Ok { response }`, or fail with
+///    [`TimerRequestReconnectError::RetriesExhausted`] once the
+///    maximum number of reconnections is reached.
+///
+/// The backoff delay starts at [`DEFAULT_INITIAL_BACKOFF`] seconds,
+/// and doubles after each reconnection up to
+/// [`DEFAULT_MAX_BACKOFF`] seconds.
+///
+/// Since the request may have been applied before the connection
+/// failed, requests modifying the timer should carry an idempotency
+/// key (see [`TimerRequestSend::with_idempotency_key`]). The new
+/// connection is expected to use the same session as the previous
+/// one.
+///
+/// [`resume`]: TimerRequestReconnect::resume
+#[derive(Clone, Debug)]
+pub struct TimerRequestReconnect {
+    request: TimerRequestSend,
+    send: TimerRequestSend,
+    max_retries: u32,
+    initial_backoff: u64,
+    max_backoff: u64,
+    retries: u32,
+    state: State,
+}
+
+impl TimerRequestReconnect {
+    /// Creates a new coroutine wrapping the given request.
+    pub fn new(request: TimerRequestSend) -> Self {
+        Self {
+            send: request.clone(),
+            request,
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            retries: 0,
+            state: State::Send,
+        }
+    }
+
+    /// Reconnects at most the given number of times, instead of
+    /// [`DEFAULT_MAX_RETRIES`].
+    pub fn with_max_retries(mut self, max: u32) -> Self {
+        self.max_retries = max;
+        self
+    }
+
+    /// Waits the given initial number of seconds before the first
+    /// reconnection, doubled after each reconnection up to the given
+    /// maximum. A zero initial delay reconnects immediately.
+    pub fn with_backoff(mut self, initial: u64, max: u64) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Returns the number of reconnections performed so far.
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(
+        &mut self,
+        mut arg: Option<TimerRequestReconnectArg>,
+    ) -> TimerRequestReconnectResult {
+        loop {
+            match &mut self.state {
+                State::Send => {
+                    let socket_arg = match arg.take() {
+                        None => None,
+                        Some(TimerRequestReconnectArg::Socket(output)) => Some(output),
+                        Some(TimerRequestReconnectArg::SocketFailed) => {
+                            debug!("socket I/O failed, reconnecting");
+                            if let Err(err) = self.retry() {
+                                return TimerRequestReconnectResult::Err { err };
+                            }
+                            continue;
+                        }
+                        Some(a) => {
+                            let err = TimerRequestReconnectError::InvalidArg(a);
+                            return TimerRequestReconnectResult::Err { err };
+                        }
+                    };
+
+                    match self.send.resume(socket_arg) {
+                        TimerRequestSendResult::Ok { response } => {
+                            return TimerRequestReconnectResult::Ok { response };
+                        }
+                        TimerRequestSendResult::Io { input } => {
+                            let input = TimerRequestReconnectInput::Socket(input);
+                            return TimerRequestReconnectResult::Io { input };
+                        }
+                        TimerRequestSendResult::Err { err } if is_connection_error(&err) => {
+                            debug!("connection failed, reconnecting: {err}");
+                            if let Err(err) = self.retry() {
+                                return TimerRequestReconnectResult::Err { err };
+                            }
+                        }
+                        TimerRequestSendResult::Err { err } => {
+                            let err = TimerRequestReconnectError::Send(err);
+                            return TimerRequestReconnectResult::Err { err };
+                        }
+                    }
+                }
+                State::Backoff(sleep) => {
+                    let time_arg = match arg.take() {
+                        None => None,
+                        Some(TimerRequestReconnectArg::Time(output)) => Some(output),
+                        Some(a) => {
+                            let err = TimerRequestReconnectError::InvalidArg(a);
+                            return TimerRequestReconnectResult::Err { err };
+                        }
+                    };
+
+                    match sleep.resume(time_arg) {
+                        TimeSleepResult::Ok => {
+                            self.state = State::Reconnect;
+                        }
+                        TimeSleepResult::Io { input } => {
+                            let input = TimerRequestReconnectInput::Time(input);
+                            return TimerRequestReconnectResult::Io { input };
+                        }
+                        TimeSleepResult::Err { err } => {
+                            let err = TimerRequestReconnectError::Sleep(err);
+                            return TimerRequestReconnectResult::Err { err };
+                        }
+                    }
+                }
+                State::Reconnect => match arg.take() {
+                    None => {
+                        trace!("wants I/O to reconnect");
+                        let input = TimerRequestReconnectInput::Reconnect;
+                        return TimerRequestReconnectResult::Io { input };
+                    }
+                    Some(TimerRequestReconnectArg::Reconnected) => {
+                        debug!("resume after reconnecting, replaying request");
+                        self.send = self.request.clone();
+                        self.state = State::Send;
+                    }
+                    Some(a) => {
+                        let err = TimerRequestReconnectError::InvalidArg(a);
+                        return TimerRequestReconnectResult::Err { err };
+                    }
+                },
+            }
+        }
+    }
+
+    fn retry(&mut self) -> Result<(), TimerRequestReconnectError> {
+        if self.retries >= self.max_retries {
+            return Err(TimerRequestReconnectError::RetriesExhausted(self.retries));
+        }
+
+        let secs = self
+            .initial_backoff
+            .saturating_mul(1 << self.retries.min(63))
+            .min(self.max_backoff);
+        self.retries += 1;
+
+        self.state = if secs == 0 {
+            State::Reconnect
+        } else {
+            State::Backoff(TimeSleep::new(secs))
+        };
+
+        Ok(())
+    }
+}
+
+fn is_connection_error(err: &TimerRequestSendError) -> bool {
+    matches!(
+        err,
+        TimerRequestSendError::ReadEof
+            | TimerRequestSendError::Read(_)
+            | TimerRequestSendError::WriteEof
+            | TimerRequestSendError::Write(_)
+    )
+}
+
+impl Coroutine for TimerRequestReconnect {
+    type Input = TimerRequestReconnectInput;
+    type Output = TimerRequestReconnectArg;
+    type Ok = TimerResponse;
+    type Error = TimerRequestReconnectError;
+
+    fn resume(
+        &mut self,
+        arg: Option<TimerRequestReconnectArg>,
+    ) -> CoroutineResult<TimerResponse, TimerRequestReconnectInput, TimerRequestReconnectError>
+    {
+        match TimerRequestReconnect::resume(self, arg) {
+            TimerRequestReconnectResult::Ok { response } => CoroutineResult::Ok(response),
+            TimerRequestReconnectResult::Io { input } => CoroutineResult::Io(input),
+            TimerRequestReconnectResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! I/O-free coroutines to manage the timers of a
+//! [`TimerRegistry`].
+//!
+//! [`TimerRegistry`]: crate::registry::TimerRegistry
+
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::{TimerRequestSend, TimerRequestSendError, TimerRequestSendResult},
+    },
+    session::Session,
+    timer::{TimerConfig, TimerError, TimerResponse, TimerStatus},
+};
+
+/// Error emitted by the [`TimerList`], [`TimerCreate`] and
+/// [`TimerDelete`] coroutines.
+#[derive(Debug, Error)]
+pub enum TimerRegistryRequestError {
+    #[error("Timer registry request rejected by the server")]
+    Rejected(#[source] TimerError),
+    #[error("Unexpected timer response {0:?}")]
+    UnexpectedResponse(Box<TimerResponse>),
+
+    #[error(transparent)]
+    Send(TimerRequestSendError),
+}
+
+/// Result emitted on each step of the [`TimerList`] coroutine.
+#[derive(Debug)]
+pub enum TimerListResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok {
+        timers: BTreeMap<String, TimerStatus>,
+    },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerRegistryRequestError },
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::List`], and return
+/// the status of every timer of the registry by name.
+///
+/// See [`TimerRequestSend`] for the steps.
+///
+/// [`TimerRequest::List`]: crate::timer::TimerRequest::List
+#[derive(Clone, Debug)]
+pub struct TimerList {
+    send: TimerRequestSend,
+}
+
+impl Default for TimerList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerList {
+    /// Creates a new coroutine.
+    pub fn new() -> Self {
+        Self {
+            send: TimerRequestSend::list(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, arg: Option<SocketOutput>) -> TimerListResult {
+        let err = match self.send.resume(arg) {
+            TimerRequestSendResult::Ok {
+                response: TimerResponse::List(timers),
+            } => return TimerListResult::Ok { timers },
+            TimerRequestSendResult::Ok { response } => rejected(response),
+            TimerRequestSendResult::Io { input } => return TimerListResult::Io { input },
+            TimerRequestSendResult::Err { err } => TimerRegistryRequestError::Send(err),
+        };
+
+        TimerListResult::Err { err }
+    }
+}
+
+impl Coroutine for TimerList {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = BTreeMap<String, TimerStatus>;
+    type Error = TimerRegistryRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<BTreeMap<String, TimerStatus>, SocketInput, TimerRegistryRequestError>
+    {
+        match TimerList::resume(self, arg) {
+            TimerListResult::Ok { timers } => CoroutineResult::Ok(timers),
+            TimerListResult::Io { input } => CoroutineResult::Io(input),
+            TimerListResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
+
+/// Result emitted on each step of the [`TimerCreate`] and
+/// [`TimerDelete`] coroutines.
+#[derive(Debug)]
+pub enum TimerRegistryRequestResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok,
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerRegistryRequestError },
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::Create`], adding a
+/// new timer to the registry.
+///
+/// Fails with [`TimerError::TimerExists`] if a timer with the same
+/// name already exists. See [`TimerRequestSend`] for the steps.
+///
+/// [`TimerRequest::Create`]: crate::timer::TimerRequest::Create
+#[derive(Clone, Debug)]
+pub struct TimerCreate {
+    send: TimerRequestSend,
+}
+
+impl TimerCreate {
+    /// Creates a new coroutine creating a timer with the given name
+    /// and configuration.
+    pub fn new(name: impl ToString, config: TimerConfig) -> Self {
+        Self {
+            send: TimerRequestSend::create(name, config),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Attaches the given idempotency key to the request, see
+    /// [`TimerRequestSend::with_idempotency_key`].
+    pub fn with_idempotency_key(mut self, key: impl ToString) -> Self {
+        self.send = self.send.with_idempotency_key(key);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, arg: Option<SocketOutput>) -> TimerRegistryRequestResult {
+        resume_ok(&mut self.send, arg)
+    }
+}
+
+impl Coroutine for TimerCreate {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = ();
+    type Error = TimerRegistryRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<(), SocketInput, TimerRegistryRequestError> {
+        TimerCreate::resume(self, arg).into()
+    }
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::Delete`], removing a
+/// timer from the registry.
+///
+/// Fails with [`TimerError::UnknownTimer`] if no timer has the
+/// given name. See [`TimerRequestSend`] for the steps.
+///
+/// [`TimerRequest::Delete`]: crate::timer::TimerRequest::Delete
+#[derive(Clone, Debug)]
+pub struct TimerDelete {
+    send: TimerRequestSend,
+}
+
+impl TimerDelete {
+    /// Creates a new coroutine deleting the timer with the given
+    /// name.
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            send: TimerRequestSend::delete(name),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Attaches the given idempotency key to the request, see
+    /// [`TimerRequestSend::with_idempotency_key`].
+    pub fn with_idempotency_key(mut self, key: impl ToString) -> Self {
+        self.send = self.send.with_idempotency_key(key);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, arg: Option<SocketOutput>) -> TimerRegistryRequestResult {
+        resume_ok(&mut self.send, arg)
+    }
+}
+
+impl Coroutine for TimerDelete {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = ();
+    type Error = TimerRegistryRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<(), SocketInput, TimerRegistryRequestError> {
+        TimerDelete::resume(self, arg).into()
+    }
+}
+
+impl From<TimerRegistryRequestResult>
+    for CoroutineResult<(), SocketInput, TimerRegistryRequestError>
+{
+    fn from(result: TimerRegistryRequestResult) -> Self {
+        match result {
+            TimerRegistryRequestResult::Ok => CoroutineResult::Ok(()),
+            TimerRegistryRequestResult::Io { input } => CoroutineResult::Io(input),
+            TimerRegistryRequestResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
+
+/// Resumes the given request, expecting [`TimerResponse::Ok`].
+fn resume_ok(send: &mut TimerRequestSend, arg: Option<SocketOutput>) -> TimerRegistryRequestResult {
+    let err = match send.resume(arg) {
+        TimerRequestSendResult::Ok {
+            response: TimerResponse::Ok,
+        } => return TimerRegistryRequestResult::Ok,
+        TimerRequestSendResult::Ok { response } => rejected(response),
+        TimerRequestSendResult::Io { input } => return TimerRegistryRequestResult::Io { input },
+        TimerRequestSendResult::Err { err } => TimerRegistryRequestError::Send(err),
+    };
+
+    TimerRegistryRequestResult::Err { err }
+}
+
+/// Turns an unexpected response into the matching error.
+fn rejected(response: TimerResponse) -> TimerRegistryRequestError {
+    match response {
+        TimerResponse::Err(err) => TimerRegistryRequestError::Rejected(err),
+        response => TimerRegistryRequestError::UnexpectedResponse(Box::new(response)),
+    }
+}
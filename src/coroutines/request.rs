@@ -0,0 +1,979 @@
+//! I/O-free coroutines to send timer requests, returning typed
+//! values instead of raw [`TimerResponse`]s.
+
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use log::trace;
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::{TimerRequestSend, TimerRequestSendError, TimerRequestSendResult},
+    },
+    session::Session,
+    stats::TimerStats,
+    timer::{
+        TimerCapabilities, TimerConfig, TimerEnvelope, TimerError, TimerRequest, TimerResponse,
+        TimerSnapshot, TimerStampedEvent, TimerStatusLine, TimerVersion,
+    },
+};
+
+/// Error emitted by the typed request coroutines.
+#[derive(Debug, Error)]
+pub enum TimerRequestError {
+    #[error("Timer request rejected by the server")]
+    Rejected(#[source] TimerError),
+    #[error("Timer server busy, retry after {0}ms")]
+    Busy(u64),
+    #[error("Unexpected timer response {0:?}")]
+    UnexpectedResponse(Box<TimerResponse>),
+
+    #[error(transparent)]
+    Send(TimerRequestSendError),
+}
+
+/// Result emitted on each step of the typed request coroutines.
+#[derive(Debug)]
+pub enum TimerRequestResult<T> {
+    /// The coroutine has successfully terminated its progression.
+    Ok { value: T },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerRequestError },
+}
+
+impl<T> From<TimerRequestResult<T>> for CoroutineResult<T, SocketInput, TimerRequestError> {
+    fn from(result: TimerRequestResult<T>) -> Self {
+        match result {
+            TimerRequestResult::Ok { value } => CoroutineResult::Ok(value),
+            TimerRequestResult::Io { input } => CoroutineResult::Io(input),
+            TimerRequestResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
+
+/// I/O-free coroutine to send a [`TimerRequest`] modifying the
+/// timer, and return the [`TimerStampedEvent`]s it emitted.
+///
+/// Use the named constructors ([`TimerCommand::start`],
+/// [`TimerCommand::pause`], …). See [`TimerRequestSend`] for the
+/// steps.
+#[derive(Clone, Debug)]
+pub struct TimerCommand {
+    send: TimerRequestSend,
+}
+
+impl TimerCommand {
+    fn new(request: TimerRequest) -> Self {
+        Self {
+            send: TimerRequestSend::new(request),
+        }
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Start`].
+    pub fn start() -> Self {
+        Self::new(TimerRequest::Start)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Stop`].
+    pub fn stop() -> Self {
+        Self::new(TimerRequest::Stop)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Pause`].
+    pub fn pause() -> Self {
+        Self::new(TimerRequest::Pause)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Reset`].
+    pub fn reset() -> Self {
+        Self::new(TimerRequest::Reset)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Activity`].
+    pub fn activity() -> Self {
+        Self::new(TimerRequest::Activity)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Resume`].
+    pub fn resume_timer() -> Self {
+        Self::new(TimerRequest::Resume)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Update`].
+    pub fn update() -> Self {
+        Self::new(TimerRequest::Update)
+    }
+
+    /// Creates a coroutine that sends a
+    /// [`TimerRequest::SetRemaining`].
+    pub fn set_remaining(secs: usize) -> Self {
+        Self::new(TimerRequest::SetRemaining(secs))
+    }
+
+    /// Creates a coroutine that sends a
+    /// [`TimerRequest::SetCycleDuration`].
+    pub fn set_cycle_duration(cycle: impl ToString, secs: usize) -> Self {
+        let cycle = cycle.to_string();
+        Self::new(TimerRequest::SetCycleDuration { cycle, secs })
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Sends the command to the timer with the given name, instead of
+    /// the default one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.send = self.send.with_timer(name);
+        self
+    }
+
+    /// Attaches the given idempotency key to the request, see
+    /// [`TimerRequestSend::with_idempotency_key`].
+    pub fn with_idempotency_key(mut self, key: impl ToString) -> Self {
+        self.send = self.send.with_idempotency_key(key);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> TimerRequestResult<Vec<TimerStampedEvent>> {
+        resume(&mut self.send, arg, |response| match response {
+            TimerResponse::Events(events) => Ok(events),
+            response => Err(response),
+        })
+    }
+}
+
+impl Coroutine for TimerCommand {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = Vec<TimerStampedEvent>;
+    type Error = TimerRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<Vec<TimerStampedEvent>, SocketInput, TimerRequestError> {
+        TimerCommand::resume(self, arg).into()
+    }
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::SetConfig`], and
+/// return the [`TimerStampedEvent`]s it emitted.
+///
+/// The configuration is validated before being sent (see
+/// [`TimerConfig::validate`]): invalid configurations fail on the
+/// first resume without any I/O, with the same
+/// [`TimerError::InvalidConfig`] error the server would answer. See
+/// [`TimerRequestSend`] for the steps.
+#[derive(Clone, Debug)]
+pub struct TimerConfigSet {
+    send: TimerRequestSend,
+    invalid: Option<TimerError>,
+}
+
+impl TimerConfigSet {
+    /// Creates a new coroutine sending the given configuration.
+    pub fn new(config: TimerConfig) -> Self {
+        Self {
+            invalid: config.validate().err(),
+            send: TimerRequestSend::set_config(config),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Configures the timer with the given name, instead of the
+    /// default one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.send = self.send.with_timer(name);
+        self
+    }
+
+    /// Attaches the given idempotency key to the request, see
+    /// [`TimerRequestSend::with_idempotency_key`].
+    pub fn with_idempotency_key(mut self, key: impl ToString) -> Self {
+        self.send = self.send.with_idempotency_key(key);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> TimerRequestResult<Vec<TimerStampedEvent>> {
+        if let Some(err) = &self.invalid {
+            let err = TimerRequestError::Rejected(err.clone());
+            return TimerRequestResult::Err { err };
+        }
+
+        resume(&mut self.send, arg, |response| match response {
+            TimerResponse::Events(events) => Ok(events),
+            response => Err(response),
+        })
+    }
+}
+
+impl Coroutine for TimerConfigSet {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = Vec<TimerStampedEvent>;
+    type Error = TimerRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<Vec<TimerStampedEvent>, SocketInput, TimerRequestError> {
+        TimerConfigSet::resume(self, arg).into()
+    }
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::Get`], and return
+/// the [`TimerSnapshot`] of the timer.
+///
+/// Servers redacting their responses answer with a
+/// [`TimerResponse::Status`] instead, which fails with
+/// [`TimerRequestError::UnexpectedResponse`]. See [`TimerRequestSend`]
+/// for the steps.
+///
+/// [`TimerRequest::Get`]: crate::timer::TimerRequest::Get
+#[derive(Clone, Debug)]
+pub struct TimerGet {
+    send: TimerRequestSend,
+}
+
+impl Default for TimerGet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerGet {
+    /// Creates a new coroutine.
+    pub fn new() -> Self {
+        Self {
+            send: TimerRequestSend::get(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Gets the timer with the given name, instead of the default
+    /// one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.send = self.send.with_timer(name);
+        self
+    }
+
+    /// Reads up to the given number of bytes at once, see
+    /// [`TimerRequestSend::with_read_buffer_size`].
+    pub fn with_read_buffer_size(mut self, size: usize) -> Self {
+        self.send = self.send.with_read_buffer_size(size);
+        self
+    }
+
+    /// Reads into the given buffer, see
+    /// [`TimerRequestSend::with_read_buffer`].
+    pub fn with_read_buffer(mut self, buf: Vec<u8>) -> Self {
+        self.send = self.send.with_read_buffer(buf);
+        self
+    }
+
+    /// Takes the read buffer back, see
+    /// [`TimerRequestSend::take_read_buffer`].
+    pub fn take_read_buffer(&mut self) -> Vec<u8> {
+        self.send.take_read_buffer()
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, arg: Option<SocketOutput>) -> TimerRequestResult<TimerSnapshot> {
+        resume(&mut self.send, arg, |response| match response {
+            TimerResponse::Snapshot(snapshot) => Ok(snapshot),
+            response => Err(response),
+        })
+    }
+}
+
+impl Coroutine for TimerGet {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = TimerSnapshot;
+    type Error = TimerRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<TimerSnapshot, SocketInput, TimerRequestError> {
+        TimerGet::resume(self, arg).into()
+    }
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::Status`], and return
+/// the compact [`TimerStatusLine`] of the timer.
+///
+/// See [`TimerRequestSend`] for the steps.
+///
+/// [`TimerRequest::Status`]: crate::timer::TimerRequest::Status
+#[derive(Clone, Debug)]
+pub struct TimerStatusGet {
+    send: TimerRequestSend,
+}
+
+impl Default for TimerStatusGet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerStatusGet {
+    /// Creates a new coroutine.
+    pub fn new() -> Self {
+        Self {
+            send: TimerRequestSend::status(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Gets the status of the timer with the given name, instead of
+    /// the default one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.send = self.send.with_timer(name);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, arg: Option<SocketOutput>) -> TimerRequestResult<TimerStatusLine> {
+        resume(&mut self.send, arg, |response| match response {
+            TimerResponse::StatusLine(status) => Ok(status),
+            response => Err(response),
+        })
+    }
+}
+
+impl Coroutine for TimerStatusGet {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = TimerStatusLine;
+    type Error = TimerRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<TimerStatusLine, SocketInput, TimerRequestError> {
+        TimerStatusGet::resume(self, arg).into()
+    }
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::GetStats`], and
+/// return the [`TimerStats`] of the timer.
+///
+/// See [`TimerRequestSend`] for the steps.
+///
+/// [`TimerRequest::GetStats`]: crate::timer::TimerRequest::GetStats
+#[derive(Clone, Debug)]
+pub struct TimerStatsGet {
+    send: TimerRequestSend,
+}
+
+impl Default for TimerStatsGet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerStatsGet {
+    /// Creates a new coroutine.
+    pub fn new() -> Self {
+        Self {
+            send: TimerRequestSend::get_stats(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Gets the statistics of the timer with the given name, instead
+    /// of the default one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.send = self.send.with_timer(name);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, arg: Option<SocketOutput>) -> TimerRequestResult<TimerStats> {
+        resume(&mut self.send, arg, |response| match response {
+            TimerResponse::Stats(stats) => Ok(stats),
+            response => Err(response),
+        })
+    }
+}
+
+impl Coroutine for TimerStatsGet {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = TimerStats;
+    type Error = TimerRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<TimerStats, SocketInput, TimerRequestError> {
+        TimerStatsGet::resume(self, arg).into()
+    }
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::ExportStats`], and
+/// return the [`TimerStats`] of every timer, by name.
+///
+/// The server streams the statistics as a sequence of
+/// [`TimerResponse::StatsExport`] frames, received one after the
+/// other until a page has no next cursor. See [`TimerRequestSend`]
+/// for the steps of each frame.
+///
+/// [`TimerRequest::ExportStats`]: crate::timer::TimerRequest::ExportStats
+#[derive(Clone, Debug)]
+pub struct TimerStatsExport {
+    send: TimerRequestSend,
+    timers: BTreeMap<String, TimerStats>,
+}
+
+impl Default for TimerStatsExport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerStatsExport {
+    /// Creates a new coroutine.
+    pub fn new() -> Self {
+        Self {
+            send: TimerRequestSend::export_stats(None),
+            timers: BTreeMap::new(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(
+        &mut self,
+        mut arg: Option<SocketOutput>,
+    ) -> TimerRequestResult<BTreeMap<String, TimerStats>> {
+        loop {
+            let result = resume(&mut self.send, arg.take(), |response| match response {
+                TimerResponse::StatsExport(page) => Ok(page),
+                response => Err(response),
+            });
+
+            let page = match result {
+                TimerRequestResult::Ok { value } => value,
+                TimerRequestResult::Io { input } => return TimerRequestResult::Io { input },
+                TimerRequestResult::Err { err } => return TimerRequestResult::Err { err },
+            };
+
+            self.timers.extend(page.timers);
+
+            let Some(cursor) = page.next else {
+                let value = core::mem::take(&mut self.timers);
+                return TimerRequestResult::Ok { value };
+            };
+
+            trace!("receiving next timer statistics page from {cursor}");
+            self.send.receive();
+        }
+    }
+}
+
+impl Coroutine for TimerStatsExport {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = BTreeMap<String, TimerStats>;
+    type Error = TimerRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<BTreeMap<String, TimerStats>, SocketInput, TimerRequestError> {
+        TimerStatsExport::resume(self, arg).into()
+    }
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::Capabilities`], and
+/// return the [`TimerCapabilities`] of the server.
+///
+/// See [`TimerRequestSend`] for the steps.
+///
+/// [`TimerRequest::Capabilities`]: crate::timer::TimerRequest::Capabilities
+#[derive(Clone, Debug)]
+pub struct TimerCapabilitiesGet {
+    send: TimerRequestSend,
+}
+
+impl Default for TimerCapabilitiesGet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerCapabilitiesGet {
+    /// Creates a new coroutine.
+    pub fn new() -> Self {
+        Self {
+            send: TimerRequestSend::capabilities(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, arg: Option<SocketOutput>) -> TimerRequestResult<TimerCapabilities> {
+        resume(&mut self.send, arg, |response| match response {
+            TimerResponse::Capabilities(capabilities) => Ok(capabilities),
+            response => Err(response),
+        })
+    }
+}
+
+impl Coroutine for TimerCapabilitiesGet {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = TimerCapabilities;
+    type Error = TimerRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<TimerCapabilities, SocketInput, TimerRequestError> {
+        TimerCapabilitiesGet::resume(self, arg).into()
+    }
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::Version`], and
+/// return the [`TimerVersion`] of the server.
+///
+/// See [`TimerRequestSend`] for the steps.
+///
+/// [`TimerRequest::Version`]: crate::timer::TimerRequest::Version
+#[derive(Clone, Debug)]
+pub struct TimerVersionGet {
+    send: TimerRequestSend,
+}
+
+impl Default for TimerVersionGet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerVersionGet {
+    /// Creates a new coroutine.
+    pub fn new() -> Self {
+        Self {
+            send: TimerRequestSend::version(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, arg: Option<SocketOutput>) -> TimerRequestResult<TimerVersion> {
+        resume(&mut self.send, arg, |response| match response {
+            TimerResponse::Version(version) => Ok(version),
+            response => Err(response),
+        })
+    }
+}
+
+impl Coroutine for TimerVersionGet {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = TimerVersion;
+    type Error = TimerRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<TimerVersion, SocketInput, TimerRequestError> {
+        TimerVersionGet::resume(self, arg).into()
+    }
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::Ping`], and return
+/// once the server answered with [`TimerResponse::Pong`].
+///
+/// See [`TimerRequestSend`] for the steps.
+///
+/// [`TimerRequest::Ping`]: crate::timer::TimerRequest::Ping
+#[derive(Clone, Debug)]
+pub struct TimerPing {
+    send: TimerRequestSend,
+}
+
+impl Default for TimerPing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerPing {
+    /// Creates a new coroutine.
+    pub fn new() -> Self {
+        Self {
+            send: TimerRequestSend::ping(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, arg: Option<SocketOutput>) -> TimerRequestResult<()> {
+        resume(&mut self.send, arg, |response| match response {
+            TimerResponse::Pong => Ok(()),
+            response => Err(response),
+        })
+    }
+}
+
+impl Coroutine for TimerPing {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = ();
+    type Error = TimerRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<(), SocketInput, TimerRequestError> {
+        TimerPing::resume(self, arg).into()
+    }
+}
+
+/// I/O-free coroutine to send a [`TimerRequest`] modifying the
+/// timer, then a [`TimerRequest::Get`], and return the fresh
+/// [`TimerSnapshot`] of the timer.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit the [`SocketInput`] requests to send the request.
+/// 2. Emit the [`SocketInput`] requests to get the timer, once the
+///    request succeeded.
+/// 3. Return `Ok { value }` with the snapshot.
+///
+/// This saves callers from sequencing a [`TimerCommand`] and a
+/// [`TimerGet`] to keep their view of the timer up to date.
+///
+/// [`resume`]: TimerResync::resume
+#[derive(Clone, Debug)]
+pub struct TimerResync {
+    timer: Option<String>,
+    send: TimerRequestSend,
+    synced: bool,
+}
+
+impl TimerResync {
+    /// Creates a new coroutine sending the given request, or request
+    /// envelope.
+    pub fn new(request: impl Into<TimerEnvelope>) -> Self {
+        let envelope = request.into();
+
+        Self {
+            timer: envelope.timer.clone(),
+            send: TimerRequestSend::new(envelope),
+            synced: false,
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Sends the request to the timer with the given name, instead
+    /// of the default one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.timer = Some(name.to_string());
+        self.send = self.send.with_timer(name);
+        self
+    }
+
+    /// Attaches the given idempotency key to the request, see
+    /// [`TimerRequestSend::with_idempotency_key`].
+    pub fn with_idempotency_key(mut self, key: impl ToString) -> Self {
+        self.send = self.send.with_idempotency_key(key);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, mut arg: Option<SocketOutput>) -> TimerRequestResult<TimerSnapshot> {
+        if !self.synced {
+            let result = resume(&mut self.send, arg.take(), |response| match response {
+                TimerResponse::Err(_) | TimerResponse::Busy { .. } => Err(response),
+                _ => Ok(()),
+            });
+
+            match result {
+                TimerRequestResult::Ok { .. } => (),
+                TimerRequestResult::Io { input } => return TimerRequestResult::Io { input },
+                TimerRequestResult::Err { err } => return TimerRequestResult::Err { err },
+            }
+
+            let mut envelope = TimerEnvelope::new(TimerRequest::Get);
+            envelope.timer = self.timer.clone();
+            trace!("timer request succeeded, getting fresh timer");
+
+            self.send.send(envelope);
+            self.synced = true;
+        }
+
+        resume(&mut self.send, arg, |response| match response {
+            TimerResponse::Snapshot(snapshot) => Ok(snapshot),
+            response => Err(response),
+        })
+    }
+}
+
+impl Coroutine for TimerResync {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = TimerSnapshot;
+    type Error = TimerRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<TimerSnapshot, SocketInput, TimerRequestError> {
+        TimerResync::resume(self, arg).into()
+    }
+}
+
+/// I/O-free coroutine to send the same [`TimerRequest`] to a group
+/// of timers, and return the outcome for each of them.
+///
+/// Use the named constructors ([`TimerGroupControl::start`],
+/// [`TimerGroupControl::pause`], …). Requests are sent one timer
+/// after the other, over the same connection. See
+/// [`TimerRequestSend`] for the steps of each request.
+///
+/// A timer rejecting the request does not interrupt the group: its
+/// [`TimerError`] is returned along with the events of the other
+/// timers. Any other failure (like a busy server) interrupts the
+/// whole group.
+#[derive(Clone, Debug)]
+pub struct TimerGroupControl {
+    request: TimerRequest,
+    timers: VecDeque<String>,
+    timer: Option<String>,
+    send: TimerRequestSend,
+    results: BTreeMap<String, Result<Vec<TimerStampedEvent>, TimerError>>,
+}
+
+impl TimerGroupControl {
+    /// Creates a new coroutine sending the given request to the
+    /// timers with the given names.
+    pub fn new(request: TimerRequest, timers: impl IntoIterator<Item = impl ToString>) -> Self {
+        let mut timers: VecDeque<String> = timers.into_iter().map(|t| t.to_string()).collect();
+        let timer = timers.pop_front();
+        let mut envelope = TimerEnvelope::new(request.clone());
+        envelope.timer = timer.clone();
+
+        Self {
+            request,
+            timers,
+            timer,
+            send: TimerRequestSend::new(envelope),
+            results: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Start`] to
+    /// the given timers.
+    pub fn start(timers: impl IntoIterator<Item = impl ToString>) -> Self {
+        Self::new(TimerRequest::Start, timers)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Pause`] to
+    /// the given timers.
+    pub fn pause(timers: impl IntoIterator<Item = impl ToString>) -> Self {
+        Self::new(TimerRequest::Pause, timers)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Resume`] to
+    /// the given timers.
+    pub fn resume_timers(timers: impl IntoIterator<Item = impl ToString>) -> Self {
+        Self::new(TimerRequest::Resume, timers)
+    }
+
+    /// Creates a coroutine that sends a [`TimerRequest::Stop`] to
+    /// the given timers.
+    pub fn stop(timers: impl IntoIterator<Item = impl ToString>) -> Self {
+        Self::new(TimerRequest::Stop, timers)
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(
+        &mut self,
+        mut arg: Option<SocketOutput>,
+    ) -> TimerRequestResult<BTreeMap<String, Result<Vec<TimerStampedEvent>, TimerError>>> {
+        loop {
+            let Some(timer) = self.timer.take() else {
+                let value = core::mem::take(&mut self.results);
+                return TimerRequestResult::Ok { value };
+            };
+
+            let result = resume(&mut self.send, arg.take(), |response| match response {
+                TimerResponse::Events(events) => Ok(Ok(events)),
+                TimerResponse::Err(err) => Ok(Err(err)),
+                response => Err(response),
+            });
+
+            match result {
+                TimerRequestResult::Ok { value } => {
+                    trace!("timer {timer} answered {value:?}");
+                    self.results.insert(timer, value);
+                }
+                TimerRequestResult::Io { input } => {
+                    self.timer = Some(timer);
+                    return TimerRequestResult::Io { input };
+                }
+                TimerRequestResult::Err { err } => return TimerRequestResult::Err { err },
+            }
+
+            if let Some(timer) = self.timers.pop_front() {
+                let envelope = TimerEnvelope::new(self.request.clone()).with_timer(&timer);
+                self.send.send(envelope);
+                self.timer = Some(timer);
+            }
+        }
+    }
+}
+
+impl Coroutine for TimerGroupControl {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = BTreeMap<String, Result<Vec<TimerStampedEvent>, TimerError>>;
+    type Error = TimerRequestError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<
+        BTreeMap<String, Result<Vec<TimerStampedEvent>, TimerError>>,
+        SocketInput,
+        TimerRequestError,
+    > {
+        TimerGroupControl::resume(self, arg).into()
+    }
+}
+
+/// Resumes the given request, extracting the expected value from its
+/// response with `f`, which gives the response back otherwise.
+fn resume<T>(
+    send: &mut TimerRequestSend,
+    arg: Option<SocketOutput>,
+    f: impl FnOnce(TimerResponse) -> Result<T, TimerResponse>,
+) -> TimerRequestResult<T> {
+    let response = match send.resume(arg) {
+        TimerRequestSendResult::Ok { response } => response,
+        TimerRequestSendResult::Io { input } => return TimerRequestResult::Io { input },
+        TimerRequestSendResult::Err { err } => {
+            let err = TimerRequestError::Send(err);
+            return TimerRequestResult::Err { err };
+        }
+    };
+
+    let err = match f(response) {
+        Ok(value) => return TimerRequestResult::Ok { value },
+        Err(TimerResponse::Err(err)) => TimerRequestError::Rejected(err),
+        Err(TimerResponse::Busy { retry_after_ms }) => TimerRequestError::Busy(retry_after_ms),
+        Err(response) => TimerRequestError::UnexpectedResponse(Box::new(response)),
+    };
+
+    TimerRequestResult::Err { err }
+}
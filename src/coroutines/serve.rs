@@ -0,0 +1,265 @@
+//! I/O-free coroutine to serve a connection, requests and pushed
+//! events included.
+
+use alloc::{boxed::Box, collections::VecDeque, string::String, vec::Vec};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use log::trace;
+use thiserror::Error;
+
+use crate::{
+    broadcast::{DEFAULT_BROADCAST_CAPACITY, TimerEventInterceptor, enqueue, intercept},
+    coroutines::{
+        push::{TimerEventPush, TimerEventPushError, TimerEventPushResult},
+        server::{
+            TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleError,
+            TimerRequestHandleResult,
+        },
+    },
+    history::TimerHistoryEntry,
+    io::TimeInput,
+    registry::TimerStore,
+    timer::TimerEvent,
+};
+
+/// Error emitted by the [`TimerServe`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimerServeError {
+    #[error("Invalid timer serve arg: {0:?}")]
+    InvalidArg(TimerRequestHandleArg),
+
+    #[error(transparent)]
+    Handle(TimerRequestHandleError),
+    #[error(transparent)]
+    Push(TimerEventPushError),
+}
+
+/// Result emitted on each step of the [`TimerServe`] coroutine.
+#[derive(Debug)]
+pub enum TimerServeResult {
+    /// A request was handled, and emitted the given events.
+    Ok { events: Vec<TimerEvent> },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// A time I/O needs to be performed to make the coroutine
+    /// progress.
+    TimeIo { input: TimeInput },
+    /// The client closed the connection between two requests.
+    Disconnected,
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerServeError },
+}
+
+#[derive(Debug)]
+enum State {
+    /// Handling requests, `idle` once the handle waits for the next
+    /// request.
+    Handle {
+        idle: bool,
+    },
+    Push(TimerEventPush),
+}
+
+/// I/O-free coroutine serving a connection: it handles the requests
+/// of the client, and pushes the events of its subscription (see
+/// [`TimerRequest::Subscribe`]) on the same stream.
+///
+/// Responses and pushed events never interleave, with the following
+/// priority:
+///
+/// 1. The response being written is always written completely.
+/// 2. Then the queued events (see [`publish`]) are pushed, before
+///    the next request is handled.
+/// 3. Then the next request is read, and handled like
+///    [`TimerRequestHandle`] does, returning `Ok { events }` once its
+///    response is written.
+///
+/// While waiting for the next request, runtimes can resume the
+/// coroutine with `None` instead of the read output (for example
+/// when events were published before the socket became readable):
+/// the read is then abandoned to push the queued events first.
+///
+/// [`TimerRequest::Subscribe`]: crate::timer::TimerRequest::Subscribe
+/// [`publish`]: TimerServe::publish
+#[derive(Debug)]
+pub struct TimerServe {
+    handle: TimerRequestHandle,
+    pending: VecDeque<(String, TimerHistoryEntry)>,
+    capacity: usize,
+    interceptors: Vec<Box<dyn TimerEventInterceptor>>,
+    state: State,
+}
+
+impl Default for TimerServe {
+    fn default() -> Self {
+        Self::new(TimerRequestHandle::new())
+    }
+}
+
+impl TimerServe {
+    /// Creates a new coroutine serving requests with the given
+    /// handle.
+    pub fn new(handle: TimerRequestHandle) -> Self {
+        Self {
+            handle,
+            pending: VecDeque::new(),
+            capacity: DEFAULT_BROADCAST_CAPACITY,
+            interceptors: Vec::new(),
+            state: State::Handle { idle: true },
+        }
+    }
+
+    /// Keeps at most the given number of events waiting to be pushed,
+    /// instead of [`DEFAULT_BROADCAST_CAPACITY`].
+    ///
+    /// Once exceeded, the oldest events are dropped and replaced by a
+    /// single [`TimerEvent::Lagged`] marker, like
+    /// [`TimerEventBroadcast::with_capacity`] does.
+    ///
+    /// [`TimerEventBroadcast::with_capacity`]: crate::broadcast::TimerEventBroadcast::with_capacity
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Passes the published events through the given interceptor,
+    /// after the previously registered ones.
+    pub fn with_interceptor(mut self, interceptor: impl TimerEventInterceptor + 'static) -> Self {
+        self.interceptors.push(Box::new(interceptor));
+        self
+    }
+
+    /// Returns the handle serving the requests of the connection.
+    pub fn handle(&self) -> &TimerRequestHandle {
+        &self.handle
+    }
+
+    /// Returns the mutable handle serving the requests of the
+    /// connection, for example to mark it as busy.
+    pub fn handle_mut(&mut self) -> &mut TimerRequestHandle {
+        &mut self.handle
+    }
+
+    /// Queues the given events of the timer with the given name, if
+    /// the connection subscribed to it.
+    ///
+    /// Returns the number of queued events, interceptors excluded.
+    pub fn publish(
+        &mut self,
+        name: &str,
+        entries: impl IntoIterator<Item = TimerHistoryEntry>,
+    ) -> usize {
+        if !self.handle.subscription().is_some_and(|s| s.matches(name)) {
+            return 0;
+        }
+
+        let entries = intercept(&mut self.interceptors, name, entries);
+        let len = entries.len();
+        trace!("queue {len} events of timer {name}");
+
+        for entry in entries {
+            enqueue(&mut self.pending, self.capacity, name, entry);
+        }
+
+        len
+    }
+
+    /// Returns the number of events waiting to be pushed,
+    /// [`TimerEvent::Lagged`] marker included.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(
+        &mut self,
+        timers: &mut impl TimerStore,
+        arg: Option<impl Into<TimerRequestHandleArg>>,
+    ) -> TimerServeResult {
+        let mut arg = arg.map(Into::into);
+
+        loop {
+            match &mut self.state {
+                State::Handle { idle } => {
+                    if *idle && arg.is_none() {
+                        if let Some(push) = self.next_push() {
+                            self.state = State::Push(push);
+                            continue;
+                        }
+                    }
+
+                    return match self.handle.resume(timers, arg.take()) {
+                        TimerRequestHandleResult::Ok { events } => {
+                            self.state = State::Handle { idle: true };
+                            TimerServeResult::Ok { events }
+                        }
+                        TimerRequestHandleResult::Io { input } => {
+                            let idle = matches!(input, SocketInput::Read { .. });
+                            self.state = State::Handle { idle };
+                            TimerServeResult::Io { input }
+                        }
+                        TimerRequestHandleResult::TimeIo { input } => {
+                            self.state = State::Handle { idle: false };
+                            TimerServeResult::TimeIo { input }
+                        }
+                        TimerRequestHandleResult::Disconnected => TimerServeResult::Disconnected,
+                        TimerRequestHandleResult::Err { err } => {
+                            let err = TimerServeError::Handle(err);
+                            TimerServeResult::Err { err }
+                        }
+                    };
+                }
+                State::Push(push) => {
+                    let socket_arg: Option<SocketOutput> = match arg.take() {
+                        None => None,
+                        Some(TimerRequestHandleArg::Socket(output)) => Some(output),
+                        Some(TimerRequestHandleArg::TimedOut) => {
+                            let secs = self.handle.write_timeout().unwrap_or_default();
+                            let err = TimerRequestHandleError::WriteTimeout(secs);
+                            let err = TimerServeError::Handle(err);
+                            return TimerServeResult::Err { err };
+                        }
+                        Some(a) => {
+                            let err = TimerServeError::InvalidArg(a);
+                            return TimerServeResult::Err { err };
+                        }
+                    };
+
+                    match push.resume(socket_arg) {
+                        TimerEventPushResult::Ok => {
+                            self.state = State::Handle { idle: true };
+                        }
+                        TimerEventPushResult::Io { input } => {
+                            return TimerServeResult::Io { input };
+                        }
+                        TimerEventPushResult::Err { err } => {
+                            let err = TimerServeError::Push(err);
+                            return TimerServeResult::Err { err };
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Takes the queued events of the next timer, and returns the
+    /// coroutine pushing them.
+    fn next_push(&mut self) -> Option<TimerEventPush> {
+        let (name, entry) = self.pending.pop_front()?;
+        let mut entries = Vec::from([entry]);
+
+        while self.pending.front().is_some_and(|(next, _)| *next == name) {
+            if let Some((_, entry)) = self.pending.pop_front() {
+                entries.push(entry);
+            }
+        }
+
+        let mut push = TimerEventPush::new(entries).with_session(*self.handle.session());
+        if self.handle.subscription().is_some_and(|s| s.is_wildcard()) {
+            push = push.with_timer(name);
+        }
+
+        Some(push)
+    }
+}
@@ -1,18 +1,45 @@
 //! I/O-free coroutine to receive a timer request and send a response.
 
-use alloc::vec::Vec;
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 
 use io_socket::{
     coroutines::{read::*, write::*},
     io::{SocketInput, SocketOutput},
 };
+#[cfg(not(feature = "tracing"))]
 use log::{debug, trace};
 use thiserror::Error;
+#[cfg(feature = "tracing")]
+use tracing::{debug, debug_span, field, trace};
 
 use crate::{
-    coroutines::now::{TimeNow, TimeNowError, TimeNowResult},
+    audit::{TimerAuditEntry, TimerAuditSink},
+    codec::CodecError,
+    compression::CompressionError,
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::DEFAULT_READ_BUFFER_SIZE,
+        now::{TimeNow, TimeNowError, TimeNowResult},
+    },
+    frame::{FrameDecoder, FrameError},
     io::{TimeInput, TimeOutput},
-    timer::{Timer, TimerEvent, TimerRequest, TimerResponse},
+    metrics::TimerServerMetrics,
+    middleware::{TimerMiddleware, TimerRouter},
+    notify::{Notifiers, Notify, TimerNotification},
+    rate_limit::RateLimit,
+    registry::{DEFAULT_TIMER, TimerStore, WILDCARD_TIMER},
+    session::{Permission, Session},
+    stats::{MAX_EXPORT_PAGE_SIZE, TimerStatsPage},
+    timer::{
+        Timer, TimerCapabilities, TimerEnvelope, TimerError, TimerEvent, TimerRequest,
+        TimerResponse, TimerSnapshot, TimerStampedEvent, TimerStatus, TimerStatusLine,
+    },
 };
 
 /// Unified argument passed back to [`TimerRequestHandle`] after any
@@ -23,6 +50,9 @@ pub enum TimerRequestHandleArg {
     Time(TimeOutput),
     /// Response to a [`SocketInput`] request.
     Socket(SocketOutput),
+    /// The [`SocketInput::Write`] request did not complete within the
+    /// write timeout, see [`TimerRequestHandle::with_write_timeout`].
+    TimedOut,
 }
 
 impl From<TimeOutput> for TimerRequestHandleArg {
@@ -37,6 +67,16 @@ impl From<SocketOutput> for TimerRequestHandleArg {
     }
 }
 
+/// Unified I/O request emitted by [`TimerRequestHandle`] when driven
+/// through the [`Coroutine`] trait, see [`TimerRequestHandle::bind`].
+#[derive(Clone, Debug)]
+pub enum TimerRequestHandleInput {
+    /// A time I/O request.
+    Time(TimeInput),
+    /// A socket I/O request.
+    Socket(SocketInput),
+}
+
 /// Error emitted by the [`TimerRequestHandle`] coroutine.
 #[derive(Debug, Error)]
 pub enum TimerRequestHandleError {
@@ -47,9 +87,11 @@ pub enum TimerRequestHandleError {
     TimeNow(TimeNowError),
 
     #[error("Failed to serialize timer response")]
-    Serialize(#[source] serde_json::Error),
-    #[error("Failed to deserialize timer request")]
-    Deserialize(#[source] serde_json::Error),
+    Serialize(#[source] CodecError),
+    #[error("Failed to compress timer response")]
+    Compress(#[source] CompressionError),
+    #[error("Failed to decode timer request frame")]
+    Frame(#[source] FrameError),
 
     #[error("Reached unexpected EOF while reading request")]
     ReadEof,
@@ -60,6 +102,11 @@ pub enum TimerRequestHandleError {
     WriteEof,
     #[error(transparent)]
     Write(SocketWriteError),
+    #[error("Response write stalled for more than {0}s")]
+    WriteTimeout(u64),
+
+    #[error("Server is shutting down")]
+    Shutdown,
 }
 
 /// Result emitted on each step of the [`TimerRequestHandle`]
@@ -74,155 +121,643 @@ pub enum TimerRequestHandleResult {
     /// A time I/O needs to be performed to make the coroutine
     /// progress.
     TimeIo { input: TimeInput },
+    /// The client closed the connection between two requests.
+    Disconnected,
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerRequestHandleError },
+}
+
+/// Result emitted on each step of [`TimerRequestHandle::resume_io`].
+#[derive(Debug)]
+pub enum TimerRequestHandleIoResult {
+    /// The request was received, and needs to be applied with
+    /// [`TimerRequestHandle::apply`] before resuming the coroutine
+    /// again.
+    Apply,
+    /// The coroutine has successfully terminated its progression.
+    Ok { events: Vec<TimerEvent> },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// A time I/O needs to be performed to make the coroutine
+    /// progress.
+    TimeIo { input: TimeInput },
+    /// The client closed the connection between two requests.
+    Disconnected,
     /// The coroutine encountered an unrecoverable error.
     Err { err: TimerRequestHandleError },
 }
 
 #[derive(Clone, Debug)]
 enum State {
-    Read(SocketRead),
+    Read,
     Deserialize(Vec<u8>),
-    GetTime(Option<TimerRequest>, TimeNow),
+    GetTime(Option<TimerEnvelope>, TimeNow),
+    Apply(TimerEnvelope, Option<u64>),
+    Reject(FrameError),
     Write(SocketWrite),
+    Stream(Streamed),
+}
+
+/// The next frame of a streamed response, built from the timer store
+/// once the previous frame is written.
+#[derive(Clone, Debug)]
+enum Streamed {
+    History { timer: Option<String>, cursor: u64 },
+    Stats { cursor: String, now: u64 },
+}
+
+/// The subscription of a connection, see
+/// [`TimerRequest::Subscribe`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TimerSubscription {
+    /// The name of the subscribed timer, `None` for the
+    /// [`DEFAULT_TIMER`], or [`WILDCARD_TIMER`] for every timer.
+    pub timer: Option<String>,
+    /// The id of the durable subscription, if any.
+    pub id: Option<String>,
+}
+
+impl TimerSubscription {
+    /// Returns `true` if the subscription covers every timer.
+    ///
+    /// Events pushed to such subscriptions should be tagged with the
+    /// name of their timer, see [`TimerEventPush::with_timer`].
+    ///
+    /// [`TimerEventPush::with_timer`]: crate::coroutines::push::TimerEventPush::with_timer
+    pub fn is_wildcard(&self) -> bool {
+        self.timer.as_deref() == Some(WILDCARD_TIMER)
+    }
+
+    /// Returns `true` if the events of the timer with the given name
+    /// should be pushed to the subscription.
+    pub fn matches(&self, name: &str) -> bool {
+        self.is_wildcard() || self.timer.as_deref().unwrap_or(DEFAULT_TIMER) == name
+    }
 }
 
 /// I/O-free coroutine to handle one complete timer request-response
-/// cycle over NDJSON.
+/// cycle.
 ///
 /// Each call to [`resume`] advances one step:
 ///
-/// 1. Emit [`SocketInput::Read`] to receive a JSON-encoded
-///    [`TimerRequest`] line.
+/// 1. Emit [`SocketInput::Read`] until an encoded [`TimerRequest`]
+///    frame is fully received.
 /// 2. Optionally emit [`TimeInput::Now`] for time-dependent requests,
 ///    driven by a [`TimeNow`] sub-coroutine.
-/// 3. Apply the request to the [`Timer`].
-/// 4. Emit [`SocketInput::Write`] with the JSON-encoded
-///    [`TimerResponse`] line.
+/// 3. Apply the request to the targeted [`Timer`] of the
+///    [`TimerStore`] (see [`resume_io`] to only borrow the store for
+///    this step).
+/// 4. Emit [`SocketInput::Write`] with the encoded [`TimerResponse`]
+///    frame.
 /// 5. Return `Ok { events }` once the write completes.
 ///
+/// Clients closing the connection instead of sending the next request
+/// make the coroutine return `Disconnected`, while closing it in the
+/// middle of a request fails with
+/// [`TimerRequestHandleError::ReadEof`].
+///
+/// Requests which cannot be decoded are answered with
+/// [`TimerError::MalformedRequest`] instead, without closing the
+/// connection.
+///
+/// The coroutine can then be resumed again to handle the next request
+/// of the same connection. Requests pipelined in the same read are
+/// kept buffered, and handled without further read.
+///
+/// Once the connection subscribed (see [`subscription`]), the runtime
+/// is expected to push the events of the subscribed timer using the
+/// [`TimerEventPush`] coroutine.
+///
+/// [`subscription`]: TimerRequestHandle::subscription
+/// [`TimerEventPush`]: crate::coroutines::push::TimerEventPush
 /// [`resume`]: TimerRequestHandle::resume
+/// [`resume_io`]: TimerRequestHandle::resume_io
 #[derive(Debug)]
 pub struct TimerRequestHandle {
+    session: Session,
     state: State,
+    buf: Vec<u8>,
+    decoder: FrameDecoder,
     events: Option<Vec<TimerEvent>>,
+    rate_limit: Option<RateLimit>,
+    audit: Option<(String, Box<dyn TimerAuditSink>)>,
+    notifiers: Notifiers,
+    redacted: bool,
+    busy: Option<u64>,
+    subscriptions: bool,
+    subscription: Option<TimerSubscription>,
+    closing: Option<FrameError>,
+    streamed: Option<Streamed>,
+    router: TimerRouter,
+    write_timeout: Option<u64>,
+    metrics: TimerServerMetrics,
+}
+
+impl Default for TimerRequestHandle {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TimerRequestHandle {
     /// Creates a new coroutine.
     pub fn new() -> Self {
         Self {
-            state: State::Read(SocketRead::default()),
+            session: Session::default(),
+            state: State::Read,
+            buf: Vec::new(),
+            decoder: FrameDecoder::new(),
             events: None,
+            rate_limit: None,
+            audit: None,
+            notifiers: Notifiers::new(),
+            redacted: false,
+            busy: None,
+            subscriptions: true,
+            subscription: None,
+            closing: None,
+            streamed: None,
+            router: TimerRouter::new(),
+            write_timeout: None,
+            metrics: TimerServerMetrics::default(),
         }
     }
 
+    /// Uses the given session, previously negotiated with
+    /// [`TimerAccept`].
+    ///
+    /// [`TimerAccept`]: crate::coroutines::accept::TimerAccept
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.decoder.set_framing(session.framing());
+        self.session = session;
+        self
+    }
+
+    /// Rejects request frames larger than the given number of bytes,
+    /// instead of [`DEFAULT_MAX_FRAME_SIZE`].
+    ///
+    /// Oversized frames are answered with
+    /// [`TimerError::FrameTooLarge`], then the coroutine fails with
+    /// [`TimerRequestHandleError::Frame`] so that the connection can
+    /// be closed.
+    ///
+    /// [`DEFAULT_MAX_FRAME_SIZE`]: crate::frame::DEFAULT_MAX_FRAME_SIZE
+    pub fn with_max_frame_size(mut self, max: usize) -> Self {
+        self.decoder.set_max_frame_size(max);
+        self
+    }
+
+    /// Gives up responses whose write stalls for more than the given
+    /// number of seconds, so that a wedged client cannot hold the
+    /// connection forever.
+    ///
+    /// Since I/O-free coroutines cannot observe time while waiting for
+    /// a write, the timeout is enforced by the runtime, typically by
+    /// using it as write timeout of the socket (see
+    /// [`write_timeout`]). Writes which time out are reported by
+    /// resuming the coroutine with [`TimerRequestHandleArg::TimedOut`]:
+    /// it then fails with [`TimerRequestHandleError::WriteTimeout`],
+    /// so that the connection can be closed.
+    ///
+    /// [`write_timeout`]: TimerRequestHandle::write_timeout
+    pub fn with_write_timeout(mut self, secs: u64) -> Self {
+        self.write_timeout = Some(secs);
+        self
+    }
+
+    /// Returns the write timeout, in seconds, see
+    /// [`with_write_timeout`].
+    ///
+    /// [`with_write_timeout`]: TimerRequestHandle::with_write_timeout
+    pub fn write_timeout(&self) -> Option<u64> {
+        self.write_timeout
+    }
+
+    /// Reserves a receive buffer of the given number of bytes.
+    ///
+    /// The buffer is kept and reused by the next requests, so that
+    /// high-frequency servers do not reallocate it for every
+    /// request.
+    pub fn with_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.decoder.reserve(capacity);
+        self
+    }
+
+    /// Reads up to the given number of bytes at once, instead of
+    /// [`DEFAULT_READ_BUFFER_SIZE`].
+    pub fn with_read_buffer_size(self, size: usize) -> Self {
+        self.with_read_buffer(vec![0; size])
+    }
+
+    /// Reads into the given buffer, up to its length.
+    ///
+    /// The buffer is handed to the runtime on each read, then kept
+    /// for the next reads and the next requests of the connection.
+    /// It can be taken back with [`take_read_buffer`], to be reused
+    /// by another connection.
+    ///
+    /// [`take_read_buffer`]: TimerRequestHandle::take_read_buffer
+    pub fn with_read_buffer(mut self, buf: Vec<u8>) -> Self {
+        self.buf = buf;
+        self
+    }
+
+    /// Takes the read buffer back, leaving the default one in place.
+    ///
+    /// The buffer is only available between two reads, for example
+    /// once the connection is closed.
+    pub fn take_read_buffer(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.buf)
+    }
+
+    /// Marks the server as busy (for example because its event queue
+    /// is full), or not busy anymore with `None`.
+    ///
+    /// While busy, requests mutating a timer are answered with
+    /// [`TimerResponse::Busy`] and the given number of milliseconds,
+    /// instead of being applied. Can be called between two requests
+    /// of the same connection.
+    pub fn set_busy(&mut self, retry_after_ms: Option<u64>) {
+        self.busy = retry_after_ms;
+    }
+
+    /// Grants the given permission to the connection, instead of the
+    /// one of its session.
+    ///
+    /// Requests mutating a timer on [`Permission::Observer`]
+    /// connections are answered with [`TimerError::Forbidden`].
+    pub fn with_permission(mut self, permission: Permission) -> Self {
+        self.set_permission(permission);
+        self
+    }
+
+    /// Changes the permission of the connection, for example once the
+    /// client authenticated. Can be called between two requests of
+    /// the same connection.
+    pub fn set_permission(&mut self, permission: Permission) {
+        self.session.permission = permission;
+    }
+
+    /// Binds the coroutine to the given timer store, so that it can be
+    /// driven through the [`Coroutine`] trait.
+    pub fn bind<'a, S: TimerStore>(
+        &'a mut self,
+        timers: &'a mut S,
+    ) -> BoundTimerRequestHandle<'a, S> {
+        BoundTimerRequestHandle {
+            handle: self,
+            timers,
+        }
+    }
+
+    /// Returns the session of the connection.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Returns `true` if the coroutine waits for the next request,
+    /// without any byte of it received yet.
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state, State::Read) && self.decoder.is_empty()
+    }
+
+    /// Returns the counters of the requests handled on the
+    /// connection.
+    pub fn metrics(&self) -> &TimerServerMetrics {
+        &self.metrics
+    }
+
+    /// Returns the subscription of the connection, if the client
+    /// subscribed.
+    pub fn subscription(&self) -> Option<&TimerSubscription> {
+        self.subscription.as_ref()
+    }
+
+    /// Accepts subscriptions, which is the default.
+    ///
+    /// Runtimes unable to push events to their clients should disable
+    /// them: [`TimerRequest::Subscribe`] and [`TimerRequest::Ack`] are
+    /// then answered with [`TimerError::Unsupported`], and
+    /// [`TimerCapabilities::subscribe`] is `false`. Subscriptions are
+    /// always disabled on datagram sessions, which have no connection
+    /// to push events to.
+    pub fn with_subscriptions(mut self, enabled: bool) -> Self {
+        self.subscriptions = enabled;
+        self
+    }
+
+    /// Returns `true` if clients can subscribe to events, see
+    /// [`with_subscriptions`].
+    ///
+    /// [`with_subscriptions`]: TimerRequestHandle::with_subscriptions
+    pub fn subscriptions(&self) -> bool {
+        self.subscriptions && !self.session.datagram
+    }
+
+    /// Answers [`TimerRequest::Get`] with a redacted
+    /// [`TimerResponse::Status`] instead of the whole timer, so that
+    /// the timer status can be exposed to untrusted clients without
+    /// leaking its configuration.
+    pub fn with_redacted(mut self, redacted: bool) -> Self {
+        self.redacted = redacted;
+        self
+    }
+
+    /// Routes the requests of the connection through the given
+    /// middleware, after the previously registered ones.
+    ///
+    /// See [`TimerMiddleware`] for the calling order.
+    pub fn with_middleware(mut self, middleware: impl TimerMiddleware + 'static) -> Self {
+        self.router.push(middleware);
+        self
+    }
+
+    /// Limits the number of requests the connection can issue.
+    ///
+    /// Requests exceeding the limit are answered with
+    /// [`TimerError::TooManyRequests`] without being applied. Since
+    /// the limit is per coroutine, the same coroutine needs to be
+    /// resumed for every request of the connection.
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Records every request of the connection into the given audit
+    /// sink, under the given connection id.
+    ///
+    /// Audited requests are stamped with the current time, which
+    /// requires a time I/O for every request. See [`TimerAuditLog`]
+    /// for an in-memory sink.
+    ///
+    /// [`TimerAuditLog`]: crate::audit::TimerAuditLog
+    pub fn with_audit(mut self, id: impl ToString, sink: impl TimerAuditSink + 'static) -> Self {
+        self.audit = Some((id.to_string(), Box::new(sink)));
+        self
+    }
+
+    /// Notifies the given sink of every event emitted by the requests
+    /// of the connection.
+    pub fn with_notifier(mut self, notifier: impl Notify + 'static) -> Self {
+        self.notifiers.push(notifier);
+        self
+    }
+
     /// Advances the coroutine by one step.
+    ///
+    /// The timer store is only accessed once the request is received,
+    /// see [`resume_io`] to borrow it for that step only.
+    ///
+    /// [`resume_io`]: TimerRequestHandle::resume_io
     pub fn resume(
         &mut self,
-        timer: &mut Timer,
-        mut arg: Option<impl Into<TimerRequestHandleArg>>,
+        timers: &mut impl TimerStore,
+        arg: Option<impl Into<TimerRequestHandleArg>>,
     ) -> TimerRequestHandleResult {
+        let mut arg = arg.map(Into::into);
+
+        loop {
+            return match self.resume_io(arg.take()) {
+                TimerRequestHandleIoResult::Apply => match self.apply(timers) {
+                    Ok(()) => continue,
+                    Err(err) => TimerRequestHandleResult::Err { err },
+                },
+                TimerRequestHandleIoResult::Ok { events } => {
+                    TimerRequestHandleResult::Ok { events }
+                }
+                TimerRequestHandleIoResult::Io { input } => TimerRequestHandleResult::Io { input },
+                TimerRequestHandleIoResult::TimeIo { input } => {
+                    TimerRequestHandleResult::TimeIo { input }
+                }
+                TimerRequestHandleIoResult::Disconnected => TimerRequestHandleResult::Disconnected,
+                TimerRequestHandleIoResult::Err { err } => TimerRequestHandleResult::Err { err },
+            };
+        }
+    }
+
+    /// Advances the coroutine by one step, without the timer store.
+    ///
+    /// Returns [`TimerRequestHandleIoResult::Apply`] once the request
+    /// is received: the store then needs to be given to [`apply`],
+    /// before resuming the coroutine again with `None`. Embedders
+    /// sharing the store between connections (for example behind a
+    /// mutex) only need to lock it for that step, instead of for
+    /// every socket I/O.
+    ///
+    /// [`apply`]: TimerRequestHandle::apply
+    pub fn resume_io(
+        &mut self,
+        mut arg: Option<impl Into<TimerRequestHandleArg>>,
+    ) -> TimerRequestHandleIoResult {
         loop {
             match &mut self.state {
-                State::Read(r) => {
-                    let socket_arg = match arg.take().map(Into::into) {
-                        None => None,
-                        Some(TimerRequestHandleArg::Socket(output)) => Some(output),
-                        Some(a) => {
-                            let err = TimerRequestHandleError::InvalidArg(a);
-                            return TimerRequestHandleResult::Err { err };
-                        }
-                    };
-                    match r.resume(socket_arg) {
-                        SocketReadResult::Ok { mut buf, n } => {
-                            buf.truncate(n);
-                            self.state = State::Deserialize(buf);
-                        }
-                        SocketReadResult::Io { input } => {
-                            return TimerRequestHandleResult::Io { input };
-                        }
-                        SocketReadResult::Eof => {
-                            let err = TimerRequestHandleError::ReadEof;
-                            return TimerRequestHandleResult::Err { err };
+                State::Read => match arg.take().map(Into::into) {
+                    None => {
+                        let mut buf = core::mem::take(&mut self.buf);
+                        if buf.is_empty() {
+                            buf.resize(DEFAULT_READ_BUFFER_SIZE, 0);
                         }
-                        SocketReadResult::Err { err } => {
-                            let err = TimerRequestHandleError::Read(err);
-                            return TimerRequestHandleResult::Err { err };
+                        let input = SocketInput::Read { buf };
+                        return TimerRequestHandleIoResult::Io { input };
+                    }
+                    Some(TimerRequestHandleArg::Socket(SocketOutput::Read { n: 0, buf })) => {
+                        self.buf = buf;
+
+                        // closing in the middle of a request is not
+                        // a clean disconnection
+                        if self.decoder.is_empty() {
+                            debug!("connection closed by the client");
+                            return TimerRequestHandleIoResult::Disconnected;
                         }
+
+                        let err = TimerRequestHandleError::ReadEof;
+                        return TimerRequestHandleIoResult::Err { err };
                     }
-                }
+                    Some(TimerRequestHandleArg::Socket(SocketOutput::Read { buf, n })) => {
+                        self.metrics.bytes_read += n as u64;
+                        self.decoder.push(&buf[..n]);
+                        self.buf = buf;
+                        self.state = match self.decoder.next_frame() {
+                            Ok(Some(frame)) => State::Deserialize(frame),
+                            Ok(None) => State::Read,
+                            Err(err) => State::Reject(err),
+                        };
+                    }
+                    Some(a) => {
+                        let err = TimerRequestHandleError::InvalidArg(a);
+                        return TimerRequestHandleIoResult::Err { err };
+                    }
+                },
                 State::Deserialize(bytes) => {
-                    let bytes = bytes.trim_ascii_end();
-                    let request: TimerRequest = match serde_json::from_slice(bytes) {
-                        Ok(r) => r,
-                        Err(e) => {
-                            let err = TimerRequestHandleError::Deserialize(e);
-                            return TimerRequestHandleResult::Err { err };
+                    // the frame is delimited, so that the next request
+                    // can still be decoded
+                    let bytes = match self.session.checksum.verify(core::mem::take(bytes)) {
+                        Ok(bytes) => bytes,
+                        Err(err) => {
+                            debug!("reject corrupted request: {err}");
+                            if let Err(err) = self.reject_malformed() {
+                                return TimerRequestHandleIoResult::Err { err };
+                            }
+                            continue;
                         }
                     };
-                    debug!("received request: {request:?}");
-                    match request {
-                        TimerRequest::Get | TimerRequest::Stop | TimerRequest::Set(_) => {
-                            match self.serialize_response(timer, &request, None) {
-                                Ok(bytes) => self.state = State::Write(SocketWrite::new(bytes)),
-                                Err(err) => return TimerRequestHandleResult::Err { err },
+                    // unsigned or forged requests are never applied
+                    #[cfg(feature = "signing")]
+                    let bytes = match &self.session.signing_key {
+                        None => bytes,
+                        Some(key) => match key.verify(bytes) {
+                            Ok(bytes) => bytes,
+                            Err(err) => {
+                                debug!("reject unsigned or forged request: {err}");
+                                if let Err(err) = self.reject_malformed() {
+                                    return TimerRequestHandleIoResult::Err { err };
+                                }
+                                continue;
                             }
+                        },
+                    };
+                    let envelope: TimerEnvelope = match self.session.codec.decode(&bytes) {
+                        Ok(r) => r,
+                        Err(err) => {
+                            debug!("reject malformed request: {err}");
+                            if let Err(err) = self.reject_malformed() {
+                                return TimerRequestHandleIoResult::Err { err };
+                            }
+                            continue;
+                        }
+                    };
+                    debug!("received request: {envelope:?}");
+                    let name = envelope.request.name();
+                    *self.metrics.requests.entry(name).or_default() += 1;
+
+                    // idempotency keys are remembered and requests are
+                    // counted for a limited time, and audited requests
+                    // are stamped, which requires the current time
+                    let keyed = envelope.key.is_some() && envelope.request.is_mutating();
+                    let timed = keyed || self.rate_limit.is_some() || self.audit.is_some();
+
+                    match envelope.request {
+                        TimerRequest::Get
+                        | TimerRequest::Capabilities
+                        | TimerRequest::Version
+                        | TimerRequest::List
+                        | TimerRequest::Ping
+                        | TimerRequest::GetHistory { .. }
+                        | TimerRequest::StreamHistory { .. }
+                        | TimerRequest::Subscribe { .. }
+                        | TimerRequest::Ack { .. }
+                        | TimerRequest::Status
+                            if !timed =>
+                        {
+                            self.state = State::Apply(envelope, None);
                         }
                         _ => {
+                            // events are stamped with the current time
                             trace!("wants time I/O before processing request");
-                            self.state = State::GetTime(Some(request), TimeNow::new());
+                            self.state = State::GetTime(Some(envelope), TimeNow::new());
                         }
                     }
                 }
-                State::GetTime(request, time_now) => {
+                State::GetTime(envelope, time_now) => {
                     let time_arg = match arg.take().map(Into::into) {
                         None => None,
                         Some(TimerRequestHandleArg::Time(output)) => Some(output),
                         Some(a) => {
                             let err = TimerRequestHandleError::InvalidArg(a);
-                            return TimerRequestHandleResult::Err { err };
+                            return TimerRequestHandleIoResult::Err { err };
                         }
                     };
                     match time_now.resume(time_arg) {
                         TimeNowResult::Ok { secs, .. } => {
-                            let request = request.take().unwrap();
-                            match self.serialize_response(timer, &request, Some(secs)) {
-                                Ok(bytes) => self.state = State::Write(SocketWrite::new(bytes)),
-                                Err(err) => return TimerRequestHandleResult::Err { err },
-                            }
+                            self.state = State::Apply(envelope.take().unwrap(), Some(secs));
                         }
                         TimeNowResult::Io { input } => {
-                            return TimerRequestHandleResult::TimeIo { input };
+                            return TimerRequestHandleIoResult::TimeIo { input };
                         }
                         TimeNowResult::Err { err } => {
                             let err = TimerRequestHandleError::TimeNow(err);
-                            return TimerRequestHandleResult::Err { err };
+                            return TimerRequestHandleIoResult::Err { err };
+                        }
+                    }
+                }
+                State::Apply(..) | State::Stream(_) => return TimerRequestHandleIoResult::Apply,
+                State::Reject(err) => {
+                    let err = err.clone();
+                    debug!("reject request frame: {err}");
+                    self.metrics.decode_failures += 1;
+                    let (response, closing) = match err {
+                        // the buffer is discarded, so that the next
+                        // request cannot be found anymore
+                        FrameError::TooLarge { max, .. } => {
+                            let response = TimerResponse::Err(TimerError::FrameTooLarge { max });
+                            (response, Some(err))
+                        }
+                        // the armored line is fully consumed, so that
+                        // the next request can still be decoded
+                        FrameError::Armor(_) => {
+                            (TimerResponse::Err(TimerError::MalformedRequest), None)
                         }
+                    };
+                    match self.encode_response(&response) {
+                        Ok(bytes) => {
+                            self.closing = closing;
+                            self.state = State::Write(SocketWrite::new(bytes));
+                        }
+                        Err(err) => return TimerRequestHandleIoResult::Err { err },
                     }
                 }
                 State::Write(w) => {
                     let socket_arg = match arg.take().map(Into::into) {
                         None => None,
                         Some(TimerRequestHandleArg::Socket(output)) => Some(output),
+                        Some(TimerRequestHandleArg::TimedOut) => {
+                            let secs = self.write_timeout.unwrap_or_default();
+                            debug!("response write stalled for more than {secs}s");
+                            let err = TimerRequestHandleError::WriteTimeout(secs);
+                            return TimerRequestHandleIoResult::Err { err };
+                        }
                         Some(a) => {
                             let err = TimerRequestHandleError::InvalidArg(a);
-                            return TimerRequestHandleResult::Err { err };
+                            return TimerRequestHandleIoResult::Err { err };
                         }
                     };
 
                     return match w.resume(socket_arg) {
-                        SocketWriteResult::Ok { .. } => {
+                        SocketWriteResult::Ok { n, .. } => {
+                            self.metrics.bytes_written += n as u64;
+
+                            // streamed responses are built one frame at
+                            // a time, from the timer store
+                            if let Some(streamed) = self.streamed.take() {
+                                self.state = State::Stream(streamed);
+                                continue;
+                            }
+
+                            // the client may have sent the next request
+                            // along with this one
+                            if let Some(err) = self.closing.take() {
+                                let err = TimerRequestHandleError::Frame(err);
+                                return TimerRequestHandleIoResult::Err { err };
+                            }
+
+                            self.state = match self.decoder.next_frame() {
+                                Ok(Some(frame)) => State::Deserialize(frame),
+                                Ok(None) => State::Read,
+                                Err(err) => State::Reject(err),
+                            };
                             let events = self.events.take().unwrap_or_default();
-                            TimerRequestHandleResult::Ok { events }
+                            self.metrics.events += events.len() as u64;
+                            TimerRequestHandleIoResult::Ok { events }
                         }
-                        SocketWriteResult::Io { input } => TimerRequestHandleResult::Io { input },
+                        SocketWriteResult::Io { input } => TimerRequestHandleIoResult::Io { input },
                         SocketWriteResult::Eof => {
                             let err = TimerRequestHandleError::WriteEof;
-                            TimerRequestHandleResult::Err { err }
+                            TimerRequestHandleIoResult::Err { err }
                         }
                         SocketWriteResult::Err { err } => {
                             let err = TimerRequestHandleError::Write(err);
-                            TimerRequestHandleResult::Err { err }
+                            TimerRequestHandleIoResult::Err { err }
                         }
                     };
                 }
@@ -230,39 +765,849 @@ impl TimerRequestHandle {
         }
     }
 
-    fn serialize_response(
+    /// Applies the received request to the given timer store, and
+    /// prepares its response.
+    ///
+    /// Does nothing unless [`resume_io`] returned
+    /// [`TimerRequestHandleIoResult::Apply`].
+    ///
+    /// [`resume_io`]: TimerRequestHandle::resume_io
+    pub fn apply(&mut self, timers: &mut impl TimerStore) -> Result<(), TimerRequestHandleError> {
+        let (envelope, secs) = match core::mem::replace(&mut self.state, State::Read) {
+            State::Apply(envelope, secs) => (envelope, secs),
+            State::Stream(streamed) => {
+                let response = self.stream(timers, streamed);
+                let bytes = self.encode_response(&response)?;
+                self.state = State::Write(SocketWrite::new(bytes));
+                return Ok(());
+            }
+            state => {
+                self.state = state;
+                return Ok(());
+            }
+        };
+
+        #[cfg(feature = "tracing")]
+        let span = debug_span!(
+            "timer_request",
+            request = envelope.request.name(),
+            timer = envelope.timer.as_deref().unwrap_or(DEFAULT_TIMER),
+            events = field::Empty,
+        )
+        .entered();
+
+        let bytes = self.serialize_response(timers, &envelope, secs)?;
+        self.state = State::Write(SocketWrite::new(bytes));
+
+        #[cfg(feature = "tracing")]
+        span.record("events", self.events.as_ref().map_or(0, Vec::len));
+
+        Ok(())
+    }
+
+    fn serialize_response<S: TimerStore>(
         &mut self,
-        timer: &mut Timer,
-        request: &TimerRequest,
+        timers: &mut S,
+        envelope: &TimerEnvelope,
         secs: Option<u64>,
     ) -> Result<Vec<u8>, TimerRequestHandleError> {
-        let response = match request {
-            TimerRequest::Get => TimerResponse::Timer(timer.clone()),
-            TimerRequest::Stop => TimerResponse::Events(timer.stop().into_iter().collect()),
-            TimerRequest::Set(d) => TimerResponse::Events(timer.set(*d).into_iter().collect()),
-            TimerRequest::Start => {
-                TimerResponse::Events(timer.start(secs.unwrap()).into_iter().collect())
+        let session = self.session;
+        let mut router = core::mem::take(&mut self.router);
+        let response = router.route(&session, envelope, || self.respond(timers, envelope, secs));
+        self.router = router;
+
+        // the response of a streamed request may have been replaced
+        if !matches!(
+            response,
+            TimerResponse::History(_) | TimerResponse::StatsExport(_)
+        ) {
+            self.streamed = None;
+        }
+
+        if let (Some((id, sink)), Some(at)) = (&mut self.audit, secs) {
+            sink.record(TimerAuditEntry {
+                at,
+                session: id.clone(),
+                request: envelope.request.name(),
+                timer: envelope.timer.clone(),
+                outcome: (&response).into(),
+            });
+        }
+
+        self.encode_response(&response)
+    }
+
+    /// Answers the request being decoded with
+    /// [`TimerError::MalformedRequest`].
+    fn reject_malformed(&mut self) -> Result<(), TimerRequestHandleError> {
+        self.metrics.decode_failures += 1;
+        let response = TimerResponse::Err(TimerError::MalformedRequest);
+        let bytes = self.encode_response(&response)?;
+        self.state = State::Write(SocketWrite::new(bytes));
+        Ok(())
+    }
+
+    fn encode_response(
+        &self,
+        response: &TimerResponse,
+    ) -> Result<Vec<u8>, TimerRequestHandleError> {
+        let bytes = self
+            .session
+            .codec
+            .encode(response)
+            .map_err(TimerRequestHandleError::Serialize)?;
+        let bytes = self
+            .session
+            .compression
+            .compress(bytes)
+            .map_err(TimerRequestHandleError::Compress)?;
+        let bytes = self.session.checksum.append(bytes);
+
+        Ok(self.session.framing().encode(bytes))
+    }
+
+    fn respond<S: TimerStore>(
+        &mut self,
+        timers: &mut S,
+        envelope: &TimerEnvelope,
+        now: Option<u64>,
+    ) -> TimerResponse {
+        if let (Some(limit), Some(now)) = (&mut self.rate_limit, now) {
+            if let Err(retry_after) = limit.check(now) {
+                debug!("too many requests, retry after {retry_after}s");
+                return TimerResponse::Err(TimerError::TooManyRequests { retry_after });
+            }
+        }
+
+        if self.session.permission == Permission::Observer && envelope.request.is_mutating() {
+            debug!("mutating request forbidden to observer");
+            return TimerResponse::Err(TimerError::Forbidden);
+        }
+
+        match (&envelope.request, now) {
+            // requests answered by the server itself, regardless of
+            // the targeted timer
+            (TimerRequest::Capabilities, _) => {
+                let capabilities = TimerCapabilities {
+                    multi_timer: S::MULTI_TIMER,
+                    subscribe: self.subscriptions(),
+                    ..Default::default()
+                };
+                TimerResponse::Capabilities(capabilities)
+            }
+            (TimerRequest::Version, _) => TimerResponse::Version(Default::default()),
+            (TimerRequest::List, _) => TimerResponse::List(timers.list()),
+            (TimerRequest::ExportStats { cursor }, Some(now)) => {
+                TimerResponse::StatsExport(self.stats_page(timers, cursor.as_deref(), now))
+            }
+            (TimerRequest::Ping, _) => TimerResponse::Pong,
+            (TimerRequest::Create { name, config }, _) => {
+                // stores are not trusted to validate configurations
+                if let Err(err) = config.validate() {
+                    debug!("reject invalid timer config: {err}");
+                    return TimerResponse::Err(err);
+                }
+                if let Err(err) = timers.create(name, config.clone()) {
+                    return TimerResponse::Err(err);
+                }
+                if let Some(timer) = timers.get_mut(Some(name)) {
+                    let snapshot = TimerSnapshot::from(&*timer);
+                    timers.changed(name, Some(&snapshot));
+                }
+                TimerResponse::Ok
+            }
+            (TimerRequest::Delete { name }, _) => match timers.delete(name) {
+                Ok(()) => {
+                    timers.changed(name, None);
+                    TimerResponse::Ok
+                }
+                Err(err) => TimerResponse::Err(err),
+            },
+            (TimerRequest::Extension { name, payload }, _) => {
+                let timer = envelope.timer.as_deref();
+                match timers.extension(timer, name, payload) {
+                    Ok(value) => TimerResponse::Extension(value),
+                    Err(err) => TimerResponse::Err(err),
+                }
+            }
+            (TimerRequest::Subscribe { .. } | TimerRequest::Ack { .. }, _)
+                if !self.subscriptions() =>
+            {
+                debug!("subscriptions disabled");
+                TimerResponse::Err(TimerError::Unsupported)
+            }
+            (TimerRequest::Ack { seq }, _) => self.ack(timers, *seq),
+            (TimerRequest::Subscribe { id }, _)
+                if envelope.timer.as_deref() == Some(WILDCARD_TIMER) =>
+            {
+                // durable subscriptions rely on the history of a
+                // single timer
+                if id.is_some() {
+                    return TimerResponse::Err(TimerError::Unsupported);
+                }
+                debug!("subscribed to every timer");
+                self.subscription = Some(TimerSubscription {
+                    timer: envelope.timer.clone(),
+                    id: None,
+                });
+                TimerResponse::Subscribed(Vec::new())
             }
-            TimerRequest::Pause => {
-                TimerResponse::Events(timer.pause(secs.unwrap()).into_iter().collect())
+
+            // requests reading the targeted timer
+            (TimerRequest::Subscribe { id }, _) => {
+                self.on_timer(timers, envelope, now, |this, timer| {
+                    let events = match id {
+                        Some(id) => timer.history.subscribe(id),
+                        None => Vec::new(),
+                    };
+                    debug!("subscribed, {} unacknowledged events", events.len());
+                    this.subscription = Some(TimerSubscription {
+                        timer: envelope.timer.clone(),
+                        id: id.clone(),
+                    });
+                    TimerResponse::Subscribed(events)
+                })
             }
-            TimerRequest::Resume => {
-                TimerResponse::Events(timer.resume(secs.unwrap()).into_iter().collect())
+            (TimerRequest::Get, _) if self.redacted => {
+                self.on_timer(timers, envelope, now, |_, timer| {
+                    TimerResponse::Status(TimerStatus::from(&*timer))
+                })
             }
-            TimerRequest::Update => {
-                TimerResponse::Events(timer.update(secs.unwrap()).into_iter().collect())
+            (TimerRequest::Get, _) => self.on_timer(timers, envelope, now, |_, timer| {
+                TimerResponse::Snapshot(TimerSnapshot::from(&*timer))
+            }),
+            (TimerRequest::Status, _) => self.on_timer(timers, envelope, now, |_, timer| {
+                TimerResponse::StatusLine(TimerStatusLine::from(&*timer))
+            }),
+            (TimerRequest::GetHistory { cursor, limit }, _) => {
+                self.on_timer(timers, envelope, now, |_, timer| {
+                    TimerResponse::History(timer.history.page(*cursor, *limit))
+                })
+            }
+            (TimerRequest::GetStats, Some(now)) => {
+                self.on_timer(timers, envelope, Some(now), |_, timer| {
+                    TimerResponse::Stats(timer.stats.at(now))
+                })
+            }
+            (TimerRequest::StreamHistory { cursor }, _) => {
+                self.on_timer(timers, envelope, now, |this, timer| {
+                    let page = timer.history.page(*cursor, None);
+
+                    // the first page is the response, the next ones
+                    // are built once it is written
+                    if let Some(cursor) = page.next {
+                        let timer = envelope.timer.clone();
+                        this.streamed = Some(Streamed::History { timer, cursor });
+                    }
+
+                    TimerResponse::History(page)
+                })
             }
+
+            // requests modifying the targeted timer
+            (TimerRequest::Start, Some(now)) => self.mutate(timers, envelope, now, |timer| {
+                timer.start(now).into_iter().collect()
+            }),
+            (TimerRequest::Pause, Some(now)) => self.mutate(timers, envelope, now, |timer| {
+                timer.pause(now).into_iter().collect()
+            }),
+            (TimerRequest::Resume, Some(now)) => self.mutate(timers, envelope, now, |timer| {
+                timer.resume(now).into_iter().collect()
+            }),
+            (TimerRequest::Stop, Some(now)) => self.mutate(timers, envelope, now, |timer| {
+                timer.stop().into_iter().collect()
+            }),
+            (TimerRequest::Reset, Some(now)) => self.mutate(timers, envelope, now, |timer| {
+                timer.reset().into_iter().collect()
+            }),
+            (TimerRequest::Update, Some(now)) => self.mutate(timers, envelope, now, |timer| {
+                timer.update(now).into_iter().collect()
+            }),
+            (TimerRequest::Activity, Some(now)) => self.mutate(timers, envelope, now, |timer| {
+                timer.activity(now).into_iter().collect()
+            }),
+            (TimerRequest::SetRemaining(remaining), Some(now)) => {
+                self.mutate(timers, envelope, now, |timer| {
+                    timer.set_remaining(now, *remaining).into_iter().collect()
+                })
+            }
+            (TimerRequest::SetCycleDuration { cycle, secs }, Some(now)) => {
+                self.mutate(timers, envelope, now, |timer| {
+                    timer.set_cycle_duration(cycle, *secs).into_iter().collect()
+                })
+            }
+            (TimerRequest::SetConfig(config), Some(now)) => {
+                self.mutate(timers, envelope, now, |timer| {
+                    timer.set_config(now, config.clone()).into_iter().collect()
+                })
+            }
+            (TimerRequest::Toggle, Some(now)) => {
+                self.on_timer(timers, envelope, Some(now), |this, timer| {
+                    let events = timer.toggle(now).into_iter().collect();
+                    this.emit(timer, now, events);
+                    TimerResponse::State(timer.state.clone())
+                })
+            }
+
+            // the current time is always fetched for the requests
+            // depending on it, see `resume_io`
+            (_, None) => {
+                debug!("reject request applied without time");
+                TimerResponse::Err(TimerError::Unsupported)
+            }
+        }
+    }
+
+    /// Acknowledges the events of the durable subscription of the
+    /// connection, up to the given sequence number.
+    fn ack<S: TimerStore>(&mut self, timers: &mut S, seq: u64) -> TimerResponse {
+        // acknowledgements target the subscribed timer
+        let Some(TimerSubscription {
+            timer,
+            id: Some(id),
+        }) = &self.subscription
+        else {
+            return TimerResponse::Err(TimerError::NotSubscribed);
         };
+        let Some(subscribed) = timers.get_mut(timer.as_deref()) else {
+            // the subscribed timer was deleted since
+            let name = timer.as_deref().unwrap_or(DEFAULT_TIMER).into();
+            return TimerResponse::Err(TimerError::UnknownTimer(name));
+        };
+        if subscribed.history.ack(id, seq) {
+            TimerResponse::Ok
+        } else {
+            TimerResponse::Err(TimerError::NotSubscribed)
+        }
+    }
+
+    /// Applies the given mutating request to the targeted timer, and
+    /// answers with the emitted events.
+    fn mutate<S: TimerStore>(
+        &mut self,
+        timers: &mut S,
+        envelope: &TimerEnvelope,
+        now: u64,
+        apply: impl FnOnce(&mut Timer) -> Vec<TimerEvent>,
+    ) -> TimerResponse {
+        self.on_timer(timers, envelope, Some(now), |this, timer| {
+            let events = apply(timer);
+            TimerResponse::Events(this.emit(timer, now, events))
+        })
+    }
+
+    /// Builds the next frame of the given streamed response.
+    fn stream<S: TimerStore>(&mut self, timers: &mut S, streamed: Streamed) -> TimerResponse {
+        match streamed {
+            Streamed::History { timer, cursor } => {
+                let Some(t) = timers.get_mut(timer.as_deref()) else {
+                    let name = timer.unwrap_or_else(|| DEFAULT_TIMER.into());
+                    debug!("timer {name} deleted while streaming its history");
+                    return TimerResponse::Err(TimerError::UnknownTimer(name));
+                };
+
+                let page = t.history.page(Some(cursor), None);
+                if let Some(cursor) = page.next {
+                    self.streamed = Some(Streamed::History { timer, cursor });
+                }
+
+                TimerResponse::History(page)
+            }
+            Streamed::Stats { cursor, now } => {
+                TimerResponse::StatsExport(self.stats_page(timers, Some(&cursor), now))
+            }
+        }
+    }
+
+    /// Returns the statistics as of `now` of at most
+    /// [`MAX_EXPORT_PAGE_SIZE`] timers, starting from the timer named
+    /// `cursor`, and streams the next page if any.
+    fn stats_page<S: TimerStore>(
+        &mut self,
+        timers: &mut S,
+        cursor: Option<&str>,
+        now: u64,
+    ) -> TimerStatsPage {
+        let mut names = timers
+            .list()
+            .into_keys()
+            .filter(|name| cursor.is_none_or(|cursor| name.as_str() >= cursor));
+        let mut page = TimerStatsPage::default();
+
+        for name in names.by_ref().take(MAX_EXPORT_PAGE_SIZE) {
+            if let Some(timer) = timers.get_mut(Some(&name)) {
+                page.timers.insert(name, timer.stats.at(now));
+            }
+        }
+
+        page.next = names.next();
+
+        if let Some(cursor) = &page.next {
+            let cursor = cursor.clone();
+            self.streamed = Some(Streamed::Stats { cursor, now });
+        }
+
+        page
+    }
+
+    /// Answers the given request from the targeted timer, once
+    /// validated.
+    ///
+    /// Responses of mutating requests with an idempotency key are
+    /// remembered, and replayed when the same key is sent again.
+    fn on_timer<S: TimerStore>(
+        &mut self,
+        timers: &mut S,
+        envelope: &TimerEnvelope,
+        secs: Option<u64>,
+        respond: impl FnOnce(&mut Self, &mut Timer) -> TimerResponse,
+    ) -> TimerResponse {
+        let name = envelope.timer.as_deref();
+
+        let busy = match self.busy {
+            Some(ms) if envelope.request.is_mutating() => Some(ms),
+            _ => timers.busy(name),
+        };
+
+        if let Some(retry_after_ms) = busy {
+            debug!("server busy, retry after {retry_after_ms}ms");
+            return TimerResponse::Busy { retry_after_ms };
+        }
+
+        let Some(timer) = timers.get_mut(name) else {
+            let name = name.unwrap_or(DEFAULT_TIMER).into();
+            debug!("unknown timer {name}");
+            return TimerResponse::Err(TimerError::UnknownTimer(name));
+        };
+
+        if let Err(err) = envelope.request.validate(timer) {
+            debug!("reject invalid request: {err}");
+            return TimerResponse::Err(err);
+        }
+
+        let response = match (&envelope.key, secs) {
+            (Some(key), Some(now)) if envelope.request.is_mutating() => {
+                match timer.idempotency.get(key, now) {
+                    Some(response) => {
+                        debug!("replay response of already applied request {key}");
+                        response.clone()
+                    }
+                    None => {
+                        let response = respond(self, timer);
+                        timer.idempotency.insert(key, now, response.clone());
+                        response
+                    }
+                }
+            }
+            _ => respond(self, timer),
+        };
+
+        let snapshot = self
+            .events
+            .as_ref()
+            .is_some_and(|events| events.iter().any(TimerNotification::changes_state))
+            .then(|| TimerSnapshot::from(&*timer));
+
+        if let Some(snapshot) = snapshot {
+            timers.changed(name.unwrap_or(DEFAULT_TIMER), Some(&snapshot));
+        }
+
+        response
+    }
+
+    /// Records the given events of a mutating request in the history
+    /// and the statistics of the timer, and keeps them to be returned
+    /// once the response is written.
+    ///
+    /// Every mutating request goes through this function, so that
+    /// subscribers are notified whatever the request.
+    fn emit(
+        &mut self,
+        timer: &mut Timer,
+        at: u64,
+        events: Vec<TimerEvent>,
+    ) -> Vec<TimerStampedEvent> {
+        for event in &events {
+            timer.history.push(at, event.clone());
+            timer.stats.record(at, event);
+            self.notifiers.notify(event);
+        }
+
+        let stamped = events
+            .iter()
+            .cloned()
+            .map(|event| TimerStampedEvent { at, event })
+            .collect();
+
+        self.events = Some(events);
+        stamped
+    }
+}
+
+/// Outcome of a [`BoundTimerRequestHandle`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TimerRequestHandled {
+    /// A request was handled, and emitted the given events.
+    Events(Vec<TimerEvent>),
+    /// The client closed the connection between two requests.
+    Disconnected,
+}
+
+/// A [`TimerRequestHandle`] bound to a [`TimerStore`], see
+/// [`TimerRequestHandle::bind`].
+#[derive(Debug)]
+pub struct BoundTimerRequestHandle<'a, S> {
+    handle: &'a mut TimerRequestHandle,
+    timers: &'a mut S,
+}
+
+impl<S: TimerStore> Coroutine for BoundTimerRequestHandle<'_, S> {
+    type Input = TimerRequestHandleInput;
+    type Output = TimerRequestHandleArg;
+    type Ok = TimerRequestHandled;
+    type Error = TimerRequestHandleError;
+
+    fn resume(
+        &mut self,
+        arg: Option<TimerRequestHandleArg>,
+    ) -> CoroutineResult<TimerRequestHandled, TimerRequestHandleInput, TimerRequestHandleError>
+    {
+        match self.handle.resume(self.timers, arg) {
+            TimerRequestHandleResult::Ok { events } => {
+                CoroutineResult::Ok(TimerRequestHandled::Events(events))
+            }
+            TimerRequestHandleResult::Io { input } => {
+                CoroutineResult::Io(TimerRequestHandleInput::Socket(input))
+            }
+            TimerRequestHandleResult::TimeIo { input } => {
+                CoroutineResult::Io(TimerRequestHandleInput::Time(input))
+            }
+            TimerRequestHandleResult::Disconnected => {
+                CoroutineResult::Ok(TimerRequestHandled::Disconnected)
+            }
+            TimerRequestHandleResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
+
+/// The [`TimerRequestHandle`]s of the connections served by a single
+/// server, keyed by connection id.
+///
+/// Connection ids are supplied by the embedder (for example a
+/// counter incremented on each accept, or a file descriptor), which
+/// registers each accepted connection with [`insert`], then resumes
+/// the handle of the connection ready for I/O with [`resume`]. Every
+/// connection shares the same [`TimerStore`], given on resume:
+///
+/// ```rust,ignore
+/// let mut connections = TimerSessionManager::new();
+/// connections.insert(id, TimerRequestHandle::new().with_session(session));
+///
+/// // once the connection `id` is readable
+/// match connections.resume(&id, &mut timers, arg) {
+///     Some(TimerRequestHandleResult::Io { input }) => todo!(),
+///     // …
+///     None => (), // the connection was closed
+/// }
+/// ```
+///
+/// [`insert`]: TimerSessionManager::insert
+/// [`resume`]: TimerSessionManager::resume
+#[derive(Debug)]
+pub struct TimerSessionManager<K> {
+    handles: BTreeMap<K, (u64, TimerRequestHandle)>,
+    next_serial: u64,
+    activity: BTreeMap<K, u64>,
+    idle_timeout: Option<u64>,
+    shutdown: bool,
+    drained: bool,
+    closed: TimerServerMetrics,
+}
+
+impl<K: Ord> Default for TimerSessionManager<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord> TimerSessionManager<K> {
+    /// Creates a new manager, without any connection.
+    pub fn new() -> Self {
+        Self {
+            handles: BTreeMap::new(),
+            next_serial: 1,
+            activity: BTreeMap::new(),
+            idle_timeout: None,
+            shutdown: false,
+            drained: false,
+            closed: TimerServerMetrics::default(),
+        }
+    }
+
+    /// Considers connections idle for the given number of seconds as
+    /// dead, see [`idle`].
+    ///
+    /// Connections which negotiated a [`Keepalive`] at handshake use
+    /// its timeout instead.
+    ///
+    /// [`idle`]: TimerSessionManager::idle
+    /// [`Keepalive`]: crate::session::Keepalive
+    pub fn with_idle_timeout(mut self, secs: u64) -> Self {
+        self.idle_timeout = Some(secs);
+        self
+    }
 
-        self.events = Some(match &response {
-            TimerResponse::Events(events) => events.clone(),
-            TimerResponse::Timer(_) => Vec::new(),
-        });
+    /// Registers the connection with the given id, served by the
+    /// given handle, and returns the handle of the previous
+    /// connection with the same id, if any.
+    ///
+    /// The connection is given a new serial number, see
+    /// [`serial`](TimerSessionManager::serial).
+    pub fn insert(&mut self, id: K, handle: TimerRequestHandle) -> Option<TimerRequestHandle> {
+        let serial = self.next_serial;
+        self.next_serial += 1;
+        let (_, handle) = self.handles.insert(id, (serial, handle))?;
+        Some(handle)
+    }
+
+    /// Unregisters the connection with the given id, typically once
+    /// closed by the embedder, and returns its handle.
+    pub fn remove(&mut self, id: &K) -> Option<TimerRequestHandle> {
+        self.activity.remove(id);
+        let (_, handle) = self.handles.remove(id)?;
+        self.closed.merge(handle.metrics());
+        Some(handle)
+    }
 
-        let mut bytes =
-            serde_json::to_vec(&response).map_err(TimerRequestHandleError::Serialize)?;
-        bytes.push(b'\n');
+    /// Returns the handle of the connection with the given id.
+    pub fn get(&self, id: &K) -> Option<&TimerRequestHandle> {
+        self.handles.get(id).map(|(_, handle)| handle)
+    }
+
+    /// Returns the handle of the connection with the given id, for
+    /// example to mark it busy.
+    pub fn get_mut(&mut self, id: &K) -> Option<&mut TimerRequestHandle> {
+        self.handles.get_mut(id).map(|(_, handle)| handle)
+    }
+
+    /// Returns the serial number of the connection with the given
+    /// id.
+    ///
+    /// Serial numbers are assigned by the manager in insertion
+    /// order, starting from 1, and are never reused. They identify
+    /// connections in traces, since connection ids are opaque to the
+    /// manager.
+    pub fn serial(&self, id: &K) -> Option<u64> {
+        self.handles.get(id).map(|(serial, _)| *serial)
+    }
+
+    /// Returns `true` if a connection has the given id.
+    pub fn contains(&self, id: &K) -> bool {
+        self.handles.contains_key(id)
+    }
+
+    /// Returns the number of connections.
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Returns `true` if there is no connection.
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// Returns the ids of the connections, in order.
+    pub fn ids(&self) -> impl Iterator<Item = &K> {
+        self.handles.keys()
+    }
+
+    /// Returns the ids of the connections subscribed to the events of
+    /// the timer with the given name, to push them the events of this
+    /// timer (see [`TimerSubscription::matches`]).
+    pub fn subscribers<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a K> {
+        self.handles.iter().filter_map(move |(id, (_, handle))| {
+            let subscription = handle.subscription()?;
+            subscription.matches(name).then_some(id)
+        })
+    }
 
-        Ok(bytes)
+    /// Records activity on the connection with the given id at `now`
+    /// (Unix epoch seconds), typically when it is accepted and when
+    /// bytes are read from it.
+    ///
+    /// Activity is also recorded when the connection is resumed with
+    /// the current time.
+    pub fn touch(&mut self, id: &K, now: u64)
+    where
+        K: Clone,
+    {
+        if self.handles.contains_key(id) {
+            self.activity.insert(id.clone(), now);
+        }
+    }
+
+    /// Returns the ids of the connections without activity for longer
+    /// than their idle timeout at `now`, which should be closed then
+    /// unregistered with [`remove`].
+    ///
+    /// Connections without idle timeout, or never touched, are never
+    /// idle.
+    ///
+    /// [`remove`]: TimerSessionManager::remove
+    pub fn idle(&self, now: u64) -> impl Iterator<Item = &K> {
+        self.activity.iter().filter_map(move |(id, last)| {
+            let (_, handle) = self.handles.get(id)?;
+            let timeout = match handle.session().keepalive {
+                Some(keepalive) => keepalive.timeout,
+                None => self.idle_timeout?,
+            };
+            (now.saturating_sub(*last) > timeout).then_some(id)
+        })
+    }
+
+    /// Shuts the server down gracefully, and returns the ids of the
+    /// connections to close right away.
+    ///
+    /// Connections waiting for their next request are unregistered
+    /// right away. The other ones are served until the response of
+    /// their request is written: resuming them afterwards fails with
+    /// [`TimerRequestHandleError::Shutdown`] and unregisters them.
+    /// Pending events (see [`TimerEventBroadcast`]) should be pushed
+    /// before resuming them.
+    ///
+    /// Once the last connection is unregistered, every timer is
+    /// notified once with [`TimerStore::changed`] so that its final
+    /// state can be persisted, and [`is_drained`] returns `true`.
+    ///
+    /// [`TimerEventBroadcast`]: crate::broadcast::TimerEventBroadcast
+    /// [`is_drained`]: TimerSessionManager::is_drained
+    pub fn shutdown(&mut self, timers: &mut impl TimerStore) -> Vec<K>
+    where
+        K: Clone,
+    {
+        debug!("shut down, drain {} connections", self.handles.len());
+        self.shutdown = true;
+
+        let idle: Vec<K> = self
+            .handles
+            .iter()
+            .filter(|(_, (_, handle))| handle.is_idle())
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in &idle {
+            self.remove(id);
+        }
+
+        self.drain(timers);
+        idle
+    }
+
+    /// Returns `true` once the server was shut down, and every
+    /// connection unregistered.
+    pub fn is_drained(&self) -> bool {
+        self.shutdown && self.handles.is_empty()
+    }
+
+    /// Returns the counters of the requests handled by the server,
+    /// closed connections included.
+    pub fn metrics(&self) -> TimerServerMetrics {
+        let mut metrics = self.closed.clone();
+
+        for (_, handle) in self.handles.values() {
+            metrics.merge(handle.metrics());
+        }
+
+        metrics
+    }
+
+    /// Marks every connection as busy, see
+    /// [`TimerRequestHandle::set_busy`].
+    pub fn set_busy(&mut self, retry_after_ms: Option<u64>) {
+        for (_, handle) in self.handles.values_mut() {
+            handle.set_busy(retry_after_ms);
+        }
+    }
+
+    /// Advances the handle of the connection with the given id by
+    /// one step, see [`TimerRequestHandle::resume`].
+    ///
+    /// Returns `None` if no connection has the given id. Connections
+    /// closed by their client (see
+    /// [`TimerRequestHandleResult::Disconnected`]) or failing with an
+    /// error are unregistered, so that the embedder only needs to
+    /// close the underlying socket.
+    ///
+    /// With the `tracing` cargo feature, each step runs within a
+    /// `timer_connection` span recording the serial number of the
+    /// connection as `id` (see [`serial`]).
+    ///
+    /// [`serial`]: TimerSessionManager::serial
+    pub fn resume(
+        &mut self,
+        id: &K,
+        timers: &mut impl TimerStore,
+        arg: Option<impl Into<TimerRequestHandleArg>>,
+    ) -> Option<TimerRequestHandleResult>
+    where
+        K: Clone,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = debug_span!("timer_connection", id = self.serial(id)).entered();
+
+        let arg = arg.map(Into::into);
+
+        if let Some(TimerRequestHandleArg::Time(TimeOutput::Now { secs, .. })) = &arg {
+            self.touch(id, *secs);
+        }
+
+        let (_, handle) = self.handles.get_mut(id)?;
+
+        if self.shutdown && handle.is_idle() {
+            debug!("unregister drained connection");
+            self.remove(id);
+            self.drain(timers);
+            let err = TimerRequestHandleError::Shutdown;
+            return Some(TimerRequestHandleResult::Err { err });
+        }
+
+        let result = handle.resume(timers, arg);
+
+        match &result {
+            TimerRequestHandleResult::Disconnected => {
+                trace!("unregister disconnected connection");
+                self.remove(id);
+                self.drain(timers);
+            }
+            TimerRequestHandleResult::Err { err } => {
+                debug!("unregister failed connection: {err}");
+                self.remove(id);
+                self.drain(timers);
+            }
+            _ => (),
+        }
+
+        Some(result)
+    }
+
+    /// Notifies the store of the final state of every timer, once
+    /// the last connection of a shut down server is unregistered.
+    ///
+    /// The final state is notified only once, later calls do
+    /// nothing.
+    fn drain(&mut self, timers: &mut impl TimerStore) {
+        if self.drained || !self.is_drained() {
+            return;
+        }
+
+        self.drained = true;
+
+        for name in timers.list().into_keys() {
+            let Some(timer) = timers.get_mut(Some(&name)) else {
+                continue;
+            };
+            let snapshot = TimerSnapshot::from(&*timer);
+            timers.changed(&name, Some(&snapshot));
+        }
     }
 }
@@ -3,7 +3,10 @@
 use log::{debug, trace};
 use thiserror::Error;
 
-use crate::io::{TimeInput, TimeOutput};
+use crate::{
+    coroutines::{Coroutine, CoroutineResult},
+    io::{TimeInput, TimeOutput},
+};
 
 /// Error emitted by the [`TimeSleep`] coroutine.
 #[derive(Clone, Debug, Error)]
@@ -64,3 +67,21 @@ impl TimeSleep {
         }
     }
 }
+
+impl Coroutine for TimeSleep {
+    type Input = TimeInput;
+    type Output = TimeOutput;
+    type Ok = ();
+    type Error = TimeSleepError;
+
+    fn resume(
+        &mut self,
+        arg: Option<TimeOutput>,
+    ) -> CoroutineResult<(), TimeInput, TimeSleepError> {
+        match TimeSleep::resume(self, arg) {
+            TimeSleepResult::Ok => CoroutineResult::Ok(()),
+            TimeSleepResult::Io { input } => CoroutineResult::Io(input),
+            TimeSleepResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
@@ -3,7 +3,10 @@
 use log::{debug, trace};
 use thiserror::Error;
 
-use crate::io::{TimeInput, TimeOutput};
+use crate::{
+    coroutines::{Coroutine, CoroutineResult},
+    io::{TimeInput, TimeOutput},
+};
 
 /// Error emitted by the [`TimeSleepUntil`] coroutine.
 #[derive(Clone, Debug, Error)]
@@ -65,3 +68,21 @@ impl TimeSleepUntil {
         }
     }
 }
+
+impl Coroutine for TimeSleepUntil {
+    type Input = TimeInput;
+    type Output = TimeOutput;
+    type Ok = ();
+    type Error = TimeSleepUntilError;
+
+    fn resume(
+        &mut self,
+        arg: Option<TimeOutput>,
+    ) -> CoroutineResult<(), TimeInput, TimeSleepUntilError> {
+        match TimeSleepUntil::resume(self, arg) {
+            TimeSleepUntilResult::Ok => CoroutineResult::Ok(()),
+            TimeSleepUntilResult::Io { input } => CoroutineResult::Io(input),
+            TimeSleepUntilResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! I/O-free coroutine to subscribe to timer events and receive them.
+
+use alloc::{
+    boxed::Box,
+    collections::VecDeque,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_socket::{
+    coroutines::{read::*, write::*},
+    io::{SocketInput, SocketOutput},
+};
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::{
+    coroutines::client::{TimerRequestSendError, decode_response, encode_request},
+    delta::TimerDelta,
+    frame::FrameDecoder,
+    history::TimerHistoryEntry,
+    session::Session,
+    timer::{TimerEnvelope, TimerError, TimerRequest, TimerResponse},
+};
+
+/// Error emitted by the [`TimerEventStream`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimerEventStreamError {
+    #[error("Timer subscription rejected")]
+    Rejected(#[source] TimerError),
+    #[error("Unexpected timer response {0:?}")]
+    UnexpectedResponse(Box<TimerResponse>),
+
+    #[error(transparent)]
+    Send(TimerRequestSendError),
+}
+
+/// Result emitted on each step of the [`TimerEventStream`] coroutine.
+#[derive(Debug)]
+pub enum TimerEventStreamResult {
+    /// The server closed the connection.
+    Ok,
+    /// A timer event was received. The coroutine can be resumed
+    /// without argument to receive the next one.
+    Event {
+        /// The name of the timer, unless subscribed to the default
+        /// timer.
+        timer: Option<String>,
+        /// The event, with its sequence number.
+        event: TimerHistoryEntry,
+    },
+    /// The changes of a timer were received, pushed by servers
+    /// broadcasting deltas (see [`TimerEventBroadcast::with_deltas`]).
+    /// The coroutine can be resumed without argument to receive the
+    /// next one.
+    ///
+    /// [`TimerEventBroadcast::with_deltas`]: crate::broadcast::TimerEventBroadcast::with_deltas
+    Delta {
+        /// The name of the timer, unless subscribed to the default
+        /// timer.
+        timer: Option<String>,
+        /// The changes, keyed by sequence numbers.
+        delta: TimerDelta,
+    },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerEventStreamError },
+}
+
+/// A message pushed by the server.
+#[derive(Clone, Debug)]
+enum Received {
+    Event(TimerHistoryEntry),
+    Delta(TimerDelta),
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    Serialize(Vec<TimerEnvelope>),
+    Write(SocketWrite),
+    Read(SocketRead),
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::Subscribe`], then
+/// receive the pushed events.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit [`SocketInput::Write`] with the encoded request frame.
+/// 2. Emit [`SocketInput::Read`] until frames are fully received.
+/// 3. Return `Event { timer, event }` for each received event,
+///    starting with the events missed by durable subscriptions, or
+///    `Delta { timer, delta }` for each received delta.
+/// 4. Go back to 2, until the server closes the connection.
+///
+/// Events of durable subscriptions can be acknowledged with
+/// [`ack`], sent on the next resume.
+///
+/// [`ack`]: TimerEventStream::ack
+/// [`resume`]: TimerEventStream::resume
+#[derive(Clone, Debug)]
+pub struct TimerEventStream {
+    envelope: TimerEnvelope,
+    session: Session,
+    state: State,
+    decoder: FrameDecoder,
+    events: VecDeque<(Option<String>, Received)>,
+    acks: Vec<u64>,
+}
+
+impl TimerEventStream {
+    /// Creates a new coroutine subscribing to the default timer,
+    /// with the given durable subscription id if any.
+    pub fn new(id: Option<String>) -> Self {
+        let envelope = TimerEnvelope::from(TimerRequest::Subscribe { id });
+
+        Self {
+            state: State::Serialize(Vec::from([envelope.clone()])),
+            envelope,
+            session: Session::default(),
+            decoder: FrameDecoder::new(),
+            events: VecDeque::new(),
+            acks: Vec::new(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.decoder.set_framing(session.framing());
+        self.session = session;
+        self
+    }
+
+    /// Rejects frames larger than the given number of bytes, instead
+    /// of [`DEFAULT_MAX_FRAME_SIZE`].
+    ///
+    /// [`DEFAULT_MAX_FRAME_SIZE`]: crate::frame::DEFAULT_MAX_FRAME_SIZE
+    pub fn with_max_frame_size(mut self, max: usize) -> Self {
+        self.decoder.set_max_frame_size(max);
+        self
+    }
+
+    /// Subscribes to the timer with the given name, or to every timer
+    /// with [`WILDCARD_TIMER`].
+    ///
+    /// [`WILDCARD_TIMER`]: crate::registry::WILDCARD_TIMER
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.envelope.timer = Some(name.to_string());
+        self.state = State::Serialize(Vec::from([self.envelope.clone()]));
+        self
+    }
+
+    /// Acknowledges the event with the given sequence number, see
+    /// [`TimerRequest::Ack`].
+    ///
+    /// The acknowledgement is sent on the next resume without
+    /// argument, and its response is skipped.
+    pub fn ack(&mut self, seq: u64) {
+        self.acks.push(seq);
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, mut arg: Option<SocketOutput>) -> TimerEventStreamResult {
+        loop {
+            match &mut self.state {
+                State::Serialize(envelopes) => {
+                    let mut bytes = Vec::new();
+
+                    for envelope in core::mem::take(envelopes) {
+                        match encode_request(&self.session, &envelope) {
+                            Ok(frame) => bytes.extend(frame),
+                            Err(err) => {
+                                let err = TimerEventStreamError::Send(err);
+                                return TimerEventStreamResult::Err { err };
+                            }
+                        }
+                    }
+
+                    self.state = State::Write(SocketWrite::new(bytes));
+                }
+                State::Write(w) => match w.resume(arg.take()) {
+                    SocketWriteResult::Ok { .. } => {
+                        self.state = State::Read(SocketRead::default());
+                    }
+                    SocketWriteResult::Io { input } => {
+                        return TimerEventStreamResult::Io { input };
+                    }
+                    SocketWriteResult::Eof => {
+                        let err = TimerEventStreamError::Send(TimerRequestSendError::WriteEof);
+                        return TimerEventStreamResult::Err { err };
+                    }
+                    SocketWriteResult::Err { err } => {
+                        let err = TimerEventStreamError::Send(TimerRequestSendError::Write(err));
+                        return TimerEventStreamResult::Err { err };
+                    }
+                },
+                State::Read(r) => {
+                    if arg.is_none() {
+                        if let Some((timer, received)) = self.events.pop_front() {
+                            trace!("timer event received: {received:?}");
+                            return match received {
+                                Received::Event(event) => {
+                                    TimerEventStreamResult::Event { timer, event }
+                                }
+                                Received::Delta(delta) => {
+                                    TimerEventStreamResult::Delta { timer, delta }
+                                }
+                            };
+                        }
+
+                        if !self.acks.is_empty() {
+                            let acks = core::mem::take(&mut self.acks);
+                            let envelopes = acks
+                                .into_iter()
+                                .map(|seq| TimerEnvelope::from(TimerRequest::Ack { seq }))
+                                .collect();
+                            self.state = State::Serialize(envelopes);
+                            continue;
+                        }
+                    }
+
+                    match r.resume(arg.take()) {
+                        SocketReadResult::Ok { buf, n } => {
+                            self.decoder.push(&buf[..n]);
+                            if let Err(err) = self.decode() {
+                                return TimerEventStreamResult::Err { err };
+                            }
+                            self.state = State::Read(SocketRead::default());
+                        }
+                        SocketReadResult::Io { input } => {
+                            return TimerEventStreamResult::Io { input };
+                        }
+                        SocketReadResult::Eof => {
+                            debug!("timer event stream closed by the server");
+                            return TimerEventStreamResult::Ok;
+                        }
+                        SocketReadResult::Err { err } => {
+                            let err = TimerEventStreamError::Send(TimerRequestSendError::Read(err));
+                            return TimerEventStreamResult::Err { err };
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Decodes the buffered frames, queueing the received events.
+    fn decode(&mut self) -> Result<(), TimerEventStreamError> {
+        loop {
+            let frame = match self.decoder.next_frame() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return Ok(()),
+                Err(err) => {
+                    return Err(TimerEventStreamError::Send(TimerRequestSendError::Frame(
+                        err,
+                    )));
+                }
+            };
+
+            let response =
+                decode_response(&self.session, frame).map_err(TimerEventStreamError::Send)?;
+
+            match response {
+                TimerResponse::Subscribed(events) => {
+                    let timer = &self.envelope.timer;
+                    let events = events
+                        .into_iter()
+                        .map(|event| (timer.clone(), Received::Event(event)));
+                    self.events.extend(events);
+                }
+                TimerResponse::Event(event) => {
+                    let timer = self.envelope.timer.clone();
+                    self.events.push_back((timer, Received::Event(event)));
+                }
+                TimerResponse::TaggedEvent { timer, event } => {
+                    self.events.push_back((Some(timer), Received::Event(event)));
+                }
+                TimerResponse::Delta(delta) => {
+                    let timer = self.envelope.timer.clone();
+                    self.events.push_back((timer, Received::Delta(delta)));
+                }
+                TimerResponse::TaggedDelta { timer, delta } => {
+                    self.events.push_back((Some(timer), Received::Delta(delta)));
+                }
+                // acknowledgements
+                TimerResponse::Ok => (),
+                TimerResponse::Err(err) => return Err(TimerEventStreamError::Rejected(err)),
+                response => {
+                    let response = Box::new(response);
+                    return Err(TimerEventStreamError::UnexpectedResponse(response));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! I/O-free coroutine to tick the timers of a server.
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+#[cfg(not(feature = "tracing"))]
+use log::{debug, trace};
+use thiserror::Error;
+#[cfg(feature = "tracing")]
+use tracing::{debug, debug_span, field, trace};
+
+use crate::{
+    coroutines::{
+        now::{TimeNow, TimeNowError, TimeNowResult},
+        sleep_until::{TimeSleepUntil, TimeSleepUntilError, TimeSleepUntilResult},
+    },
+    io::{TimeInput, TimeOutput},
+    notify::{Notifiers, Notify, TimerNotification},
+    registry::TimerStore,
+    timer::{Timer, TimerEvent, TimerSnapshot},
+};
+
+/// The default maximum number of seconds between two ticks.
+pub const DEFAULT_TICK_INTERVAL: u64 = 1;
+
+/// Error emitted by the [`TimerTick`] coroutine.
+#[derive(Clone, Debug, Error)]
+pub enum TimerTickError {
+    #[error(transparent)]
+    Now(TimeNowError),
+    #[error(transparent)]
+    Sleep(TimeSleepUntilError),
+}
+
+/// Result emitted on each step of the [`TimerTick`] coroutine.
+#[derive(Clone, Debug)]
+pub enum TimerTickResult {
+    /// The timers were ticked at `at` (Unix epoch seconds), and
+    /// emitted the given events, by timer name.
+    Ok {
+        at: u64,
+        events: BTreeMap<String, Vec<TimerEvent>>,
+    },
+    /// A time I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: TimeInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerTickError },
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    Now(TimeNow),
+    Sleep(TimeSleepUntil),
+}
+
+/// I/O-free coroutine ticking the timers of a [`TimerStore`], so
+/// that servers do not need to send themselves
+/// [`TimerRequest::Update`] requests.
+///
+/// Each call to [`resume`] advances one step:
+///
+/// 1. Emit [`TimeInput::Now`], then update every timer.
+/// 2. Return `Ok { at, events }` if timers emitted events.
+/// 3. Emit [`TimeInput::SleepUntil`] for the next deadline, then go
+///    back to 1.
+///
+/// Each timer is ticked at the end of its running cycle (or at its
+/// idle deadline, see [`Timer::with_idle_timeout`]), but no later
+/// than its tick interval: the one of the store (see
+/// [`TimerStore::tick_interval`]), or the one of the coroutine (see
+/// [`with_interval`]). Timers which are not due yet are left
+/// untouched, and the next deadline is the earliest one. Deadlines
+/// are computed from the store on every wakeup, so that requests
+/// handled between two ticks (like [`TimerRequest::SetRemaining`])
+/// move them. Emitted
+/// events are recorded in the history and the statistics of their
+/// timer, like the events of handled requests, so that they can be
+/// pushed to subscribers with a [`TimerEventBroadcast`]. The store is
+/// then notified with [`TimerStore::changed`].
+///
+/// The coroutine never terminates: it needs to be resumed again after
+/// each `Ok`, typically along with the connections of the server.
+///
+/// [`TimerRequest::Update`]: crate::timer::TimerRequest::Update
+/// [`TimerRequest::SetRemaining`]: crate::timer::TimerRequest::SetRemaining
+/// [`Timer::with_idle_timeout`]: crate::timer::Timer::with_idle_timeout
+/// [`TimerEventBroadcast`]: crate::broadcast::TimerEventBroadcast
+/// [`resume`]: TimerTick::resume
+/// [`with_interval`]: TimerTick::with_interval
+#[derive(Debug)]
+pub struct TimerTick {
+    interval: u64,
+    ticked: BTreeMap<String, u64>,
+    notifiers: Notifiers,
+    state: State,
+}
+
+impl Default for TimerTick {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerTick {
+    /// Creates a new coroutine, ticking every
+    /// [`DEFAULT_TICK_INTERVAL`] seconds at most.
+    pub fn new() -> Self {
+        Self {
+            interval: DEFAULT_TICK_INTERVAL,
+            ticked: BTreeMap::new(),
+            notifiers: Notifiers::new(),
+            state: State::Now(TimeNow::new()),
+        }
+    }
+
+    /// Ticks every given number of seconds at most, instead of
+    /// [`DEFAULT_TICK_INTERVAL`], unless the store defines the tick
+    /// interval of the timer.
+    ///
+    /// Running timers emit a [`TimerEvent::Running`] event on every
+    /// tick, longer intervals make them less chatty.
+    pub fn with_interval(mut self, secs: u64) -> Self {
+        self.interval = secs.max(1);
+        self
+    }
+
+    /// Notifies the given sink of every event emitted by the ticked
+    /// timers.
+    pub fn with_notifier(mut self, notifier: impl Notify + 'static) -> Self {
+        self.notifiers.push(notifier);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(
+        &mut self,
+        timers: &mut impl TimerStore,
+        mut arg: Option<TimeOutput>,
+    ) -> TimerTickResult {
+        loop {
+            match &mut self.state {
+                State::Now(now) => match now.resume(arg.take()) {
+                    TimeNowResult::Ok { secs, .. } => {
+                        let (events, deadline) = self.tick(timers, secs);
+                        trace!("next tick at {deadline}");
+                        self.state = State::Sleep(TimeSleepUntil::new(deadline));
+
+                        if !events.is_empty() {
+                            return TimerTickResult::Ok { at: secs, events };
+                        }
+                    }
+                    TimeNowResult::Io { input } => {
+                        return TimerTickResult::Io { input };
+                    }
+                    TimeNowResult::Err { err } => {
+                        let err = TimerTickError::Now(err);
+                        return TimerTickResult::Err { err };
+                    }
+                },
+                State::Sleep(sleep) => match sleep.resume(arg.take()) {
+                    TimeSleepUntilResult::Ok => {
+                        self.state = State::Now(TimeNow::new());
+                    }
+                    TimeSleepUntilResult::Io { input } => {
+                        return TimerTickResult::Io { input };
+                    }
+                    TimeSleepUntilResult::Err { err } => {
+                        let err = TimerTickError::Sleep(err);
+                        return TimerTickResult::Err { err };
+                    }
+                },
+            }
+        }
+    }
+
+    /// Updates every due timer at `now`, and returns the emitted
+    /// events along with the next deadline.
+    fn tick(
+        &mut self,
+        timers: &mut impl TimerStore,
+        now: u64,
+    ) -> (BTreeMap<String, Vec<TimerEvent>>, u64) {
+        #[cfg(feature = "tracing")]
+        let span = debug_span!("timer_tick", at = now, events = field::Empty).entered();
+
+        let mut events = BTreeMap::new();
+        let mut deadline: Option<u64> = None;
+        let names = timers.list();
+
+        // deleted timers are forgotten
+        self.ticked.retain(|name, _| names.contains_key(name));
+
+        for name in names.into_keys() {
+            let interval = timers.tick_interval(&name).unwrap_or(self.interval).max(1);
+            let Some(timer) = timers.get_mut(Some(&name)) else {
+                continue;
+            };
+
+            // deadlines may have been moved by requests since the
+            // last tick, so they are not cached
+            let ticked = self.ticked.get(&name).copied();
+            let due = ticked.map(|ticked| due_at(timer, ticked, interval));
+
+            if let Some(due) = due.filter(|due| *due > now) {
+                deadline = Some(deadline.map_or(due, |deadline| deadline.min(due)));
+                continue;
+            }
+
+            let emitted: Vec<_> = timer.update(now).into_iter().collect();
+
+            for event in &emitted {
+                timer.history.push(now, event.clone());
+                timer.stats.record(now, event);
+                self.notifiers.notify(event);
+            }
+
+            self.ticked.insert(name.clone(), now);
+            let due = due_at(timer, now, interval).max(now + 1);
+            deadline = Some(deadline.map_or(due, |deadline| deadline.min(due)));
+
+            if !emitted.is_empty() {
+                debug!("timer {name} emitted {} events", emitted.len());
+                if emitted.iter().any(TimerNotification::changes_state) {
+                    let snapshot = TimerSnapshot::from(&*timer);
+                    timers.changed(&name, Some(&snapshot));
+                }
+                events.insert(name, emitted);
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        span.record("events", events.values().map(Vec::len).sum::<usize>());
+
+        (events, deadline.unwrap_or(now + self.interval))
+    }
+}
+
+/// Returns the time at which the given timer, last ticked at
+/// `ticked`, is due: at the end of its running cycle or at its idle
+/// deadline, but no later than `interval` seconds after the last
+/// tick.
+fn due_at(timer: &Timer, ticked: u64, interval: u64) -> u64 {
+    let mut due = ticked + interval;
+    if let Some(end) = timer.cycle_deadline() {
+        due = due.min(end);
+    }
+    if let Some(idle) = timer.idle_deadline() {
+        due = due.min(idle);
+    }
+    due
+}
@@ -0,0 +1,119 @@
+//! I/O-free coroutine to toggle a timer.
+
+use alloc::{boxed::Box, string::ToString};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::{TimerRequestSend, TimerRequestSendError, TimerRequestSendResult},
+    },
+    session::Session,
+    timer::{TimerError, TimerResponse, TimerState},
+};
+
+/// Error emitted by the [`TimerToggle`] coroutine.
+#[derive(Debug, Error)]
+pub enum TimerToggleError {
+    #[error("Timer toggle rejected by the server")]
+    Rejected(#[source] TimerError),
+    #[error("Unexpected timer response {0:?}")]
+    UnexpectedResponse(Box<TimerResponse>),
+
+    #[error(transparent)]
+    Send(TimerRequestSendError),
+}
+
+/// Result emitted on each step of the [`TimerToggle`] coroutine.
+#[derive(Debug)]
+pub enum TimerToggleResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok { state: TimerState },
+    /// A socket I/O needs to be performed to make the coroutine
+    /// progress.
+    Io { input: SocketInput },
+    /// The coroutine encountered an unrecoverable error.
+    Err { err: TimerToggleError },
+}
+
+/// I/O-free coroutine to send a [`TimerRequest::Toggle`], and
+/// return the resulting [`TimerState`].
+///
+/// Meant for hotkey daemons binding a single key to start, pause and
+/// resume the timer. See [`TimerRequestSend`] for the steps.
+///
+/// [`TimerRequest::Toggle`]: crate::timer::TimerRequest::Toggle
+#[derive(Clone, Debug)]
+pub struct TimerToggle {
+    send: TimerRequestSend,
+}
+
+impl Default for TimerToggle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerToggle {
+    /// Creates a new coroutine.
+    pub fn new() -> Self {
+        Self {
+            send: TimerRequestSend::toggle(),
+        }
+    }
+
+    /// Uses the given session, previously negotiated with
+    /// [`TimerConnect`].
+    ///
+    /// [`TimerConnect`]: crate::coroutines::connect::TimerConnect
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.send = self.send.with_session(session);
+        self
+    }
+
+    /// Toggles the timer with the given name, instead of the default
+    /// one.
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.send = self.send.with_timer(name);
+        self
+    }
+
+    /// Advances the coroutine by one step.
+    pub fn resume(&mut self, arg: Option<SocketOutput>) -> TimerToggleResult {
+        let err = match self.send.resume(arg) {
+            TimerRequestSendResult::Ok {
+                response: TimerResponse::State(state),
+            } => return TimerToggleResult::Ok { state },
+            TimerRequestSendResult::Ok {
+                response: TimerResponse::Err(err),
+            } => TimerToggleError::Rejected(err),
+            TimerRequestSendResult::Ok { response } => {
+                TimerToggleError::UnexpectedResponse(Box::new(response))
+            }
+            TimerRequestSendResult::Io { input } => return TimerToggleResult::Io { input },
+            TimerRequestSendResult::Err { err } => TimerToggleError::Send(err),
+        };
+
+        TimerToggleResult::Err { err }
+    }
+}
+
+impl Coroutine for TimerToggle {
+    type Input = SocketInput;
+    type Output = SocketOutput;
+    type Ok = TimerState;
+    type Error = TimerToggleError;
+
+    fn resume(
+        &mut self,
+        arg: Option<SocketOutput>,
+    ) -> CoroutineResult<TimerState, SocketInput, TimerToggleError> {
+        match TimerToggle::resume(self, arg) {
+            TimerToggleResult::Ok { state } => CoroutineResult::Ok(state),
+            TimerToggleResult::Io { input } => CoroutineResult::Io(input),
+            TimerToggleResult::Err { err } => CoroutineResult::Err(err),
+        }
+    }
+}
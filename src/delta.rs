@@ -0,0 +1,298 @@
+//! Delta encoding of timer changes.
+//!
+//! Sending the whole [`Timer`] to clients following a timer is
+//! wasteful, since most of its fields do not change between two
+//! ticks. A [`TimerDelta`] contains only the fields that changed
+//! between two versions of a timer, identified by sequence numbers.
+//!
+//! Servers push deltas to subscribed connections when configured
+//! with [`TimerEventBroadcast::with_deltas`]. A client keeping a
+//! local replica applies them in order with [`TimerDelta::apply`],
+//! or with [`TimerMirror::apply_delta`]. A delta whose base sequence
+//! number does not match the replica means that a delta was missed:
+//! the client needs to resynchronize with a full
+//! [`TimerRequest::Get`].
+//!
+//! [`TimerEventBroadcast::with_deltas`]: crate::broadcast::TimerEventBroadcast::with_deltas
+//! [`TimerMirror::apply_delta`]: crate::mirror::TimerMirror::apply_delta
+//! [`TimerRequest::Get`]: crate::timer::TimerRequest::Get
+
+use alloc::vec::Vec;
+use core::mem;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::timer::{Timer, TimerConfig, TimerCycle, TimerLoop, TimerState};
+
+/// Error emitted when applying a [`TimerDelta`].
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum TimerDeltaError {
+    #[error("Cannot apply timer delta on top of #{got}, expected #{expected}")]
+    SequenceMismatch { expected: u64, got: u64 },
+}
+
+/// A single changed field of a [`Timer`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum TimerField {
+    Config(TimerConfig),
+    State(TimerState),
+    Cycle(TimerCycle),
+    CycleIndex(usize),
+    CycleOffset(usize),
+    CycleAdjustment(isize),
+    CyclesCount(TimerLoop),
+    StartedAt(Option<u64>),
+    Elapsed(usize),
+}
+
+/// The changes between two versions of a [`Timer`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerDelta {
+    /// The sequence number of the version the delta applies to,
+    /// ignored by keyframes.
+    pub base: u64,
+    /// The sequence number of the version resulting from the delta.
+    pub seq: u64,
+    /// Whether the delta applies on top of a fresh timer, created
+    /// from the configuration it carries, whatever the version of the
+    /// replica (see [`TimerDelta::keyframe`]).
+    pub keyframe: bool,
+    /// The changed fields.
+    pub fields: Vec<TimerField>,
+}
+
+impl TimerDelta {
+    /// Computes the delta between `prev`, identified by the sequence
+    /// number `base`, and `next`, identified by `base + 1`.
+    pub fn diff(base: u64, prev: &Timer, next: &Timer) -> Self {
+        let mut fields = Vec::new();
+
+        if prev.config != next.config {
+            fields.push(TimerField::Config(next.config.clone()));
+        }
+        if prev.state != next.state {
+            fields.push(TimerField::State(next.state.clone()));
+        }
+        if prev.cycle != next.cycle {
+            fields.push(TimerField::Cycle(next.cycle.clone()));
+        }
+        if prev.cycle_index != next.cycle_index {
+            fields.push(TimerField::CycleIndex(next.cycle_index));
+        }
+        if prev.cycle_offset != next.cycle_offset {
+            fields.push(TimerField::CycleOffset(next.cycle_offset));
+        }
+        if prev.cycle_adjustment != next.cycle_adjustment {
+            fields.push(TimerField::CycleAdjustment(next.cycle_adjustment));
+        }
+        if prev.cycles_count != next.cycles_count {
+            fields.push(TimerField::CyclesCount(next.cycles_count.clone()));
+        }
+        if prev.started_at != next.started_at {
+            fields.push(TimerField::StartedAt(next.started_at));
+        }
+        if prev.elapsed != next.elapsed {
+            fields.push(TimerField::Elapsed(next.elapsed));
+        }
+
+        Self {
+            base,
+            seq: base + 1,
+            keyframe: false,
+            fields,
+        }
+    }
+
+    /// Computes the delta between a fresh timer and `timer`,
+    /// identified by the sequence number `seq`.
+    ///
+    /// Keyframes resynchronize replicas whose version is unknown,
+    /// like the ones of newly subscribed clients.
+    pub fn keyframe(seq: u64, timer: &Timer) -> Self {
+        let fresh = Timer::new(timer.config.clone());
+        let mut delta = Self::diff(0, &fresh, timer);
+
+        // the fresh timer is created from the carried configuration
+        delta
+            .fields
+            .insert(0, TimerField::Config(timer.config.clone()));
+        delta.seq = seq;
+        delta.keyframe = true;
+        delta
+    }
+
+    /// Returns `true` if no field changed.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Applies the delta to the given timer, currently at the
+    /// sequence number `seq`.
+    ///
+    /// Keyframes replace the given timer by a fresh one first, whatever
+    /// its sequence number.
+    ///
+    /// Returns the new sequence number of the timer.
+    pub fn apply(&self, timer: &mut Timer, seq: u64) -> Result<u64, TimerDeltaError> {
+        if self.keyframe {
+            let config = self.fields.iter().find_map(|field| match field {
+                TimerField::Config(config) => Some(config.clone()),
+                _ => None,
+            });
+            *timer = Timer::new(config.unwrap_or_else(|| timer.config.clone()));
+        } else if self.base != seq {
+            return Err(TimerDeltaError::SequenceMismatch {
+                expected: self.base,
+                got: seq,
+            });
+        }
+
+        for field in &self.fields {
+            match field {
+                TimerField::Config(config) => timer.config = config.clone(),
+                TimerField::State(state) => timer.state = state.clone(),
+                TimerField::Cycle(cycle) => timer.cycle = cycle.clone(),
+                TimerField::CycleIndex(index) => timer.cycle_index = *index,
+                TimerField::CycleOffset(offset) => timer.cycle_offset = *offset,
+                TimerField::CycleAdjustment(adjustment) => timer.cycle_adjustment = *adjustment,
+                TimerField::CyclesCount(count) => timer.cycles_count = count.clone(),
+                TimerField::StartedAt(started_at) => timer.started_at = *started_at,
+                TimerField::Elapsed(elapsed) => timer.elapsed = *elapsed,
+            }
+        }
+
+        Ok(self.seq)
+    }
+
+    /// Merges the given delta, applying on top of this one, so that
+    /// both can be applied at once.
+    ///
+    /// Fields changed by both deltas take the value of `next`, and
+    /// keyframes replace this delta.
+    pub fn merge(&mut self, next: TimerDelta) -> Result<(), TimerDeltaError> {
+        if next.keyframe {
+            *self = next;
+            return Ok(());
+        }
+
+        if next.base != self.seq {
+            return Err(TimerDeltaError::SequenceMismatch {
+                expected: next.base,
+                got: self.seq,
+            });
+        }
+
+        for field in next.fields {
+            let kind = mem::discriminant(&field);
+            let prev = self
+                .fields
+                .iter_mut()
+                .find(|f| mem::discriminant(*f) == kind);
+
+            match prev {
+                Some(prev) => *prev = field,
+                None => self.fields.push(field),
+            }
+        }
+
+        self.seq = next.seq;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timer::TimerCycles;
+
+    fn testing_timer() -> Timer {
+        Timer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("a", 3), TimerCycle::new("b", 2)]),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn delta_contains_changed_fields_only() {
+        let prev = testing_timer();
+        let mut next = prev.clone();
+        next.start(10).into_iter().for_each(drop);
+
+        let delta = TimerDelta::diff(4, &prev, &next);
+
+        assert_eq!(delta.seq, 5);
+        assert_eq!(
+            delta.fields,
+            [
+                TimerField::State(TimerState::Running),
+                TimerField::StartedAt(Some(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_delta_on_replica() {
+        let prev = testing_timer();
+        let mut next = prev.clone();
+        next.start(0).into_iter().for_each(drop);
+        next.update(4).into_iter().for_each(drop);
+
+        let mut replica = prev.clone();
+        let delta = TimerDelta::diff(0, &prev, &next);
+
+        assert_eq!(delta.apply(&mut replica, 0), Ok(1));
+        assert_eq!(replica, next);
+        assert_eq!(
+            delta.apply(&mut replica, 1),
+            Err(TimerDeltaError::SequenceMismatch {
+                expected: 0,
+                got: 1
+            })
+        );
+    }
+
+    #[test]
+    fn merged_deltas_apply_at_once() {
+        let first = testing_timer();
+        let mut second = first.clone();
+        second.start(0).into_iter().for_each(drop);
+        let mut third = second.clone();
+        third.pause(4).into_iter().for_each(drop);
+
+        let mut delta = TimerDelta::diff(0, &first, &second);
+        delta.merge(TimerDelta::diff(1, &second, &third)).unwrap();
+
+        let mut replica = first.clone();
+        assert_eq!(delta.apply(&mut replica, 0), Ok(2));
+        assert_eq!(replica, third);
+
+        let stale = TimerDelta::diff(0, &first, &second);
+        assert_eq!(
+            delta.merge(stale),
+            Err(TimerDeltaError::SequenceMismatch {
+                expected: 0,
+                got: 2
+            })
+        );
+    }
+
+    #[test]
+    fn keyframe_applies_whatever_the_replica_version() {
+        let mut next = testing_timer();
+        next.start(0).into_iter().for_each(drop);
+        next.pause(4).into_iter().for_each(drop);
+
+        let keyframe = TimerDelta::keyframe(7, &next);
+        assert_eq!(keyframe.fields[0], TimerField::Config(next.config.clone()));
+
+        let mut replica = testing_timer();
+        replica.start(2).into_iter().for_each(drop);
+        assert_eq!(keyframe.apply(&mut replica, 3), Ok(7));
+        assert_eq!(replica, next);
+
+        // genuine deltas based on the first version are no keyframes
+        let delta = TimerDelta::diff(0, &testing_timer(), &next);
+        assert!(delta.apply(&mut replica, 7).is_err());
+    }
+}
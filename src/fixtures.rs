@@ -0,0 +1,499 @@
+//! Canonical encodings of the protocol messages.
+//!
+//! Every request and response variant comes with its canonical JSON
+//! encoding (the default [`Codec`]), as produced by this crate. Third
+//! party client implementations can validate themselves against
+//! these golden encodings, either by comparing their output with
+//! [`Fixture::json`], or by using the helper asserts from Rust.
+
+use alloc::{collections::BTreeMap, string::ToString, vec, vec::Vec};
+use core::fmt::Debug;
+
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::json;
+
+use crate::{
+    codec::Codec,
+    compression::Compression,
+    delta::{TimerDelta, TimerField},
+    history::{TimerHistoryEntry, TimerHistoryPage},
+    stats::{TimerStats, TimerStatsPage},
+    timer::{
+        PauseReason, TimerCapabilities, TimerConfig, TimerCycle, TimerCycles, TimerEnvelope,
+        TimerError, TimerEvent, TimerLoop, TimerRequest, TimerResponse, TimerSnapshot,
+        TimerStampedEvent, TimerState, TimerStatus, TimerStatusLine, TimerVersion,
+    },
+};
+
+/// A protocol message along with its canonical encoding.
+#[derive(Clone, Debug)]
+pub struct Fixture<T> {
+    /// The name of the fixture, unique per message type.
+    pub name: &'static str,
+    /// The message itself.
+    pub value: T,
+    /// The canonical JSON encoding of the message, without frame
+    /// delimiter.
+    pub json: &'static str,
+}
+
+impl<T: Debug + PartialEq + Serialize + DeserializeOwned> Fixture<T> {
+    /// Asserts that the given bytes are the canonical encoding of the
+    /// fixture.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bytes differ from [`Fixture::json`].
+    pub fn assert_encoded(&self, bytes: &[u8]) {
+        assert_eq!(
+            bytes,
+            self.json.as_bytes(),
+            "fixture {}: invalid encoding",
+            self.name
+        );
+    }
+
+    /// Asserts that the given bytes decode to the fixture message.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bytes cannot be decoded, or if they decode to
+    /// another message.
+    pub fn assert_decoded(&self, bytes: &[u8]) {
+        let value: T = match Codec::Json.decode(bytes) {
+            Ok(value) => value,
+            Err(err) => panic!("fixture {}: cannot decode: {err}", self.name),
+        };
+
+        assert_eq!(value, self.value, "fixture {}: invalid message", self.name);
+    }
+}
+
+fn request(
+    name: &'static str,
+    request: TimerRequest,
+    json: &'static str,
+) -> Fixture<TimerEnvelope> {
+    Fixture {
+        name,
+        value: TimerEnvelope::from(request),
+        json,
+    }
+}
+
+fn response(
+    name: &'static str,
+    value: TimerResponse,
+    json: &'static str,
+) -> Fixture<TimerResponse> {
+    Fixture { name, value, json }
+}
+
+/// Returns the fixtures of every request variant.
+pub fn requests() -> Vec<Fixture<TimerEnvelope>> {
+    vec![
+        request(
+            "get",
+            TimerRequest::Get,
+            r#"{"request":"Get","key":null,"timer":null}"#,
+        ),
+        request(
+            "start",
+            TimerRequest::Start,
+            r#"{"request":"Start","key":null,"timer":null}"#,
+        ),
+        request(
+            "stop",
+            TimerRequest::Stop,
+            r#"{"request":"Stop","key":null,"timer":null}"#,
+        ),
+        request(
+            "pause",
+            TimerRequest::Pause,
+            r#"{"request":"Pause","key":null,"timer":null}"#,
+        ),
+        request(
+            "resume",
+            TimerRequest::Resume,
+            r#"{"request":"Resume","key":null,"timer":null}"#,
+        ),
+        request(
+            "update",
+            TimerRequest::Update,
+            r#"{"request":"Update","key":null,"timer":null}"#,
+        ),
+        request(
+            "set-remaining",
+            TimerRequest::SetRemaining(60),
+            r#"{"request":{"SetRemaining":60},"key":null,"timer":null}"#,
+        ),
+        request(
+            "set-cycle-duration",
+            TimerRequest::SetCycleDuration {
+                cycle: "Break".to_string(),
+                secs: 600,
+            },
+            r#"{"request":{"SetCycleDuration":{"cycle":"Break","secs":600}},"key":null,"timer":null}"#,
+        ),
+        request(
+            "set-config",
+            TimerRequest::SetConfig(TimerConfig {
+                cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+                cycles_count: TimerLoop::Infinite,
+            }),
+            r#"{"request":{"SetConfig":{"cycles":[{"name":"Focus","duration":1500}],"cycles_count":"Infinite"}},"key":null,"timer":null}"#,
+        ),
+        request(
+            "reset",
+            TimerRequest::Reset,
+            r#"{"request":"Reset","key":null,"timer":null}"#,
+        ),
+        request(
+            "activity",
+            TimerRequest::Activity,
+            r#"{"request":"Activity","key":null,"timer":null}"#,
+        ),
+        request(
+            "get-stats",
+            TimerRequest::GetStats,
+            r#"{"request":"GetStats","key":null,"timer":null}"#,
+        ),
+        request(
+            "toggle",
+            TimerRequest::Toggle,
+            r#"{"request":"Toggle","key":null,"timer":null}"#,
+        ),
+        request(
+            "capabilities",
+            TimerRequest::Capabilities,
+            r#"{"request":"Capabilities","key":null,"timer":null}"#,
+        ),
+        request(
+            "version",
+            TimerRequest::Version,
+            r#"{"request":"Version","key":null,"timer":null}"#,
+        ),
+        request(
+            "list",
+            TimerRequest::List,
+            r#"{"request":"List","key":null,"timer":null}"#,
+        ),
+        request(
+            "create",
+            TimerRequest::Create {
+                name: "tea".to_string(),
+                config: TimerConfig {
+                    cycles: TimerCycles::from([TimerCycle::new("Steep", 180)]),
+                    cycles_count: TimerLoop::Fixed(1),
+                },
+            },
+            r#"{"request":{"Create":{"name":"tea","config":{"cycles":[{"name":"Steep","duration":180}],"cycles_count":{"Fixed":1}}}},"key":null,"timer":null}"#,
+        ),
+        request(
+            "delete",
+            TimerRequest::Delete {
+                name: "tea".to_string(),
+            },
+            r#"{"request":{"Delete":{"name":"tea"}},"key":null,"timer":null}"#,
+        ),
+        request(
+            "status",
+            TimerRequest::Status,
+            r#"{"request":"Status","key":null,"timer":null}"#,
+        ),
+        request(
+            "extension",
+            TimerRequest::Extension {
+                name: "tag".to_string(),
+                payload: json!({"label": "billable"}),
+            },
+            r#"{"request":{"Extension":{"name":"tag","payload":{"label":"billable"}}},"key":null,"timer":null}"#,
+        ),
+        request(
+            "ping",
+            TimerRequest::Ping,
+            r#"{"request":"Ping","key":null,"timer":null}"#,
+        ),
+        request(
+            "get-history",
+            TimerRequest::GetHistory {
+                cursor: Some(42),
+                limit: None,
+            },
+            r#"{"request":{"GetHistory":{"cursor":42,"limit":null}},"key":null,"timer":null}"#,
+        ),
+        request(
+            "stream-history",
+            TimerRequest::StreamHistory { cursor: Some(42) },
+            r#"{"request":{"StreamHistory":{"cursor":42}},"key":null,"timer":null}"#,
+        ),
+        request(
+            "export-stats",
+            TimerRequest::ExportStats {
+                cursor: Some("work".to_string()),
+            },
+            r#"{"request":{"ExportStats":{"cursor":"work"}},"key":null,"timer":null}"#,
+        ),
+        request(
+            "subscribe",
+            TimerRequest::Subscribe {
+                id: Some("billing".to_string()),
+            },
+            r#"{"request":{"Subscribe":{"id":"billing"}},"key":null,"timer":null}"#,
+        ),
+        request(
+            "ack",
+            TimerRequest::Ack { seq: 7 },
+            r#"{"request":{"Ack":{"seq":7}},"key":null,"timer":null}"#,
+        ),
+        Fixture {
+            name: "start-keyed-targeted",
+            value: TimerEnvelope {
+                request: TimerRequest::Start,
+                key: Some("start-1".to_string()),
+                timer: Some("tea".to_string()),
+            },
+            json: r#"{"request":"Start","key":"start-1","timer":"tea"}"#,
+        },
+    ]
+}
+
+/// Returns the fixtures of every response variant.
+pub fn responses() -> Vec<Fixture<TimerResponse>> {
+    let cycle = TimerCycle::new("Focus", 1500);
+    let mut stats = TimerStats::new();
+    stats.completed_cycles = 2;
+    stats.pauses = 1;
+    stats.durations.insert("Focus".to_string(), 3000);
+
+    let status = TimerStatus {
+        state: TimerState::Running,
+        cycle: "Focus".to_string(),
+        remaining: 1500,
+    };
+
+    let delta = TimerDelta {
+        base: 8,
+        seq: 9,
+        keyframe: false,
+        fields: vec![
+            TimerField::State(TimerState::Running),
+            TimerField::StartedAt(Some(1700000000)),
+        ],
+    };
+
+    vec![
+        response(
+            "snapshot",
+            TimerResponse::Snapshot(TimerSnapshot {
+                state: TimerState::Running,
+                cycle: "Focus".to_string(),
+                remaining: 1500,
+                cycle_index: 0,
+                cycles_count: TimerLoop::Infinite,
+                elapsed: 0,
+                started_at: Some(1700000000),
+            }),
+            r#"{"Snapshot":{"state":"Running","cycle":"Focus","remaining":1500,"cycle_index":0,"cycles_count":"Infinite","elapsed":0,"started_at":1700000000}}"#,
+        ),
+        response(
+            "events",
+            TimerResponse::Events(vec![
+                TimerStampedEvent {
+                    at: 1700000000,
+                    event: TimerEvent::Started,
+                },
+                TimerStampedEvent {
+                    at: 1700000000,
+                    event: TimerEvent::Began(cycle.clone()),
+                },
+            ]),
+            r#"{"Events":[{"at":1700000000,"event":"Started"},{"at":1700000000,"event":{"Began":{"name":"Focus","duration":1500}}}]}"#,
+        ),
+        response(
+            "state",
+            TimerResponse::State(TimerState::Paused),
+            r#"{"State":"Paused"}"#,
+        ),
+        response(
+            "capabilities",
+            TimerResponse::Capabilities(TimerCapabilities {
+                version: "1.0.0".to_string(),
+                codecs: vec![Codec::Json],
+                compressions: vec![Compression::None],
+                multi_timer: false,
+                subscribe: false,
+                history: true,
+            }),
+            r#"{"Capabilities":{"version":"1.0.0","codecs":["Json"],"compressions":["None"],"multi_timer":false,"subscribe":false,"history":true}}"#,
+        ),
+        response(
+            "version",
+            TimerResponse::Version(TimerVersion {
+                crate_version: "1.0.0".to_string(),
+                protocol: 1,
+            }),
+            r#"{"Version":{"crate_version":"1.0.0","protocol":1}}"#,
+        ),
+        response(
+            "status",
+            TimerResponse::Status(status.clone()),
+            r#"{"Status":{"state":"Running","cycle":"Focus","remaining":1500}}"#,
+        ),
+        response(
+            "status-line",
+            TimerResponse::StatusLine(TimerStatusLine {
+                text: "Focus 20:00 ▶".to_string(),
+                state: TimerState::Running,
+                percent: 20,
+            }),
+            r#"{"StatusLine":{"text":"Focus 20:00 ▶","state":"Running","percent":20}}"#,
+        ),
+        response(
+            "list",
+            TimerResponse::List(BTreeMap::from([("default".to_string(), status)])),
+            r#"{"List":{"default":{"state":"Running","cycle":"Focus","remaining":1500}}}"#,
+        ),
+        response(
+            "history",
+            TimerResponse::History(TimerHistoryPage {
+                events: vec![TimerHistoryEntry {
+                    seq: 7,
+                    at: 1700000000,
+                    event: TimerEvent::Ended(cycle),
+                }],
+                next: Some(8),
+            }),
+            r#"{"History":{"events":[{"seq":7,"at":1700000000,"event":{"Ended":{"name":"Focus","duration":1500}}}],"next":8}}"#,
+        ),
+        response(
+            "subscribed",
+            TimerResponse::Subscribed(vec![]),
+            r#"{"Subscribed":[]}"#,
+        ),
+        response(
+            "event",
+            TimerResponse::Event(TimerHistoryEntry {
+                seq: 8,
+                at: 1700000000,
+                event: TimerEvent::Started,
+            }),
+            r#"{"Event":{"seq":8,"at":1700000000,"event":"Started"}}"#,
+        ),
+        response(
+            "paused-event",
+            TimerResponse::Event(TimerHistoryEntry {
+                seq: 9,
+                at: 1700000000,
+                event: TimerEvent::Paused {
+                    cycle: TimerCycle::new("Focus", 1200),
+                    reason: PauseReason::Idle,
+                },
+            }),
+            r#"{"Event":{"seq":9,"at":1700000000,"event":{"Paused":{"cycle":{"name":"Focus","duration":1200},"reason":"Idle"}}}}"#,
+        ),
+        response(
+            "tagged-event",
+            TimerResponse::TaggedEvent {
+                timer: "work".to_string(),
+                event: TimerHistoryEntry {
+                    seq: 8,
+                    at: 1700000000,
+                    event: TimerEvent::Started,
+                },
+            },
+            r#"{"TaggedEvent":{"timer":"work","event":{"seq":8,"at":1700000000,"event":"Started"}}}"#,
+        ),
+        response(
+            "delta",
+            TimerResponse::Delta(delta.clone()),
+            r#"{"Delta":{"base":8,"seq":9,"keyframe":false,"fields":[{"State":"Running"},{"StartedAt":1700000000}]}}"#,
+        ),
+        response(
+            "tagged-delta",
+            TimerResponse::TaggedDelta {
+                timer: "work".to_string(),
+                delta,
+            },
+            r#"{"TaggedDelta":{"timer":"work","delta":{"base":8,"seq":9,"keyframe":false,"fields":[{"State":"Running"},{"StartedAt":1700000000}]}}}"#,
+        ),
+        response(
+            "extension",
+            TimerResponse::Extension(json!(["billable"])),
+            r#"{"Extension":["billable"]}"#,
+        ),
+        response(
+            "stats",
+            TimerResponse::Stats(stats.clone()),
+            r#"{"Stats":{"completed_cycles":2,"pauses":1,"durations":{"Focus":3000}}}"#,
+        ),
+        response(
+            "stats-export",
+            TimerResponse::StatsExport(TimerStatsPage {
+                timers: BTreeMap::from([("work".to_string(), stats)]),
+                next: Some("zen".to_string()),
+            }),
+            r#"{"StatsExport":{"timers":{"work":{"completed_cycles":2,"pauses":1,"durations":{"Focus":3000}}},"next":"zen"}}"#,
+        ),
+        response("pong", TimerResponse::Pong, r#""Pong""#),
+        response("ok", TimerResponse::Ok, r#""Ok""#),
+        response(
+            "busy",
+            TimerResponse::Busy {
+                retry_after_ms: 250,
+            },
+            r#"{"Busy":{"retry_after_ms":250}}"#,
+        ),
+        response(
+            "err-too-many-requests",
+            TimerResponse::Err(TimerError::TooManyRequests { retry_after: 3 }),
+            r#"{"Err":{"TooManyRequests":{"retry_after":3}}}"#,
+        ),
+        response(
+            "err-forbidden",
+            TimerResponse::Err(TimerError::Forbidden),
+            r#"{"Err":"Forbidden"}"#,
+        ),
+        response(
+            "err-unknown-timer",
+            TimerResponse::Err(TimerError::UnknownTimer("tea".to_string())),
+            r#"{"Err":{"UnknownTimer":"tea"}}"#,
+        ),
+        response(
+            "err-frame-too-large",
+            TimerResponse::Err(TimerError::FrameTooLarge { max: 1024 }),
+            r#"{"Err":{"FrameTooLarge":{"max":1024}}}"#,
+        ),
+        response(
+            "err-malformed-request",
+            TimerResponse::Err(TimerError::MalformedRequest),
+            r#"{"Err":"MalformedRequest"}"#,
+        ),
+        response(
+            "err-invalid-request",
+            TimerResponse::Err(TimerError::InvalidRequest("timer is stopped".into())),
+            r#"{"Err":{"InvalidRequest":"timer is stopped"}}"#,
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_match_canonical_encoding() {
+        for fixture in requests() {
+            fixture.assert_encoded(&Codec::Json.encode(&fixture.value).unwrap());
+            fixture.assert_decoded(fixture.json.as_bytes());
+        }
+    }
+
+    #[test]
+    fn responses_match_canonical_encoding() {
+        for fixture in responses() {
+            fixture.assert_encoded(&Codec::Json.encode(&fixture.value).unwrap());
+            fixture.assert_decoded(fixture.json.as_bytes());
+        }
+    }
+}
@@ -0,0 +1,371 @@
+//! Frame encoding and decoding.
+//!
+//! By default, timer requests and responses travel over the socket
+//! as NDJSON: one compact JSON document per line. Compact JSON never
+//! contains a raw `\n` byte (newlines inside strings are always
+//! escaped), which makes the line feed a safe frame delimiter.
+//!
+//! Binary payloads (like compressed ones) may contain any byte, so
+//! they use the [`Framing::LengthPrefixed`] framing instead.
+//!
+//! Text-only transports may mangle binary bytes or line endings:
+//! they use the [`Framing::Armored`] framing, where each payload is
+//! armored in printable ASCII and sent as one line.
+//!
+//! Message-oriented transports (like UDP) already preserve message
+//! boundaries: they use the [`Framing::Datagram`] framing, where each
+//! read or write carries exactly one frame.
+//!
+//! The [`FrameDecoder`] is shared by the client and server
+//! coroutines. It accumulates bytes across reads and yields complete
+//! frames one by one, so frames split across several reads or packed
+//! together in a single read are both handled correctly. Frames
+//! larger than the configured maximum size are rejected, so that a
+//! garbage stream cannot exhaust the memory.
+
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::armor::{self, ArmorError};
+
+/// The byte delimiting two frames in [`Framing::Line`] mode.
+pub const DELIMITER: u8 = b'\n';
+
+/// The size of the length header in [`Framing::LengthPrefixed`]
+/// mode.
+const LENGTH_PREFIX_SIZE: usize = 4;
+
+/// The default maximum size of a frame, in bytes.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+/// Error emitted when decoding a frame.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum FrameError {
+    #[error("Frame of at least {size} bytes exceeds the maximum size of {max} bytes")]
+    TooLarge { size: usize, max: usize },
+    #[error("Failed to remove the armor of the frame")]
+    Armor(#[source] ArmorError),
+}
+
+/// The way frames are delimited on the wire.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Framing {
+    /// Each frame is terminated by a [`DELIMITER`].
+    ///
+    /// The payload must not contain the delimiter itself, which is
+    /// always the case for compact JSON.
+    #[default]
+    Line,
+    /// Each frame is preceded by its length, encoded as a big-endian
+    /// `u32`.
+    ///
+    /// The payload can contain any byte.
+    LengthPrefixed,
+    /// Each frame is armored (see [`armor`]), then terminated by a
+    /// [`DELIMITER`].
+    ///
+    /// The payload can contain any byte, at the cost of a third more
+    /// bytes on the wire.
+    Armored,
+    /// Each frame is sent as a single datagram, without delimiter.
+    ///
+    /// The payload can contain any byte, but must fit in one
+    /// datagram and in the read buffer of the receiver, otherwise it
+    /// is truncated.
+    Datagram,
+}
+
+impl Framing {
+    /// Encodes the given payload into a frame.
+    pub fn encode(self, mut payload: Vec<u8>) -> Vec<u8> {
+        match self {
+            Self::Line => {
+                debug_assert!(!payload.contains(&DELIMITER));
+                payload.push(DELIMITER);
+                payload
+            }
+            Self::LengthPrefixed => {
+                let len = payload.len() as u32;
+                let mut frame = Vec::with_capacity(LENGTH_PREFIX_SIZE + payload.len());
+                frame.extend_from_slice(&len.to_be_bytes());
+                frame.append(&mut payload);
+                frame
+            }
+            Self::Armored => {
+                let mut frame = armor::encode(&payload);
+                frame.push(DELIMITER);
+                frame
+            }
+            Self::Datagram => payload,
+        }
+    }
+}
+
+/// Incremental frame decoder.
+///
+/// Bytes received from the socket are fed with [`push`], then
+/// complete frames are extracted with [`next_frame`]. Bytes following
+/// the last complete frame are kept until the rest of the frame
+/// arrives.
+///
+/// [`push`]: FrameDecoder::push
+/// [`next_frame`]: FrameDecoder::next_frame
+#[derive(Clone, Debug)]
+pub struct FrameDecoder {
+    framing: Framing,
+    max_frame_size: usize,
+    buf: Vec<u8>,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::with_framing(Framing::default())
+    }
+}
+
+impl FrameDecoder {
+    /// Creates a new, empty decoder using the [`Framing::Line`]
+    /// framing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new, empty decoder using the given framing.
+    pub fn with_framing(framing: Framing) -> Self {
+        Self {
+            framing,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Changes the maximum size of the frames, in bytes.
+    pub fn set_max_frame_size(&mut self, max: usize) {
+        self.max_frame_size = max;
+    }
+
+    /// Reserves room for at least `capacity` buffered bytes.
+    ///
+    /// The buffer is reused across frames, so reserving the size of
+    /// the expected frames upfront saves reallocations while they
+    /// are received.
+    pub fn reserve(&mut self, capacity: usize) {
+        self.buf.reserve(capacity.saturating_sub(self.buf.len()));
+    }
+
+    /// Changes the framing used to decode the next frames.
+    pub fn set_framing(&mut self, framing: Framing) {
+        self.framing = framing;
+    }
+
+    /// Appends received bytes to the decoder buffer.
+    ///
+    /// In [`Framing::Datagram`] mode, the given bytes form one
+    /// complete frame.
+    pub fn push(&mut self, bytes: &[u8]) {
+        match self.framing {
+            // datagram boundaries are kept by buffering datagrams as
+            // length-prefixed frames
+            Framing::Datagram => {
+                let len = bytes.len() as u32;
+                self.buf.extend_from_slice(&len.to_be_bytes());
+                self.buf.extend_from_slice(bytes);
+            }
+            _ => self.buf.extend_from_slice(bytes),
+        }
+    }
+
+    /// Extracts the next complete frame, without its delimiter or
+    /// length prefix.
+    ///
+    /// Returns `None` if no complete frame is buffered yet. In
+    /// [`Framing::Line`] and [`Framing::Armored`] modes, trailing
+    /// whitespace (like the `\r` of a `\r\n` line ending) is trimmed.
+    ///
+    /// Fails as soon as the next frame is known to exceed the maximum
+    /// frame size, in which case the buffer is discarded.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, FrameError> {
+        let frame = match self.framing {
+            Framing::Line | Framing::Armored => {
+                match self.buf.iter().position(|b| *b == DELIMITER) {
+                    Some(pos) => {
+                        self.check_frame_size(pos)?;
+                        let mut frame: Vec<u8> = self.buf.drain(..=pos).collect();
+                        frame.pop();
+                        let len = frame.trim_ascii_end().len();
+                        frame.truncate(len);
+
+                        if self.framing == Framing::Armored {
+                            frame = armor::decode(&frame).map_err(FrameError::Armor)?;
+                        }
+
+                        Some(frame)
+                    }
+                    None => {
+                        self.check_frame_size(self.buf.len())?;
+                        None
+                    }
+                }
+            }
+            Framing::LengthPrefixed | Framing::Datagram => {
+                let Some(prefix) = self.buf.first_chunk::<LENGTH_PREFIX_SIZE>() else {
+                    return Ok(None);
+                };
+                let len = u32::from_be_bytes(*prefix) as usize;
+                self.check_frame_size(len)?;
+                let end = LENGTH_PREFIX_SIZE + len;
+                if self.buf.len() < end {
+                    return Ok(None);
+                }
+                let frame = self.buf[LENGTH_PREFIX_SIZE..end].to_vec();
+                self.buf.drain(..end);
+                Some(frame)
+            }
+        };
+
+        Ok(frame)
+    }
+
+    fn check_frame_size(&mut self, size: usize) -> Result<(), FrameError> {
+        let max = self.max_frame_size;
+
+        if size > max {
+            self.buf.clear();
+            return Err(FrameError::TooLarge { size, max });
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if no byte is buffered.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the buffered bytes that do not form a complete frame
+    /// yet.
+    pub fn remaining(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_split_across_pushes() {
+        let mut decoder = FrameDecoder::new();
+
+        decoder.push(b"\"Sta");
+        assert_eq!(decoder.next_frame().unwrap(), None);
+
+        decoder.push(b"rt\"\n");
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"\"Start\"".to_vec()));
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn frames_packed_in_one_push() {
+        let mut decoder = FrameDecoder::new();
+        decoder.push(b"\"Start\"\n\"Get\"\r\n\"Pa");
+
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"\"Start\"".to_vec()));
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"\"Get\"".to_vec()));
+        assert_eq!(decoder.next_frame().unwrap(), None);
+        assert_eq!(decoder.remaining(), b"\"Pa");
+    }
+
+    #[test]
+    fn encoded_frame_round_trip() {
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&Framing::Line.encode(b"{\"a\":\"b\\nc\"}".to_vec()));
+
+        assert_eq!(
+            decoder.next_frame().unwrap(),
+            Some(b"{\"a\":\"b\\nc\"}".to_vec())
+        );
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn length_prefixed_frames_with_delimiters() {
+        let mut decoder = FrameDecoder::with_framing(Framing::LengthPrefixed);
+        let frame = Framing::LengthPrefixed.encode(b"a\nb".to_vec());
+
+        decoder.push(&frame[..5]);
+        assert_eq!(decoder.next_frame().unwrap(), None);
+
+        decoder.push(&frame[5..]);
+        decoder.push(&Framing::LengthPrefixed.encode(b"\n".to_vec()));
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"a\nb".to_vec()));
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"\n".to_vec()));
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn datagrams_keep_their_boundaries() {
+        let mut decoder = FrameDecoder::with_framing(Framing::Datagram);
+        decoder.push(&Framing::Datagram.encode(b"a\n".to_vec()));
+        decoder.push(b"");
+        decoder.push(b"b");
+
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"a\n".to_vec()));
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"".to_vec()));
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"b".to_vec()));
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn armored_frames_with_delimiters() {
+        let mut decoder = FrameDecoder::with_framing(Framing::Armored);
+        let frame = Framing::Armored.encode(b"a\nb\0".to_vec());
+        assert!(frame[..frame.len() - 1].is_ascii());
+
+        decoder.push(&frame);
+        decoder.push(b"!!!!\r\n");
+        assert_eq!(decoder.next_frame().unwrap(), Some(b"a\nb\0".to_vec()));
+        assert!(matches!(decoder.next_frame(), Err(FrameError::Armor(_))));
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn frames_larger_than_max_size_rejected() {
+        let mut decoder = FrameDecoder::new();
+        decoder.set_max_frame_size(4);
+        decoder.push(b"abcd\nabcde");
+
+        assert_eq!(decoder.next_frame(), Ok(Some(b"abcd".to_vec())));
+        assert_eq!(
+            decoder.next_frame(),
+            Err(FrameError::TooLarge { size: 5, max: 4 })
+        );
+        assert!(decoder.is_empty());
+
+        let mut decoder = FrameDecoder::with_framing(Framing::LengthPrefixed);
+        decoder.set_max_frame_size(4);
+        decoder.push(&u32::MAX.to_be_bytes());
+
+        assert!(matches!(
+            decoder.next_frame(),
+            Err(FrameError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn reserved_buffer_reused_across_frames() {
+        let mut decoder = FrameDecoder::new();
+        decoder.reserve(64);
+        let capacity = decoder.buf.capacity();
+
+        for _ in 0..4 {
+            decoder.push(b"\"Get\"\n");
+            assert_eq!(decoder.next_frame(), Ok(Some(b"\"Get\"".to_vec())));
+        }
+
+        assert!(capacity >= 64);
+        assert_eq!(decoder.buf.capacity(), capacity);
+    }
+}
@@ -0,0 +1,324 @@
+//! History of timer events.
+//!
+//! The server remembers the last events emitted by each timer, so
+//! that clients can backfill the events they missed (for example
+//! while disconnected) with [`TimerRequest::GetHistory`]. Each event
+//! is identified by a sequence number, used as cursor to fetch the
+//! history page by page.
+//!
+//! The history also backs the at-least-once delivery of subscribed
+//! events: durable subscribers acknowledge the events they received,
+//! and the events they did not acknowledge are pushed again when
+//! they subscribe again (see [`TimerRequest::Subscribe`]).
+//!
+//! [`TimerRequest::GetHistory`]: crate::timer::TimerRequest::GetHistory
+//! [`TimerRequest::Subscribe`]: crate::timer::TimerRequest::Subscribe
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::timer::TimerEvent;
+
+/// The default number of events remembered by a [`TimerHistory`].
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// The maximum number of events returned in a single
+/// [`TimerHistoryPage`].
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// An event of the history.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerHistoryEntry {
+    /// The sequence number of the event.
+    pub seq: u64,
+    /// Unix epoch seconds at which the event occurred.
+    pub at: u64,
+    /// The event itself.
+    pub event: TimerEvent,
+}
+
+/// A page of the history.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerHistoryPage {
+    /// The events of the page, oldest first.
+    pub events: Vec<TimerHistoryEntry>,
+    /// The cursor of the next page, if any.
+    pub next: Option<u64>,
+}
+
+/// Bounded memory of the last events emitted by a timer.
+///
+/// The oldest events are forgotten when the capacity is reached, or
+/// once older than the maximum age if any (see
+/// [`TimerHistory::with_max_age`]).
+///
+/// Each timer owns its history, so that retention can be tuned per
+/// timer, before giving timers to the server:
+///
+/// ```rust,ignore
+/// let mut timer = Timer::new(config);
+/// timer.history = TimerHistory::new(64).with_max_age(3600);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TimerHistory {
+    capacity: usize,
+    max_age: Option<u64>,
+    next_seq: u64,
+    entries: VecDeque<TimerHistoryEntry>,
+    acks: BTreeMap<String, u64>,
+}
+
+impl Default for TimerHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl TimerHistory {
+    /// Creates a new history remembering at most `capacity` events.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            max_age: None,
+            next_seq: 0,
+            entries: VecDeque::new(),
+            acks: BTreeMap::new(),
+        }
+    }
+
+    /// Forgets the events older than the given number of seconds.
+    ///
+    /// Since the history has no clock, old events are evicted each
+    /// time an event is recorded, or explicitly with
+    /// [`TimerHistory::evict`].
+    pub fn with_max_age(mut self, secs: u64) -> Self {
+        self.max_age = Some(secs);
+        self
+    }
+
+    /// Returns the maximum number of remembered events.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the maximum number of remembered events, forgetting
+    /// the oldest ones if needed.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Returns the maximum age of remembered events, in seconds.
+    pub fn max_age(&self) -> Option<u64> {
+        self.max_age
+    }
+
+    /// Changes the maximum age of remembered events, in seconds, see
+    /// [`TimerHistory::with_max_age`].
+    pub fn set_max_age(&mut self, secs: Option<u64>) {
+        self.max_age = secs;
+    }
+
+    /// Forgets the events older than the maximum age at the given
+    /// time (Unix epoch seconds).
+    ///
+    /// Returns the number of forgotten events.
+    pub fn evict(&mut self, now: u64) -> usize {
+        let Some(max_age) = self.max_age else {
+            return 0;
+        };
+
+        let len = self.entries.len();
+        let oldest = now.saturating_sub(max_age);
+
+        while self.entries.front().is_some_and(|entry| entry.at < oldest) {
+            self.entries.pop_front();
+        }
+
+        len - self.entries.len()
+    }
+
+    /// Records an event emitted at `at` (Unix epoch seconds).
+    ///
+    /// Returns the sequence number of the event.
+    pub fn push(&mut self, at: u64, event: TimerEvent) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        self.evict(at);
+
+        if self.capacity == 0 {
+            return seq;
+        }
+
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(TimerHistoryEntry { seq, at, event });
+        seq
+    }
+
+    /// Returns at most `limit` events, starting from the sequence
+    /// number `cursor` (or from the oldest remembered event).
+    ///
+    /// The limit is capped to [`MAX_PAGE_SIZE`].
+    pub fn page(&self, cursor: Option<u64>, limit: Option<usize>) -> TimerHistoryPage {
+        let cursor = cursor.unwrap_or_default();
+        let limit = limit.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE);
+
+        let mut entries = self.entries.iter().filter(|entry| entry.seq >= cursor);
+        let events: Vec<_> = entries.by_ref().take(limit).cloned().collect();
+        let next = entries.next().map(|entry| entry.seq);
+
+        TimerHistoryPage { events, next }
+    }
+
+    /// Registers the durable subscriber with the given id, and
+    /// returns the remembered events it did not acknowledge yet.
+    ///
+    /// A new subscriber starts from the next event.
+    pub fn subscribe(&mut self, id: &str) -> Vec<TimerHistoryEntry> {
+        let cursor = *self.acks.entry(id.to_string()).or_insert(self.next_seq);
+
+        self.entries
+            .iter()
+            .filter(|entry| entry.seq >= cursor)
+            .cloned()
+            .collect()
+    }
+
+    /// Acknowledges every event up to the sequence number `seq`
+    /// (included) on behalf of the durable subscriber with the given
+    /// id.
+    ///
+    /// Returns `false` if the subscriber is unknown.
+    pub fn ack(&mut self, id: &str, seq: u64) -> bool {
+        match self.acks.get_mut(id) {
+            Some(cursor) => {
+                *cursor = (*cursor).max(seq + 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the sequence number of the next recorded event.
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Returns the number of remembered events.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no event is remembered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Forgets every event.
+    ///
+    /// Sequence numbers keep increasing, so that cursors held by
+    /// clients and acknowledgements of durable subscribers stay
+    /// valid.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_follow_cursors() {
+        let mut history = TimerHistory::new(8);
+        for at in 0..5 {
+            history.push(at, TimerEvent::Started);
+        }
+
+        let page = history.page(None, Some(2));
+        assert_eq!(page.events.len(), 2);
+        assert_eq!(page.events[0].seq, 0);
+        assert_eq!(page.next, Some(2));
+
+        let page = history.page(page.next, Some(2));
+        assert_eq!(page.events[0].seq, 2);
+        assert_eq!(page.next, Some(4));
+
+        let page = history.page(page.next, Some(2));
+        assert_eq!(page.events.len(), 1);
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn unacked_events_returned_on_subscribe() {
+        let mut history = TimerHistory::new(8);
+        history.push(0, TimerEvent::Started);
+
+        assert!(history.subscribe("billing").is_empty());
+        assert!(!history.ack("unknown", 0));
+
+        for at in 1..4 {
+            history.push(at, TimerEvent::Stopped);
+        }
+        assert!(history.ack("billing", 1));
+
+        let events = history.subscribe("billing");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].seq, 2);
+        assert_eq!(events[1].seq, 3);
+
+        // stale acknowledgements do not rewind the subscriber
+        assert!(history.ack("billing", 3));
+        assert!(history.ack("billing", 0));
+        assert!(history.subscribe("billing").is_empty());
+    }
+
+    #[test]
+    fn oldest_events_evicted_when_full() {
+        let mut history = TimerHistory::new(2);
+        for at in 0..3 {
+            history.push(at, TimerEvent::Stopped);
+        }
+
+        let page = history.page(None, None);
+        assert_eq!(history.len(), 2);
+        assert_eq!(page.events[0].seq, 1);
+        assert_eq!(page.events[1].at, 2);
+
+        history.set_capacity(1);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.page(None, None).events[0].seq, 2);
+    }
+
+    #[test]
+    fn old_events_evicted_after_max_age() {
+        let mut history = TimerHistory::new(8).with_max_age(10);
+        for at in [0, 5, 10] {
+            history.push(at, TimerEvent::Stopped);
+        }
+        assert_eq!(history.len(), 3);
+
+        history.push(12, TimerEvent::Stopped);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.page(None, None).events[0].at, 5);
+
+        assert_eq!(history.evict(20), 1);
+        assert_eq!(history.page(None, None).events[0].at, 10);
+
+        history.set_max_age(None);
+        assert_eq!(history.evict(100), 0);
+        assert_eq!(history.len(), 2);
+    }
+}
@@ -0,0 +1,128 @@
+//! Idempotency keys for safely retried requests.
+//!
+//! A client that lost its connection after sending a mutating
+//! request cannot know whether the request was applied. By attaching
+//! an idempotency key to the request, the client can safely retry
+//! it: the server remembers the keys of recently applied requests
+//! and replays the original response instead of applying the request
+//! a second time.
+
+use alloc::{collections::VecDeque, string::String};
+
+use crate::timer::TimerResponse;
+
+/// The default number of keys remembered by an
+/// [`IdempotencyCache`].
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// The default number of seconds a key is remembered by an
+/// [`IdempotencyCache`].
+pub const DEFAULT_TTL: u64 = 300;
+
+#[derive(Clone, Debug)]
+struct Entry {
+    key: String,
+    at: u64,
+    response: TimerResponse,
+}
+
+/// Bounded memory of recently applied idempotency keys, with their
+/// response.
+///
+/// Keys are forgotten after a time-to-live, or when the capacity is
+/// reached (oldest first).
+#[derive(Clone, Debug)]
+pub struct IdempotencyCache {
+    capacity: usize,
+    ttl: u64,
+    entries: VecDeque<Entry>,
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY, DEFAULT_TTL)
+    }
+}
+
+impl IdempotencyCache {
+    /// Creates a new cache remembering at most `capacity` keys for
+    /// `ttl` seconds.
+    pub fn new(capacity: usize, ttl: u64) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Returns the response of the request applied with the given
+    /// key, if still remembered at `now` (Unix epoch seconds).
+    pub fn get(&mut self, key: &str, now: u64) -> Option<&TimerResponse> {
+        self.evict(now);
+        self.entries
+            .iter()
+            .find(|entry| entry.key == key)
+            .map(|entry| &entry.response)
+    }
+
+    /// Remembers the response of the request applied with the given
+    /// key at `now` (Unix epoch seconds).
+    pub fn insert(&mut self, key: impl Into<String>, now: u64, response: TimerResponse) {
+        self.evict(now);
+
+        if self.capacity == 0 {
+            return;
+        }
+
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(Entry {
+            key: key.into(),
+            at: now,
+            response,
+        });
+    }
+
+    /// Forgets every key.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn evict(&mut self, now: u64) {
+        let ttl = self.ttl;
+        self.entries
+            .retain(|entry| now.saturating_sub(entry.at) < ttl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::timer::TimerState;
+
+    #[test]
+    fn keys_expire_after_ttl() {
+        let mut cache = IdempotencyCache::new(8, 10);
+        let response = TimerResponse::State(TimerState::Running);
+        cache.insert("a", 100, response.clone());
+
+        assert_eq!(cache.get("a", 109), Some(&response));
+        assert_eq!(cache.get("a", 110), None);
+    }
+
+    #[test]
+    fn oldest_keys_evicted_when_full() {
+        let mut cache = IdempotencyCache::new(2, 10);
+        cache.insert("a", 0, TimerResponse::Events(vec![]));
+        cache.insert("b", 0, TimerResponse::Events(vec![]));
+        cache.insert("c", 0, TimerResponse::Events(vec![]));
+
+        assert!(cache.get("a", 0).is_none());
+        assert!(cache.get("b", 0).is_some());
+        assert!(cache.get("c", 0).is_some());
+    }
+}
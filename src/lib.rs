@@ -4,8 +4,60 @@
 
 extern crate alloc;
 
+#[cfg(feature = "timer")]
+pub mod armor;
+#[cfg(feature = "timer")]
+pub mod audit;
+#[cfg(feature = "timer")]
+pub mod auth;
+#[cfg(feature = "timer")]
+pub mod broadcast;
+#[cfg(feature = "timer")]
+pub mod checksum;
+#[cfg(feature = "timer")]
+pub mod client;
+#[cfg(feature = "timer")]
+pub mod codec;
+#[cfg(feature = "timer")]
+pub mod compression;
 pub mod coroutines;
+#[cfg(feature = "timer")]
+pub mod delta;
+#[cfg(feature = "timer")]
+pub mod fixtures;
+#[cfg(feature = "timer")]
+pub mod frame;
+#[cfg(feature = "timer")]
+pub mod history;
+#[cfg(feature = "timer")]
+pub mod idempotency;
 pub mod io;
+#[cfg(feature = "timer")]
+pub mod metrics;
+#[cfg(feature = "timer")]
+pub mod middleware;
+#[cfg(feature = "timer")]
+pub mod mirror;
+#[cfg(feature = "test-utils")]
+pub mod mock;
+#[cfg(feature = "timer")]
+pub mod notify;
+#[cfg(feature = "timer")]
+pub mod queue;
+#[cfg(feature = "timer")]
+pub mod rate_limit;
+#[cfg(feature = "timer")]
+pub mod registry;
 pub mod runtimes;
 #[cfg(feature = "timer")]
+pub mod schema;
+#[cfg(feature = "timer")]
+pub mod server;
+#[cfg(feature = "timer")]
+pub mod session;
+#[cfg(feature = "signing")]
+pub mod signing;
+#[cfg(feature = "timer")]
+pub mod stats;
+#[cfg(feature = "timer")]
 pub mod timer;
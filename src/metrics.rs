@@ -0,0 +1,118 @@
+//! Observability of timer requests.
+//!
+//! [`TimerRequestSend`] keeps [`TimerRequestMetrics`] counters, and
+//! optionally calls a [`TimerRequestHook`] on each step of a request,
+//! so that embedders can wire their observability without wrapping
+//! every resume call.
+//!
+//! Since coroutines are I/O-free, they cannot observe time: round
+//! trips are measured by the hook, by timestamping
+//! [`TimerRequestEvent::Started`] and [`TimerRequestEvent::Received`]
+//! (or [`TimerRequestEvent::Failed`]).
+//!
+//! On the server side, [`TimerRequestHandle`] keeps
+//! [`TimerServerMetrics`] counters for its connection, aggregated
+//! for every connection by [`TimerSessionManager::metrics`].
+//!
+//! [`TimerRequestSend`]: crate::coroutines::client::TimerRequestSend
+//! [`TimerRequestHandle`]: crate::coroutines::server::TimerRequestHandle
+//! [`TimerSessionManager::metrics`]: crate::coroutines::server::TimerSessionManager::metrics
+
+use alloc::collections::BTreeMap;
+
+/// Counters of the requests sent by a coroutine.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TimerRequestMetrics {
+    /// The number of requests started.
+    pub requests: u64,
+    /// The number of responses received and decoded.
+    pub responses: u64,
+    /// The number of requests which failed, including malformed
+    /// responses.
+    pub failures: u64,
+    /// The number of malformed responses (corrupted frames, unknown
+    /// responses…).
+    pub decode_failures: u64,
+    /// The number of bytes written to the socket.
+    pub bytes_written: u64,
+    /// The number of bytes read from the socket.
+    pub bytes_read: u64,
+}
+
+impl TimerRequestMetrics {
+    /// Accounts the given event.
+    pub fn record(&mut self, event: TimerRequestEvent) {
+        match event {
+            TimerRequestEvent::Started => self.requests += 1,
+            TimerRequestEvent::Wrote(n) => self.bytes_written += n as u64,
+            TimerRequestEvent::Read(n) => self.bytes_read += n as u64,
+            TimerRequestEvent::Received => self.responses += 1,
+            TimerRequestEvent::DecodeFailed => {
+                self.decode_failures += 1;
+                self.failures += 1;
+            }
+            TimerRequestEvent::Failed => self.failures += 1,
+        }
+    }
+}
+
+/// A step of a request, given to the [`TimerRequestHook`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimerRequestEvent {
+    /// The request is about to be encoded and sent.
+    Started,
+    /// The given number of bytes were written to the socket.
+    Wrote(usize),
+    /// The given number of bytes were read from the socket.
+    Read(usize),
+    /// The response was received and decoded.
+    Received,
+    /// The response was received, but could not be decoded.
+    DecodeFailed,
+    /// The request failed before its response was received.
+    Failed,
+}
+
+/// Function called on each step of a request.
+///
+/// Hooks are plain functions so that coroutines stay cloneable:
+/// they typically forward events to a global metrics registry.
+pub type TimerRequestHook = fn(TimerRequestEvent);
+
+/// Counters of the requests handled by a server.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TimerServerMetrics {
+    /// The number of decoded requests, by request name (see
+    /// [`TimerRequest::name`]).
+    ///
+    /// [`TimerRequest::name`]: crate::timer::TimerRequest::name
+    pub requests: BTreeMap<&'static str, u64>,
+    /// The number of requests which could not be decoded (corrupted
+    /// or oversized frames, unknown requests…).
+    pub decode_failures: u64,
+    /// The number of events emitted by the handled requests.
+    pub events: u64,
+    /// The number of bytes read from the socket.
+    pub bytes_read: u64,
+    /// The number of bytes written to the socket.
+    pub bytes_written: u64,
+}
+
+impl TimerServerMetrics {
+    /// Returns the number of decoded requests, all names included.
+    pub fn total_requests(&self) -> u64 {
+        self.requests.values().sum()
+    }
+
+    /// Adds the given counters to these ones.
+    pub fn merge(&mut self, other: &Self) {
+        for (name, count) in &other.requests {
+            *self.requests.entry(name).or_default() += count;
+        }
+
+        self.decode_failures += other.decode_failures;
+        self.events += other.events;
+        self.bytes_read += other.bytes_read;
+        self.bytes_written += other.bytes_written;
+    }
+}
@@ -0,0 +1,95 @@
+//! Embedder hooks around the requests handled by a server.
+//!
+//! Requests are handled by [`TimerRequestHandle`] with built-in
+//! handlers, one per request type. Embedders can wrap or replace
+//! them without forking the server, by registering
+//! [`TimerMiddleware`]s with [`TimerRequestHandle::with_middleware`]:
+//! logging, custom authorization, or requests answered by the
+//! embedder itself.
+//!
+//! Middlewares are called in registration order before the request
+//! is applied, then in reverse order once it was answered.
+//!
+//! [`TimerRequestHandle`]: crate::coroutines::server::TimerRequestHandle
+//! [`TimerRequestHandle::with_middleware`]: crate::coroutines::server::TimerRequestHandle::with_middleware
+
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+
+use crate::{
+    session::Session,
+    timer::{TimerEnvelope, TimerResponse},
+};
+
+/// Hooks called by the server around each handled request.
+///
+/// Both hooks do nothing by default.
+pub trait TimerMiddleware: fmt::Debug + Send {
+    /// Called before the request of the given session is applied.
+    ///
+    /// Returning a response answers the request with it instead of
+    /// applying it: neither the built-in handler nor the next
+    /// middlewares are called.
+    fn before(&mut self, session: &Session, envelope: &TimerEnvelope) -> Option<TimerResponse> {
+        let _ = (session, envelope);
+        None
+    }
+
+    /// Called once the request of the given session was answered,
+    /// with the response which can be replaced.
+    ///
+    /// Only middlewares whose [`before`](TimerMiddleware::before) was
+    /// called are called.
+    fn after(&mut self, session: &Session, envelope: &TimerEnvelope, response: &mut TimerResponse) {
+        let _ = (session, envelope, response);
+    }
+}
+
+/// The ordered middlewares of a server connection.
+#[derive(Debug, Default)]
+pub struct TimerRouter {
+    middlewares: Vec<Box<dyn TimerMiddleware>>,
+}
+
+impl TimerRouter {
+    /// Creates a new router, without middleware.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the given middleware, called after the previous ones.
+    pub fn push(&mut self, middleware: impl TimerMiddleware + 'static) {
+        self.middlewares.push(Box::new(middleware));
+    }
+
+    /// Routes the given request through the middlewares, then through
+    /// the given handler unless a middleware answered it.
+    pub fn route(
+        &mut self,
+        session: &Session,
+        envelope: &TimerEnvelope,
+        handler: impl FnOnce() -> TimerResponse,
+    ) -> TimerResponse {
+        let mut called = 0;
+        let mut response = None;
+
+        for middleware in &mut self.middlewares {
+            called += 1;
+            response = middleware.before(session, envelope);
+            if response.is_some() {
+                break;
+            }
+        }
+
+        let mut response = match response {
+            Some(response) => response,
+            None => handler(),
+        };
+
+        for middleware in self.middlewares[..called].iter_mut().rev() {
+            middleware.after(session, envelope, &mut response);
+        }
+
+        response
+    }
+}
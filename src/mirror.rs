@@ -0,0 +1,263 @@
+//! Client-side replica of a timer.
+//!
+//! GUI clients need to render the countdown every second, which is
+//! too chatty to be done by polling the server. A [`TimerMirror`]
+//! keeps a local [`Timer`] instead, driven by the events pushed to a
+//! subscription (see [`TimerEventStream`]) and corrected by the
+//! snapshots received from time to time (see [`TimerPoll`] or
+//! [`TimerResync`]). Servers can push the changes of the timer
+//! instead of its events, applied with [`TimerMirror::apply_delta`].
+//! The countdown is then computed locally with
+//! [`TimerMirror::update`].
+//!
+//! [`TimerEventStream`]: crate::coroutines::stream::TimerEventStream
+//! [`TimerPoll`]: crate::coroutines::poll::TimerPoll
+//! [`TimerResync`]: crate::coroutines::request::TimerResync
+
+use alloc::vec::Vec;
+
+use crate::{
+    delta::{TimerDelta, TimerDeltaError},
+    history::TimerHistoryEntry,
+    timer::{Timer, TimerConfig, TimerCycle, TimerEvent, TimerSnapshot, TimerState},
+};
+
+/// Local replica of a timer managed by a server.
+#[derive(Clone, Debug)]
+pub struct TimerMirror {
+    timer: Timer,
+    seq: Option<u64>,
+}
+
+impl TimerMirror {
+    /// Creates a new replica of a stopped timer with the given
+    /// configuration, which needs to match the server one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config` has no cycles.
+    pub fn new(config: TimerConfig) -> Self {
+        Self {
+            timer: Timer::new(config),
+            seq: None,
+        }
+    }
+
+    /// Returns the local timer.
+    pub fn timer(&self) -> &Timer {
+        &self.timer
+    }
+
+    /// Returns the snapshot of the local timer.
+    pub fn snapshot(&self) -> TimerSnapshot {
+        TimerSnapshot::from(&self.timer)
+    }
+
+    /// Returns the sequence number of the last applied event, to be
+    /// used as cursor to backfill missed events.
+    pub fn seq(&self) -> Option<u64> {
+        self.seq
+    }
+
+    /// Advances the local timer, and returns the events it fired.
+    ///
+    /// Events computed locally (like the end of a cycle) are also
+    /// pushed by the server, applying them afterwards is harmless.
+    pub fn update(&mut self, now: u64) -> Vec<TimerEvent> {
+        self.timer.update(now).into_iter().collect()
+    }
+
+    /// Replaces the state of the local timer by the given snapshot,
+    /// received at `now`.
+    pub fn apply_snapshot(&mut self, snapshot: &TimerSnapshot, now: u64) {
+        let mut cycle = match self.timer.config.cycles.get(snapshot.cycle_index) {
+            Some(cycle) => cycle.clone(),
+            None => TimerCycle::default(),
+        };
+        cycle.name.clone_from(&snapshot.cycle);
+
+        self.timer.state = snapshot.state.clone();
+        self.timer.cycle = cycle;
+        self.timer.cycle_index = snapshot.cycle_index;
+        self.timer.cycles_count = snapshot.cycles_count.clone();
+        self.timer.started_at = snapshot.started_at;
+        self.timer.elapsed = snapshot.elapsed;
+        self.anchor(now, snapshot.remaining);
+    }
+
+    /// Applies the given event pushed by the server.
+    ///
+    /// Events already applied (with a sequence number lower than or
+    /// equal to the last applied one) are skipped, in which case
+    /// `false` is returned.
+    ///
+    /// [`TimerEvent::Lagged`] markers are skipped as well: the missed
+    /// events can then be backfilled from [`seq`](TimerMirror::seq),
+    /// or the mirror corrected with a snapshot.
+    pub fn apply(&mut self, entry: &TimerHistoryEntry) -> bool {
+        if self.seq.is_some_and(|seq| entry.seq <= seq) {
+            return false;
+        }
+
+        let at = entry.at;
+
+        match &entry.event {
+            TimerEvent::Lagged { .. } => {
+                return false;
+            }
+            TimerEvent::Started => {
+                self.timer.start(at);
+            }
+            TimerEvent::Began(cycle) | TimerEvent::Running(cycle) => {
+                self.timer.update(at);
+                self.anchor_cycle(at, cycle);
+            }
+            TimerEvent::Ended(_) => {
+                self.timer.update(at);
+            }
+            TimerEvent::Set(cycle) => {
+                self.timer.set_remaining(at, cycle.duration);
+            }
+            TimerEvent::Configured(cycle) => {
+                self.timer.set_cycle_duration(&cycle.name, cycle.duration);
+            }
+            TimerEvent::Paused { cycle, .. } => {
+                self.timer.pause(at);
+                self.anchor_cycle(at, cycle);
+            }
+            TimerEvent::Resumed(cycle) => {
+                self.timer.resume(at);
+                self.anchor_cycle(at, cycle);
+            }
+            TimerEvent::Stopped => {
+                self.timer.reset();
+            }
+        }
+
+        self.seq = Some(entry.seq);
+        true
+    }
+
+    /// Applies the given delta pushed by the server (see
+    /// [`TimerEventBroadcast::with_deltas`]).
+    ///
+    /// Keyframes replace the local timer. Other deltas need to apply
+    /// on top of the last applied event or delta, otherwise an error
+    /// is returned and the local timer is left untouched: the client
+    /// then needs to subscribe again to receive a keyframe.
+    ///
+    /// [`TimerEventBroadcast::with_deltas`]: crate::broadcast::TimerEventBroadcast::with_deltas
+    pub fn apply_delta(&mut self, delta: &TimerDelta) -> Result<(), TimerDeltaError> {
+        // deltas are keyed by the sequence number following the last
+        // covered event
+        let seq = self.seq.map_or(0, |seq| seq + 1);
+        let seq = delta.apply(&mut self.timer, seq)?;
+        self.seq = seq.checked_sub(1);
+        Ok(())
+    }
+
+    /// Makes the current cycle end `remaining` seconds after `now`,
+    /// if it is the given cycle.
+    fn anchor_cycle(&mut self, now: u64, cycle: &TimerCycle) {
+        if self.timer.cycle.name == cycle.name {
+            self.anchor(now, cycle.duration);
+        }
+    }
+
+    /// Makes the current cycle end `remaining` seconds after `now`.
+    fn anchor(&mut self, now: u64, remaining: usize) {
+        let configured = self
+            .timer
+            .config
+            .cycles
+            .get(self.timer.cycle_index)
+            .map(|cycle| cycle.duration)
+            .unwrap_or_default();
+
+        let elapsed = match self.timer.state {
+            TimerState::Stopped => 0,
+            _ => self.timer.elapsed(now),
+        };
+        let end = elapsed + remaining;
+        let offset = elapsed.min(end.saturating_sub(configured));
+
+        self.timer.cycle_offset = offset;
+        self.timer.cycle_adjustment = (end - offset) as isize - configured as isize;
+        self.timer.cycle.duration = remaining;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timer::{PauseReason, TimerCycles, TimerLoop};
+
+    use super::*;
+
+    fn config() -> TimerConfig {
+        TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("Focus", 60), TimerCycle::new("Break", 30)]),
+            cycles_count: TimerLoop::Infinite,
+        }
+    }
+
+    fn entry(seq: u64, at: u64, event: TimerEvent) -> TimerHistoryEntry {
+        TimerHistoryEntry { seq, at, event }
+    }
+
+    #[test]
+    fn events_drive_local_countdown() {
+        let mut mirror = TimerMirror::new(config());
+
+        assert!(mirror.apply(&entry(0, 100, TimerEvent::Started)));
+        mirror.update(110);
+        assert_eq!(mirror.timer().cycle.duration, 50);
+
+        let paused = TimerEvent::Paused {
+            cycle: TimerCycle::new("Focus", 45),
+            reason: PauseReason::Request,
+        };
+        assert!(mirror.apply(&entry(1, 115, paused.clone())));
+        assert!(!mirror.apply(&entry(1, 115, paused)));
+        mirror.update(200);
+        assert_eq!(mirror.timer().state, TimerState::Paused);
+        assert_eq!(mirror.timer().cycle.duration, 45);
+
+        assert!(mirror.apply(&entry(
+            2,
+            200,
+            TimerEvent::Resumed(TimerCycle::new("Focus", 45))
+        )));
+        mirror.update(250);
+        assert_eq!(mirror.timer().cycle.name, "Break");
+        assert_eq!(mirror.timer().cycle.duration, 25);
+
+        assert!(mirror.apply(&entry(3, 260, TimerEvent::Stopped)));
+        assert_eq!(mirror.timer().state, TimerState::Stopped);
+        assert_eq!(mirror.seq(), Some(3));
+    }
+
+    #[test]
+    fn lagged_marker_is_skipped() {
+        let mut mirror = TimerMirror::new(config());
+
+        assert!(mirror.apply(&entry(0, 100, TimerEvent::Started)));
+        assert!(!mirror.apply(&entry(3, 110, TimerEvent::Lagged { missed: 2 })));
+        assert_eq!(mirror.seq(), Some(0));
+        assert_eq!(mirror.timer().state, TimerState::Running);
+    }
+
+    #[test]
+    fn snapshot_resyncs_local_countdown() {
+        let mut server = Timer::new(config());
+        server.start(100);
+        server.set_remaining(110, 100);
+        server.update(130);
+
+        let mut mirror = TimerMirror::new(config());
+        mirror.apply_snapshot(&TimerSnapshot::from(&server), 130);
+
+        server.update(300);
+        mirror.update(300);
+        assert_eq!(mirror.snapshot(), TimerSnapshot::from(&server));
+    }
+}
@@ -0,0 +1,239 @@
+//! Scripted socket to unit-test client coroutines.
+//!
+//! A [`MockSocket`] answers the [`SocketInput`] requests emitted by
+//! coroutines without any real socket: reads are fed with scripted
+//! byte chunks, writes are recorded. Downstream crates can then test
+//! their client logic by scripting the server responses, then by
+//! checking the requests actually sent.
+//!
+//! ```rust,ignore
+//! let mut socket = MockSocket::new();
+//! socket.push_response(&TimerResponse::Ok);
+//!
+//! let response = socket.run(&mut TimerRequestSend::start()).unwrap();
+//!
+//! assert_eq!(response, TimerResponse::Ok);
+//! assert_eq!(socket.requests()[0].request, TimerRequest::Start);
+//! ```
+
+use alloc::{collections::VecDeque, vec::Vec};
+
+use io_socket::io::{SocketInput, SocketOutput};
+
+use crate::{
+    coroutines::{Coroutine, CoroutineResult},
+    frame::FrameDecoder,
+    session::Session,
+    timer::{TimerEnvelope, TimerResponse},
+};
+
+/// In-memory socket fed with scripted chunks.
+#[derive(Clone, Debug, Default)]
+pub struct MockSocket {
+    session: Session,
+    chunks: VecDeque<Vec<u8>>,
+    written: Vec<u8>,
+}
+
+impl MockSocket {
+    /// Creates a new socket, without any chunk to read.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encodes scripted responses and decodes recorded requests
+    /// using the given session, instead of the default one.
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = session;
+        self
+    }
+
+    /// Queues the given chunk, returned as is by the next read.
+    ///
+    /// A chunk larger than the read buffer is returned over several
+    /// reads. Once every chunk has been read, reads return 0 bytes,
+    /// as if the peer closed the connection.
+    pub fn push_bytes(&mut self, chunk: impl Into<Vec<u8>>) {
+        self.chunks.push_back(chunk.into());
+    }
+
+    /// Queues the frame of the given response, encoded like a
+    /// server of the session would do.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the response cannot be encoded.
+    pub fn push_response(&mut self, response: &TimerResponse) {
+        let bytes = match self.session.codec.encode(response) {
+            Ok(bytes) => bytes,
+            Err(err) => panic!("cannot encode mock response: {err}"),
+        };
+        let bytes = match self.session.compression.compress(bytes) {
+            Ok(bytes) => bytes,
+            Err(err) => panic!("cannot compress mock response: {err}"),
+        };
+        let bytes = self.session.checksum.append(bytes);
+
+        self.push_bytes(self.session.framing().encode(bytes));
+    }
+
+    /// Performs the given socket I/O request.
+    pub fn io(&mut self, input: SocketInput) -> SocketOutput {
+        match input {
+            SocketInput::Read { mut buf } => {
+                let Some(mut chunk) = self.chunks.pop_front() else {
+                    return SocketOutput::Read { buf, n: 0 };
+                };
+
+                let n = chunk.len().min(buf.len());
+                buf[..n].copy_from_slice(&chunk[..n]);
+
+                if n < chunk.len() {
+                    self.chunks.push_front(chunk.split_off(n));
+                }
+
+                SocketOutput::Read { buf, n }
+            }
+            SocketInput::Write { buf } => {
+                self.written.extend_from_slice(&buf);
+                let n = buf.len();
+                SocketOutput::Wrote { buf, n }
+            }
+        }
+    }
+
+    /// Drives the given coroutine until it terminates.
+    pub fn run<C>(&mut self, coroutine: &mut C) -> Result<C::Ok, C::Error>
+    where
+        C: Coroutine<Input = SocketInput, Output = SocketOutput>,
+    {
+        let mut arg = None;
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                CoroutineResult::Ok(ok) => return Ok(ok),
+                CoroutineResult::Io(input) => arg = Some(self.io(input)),
+                CoroutineResult::Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Returns the number of chunks not read yet.
+    pub fn pending(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns every byte written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// Returns the frames written so far, without their delimiters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the written bytes contain an invalid frame.
+    pub fn frames(&self) -> Vec<Vec<u8>> {
+        let mut decoder = FrameDecoder::new();
+        decoder.set_framing(self.session.framing());
+        decoder.push(&self.written);
+
+        let mut frames = Vec::new();
+
+        loop {
+            match decoder.next_frame() {
+                Ok(Some(frame)) => frames.push(frame),
+                Ok(None) => return frames,
+                Err(err) => panic!("invalid mock frame: {err}"),
+            }
+        }
+    }
+
+    /// Returns the request envelopes written so far.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the written bytes contain an invalid request.
+    pub fn requests(&self) -> Vec<TimerEnvelope> {
+        self.frames()
+            .into_iter()
+            .map(|frame| {
+                let bytes = match self.session.checksum.verify(frame) {
+                    Ok(bytes) => bytes,
+                    Err(err) => panic!("invalid mock request checksum: {err}"),
+                };
+                #[cfg(feature = "signing")]
+                let bytes = match &self.session.signing_key {
+                    None => bytes,
+                    Some(key) => match key.verify(bytes) {
+                        Ok(bytes) => bytes,
+                        Err(err) => panic!("invalid mock request signature: {err}"),
+                    },
+                };
+
+                match self.session.codec.decode(&bytes) {
+                    Ok(envelope) => envelope,
+                    Err(err) => panic!("cannot decode mock request: {err}"),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use crate::{
+        checksum::Checksum,
+        coroutines::client::{TimerRequestSend, TimerRequestSendError},
+        timer::{TimerError, TimerRequest},
+    };
+
+    use super::*;
+
+    #[test]
+    fn scripted_response_received() {
+        let mut socket = MockSocket::new();
+        socket.push_response(&TimerResponse::Err(TimerError::Forbidden));
+
+        let response = socket.run(&mut TimerRequestSend::start()).unwrap();
+
+        assert_eq!(response, TimerResponse::Err(TimerError::Forbidden));
+        assert_eq!(
+            socket.requests(),
+            vec![TimerEnvelope::from(TimerRequest::Start)]
+        );
+        assert_eq!(socket.pending(), 0);
+    }
+
+    #[test]
+    fn chunk_split_over_reads() {
+        let session = Session {
+            checksum: Checksum::Crc32,
+            ..Default::default()
+        };
+        let mut socket = MockSocket::new().with_session(session);
+        socket.push_response(&TimerResponse::Ok);
+        let frame = socket.chunks.pop_front().unwrap();
+        let (head, tail) = frame.split_at(3);
+        socket.push_bytes(head);
+        socket.push_bytes(tail);
+
+        let mut send = TimerRequestSend::pause().with_session(session);
+        let response = socket.run(&mut send).unwrap();
+
+        assert_eq!(response, TimerResponse::Ok);
+        assert_eq!(
+            socket.requests(),
+            vec![TimerEnvelope::from(TimerRequest::Pause)]
+        );
+    }
+
+    #[test]
+    fn eof_once_chunks_read() {
+        let mut socket = MockSocket::new();
+        let err = socket.run(&mut TimerRequestSend::get()).unwrap_err();
+        assert!(matches!(err, TimerRequestSendError::ReadEof));
+    }
+}
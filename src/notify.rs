@@ -0,0 +1,260 @@
+//! Notifications of the changes of the timers of a server.
+//!
+//! Embedders can observe every event emitted by the timers with a
+//! [`Notify`] sink, registered on the coroutines emitting them (see
+//! [`TimerRequestHandle::with_notifier`] and
+//! [`TimerTick::with_notifier`]). Channel senders are sinks, so that
+//! events can be consumed from another thread or task:
+//!
+//! ```rust,ignore
+//! let (sender, receiver) = mpsc::channel();
+//! let tick = TimerTick::new().with_notifier(sender);
+//!
+//! thread::spawn(move || {
+//!     for event in receiver {
+//!         println!("{event:?}");
+//!     }
+//! });
+//! ```
+//!
+//! Several sinks can be registered at once with [`Notifiers`].
+//!
+//! Persistence drivers, MQTT bridges or metrics exporters are not
+//! interested in every [`TimerEvent::Running`] tick, only in the
+//! changes of the observable state of the timers. Requests and ticks
+//! report these changes to their store with [`TimerStore::changed`],
+//! and a [`NotifiedStore`] forwards them to a
+//! [`TimerNotificationSink`] as [`TimerNotification::StateChanged`]:
+//!
+//! ```rust,ignore
+//! #[derive(Debug)]
+//! struct Persist(PathBuf);
+//!
+//! impl TimerNotificationSink for Persist {
+//!     fn notify(&mut self, timer: &str, notification: TimerNotification) {
+//!         let TimerNotification::StateChanged(snapshot) = notification;
+//!         let path = self.0.join(timer).with_extension("json");
+//!         let _ = fs::write(path, serde_json::to_vec(&snapshot).unwrap());
+//!     }
+//! }
+//!
+//! let mut timers = NotifiedStore::new(timer).with_sink(Persist(dir));
+//! let result = handle.resume(&mut timers, arg);
+//! ```
+//!
+//! The std and tokio servers wrap their timers the same way, see for
+//! example [`StdServeOptions::with_notifications`].
+//!
+//! [`StdServeOptions::with_notifications`]: crate::server::std::StdServeOptions::with_notifications
+//! [`TimerStore::changed`]: crate::registry::TimerStore::changed
+//! [`TimerRequestHandle::with_notifier`]: crate::coroutines::server::TimerRequestHandle::with_notifier
+//! [`TimerTick::with_notifier`]: crate::coroutines::tick::TimerTick::with_notifier
+
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
+use core::fmt;
+
+use serde_json::Value;
+
+use crate::{
+    registry::TimerStore,
+    timer::{Timer, TimerConfig, TimerError, TimerEvent, TimerSnapshot, TimerStatus},
+};
+
+/// A notification of a server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TimerNotification {
+    /// The observable state of the timer changed: it started,
+    /// stopped, was paused, resumed, set or configured, or began a
+    /// new cycle. Periodic ticks of a running timer are not
+    /// notified.
+    StateChanged(TimerSnapshot),
+}
+
+impl TimerNotification {
+    /// Returns `true` if the given event changes the observable state
+    /// of a timer.
+    pub fn changes_state(event: &TimerEvent) -> bool {
+        !matches!(event, TimerEvent::Running(_) | TimerEvent::Lagged { .. })
+    }
+}
+
+/// Destination of the notifications of a server.
+pub trait TimerNotificationSink: fmt::Debug + Send {
+    /// Receives the given notification about the timer with the given
+    /// name.
+    fn notify(&mut self, timer: &str, notification: TimerNotification);
+}
+
+#[cfg(feature = "std")]
+impl<T: TimerNotificationSink> TimerNotificationSink for std::sync::Arc<std::sync::Mutex<T>> {
+    fn notify(&mut self, timer: &str, notification: TimerNotification) {
+        match self.lock() {
+            Ok(mut sink) => sink.notify(timer, notification),
+            Err(poisoned) => poisoned.into_inner().notify(timer, notification),
+        }
+    }
+}
+
+/// A [`TimerStore`] notifying a [`TimerNotificationSink`] of the
+/// state changes of the timers of the wrapped store.
+///
+/// Every change reported to the store with [`TimerStore::changed`]
+/// is forwarded to the wrapped store, then notified to the sink as
+/// [`TimerNotification::StateChanged`]. Deleted timers are not
+/// notified.
+#[derive(Debug)]
+pub struct NotifiedStore<S> {
+    store: S,
+    sink: Option<Box<dyn TimerNotificationSink>>,
+}
+
+impl<S> NotifiedStore<S> {
+    /// Wraps the given store, without sink.
+    pub fn new(store: S) -> Self {
+        Self { store, sink: None }
+    }
+
+    /// Notifies the given sink of the state changes.
+    pub fn with_sink(self, sink: impl TimerNotificationSink + 'static) -> Self {
+        self.with_boxed_sink(Some(Box::new(sink)))
+    }
+
+    /// Notifies the given sink of the state changes, if any.
+    pub(crate) fn with_boxed_sink(mut self, sink: Option<Box<dyn TimerNotificationSink>>) -> Self {
+        self.sink = sink;
+        self
+    }
+
+    /// Returns the wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the wrapped store.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    /// Unwraps the store.
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+}
+
+impl<S: TimerStore> TimerStore for NotifiedStore<S> {
+    const MULTI_TIMER: bool = S::MULTI_TIMER;
+
+    fn get_mut(&mut self, name: Option<&str>) -> Option<&mut Timer> {
+        self.store.get_mut(name)
+    }
+
+    fn busy(&self, name: Option<&str>) -> Option<u64> {
+        self.store.busy(name)
+    }
+
+    fn list(&self) -> BTreeMap<String, TimerStatus> {
+        self.store.list()
+    }
+
+    fn create(&mut self, name: &str, config: TimerConfig) -> Result<(), TimerError> {
+        self.store.create(name, config)
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), TimerError> {
+        self.store.delete(name)
+    }
+
+    fn changed(&mut self, name: &str, snapshot: Option<&TimerSnapshot>) {
+        self.store.changed(name, snapshot);
+
+        if let (Some(sink), Some(snapshot)) = (&mut self.sink, snapshot) {
+            sink.notify(name, TimerNotification::StateChanged(snapshot.clone()));
+        }
+    }
+
+    fn tick_interval(&self, name: &str) -> Option<u64> {
+        self.store.tick_interval(name)
+    }
+
+    fn extension(
+        &mut self,
+        timer: Option<&str>,
+        name: &str,
+        payload: &Value,
+    ) -> Result<Value, TimerError> {
+        self.store.extension(timer, name, payload)
+    }
+}
+
+/// Sink of the events emitted by the timers.
+pub trait Notify: fmt::Debug + Send {
+    /// Receives the given event, once recorded in the history of its
+    /// timer.
+    fn notify(&mut self, event: &TimerEvent);
+}
+
+/// Collection of sinks, notified in registration order.
+#[derive(Debug, Default)]
+pub struct Notifiers(Vec<Box<dyn Notify>>);
+
+impl Notifiers {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the given sink.
+    pub fn with(mut self, notifier: impl Notify + 'static) -> Self {
+        self.push(notifier);
+        self
+    }
+
+    /// Registers the given sink.
+    pub fn push(&mut self, notifier: impl Notify + 'static) {
+        self.0.push(Box::new(notifier));
+    }
+
+    /// Returns the number of registered sinks.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if no sink is registered.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Notify for Notifiers {
+    fn notify(&mut self, event: &TimerEvent) {
+        for notifier in &mut self.0 {
+            notifier.notify(event);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Notify> Notify for std::sync::Arc<std::sync::Mutex<T>> {
+    fn notify(&mut self, event: &TimerEvent) {
+        match self.lock() {
+            Ok(mut notifier) => notifier.notify(event),
+            Err(poisoned) => poisoned.into_inner().notify(event),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Notify for std::sync::mpsc::Sender<TimerEvent> {
+    fn notify(&mut self, event: &TimerEvent) {
+        // receivers are free to go away
+        let _ = self.send(event.clone());
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Notify for tokio::sync::mpsc::UnboundedSender<TimerEvent> {
+    fn notify(&mut self, event: &TimerEvent) {
+        // receivers are free to go away
+        let _ = self.send(event.clone());
+    }
+}
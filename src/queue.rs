@@ -0,0 +1,159 @@
+//! Client-side queue of requests awaiting a connection.
+//!
+//! GUI clients need to stay responsive while the server is
+//! unreachable (for example while the daemon restarts). Requests
+//! modifying the timer can then be pushed to a [`TimerRequestQueue`],
+//! which attaches an idempotency key to each of them, and flushed
+//! with [`TimerQueueFlush`] once a connection is re-established.
+//! Thanks to the keys, requests applied right before a connection
+//! failure are not applied twice.
+//!
+//! [`TimerQueueFlush`]: crate::coroutines::flush::TimerQueueFlush
+
+use alloc::{
+    collections::VecDeque,
+    format,
+    string::{String, ToString},
+};
+
+use thiserror::Error;
+
+use crate::timer::TimerEnvelope;
+
+/// The default number of requests kept by a [`TimerRequestQueue`].
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// Error emitted when pushing a request to a [`TimerRequestQueue`].
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum TimerRequestQueueError {
+    #[error("Timer request queue full ({0} requests)")]
+    Full(usize),
+    #[error("Cannot queue timer request not modifying the timer")]
+    NotMutating,
+}
+
+/// Bounded queue of requests modifying the timer, waiting to be sent.
+#[derive(Clone, Debug)]
+pub struct TimerRequestQueue {
+    prefix: String,
+    next_key: u64,
+    capacity: usize,
+    envelopes: VecDeque<TimerEnvelope>,
+}
+
+impl TimerRequestQueue {
+    /// Creates a new, empty queue.
+    ///
+    /// Idempotency keys are generated from the given prefix, which
+    /// needs to be unique per client (for example a random string
+    /// generated at startup).
+    pub fn new(prefix: impl ToString) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            next_key: 0,
+            capacity: DEFAULT_CAPACITY,
+            envelopes: VecDeque::new(),
+        }
+    }
+
+    /// Keeps at most the given number of requests, instead of
+    /// [`DEFAULT_CAPACITY`].
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Queues the given request, or request envelope, and returns
+    /// its idempotency key.
+    ///
+    /// Envelopes already carrying a key keep it. Only requests
+    /// modifying the timer can be queued: the others are meant to be
+    /// sent again once connected.
+    pub fn push(
+        &mut self,
+        request: impl Into<TimerEnvelope>,
+    ) -> Result<String, TimerRequestQueueError> {
+        let mut envelope = request.into();
+
+        if !envelope.request.is_mutating() {
+            return Err(TimerRequestQueueError::NotMutating);
+        }
+
+        if self.envelopes.len() >= self.capacity {
+            return Err(TimerRequestQueueError::Full(self.capacity));
+        }
+
+        let key = match &envelope.key {
+            Some(key) => key.clone(),
+            None => {
+                let key = format!("{}-{}", self.prefix, self.next_key);
+                self.next_key += 1;
+                envelope.key = Some(key.clone());
+                key
+            }
+        };
+
+        self.envelopes.push_back(envelope);
+        Ok(key)
+    }
+
+    /// Returns the next request to send, if any.
+    pub fn front(&self) -> Option<&TimerEnvelope> {
+        self.envelopes.front()
+    }
+
+    /// Removes the next request, once sent.
+    pub fn pop(&mut self) -> Option<TimerEnvelope> {
+        self.envelopes.pop_front()
+    }
+
+    /// Returns the number of queued requests.
+    pub fn len(&self) -> usize {
+        self.envelopes.len()
+    }
+
+    /// Returns `true` if no request is queued.
+    pub fn is_empty(&self) -> bool {
+        self.envelopes.is_empty()
+    }
+
+    /// Forgets every queued request.
+    pub fn clear(&mut self) {
+        self.envelopes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timer::TimerRequest;
+
+    #[test]
+    fn keys_attached_to_queued_requests() {
+        let mut queue = TimerRequestQueue::new("gui");
+
+        assert_eq!(queue.push(TimerRequest::Start), Ok("gui-0".into()));
+        assert_eq!(
+            queue.push(TimerEnvelope::new(TimerRequest::Pause).with_key("mine")),
+            Ok("mine".into())
+        );
+        assert_eq!(queue.push(TimerRequest::Stop), Ok("gui-1".into()));
+        assert_eq!(queue.front().unwrap().key.as_deref(), Some("gui-0"));
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn only_mutating_requests_queued_up_to_capacity() {
+        let mut queue = TimerRequestQueue::new("gui").with_capacity(1);
+
+        assert_eq!(
+            queue.push(TimerRequest::Get),
+            Err(TimerRequestQueueError::NotMutating)
+        );
+        assert!(queue.push(TimerRequest::Start).is_ok());
+        assert_eq!(
+            queue.push(TimerRequest::Stop),
+            Err(TimerRequestQueueError::Full(1))
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! Protection against clients flooding the server with requests.
+//!
+//! A buggy client issuing requests in a tight loop keeps the timer
+//! busy, preventing other clients from being served. A
+//! [`RateLimit`] bounds the number of requests a connection can
+//! issue in a time window: requests exceeding the limit are rejected
+//! with [`TimerError::TooManyRequests`], telling the client how long
+//! to wait before retrying.
+//!
+//! [`TimerError::TooManyRequests`]: crate::timer::TimerError::TooManyRequests
+
+/// The default number of requests allowed per window by a
+/// [`RateLimit`].
+pub const DEFAULT_MAX_REQUESTS: usize = 20;
+
+/// The default window of a [`RateLimit`], in seconds.
+pub const DEFAULT_WINDOW: u64 = 1;
+
+/// Fixed-window request counter.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    max_requests: usize,
+    window: u64,
+    window_start: u64,
+    count: usize,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_REQUESTS, DEFAULT_WINDOW)
+    }
+}
+
+impl RateLimit {
+    /// Creates a new limit allowing at most `max_requests` requests
+    /// every `window` seconds.
+    pub fn new(max_requests: usize, window: u64) -> Self {
+        Self {
+            max_requests,
+            window: window.max(1),
+            window_start: 0,
+            count: 0,
+        }
+    }
+
+    /// Records a request issued at `now` (Unix epoch seconds).
+    ///
+    /// Returns the number of seconds to wait before retrying if the
+    /// limit is exceeded. Rejected requests are not counted.
+    pub fn check(&mut self, now: u64) -> Result<(), u64> {
+        if now.saturating_sub(self.window_start) >= self.window {
+            self.window_start = now;
+            self.count = 0;
+        }
+
+        if self.count >= self.max_requests {
+            let retry_after = (self.window_start + self.window).saturating_sub(now);
+            return Err(retry_after);
+        }
+
+        self.count += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_rejected_until_next_window() {
+        let mut limit = RateLimit::new(2, 10);
+
+        assert_eq!(limit.check(100), Ok(()));
+        assert_eq!(limit.check(101), Ok(()));
+        assert_eq!(limit.check(104), Err(6));
+        assert_eq!(limit.check(109), Err(1));
+        assert_eq!(limit.check(110), Ok(()));
+    }
+}
@@ -0,0 +1,246 @@
+//! Registry of named timers.
+//!
+//! A server can manage several timers at once (for example one per
+//! project), each identified by its name. Requests target a timer by
+//! its name (see [`TimerEnvelope::timer`]), or the
+//! [`DEFAULT_TIMER`] when no name is given.
+//!
+//! The [`TimerRequestHandle`] coroutine works on any [`TimerStore`]:
+//! either a single [`Timer`], or a [`TimerRegistry`].
+//!
+//! [`TimerEnvelope::timer`]: crate::timer::TimerEnvelope::timer
+//! [`TimerRequestHandle`]: crate::coroutines::server::TimerRequestHandle
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+};
+
+use serde_json::Value;
+
+use crate::timer::{Timer, TimerConfig, TimerError, TimerSnapshot, TimerStatus};
+
+/// The name of the timer targeted by requests without timer name.
+pub const DEFAULT_TIMER: &str = "default";
+
+/// The timer name matching every timer, used to subscribe to the
+/// events of all the timers of a server.
+pub const WILDCARD_TIMER: &str = "*";
+
+/// Storage of the timers managed by a server.
+pub trait TimerStore {
+    /// Whether the store can manage several timers.
+    const MULTI_TIMER: bool;
+
+    /// Returns the timer with the given name, or the
+    /// [`DEFAULT_TIMER`] if no name is given.
+    fn get_mut(&mut self, name: Option<&str>) -> Option<&mut Timer>;
+
+    /// Returns the number of milliseconds after which the timer with
+    /// the given name can be obtained, if it cannot be obtained right
+    /// now (for example because another connection holds it).
+    ///
+    /// Never busy by default.
+    fn busy(&self, name: Option<&str>) -> Option<u64> {
+        let _ = name;
+        None
+    }
+
+    /// Returns the status of every timer, by name.
+    fn list(&self) -> BTreeMap<String, TimerStatus>;
+
+    /// Creates a new timer with the given name and configuration.
+    ///
+    /// Not supported by default.
+    fn create(&mut self, name: &str, config: TimerConfig) -> Result<(), TimerError> {
+        let _ = (name, config);
+        Err(TimerError::Unsupported)
+    }
+
+    /// Deletes the timer with the given name.
+    ///
+    /// Not supported by default.
+    fn delete(&mut self, name: &str) -> Result<(), TimerError> {
+        let _ = name;
+        Err(TimerError::Unsupported)
+    }
+
+    /// Called once the observable state of the timer with the given
+    /// name changed, with its new snapshot: after a request or a tick
+    /// emitted events other than periodic ticks (see
+    /// [`TimerNotification::changes_state`]), including the end of a
+    /// fixed timer, or once the timer was created. The snapshot is
+    /// `None` once the timer was deleted.
+    ///
+    /// This is the place to persist the timer (see [`schema`]), so
+    /// that it can be loaded back after a restart. Does nothing by
+    /// default. See [`NotifiedStore`] to forward these changes to a
+    /// notification sink.
+    ///
+    /// [`schema`]: crate::schema
+    /// [`NotifiedStore`]: crate::notify::NotifiedStore
+    /// [`TimerNotification::changes_state`]: crate::notify::TimerNotification::changes_state
+    fn changed(&mut self, name: &str, snapshot: Option<&TimerSnapshot>) {
+        let _ = (name, snapshot);
+    }
+
+    /// Returns the maximum number of seconds between two ticks of the
+    /// timer with the given name (see [`TimerTick`]), or `None` to use
+    /// the interval of the tick coroutine.
+    ///
+    /// Returns `None` by default.
+    ///
+    /// [`TimerTick`]: crate::coroutines::tick::TimerTick
+    fn tick_interval(&self, name: &str) -> Option<u64> {
+        let _ = name;
+        None
+    }
+
+    /// Handles the embedder-defined request with the given name and
+    /// payload, targeting the timer with the given name (if any).
+    ///
+    /// This is the way applications embedding the server can extend
+    /// the protocol, by wrapping their store. Not supported by
+    /// default.
+    fn extension(
+        &mut self,
+        timer: Option<&str>,
+        name: &str,
+        payload: &Value,
+    ) -> Result<Value, TimerError> {
+        let _ = (timer, name, payload);
+        Err(TimerError::Unsupported)
+    }
+}
+
+impl TimerStore for Timer {
+    const MULTI_TIMER: bool = false;
+
+    fn get_mut(&mut self, name: Option<&str>) -> Option<&mut Timer> {
+        match name {
+            None | Some(DEFAULT_TIMER) => Some(self),
+            Some(_) => None,
+        }
+    }
+
+    fn list(&self) -> BTreeMap<String, TimerStatus> {
+        BTreeMap::from([(DEFAULT_TIMER.to_string(), TimerStatus::from(self))])
+    }
+}
+
+/// A collection of named timers.
+#[derive(Clone, Debug, Default)]
+pub struct TimerRegistry {
+    timers: BTreeMap<String, Timer>,
+    tick_intervals: BTreeMap<String, u64>,
+}
+
+impl TimerRegistry {
+    /// Creates a new, empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the given timer to the registry.
+    ///
+    /// Returns the timer previously registered with the same name,
+    /// if any.
+    pub fn insert(&mut self, name: impl ToString, timer: Timer) -> Option<Timer> {
+        self.timers.insert(name.to_string(), timer)
+    }
+
+    /// Removes the timer with the given name from the registry.
+    pub fn remove(&mut self, name: &str) -> Option<Timer> {
+        self.tick_intervals.remove(name);
+        self.timers.remove(name)
+    }
+
+    /// Ticks the timer with the given name every given number of
+    /// seconds at most, see [`TimerStore::tick_interval`].
+    ///
+    /// Timers rarely observed can be ticked less often than the
+    /// others, for example.
+    pub fn set_tick_interval(&mut self, name: impl ToString, secs: u64) {
+        self.tick_intervals.insert(name.to_string(), secs);
+    }
+
+    /// Returns the timer with the given name.
+    pub fn get(&self, name: &str) -> Option<&Timer> {
+        self.timers.get(name)
+    }
+
+    /// Returns `true` if a timer is registered with the given name.
+    pub fn contains(&self, name: &str) -> bool {
+        self.timers.contains_key(name)
+    }
+
+    /// Returns the number of registered timers.
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    /// Returns `true` if no timer is registered.
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    /// Iterates over the registered timers, by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Timer)> {
+        self.timers
+            .iter()
+            .map(|(name, timer)| (name.as_str(), timer))
+    }
+}
+
+impl TimerStore for TimerRegistry {
+    const MULTI_TIMER: bool = true;
+
+    fn get_mut(&mut self, name: Option<&str>) -> Option<&mut Timer> {
+        self.timers.get_mut(name.unwrap_or(DEFAULT_TIMER))
+    }
+
+    fn list(&self) -> BTreeMap<String, TimerStatus> {
+        self.iter()
+            .map(|(name, timer)| (name.to_string(), TimerStatus::from(timer)))
+            .collect()
+    }
+
+    fn create(&mut self, name: &str, config: TimerConfig) -> Result<(), TimerError> {
+        // the wildcard targets every timer, hence cannot name one
+        if name == WILDCARD_TIMER {
+            let reason = "the wildcard is not a valid timer name";
+            return Err(TimerError::InvalidConfig(reason.to_string()));
+        }
+
+        if self.contains(name) {
+            return Err(TimerError::TimerExists(name.to_string()));
+        }
+
+        config.validate()?;
+        self.insert(name, Timer::new(config));
+        Ok(())
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), TimerError> {
+        match self.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(TimerError::UnknownTimer(name.to_string())),
+        }
+    }
+
+    fn tick_interval(&self, name: &str) -> Option<u64> {
+        self.tick_intervals.get(name).copied()
+    }
+}
+
+impl<N: ToString> FromIterator<(N, Timer)> for TimerRegistry {
+    fn from_iter<T: IntoIterator<Item = (N, Timer)>>(iter: T) -> Self {
+        Self {
+            timers: iter
+                .into_iter()
+                .map(|(name, timer)| (name.to_string(), timer))
+                .collect(),
+            tick_intervals: BTreeMap::new(),
+        }
+    }
+}
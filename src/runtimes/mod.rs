@@ -14,3 +14,5 @@
 
 #[cfg(feature = "std")]
 pub mod std;
+#[cfg(feature = "tokio")]
+pub mod tokio;
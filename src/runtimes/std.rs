@@ -1,7 +1,7 @@
 //! Synchronous time runtime backed by [`std::time`].
 
 use std::{
-    io::{Error, ErrorKind, Result},
+    io::{Error, Result},
     thread,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
@@ -22,7 +22,7 @@ pub fn handle(input: TimeInput) -> Result<TimeOutput> {
 pub fn now() -> Result<TimeOutput> {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .map_err(|err| Error::new(ErrorKind::Other, err))?;
+        .map_err(Error::other)?;
 
     let secs = now.as_secs();
     let nanos = now.subsec_nanos();
@@ -42,7 +42,7 @@ pub fn sleep_until(timestamp: u64) -> Result<TimeOutput> {
 
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .map_err(|err| Error::new(ErrorKind::Other, err))?;
+        .map_err(Error::other)?;
 
     if target > now {
         thread::sleep(target - now);
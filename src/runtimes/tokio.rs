@@ -0,0 +1,40 @@
+//! Asynchronous time runtime backed by [`tokio::time`].
+
+use std::{
+    io::Result,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::time;
+
+use crate::{
+    io::{TimeInput, TimeOutput},
+    runtimes::std::now,
+};
+
+/// Processes a [`TimeInput`] request asynchronously using
+/// [`tokio::time`].
+pub async fn handle(input: TimeInput) -> Result<TimeOutput> {
+    match input {
+        TimeInput::Now => now(),
+        TimeInput::Sleep { secs } => sleep(secs).await,
+        TimeInput::SleepUntil { timestamp } => sleep_until(timestamp).await,
+    }
+}
+
+/// Waits for the given number of seconds.
+pub async fn sleep(secs: u64) -> Result<TimeOutput> {
+    time::sleep(Duration::from_secs(secs)).await;
+    Ok(TimeOutput::Slept)
+}
+
+/// Waits until the given Unix epoch second is reached.
+pub async fn sleep_until(timestamp: u64) -> Result<TimeOutput> {
+    let target = UNIX_EPOCH + Duration::from_secs(timestamp);
+
+    if let Ok(duration) = target.duration_since(SystemTime::now()) {
+        time::sleep(duration).await;
+    }
+
+    Ok(TimeOutput::Slept)
+}
@@ -0,0 +1,196 @@
+//! Versioned schema of persisted timers.
+//!
+//! Daemons may save their [`Timer`] (for example on shutdown) and
+//! load it back later, possibly after an upgrade. Serialized timers
+//! therefore carry the [`TIMER_SCHEMA_VERSION`] they were written
+//! with, and timers written with an older schema are migrated when
+//! deserialized.
+//!
+//! Schema history:
+//!
+//! - `1`: the original schema, without `version` field. The position
+//!   of the current cycle was derived from the elapsed time.
+//! - `2`: adds the `version`, `cycle_index`, `cycle_offset` and
+//!   `cycle_adjustment` fields.
+
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::timer::{Timer, TimerConfig, TimerCycle, TimerLoop, TimerState};
+
+/// The schema version of the timers serialized by this build.
+pub const TIMER_SCHEMA_VERSION: u32 = 2;
+
+/// The schema version of timers serialized without version.
+const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+/// Error emitted when deserializing a timer written with an unknown
+/// schema, typically by a newer build.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SchemaError(pub u32);
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (version, current) = (self.0, TIMER_SCHEMA_VERSION);
+        write!(
+            f,
+            "Unsupported timer schema version {version} (max {current})"
+        )
+    }
+}
+
+impl core::error::Error for SchemaError {}
+
+/// The serialized form of a [`Timer`], covering every known schema.
+#[derive(Deserialize, Serialize)]
+pub(crate) struct TimerRecord {
+    #[serde(default = "legacy_schema_version")]
+    version: u32,
+    config: TimerConfig,
+    state: TimerState,
+    cycle: TimerCycle,
+    #[serde(default)]
+    cycle_index: Option<usize>,
+    #[serde(default)]
+    cycle_offset: Option<usize>,
+    #[serde(default)]
+    cycle_adjustment: Option<isize>,
+    cycles_count: TimerLoop,
+    started_at: Option<u64>,
+    elapsed: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idle_timeout: Option<u64>,
+}
+
+fn legacy_schema_version() -> u32 {
+    LEGACY_SCHEMA_VERSION
+}
+
+impl TimerRecord {
+    /// Upgrades a record written with the schema `1`.
+    ///
+    /// The cycle position is derived from the elapsed time, the same
+    /// way the original schema did: the current cycle is the one of
+    /// the same name in the current loop.
+    fn migrate_v1(&mut self) {
+        let loop_duration: usize = self.config.cycles.iter().map(|c| c.duration).sum();
+        let index = self
+            .config
+            .cycles
+            .iter()
+            .position(|c| c.name == self.cycle.name)
+            .unwrap_or_default();
+        let previous: usize = self.config.cycles[..index].iter().map(|c| c.duration).sum();
+        let loops = self.elapsed.checked_div(loop_duration).unwrap_or_default();
+
+        self.cycle_index = Some(index);
+        self.cycle_offset = Some(loops * loop_duration + previous);
+        self.cycle_adjustment = Some(0);
+        self.version = 2;
+    }
+}
+
+impl TryFrom<TimerRecord> for Timer {
+    type Error = SchemaError;
+
+    fn try_from(mut record: TimerRecord) -> Result<Self, Self::Error> {
+        if record.version == LEGACY_SCHEMA_VERSION {
+            record.migrate_v1();
+        }
+
+        if record.version != TIMER_SCHEMA_VERSION {
+            return Err(SchemaError(record.version));
+        }
+
+        Ok(Timer {
+            config: record.config,
+            state: record.state,
+            cycle: record.cycle,
+            cycle_index: record.cycle_index.unwrap_or_default(),
+            cycle_offset: record.cycle_offset.unwrap_or_default(),
+            cycle_adjustment: record.cycle_adjustment.unwrap_or_default(),
+            cycles_count: record.cycles_count,
+            started_at: record.started_at,
+            elapsed: record.elapsed,
+            idle_timeout: record.idle_timeout,
+            ..Default::default()
+        })
+    }
+}
+
+impl From<Timer> for TimerRecord {
+    fn from(timer: Timer) -> Self {
+        Self {
+            version: TIMER_SCHEMA_VERSION,
+            config: timer.config,
+            state: timer.state,
+            cycle: timer.cycle,
+            cycle_index: Some(timer.cycle_index),
+            cycle_offset: Some(timer.cycle_offset),
+            cycle_adjustment: Some(timer.cycle_adjustment),
+            cycles_count: timer.cycles_count,
+            started_at: timer.started_at,
+            elapsed: timer.elapsed,
+            idle_timeout: timer.idle_timeout,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timer::TimerCycles;
+
+    fn config() -> TimerConfig {
+        TimerConfig {
+            cycles: TimerCycles::from([
+                TimerCycle::new("Focus", 1500),
+                TimerCycle::new("Break", 300),
+            ]),
+            cycles_count: TimerLoop::Infinite,
+        }
+    }
+
+    #[test]
+    fn timer_serialized_with_schema_version() {
+        let mut timer = Timer::new(config());
+        timer.start(10).into_iter().for_each(drop);
+
+        let json = serde_json::to_value(&timer).unwrap();
+        assert_eq!(json["version"], TIMER_SCHEMA_VERSION);
+
+        let loaded: Timer = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.state, timer.state);
+        assert_eq!(loaded.cycle, timer.cycle);
+        assert_eq!(loaded.started_at, timer.started_at);
+    }
+
+    #[test]
+    fn legacy_timer_migrated() {
+        let json = r#"{
+            "config": {"cycles": [{"name": "Focus", "duration": 1500}, {"name": "Break", "duration": 300}], "cycles_count": "Infinite"},
+            "state": "Paused",
+            "cycle": {"name": "Break", "duration": 100},
+            "cycles_count": "Infinite",
+            "started_at": null,
+            "elapsed": 5300
+        }"#;
+
+        let timer: Timer = serde_json::from_str(json).unwrap();
+        assert_eq!(timer.config, config());
+        assert_eq!(timer.cycle_index, 1);
+        assert_eq!(timer.cycle_offset, 3600 + 1500);
+        assert_eq!(timer.cycle_adjustment, 0);
+        assert_eq!(timer.elapsed, 5300);
+    }
+
+    #[test]
+    fn future_schema_rejected() {
+        let mut json = serde_json::to_value(Timer::new(config())).unwrap();
+        json["version"] = (TIMER_SCHEMA_VERSION + 1).into();
+
+        let err = serde_json::from_value::<Timer>(json).unwrap_err();
+        assert!(err.to_string().contains("Unsupported timer schema version"));
+    }
+}
@@ -0,0 +1,123 @@
+//! Socket activation of servers.
+//!
+//! Service managers like systemd can open the listeners of a daemon
+//! on its behalf, then start it on the first connection. Listeners
+//! are passed as file descriptors, starting at
+//! [`LISTEN_FDS_START`], and announced with the `LISTEN_PID` and
+//! `LISTEN_FDS` environment variables. [`listeners`] takes them back,
+//! ready to be given to [`serve`]:
+//!
+//! ```rust,ignore
+//! use io_time::server::{activation::{self, ActivatedListener}, std::{serve, StdServeOptions}};
+//!
+//! match activation::listeners()?.pop() {
+//!     Some(ActivatedListener::Tcp(listener)) => serve(listener, timer, options)?,
+//!     Some(ActivatedListener::Unix(listener)) => serve(listener, timer, options)?,
+//!     None => serve(TcpListener::bind("localhost:1234")?, timer, options)?,
+//! }
+//! ```
+//!
+//! Other service managers hand listeners out differently (launchd
+//! for example with `launch_activate_socket`): their file descriptor
+//! can be turned into an [`ActivatedListener`] with
+//! [`FromRawFd`].
+//!
+//! [`serve`]: crate::server::std::serve
+
+use std::{
+    env, io,
+    net::TcpListener,
+    os::{
+        fd::{FromRawFd, IntoRawFd, RawFd},
+        unix::net::UnixListener,
+    },
+    process,
+};
+
+use log::debug;
+
+/// The file descriptor of the first listener passed by systemd.
+pub const LISTEN_FDS_START: RawFd = 3;
+
+/// A listener opened by the service manager.
+#[derive(Debug)]
+pub enum ActivatedListener {
+    /// A TCP socket.
+    Tcp(TcpListener),
+    /// A Unix socket.
+    Unix(UnixListener),
+}
+
+impl FromRawFd for ActivatedListener {
+    /// Takes the ownership of the given listening socket, either a
+    /// Unix or a TCP one.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        // SAFETY: the ownership of the fd is given by the caller
+        let listener = unsafe { UnixListener::from_raw_fd(fd) };
+
+        // the address of other sockets is not a Unix one
+        if listener.local_addr().is_ok() {
+            return Self::Unix(listener);
+        }
+
+        let fd = listener.into_raw_fd();
+        // SAFETY: the fd was released by the Unix listener above
+        Self::Tcp(unsafe { TcpListener::from_raw_fd(fd) })
+    }
+}
+
+/// Returns the listeners passed by systemd to the current process,
+/// in order.
+///
+/// Returns no listener if the process was not socket-activated. The
+/// environment variables are left untouched, so the function should
+/// be called only once.
+pub fn listeners() -> io::Result<Vec<ActivatedListener>> {
+    let pid = env::var("LISTEN_PID").ok();
+    let fds = env::var("LISTEN_FDS").ok();
+    let count = listen_fds(pid.as_deref(), fds.as_deref(), process::id())?;
+    debug!("{count} socket-activated listeners");
+
+    let fds = LISTEN_FDS_START..LISTEN_FDS_START + count;
+    // SAFETY: systemd gives the ownership of the listeners to the
+    // process, and they are taken only once
+    let listeners = fds.map(|fd| unsafe { ActivatedListener::from_raw_fd(fd) });
+    Ok(listeners.collect())
+}
+
+/// Returns the number of listeners announced by the given
+/// `LISTEN_PID` and `LISTEN_FDS` environment variables.
+fn listen_fds(pid: Option<&str>, fds: Option<&str>, id: u32) -> io::Result<RawFd> {
+    let (Some(pid), Some(fds)) = (pid, fds) else {
+        return Ok(0);
+    };
+
+    // listeners passed to another process (for example the parent
+    // of the current one) are not ours
+    if pid.parse::<u32>().ok() != Some(id) {
+        return Ok(0);
+    }
+
+    match fds.parse() {
+        Ok(count) if count >= 0 => Ok(count),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid LISTEN_FDS {fds:?}"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::listen_fds;
+
+    #[test]
+    fn listen_fds_of_current_process_only() {
+        assert_eq!(listen_fds(None, None, 42).unwrap(), 0);
+        assert_eq!(listen_fds(Some("42"), None, 42).unwrap(), 0);
+        assert_eq!(listen_fds(Some("41"), Some("2"), 42).unwrap(), 0);
+        assert_eq!(listen_fds(Some("42"), Some("2"), 42).unwrap(), 2);
+        assert!(listen_fds(Some("42"), Some("two"), 42).is_err());
+        assert!(listen_fds(Some("42"), Some("-1"), 42).is_err());
+    }
+}
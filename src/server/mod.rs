@@ -0,0 +1,241 @@
+//! Collection of high-level timer servers.
+//!
+//! Servers own a listener, and serve the timers of a [`TimerStore`]
+//! to every accepted connection by driving the [coroutines] against
+//! them: requests, ticks and pushed events included. Use the
+//! coroutines directly if you need a finer control over the I/O.
+//!
+//! [`TimerStore`]: crate::registry::TimerStore
+//! [coroutines]: crate::coroutines
+
+#[cfg(all(feature = "std-runtime", unix))]
+pub mod activation;
+#[cfg(feature = "std-runtime")]
+pub mod std;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+#[cfg(feature = "std-runtime")]
+use ::std::sync::{Mutex, MutexGuard};
+#[cfg(feature = "std-runtime")]
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+};
+
+#[cfg(feature = "std-runtime")]
+use io_socket::io::{SocketInput, SocketOutput};
+#[cfg(feature = "std-runtime")]
+use log::trace;
+
+#[cfg(feature = "std-runtime")]
+use crate::{
+    broadcast::TimerEventBroadcast,
+    coroutines::{
+        push::{TimerEventPush, TimerEventPushResult},
+        serve::{TimerServeError, TimerServeResult},
+        server::{
+            TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleError,
+            TimerRequestHandleResult, TimerSessionManager,
+        },
+    },
+    registry::TimerStore,
+};
+
+/// Wakes the connection waiting for pushed events.
+#[cfg(feature = "std-runtime")]
+pub(crate) trait TimerEventWaker {
+    /// Notifies the connection that events are waiting to be pushed.
+    fn wake(&self);
+}
+
+/// State of a connection kept by its thread or task, driven by
+/// [`Shared::resume`].
+#[cfg(feature = "std-runtime")]
+#[derive(Debug)]
+pub(crate) struct Connection {
+    pub(crate) id: usize,
+    push: Option<TimerEventPush>,
+    idle: bool,
+}
+
+/// Timers shared between the tasks of a server, along with the
+/// connections serving them and the events waiting to be pushed to
+/// them.
+#[cfg(feature = "std-runtime")]
+pub(crate) struct Shared<S, W> {
+    pub(crate) timers: S,
+    connections: TimerSessionManager<usize>,
+    broadcast: TimerEventBroadcast<usize>,
+    published: BTreeSet<String>,
+    wakers: BTreeMap<usize, W>,
+    next_id: usize,
+}
+
+#[cfg(feature = "std-runtime")]
+impl<S: TimerStore, W: TimerEventWaker> Shared<S, W> {
+    /// Wraps the given timers, so that only the events emitted from
+    /// now on are published by the given broadcast.
+    pub(crate) fn new(timers: S, broadcast: TimerEventBroadcast<usize>) -> Self {
+        let mut shared = Self {
+            timers,
+            connections: TimerSessionManager::new(),
+            broadcast,
+            published: BTreeSet::new(),
+            wakers: BTreeMap::new(),
+            next_id: 0,
+        };
+
+        // no connection is subscribed yet
+        shared.publish();
+        shared
+    }
+
+    /// Registers a new connection served by the given handle, and
+    /// returns its state.
+    pub(crate) fn insert(
+        &mut self,
+        handle: impl FnOnce(usize) -> TimerRequestHandle,
+        waker: W,
+    ) -> Connection {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.connections.insert(id, handle(id));
+        self.wakers.insert(id, waker);
+
+        Connection {
+            id,
+            push: None,
+            idle: true,
+        }
+    }
+
+    /// Unregisters the connection with the given id, along with its
+    /// pending events.
+    pub(crate) fn remove(&mut self, id: usize) {
+        self.connections.remove(&id);
+        self.broadcast.remove(&id);
+        self.wakers.remove(&id);
+    }
+
+    /// Returns the write timeout of the connection with the given id,
+    /// see [`TimerRequestHandle::write_timeout`].
+    pub(crate) fn write_timeout(&self, id: usize) -> Option<u64> {
+        self.connections.get(&id)?.write_timeout()
+    }
+
+    /// Publishes the events recorded in the history of every timer
+    /// since the previous publication, and wakes the connections
+    /// they are queued for.
+    pub(crate) fn publish(&mut self) {
+        let names: BTreeSet<String> = self.timers.list().into_keys().collect();
+
+        for name in self.published.difference(&names) {
+            trace!("forget deleted timer {name}");
+            self.broadcast.forget(name);
+        }
+
+        for name in &names {
+            let Some(timer) = self.timers.get_mut(Some(name)) else {
+                continue;
+            };
+
+            self.broadcast.publish(&self.connections, name, timer);
+        }
+
+        self.published = names;
+
+        for id in self.broadcast.ready() {
+            if let Some(waker) = self.wakers.get(id) {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Advances the given connection by one step, like
+    /// [`TimerServe::resume`] does with the events of the broadcast.
+    ///
+    /// [`TimerServe::resume`]: crate::coroutines::serve::TimerServe::resume
+    pub(crate) fn resume(
+        &mut self,
+        connection: &mut Connection,
+        mut arg: Option<TimerRequestHandleArg>,
+    ) -> TimerServeResult {
+        let id = connection.id;
+
+        loop {
+            if let Some(push) = &mut connection.push {
+                let socket_arg: Option<SocketOutput> = match arg.take() {
+                    None => None,
+                    Some(TimerRequestHandleArg::Socket(output)) => Some(output),
+                    Some(TimerRequestHandleArg::TimedOut) => {
+                        let secs = self.write_timeout(id).unwrap_or_default();
+                        let err = TimerRequestHandleError::WriteTimeout(secs);
+                        let err = TimerServeError::Handle(err);
+                        return TimerServeResult::Err { err };
+                    }
+                    Some(a) => {
+                        let err = TimerServeError::InvalidArg(a);
+                        return TimerServeResult::Err { err };
+                    }
+                };
+
+                match push.resume(socket_arg) {
+                    TimerEventPushResult::Ok => {
+                        connection.push = None;
+                        continue;
+                    }
+                    TimerEventPushResult::Io { input } => {
+                        return TimerServeResult::Io { input };
+                    }
+                    TimerEventPushResult::Err { err } => {
+                        let err = TimerServeError::Push(err);
+                        return TimerServeResult::Err { err };
+                    }
+                }
+            }
+
+            if connection.idle && arg.is_none() {
+                if let Some(push) = self.broadcast.take(&self.connections, &id) {
+                    connection.push = Some(push);
+                    continue;
+                }
+            }
+
+            let Some(result) = self.connections.resume(&id, &mut self.timers, arg.take()) else {
+                return TimerServeResult::Disconnected;
+            };
+
+            return match result {
+                TimerRequestHandleResult::Ok { events } => {
+                    connection.idle = true;
+                    if !events.is_empty() {
+                        self.publish();
+                    }
+                    TimerServeResult::Ok { events }
+                }
+                TimerRequestHandleResult::Io { input } => {
+                    connection.idle = matches!(input, SocketInput::Read { .. });
+                    TimerServeResult::Io { input }
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    connection.idle = false;
+                    TimerServeResult::TimeIo { input }
+                }
+                TimerRequestHandleResult::Disconnected => TimerServeResult::Disconnected,
+                TimerRequestHandleResult::Err { err } => {
+                    let err = TimerServeError::Handle(err);
+                    TimerServeResult::Err { err }
+                }
+            };
+        }
+    }
+}
+
+#[cfg(feature = "std-runtime")]
+pub(crate) fn lock<S, W>(shared: &Mutex<Shared<S, W>>) -> MutexGuard<'_, Shared<S, W>> {
+    // timers are never left half-updated by a panicking task
+    shared
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
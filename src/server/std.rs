@@ -0,0 +1,405 @@
+//! Standard blocking timer server.
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::{
+    fmt,
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use log::debug;
+
+use crate::{
+    broadcast::TimerEventBroadcast,
+    coroutines::{
+        serve::TimerServeResult,
+        server::{TimerRequestHandle, TimerRequestHandleArg},
+        tick::{DEFAULT_TICK_INTERVAL, TimerTick, TimerTickResult},
+    },
+    history::TimerHistory,
+    notify::{NotifiedStore, TimerNotificationSink},
+    registry::TimerStore,
+    runtimes::std::handle as time_handle,
+    server::{Connection, Shared, TimerEventWaker, lock},
+};
+
+/// The default maximum duration a connection waits for a request
+/// before pushing the events published in the meantime.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Listener accepting the connections of [`serve`].
+pub trait StdListener {
+    /// The stream of accepted connections.
+    type Stream: Read + Write + Send + 'static;
+
+    /// Waits for the next connection.
+    fn accept(&self) -> io::Result<Self::Stream>;
+
+    /// Sets the read timeout of the given accepted stream.
+    fn set_read_timeout(stream: &Self::Stream, timeout: Option<Duration>) -> io::Result<()>;
+
+    /// Sets the write timeout of the given accepted stream.
+    fn set_write_timeout(stream: &Self::Stream, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl StdListener for TcpListener {
+    type Stream = TcpStream;
+
+    fn accept(&self) -> io::Result<TcpStream> {
+        TcpListener::accept(self).map(|(stream, _)| stream)
+    }
+
+    fn set_read_timeout(stream: &TcpStream, timeout: Option<Duration>) -> io::Result<()> {
+        stream.set_read_timeout(timeout)
+    }
+
+    fn set_write_timeout(stream: &TcpStream, timeout: Option<Duration>) -> io::Result<()> {
+        stream.set_write_timeout(timeout)
+    }
+}
+
+#[cfg(unix)]
+impl StdListener for UnixListener {
+    type Stream = UnixStream;
+
+    fn accept(&self) -> io::Result<UnixStream> {
+        UnixListener::accept(self).map(|(stream, _)| stream)
+    }
+
+    fn set_read_timeout(stream: &UnixStream, timeout: Option<Duration>) -> io::Result<()> {
+        stream.set_read_timeout(timeout)
+    }
+
+    fn set_write_timeout(stream: &UnixStream, timeout: Option<Duration>) -> io::Result<()> {
+        stream.set_write_timeout(timeout)
+    }
+}
+
+type NewHandle = Box<dyn Fn(usize) -> TimerRequestHandle + Send + Sync>;
+
+/// Options of [`serve`].
+pub struct StdServeOptions {
+    tick_interval: u64,
+    poll_interval: Duration,
+    handle: NewHandle,
+    notifications: Option<Box<dyn TimerNotificationSink + Sync>>,
+    broadcast: TimerEventBroadcast<usize>,
+}
+
+impl fmt::Debug for StdServeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StdServeOptions")
+            .field("tick_interval", &self.tick_interval)
+            .field("poll_interval", &self.poll_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for StdServeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StdServeOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self {
+            tick_interval: DEFAULT_TICK_INTERVAL,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            handle: Box::new(|_| TimerRequestHandle::new()),
+            notifications: None,
+            broadcast: TimerEventBroadcast::new(),
+        }
+    }
+
+    /// Ticks the timers every given number of seconds at most, see
+    /// [`TimerTick::with_interval`].
+    pub fn with_tick_interval(mut self, secs: u64) -> Self {
+        self.tick_interval = secs;
+        self
+    }
+
+    /// Pushes the published events to idle connections every given
+    /// duration at most, instead of [`DEFAULT_POLL_INTERVAL`].
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Creates the handle of each connection with the given function,
+    /// called with the id of the connection (for example to register
+    /// middlewares or an audit sink).
+    pub fn with_handle(
+        mut self,
+        handle: impl Fn(usize) -> TimerRequestHandle + Send + Sync + 'static,
+    ) -> Self {
+        self.handle = Box::new(handle);
+        self
+    }
+
+    /// Notifies the given sink each time the state of a timer
+    /// changes, see [`TimerNotification::StateChanged`].
+    ///
+    /// [`TimerNotification::StateChanged`]: crate::notify::TimerNotification::StateChanged
+    pub fn with_notifications(mut self, sink: impl TimerNotificationSink + Sync + 'static) -> Self {
+        self.notifications = Some(Box::new(sink));
+        self
+    }
+
+    /// Pushes the published events to subscribed connections with
+    /// the given broadcast, for example to tune its capacity or to
+    /// push deltas (see [`TimerEventBroadcast::with_deltas`]).
+    ///
+    /// Connections are identified by their id in the broadcast, the
+    /// one given to the function of [`with_handle`].
+    ///
+    /// [`with_handle`]: StdServeOptions::with_handle
+    pub fn with_broadcast(mut self, broadcast: TimerEventBroadcast<usize>) -> Self {
+        self.broadcast = broadcast;
+        self
+    }
+}
+
+/// Serves the given timers to the connections of the given listener,
+/// until it fails to accept one.
+///
+/// Each connection is served in its own thread, like a [`TimerServe`]
+/// coroutine does, while another thread ticks the timers with a
+/// [`TimerTick`]. Events emitted by requests and ticks are pushed to
+/// every subscribed connection by a [`TimerEventBroadcast`] (see
+/// [`StdServeOptions::with_broadcast`]). A minimal daemon looks like:
+///
+/// ```rust,ignore
+/// use std::net::TcpListener;
+///
+/// use io_time::{server::std::{serve, StdServeOptions}, timer::Timer};
+///
+/// let listener = TcpListener::bind("localhost:1234")?;
+/// serve(listener, Timer::new(config), StdServeOptions::new())?;
+/// ```
+///
+/// Use a [`StdServer`] to serve several listeners at once.
+///
+/// [`TimerServe`]: crate::coroutines::serve::TimerServe
+pub fn serve<L, S>(listener: L, timers: S, options: StdServeOptions) -> io::Result<()>
+where
+    L: StdListener,
+    S: TimerStore + Send + 'static,
+{
+    StdServer::new(timers, options).accept(listener)
+}
+
+/// Standard blocking timer server, serving the same timers to the
+/// connections of several listeners.
+///
+/// For example a Unix socket for local clients, along with a TCP
+/// socket for remote dashboards:
+///
+/// ```rust,ignore
+/// let server = StdServer::new(timer, StdServeOptions::new());
+/// server.spawn(UnixListener::bind(path)?);
+/// server.accept(TcpListener::bind("0.0.0.0:1234")?)?;
+/// ```
+///
+/// Connections of every listener share the timers, and receive the
+/// events emitted by any of them.
+pub struct StdServer<S> {
+    shared: Arc<Mutex<Shared<NotifiedStore<S>, ()>>>,
+    handle: Arc<NewHandle>,
+    poll_interval: Duration,
+}
+
+impl<S> Clone for StdServer<S> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            handle: self.handle.clone(),
+            poll_interval: self.poll_interval,
+        }
+    }
+}
+
+impl<S> fmt::Debug for StdServer<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StdServer")
+            .field("poll_interval", &self.poll_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: TimerStore + Send + 'static> StdServer<S> {
+    /// Creates a new server, and starts ticking the given timers in
+    /// a dedicated thread.
+    pub fn new(timers: S, options: StdServeOptions) -> Self {
+        let StdServeOptions {
+            tick_interval,
+            poll_interval,
+            handle,
+            notifications,
+            broadcast,
+        } = options;
+
+        let sink = notifications.map(|sink| sink as Box<_>);
+        let timers = NotifiedStore::new(timers).with_boxed_sink(sink);
+        let shared = Arc::new(Mutex::new(Shared::new(timers, broadcast)));
+
+        let tick = TimerTick::new().with_interval(tick_interval);
+        let ticker = shared.clone();
+        thread::spawn(move || {
+            if let Err(err) = run_tick(&ticker, tick) {
+                debug!("stop ticking timers: {err}");
+            }
+        });
+
+        Self {
+            shared,
+            handle: Arc::new(handle),
+            poll_interval,
+        }
+    }
+
+    /// Accepts the connections of the given listener in the current
+    /// thread, until it fails to accept one.
+    pub fn accept<L: StdListener>(&self, listener: L) -> io::Result<()> {
+        loop {
+            let stream = listener.accept()?;
+            L::set_read_timeout(&stream, Some(self.poll_interval))?;
+
+            let (connection, write_timeout) = {
+                let mut shared = lock(&self.shared);
+                let connection = shared.insert(&*self.handle, ());
+                let write_timeout = shared.write_timeout(connection.id);
+                (connection, write_timeout)
+            };
+
+            let id = connection.id;
+            L::set_write_timeout(&stream, write_timeout.map(Duration::from_secs))?;
+
+            let shared = self.shared.clone();
+            thread::spawn(move || {
+                match run_connection(&shared, connection, stream) {
+                    Ok(()) => debug!("connection {id} closed"),
+                    Err(err) => debug!("connection {id} closed: {err}"),
+                }
+                lock(&shared).remove(id);
+            });
+        }
+    }
+
+    /// Accepts the connections of the given listener in a dedicated
+    /// thread, see [`accept`].
+    ///
+    /// [`accept`]: StdServer::accept
+    pub fn spawn<L>(&self, listener: L) -> JoinHandle<io::Result<()>>
+    where
+        L: StdListener + Send + 'static,
+    {
+        let server = self.clone();
+        thread::spawn(move || server.accept(listener))
+    }
+
+    /// Gives the history of the timer with the given name to the
+    /// given function, for example to tune its retention or to clear
+    /// it while serving:
+    ///
+    /// ```rust,ignore
+    /// server.with_history(None, |history| history.set_max_age(Some(3600)));
+    /// server.with_history(Some("work"), TimerHistory::clear);
+    /// ```
+    ///
+    /// Returns `None` if the timer does not exist.
+    pub fn with_history<T>(
+        &self,
+        timer: Option<&str>,
+        f: impl FnOnce(&mut TimerHistory) -> T,
+    ) -> Option<T> {
+        let mut shared = lock(&self.shared);
+        let timer = shared.timers.get_mut(timer)?;
+        Some(f(&mut timer.history))
+    }
+}
+
+fn run_tick<S: TimerStore, W: TimerEventWaker>(
+    shared: &Mutex<Shared<S, W>>,
+    mut tick: TimerTick,
+) -> io::Result<()> {
+    let mut arg = None;
+
+    loop {
+        let result = {
+            let mut shared = lock(shared);
+            let result = tick.resume(&mut shared.timers, arg.take());
+            if let TimerTickResult::Ok { .. } = result {
+                shared.publish();
+            }
+            result
+        };
+
+        match result {
+            TimerTickResult::Ok { .. } => continue,
+            TimerTickResult::Io { input } => arg = Some(time_handle(input)?),
+            TimerTickResult::Err { err } => return Err(io::Error::other(err)),
+        }
+    }
+}
+
+fn run_connection<S: TimerStore>(
+    shared: &Mutex<Shared<S, ()>>,
+    mut connection: Connection,
+    mut stream: impl Read + Write,
+) -> io::Result<()> {
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        let result = lock(shared).resume(&mut connection, arg.take());
+
+        match result {
+            TimerServeResult::Ok { .. } => continue,
+            TimerServeResult::Io { input } => {
+                arg = socket_io(&mut stream, input)?;
+            }
+            TimerServeResult::TimeIo { input } => {
+                arg = Some(time_handle(input)?.into());
+            }
+            TimerServeResult::Disconnected => return Ok(()),
+            TimerServeResult::Err { err } => return Err(io::Error::other(err)),
+        }
+    }
+}
+
+impl TimerEventWaker for () {
+    fn wake(&self) {
+        // idle connections look for pending events every poll
+        // interval, once their read times out
+    }
+}
+
+/// Performs the given socket I/O, returning `None` when no request
+/// was received before the read timeout.
+fn socket_io(
+    stream: &mut (impl Read + Write),
+    input: SocketInput,
+) -> io::Result<Option<TimerRequestHandleArg>> {
+    match input {
+        SocketInput::Read { mut buf } => match stream.read(&mut buf) {
+            Ok(n) => Ok(Some(SocketOutput::Read { buf, n }.into())),
+            Err(err) if is_timeout(&err) => Ok(None),
+            Err(err) => Err(err),
+        },
+        SocketInput::Write { buf } => match stream.write(&buf) {
+            Ok(n) => Ok(Some(SocketOutput::Wrote { buf, n }.into())),
+            Err(err) if is_timeout(&err) => Ok(Some(TimerRequestHandleArg::TimedOut)),
+            Err(err) => Err(err),
+        },
+    }
+}
+
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
@@ -0,0 +1,376 @@
+//! Asynchronous timer server backed by [`tokio`].
+
+use std::{
+    fmt,
+    future::Future,
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use log::debug;
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Notify,
+    task::{self, JoinHandle},
+    time,
+};
+
+use crate::{
+    broadcast::TimerEventBroadcast,
+    coroutines::{
+        serve::TimerServeResult,
+        server::{TimerRequestHandle, TimerRequestHandleArg},
+        tick::{DEFAULT_TICK_INTERVAL, TimerTick, TimerTickResult},
+    },
+    history::TimerHistory,
+    notify::{NotifiedStore, TimerNotificationSink},
+    registry::TimerStore,
+    runtimes::tokio::handle as time_handle,
+    server::{Connection, Shared, TimerEventWaker, lock},
+};
+
+type NewHandle = Box<dyn Fn(usize) -> TimerRequestHandle + Send + Sync>;
+
+/// Listener accepting the connections of [`serve`].
+pub trait TokioListener {
+    /// The stream of accepted connections.
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    /// Waits for the next connection.
+    fn accept(&self) -> impl Future<Output = io::Result<Self::Stream>> + Send;
+}
+
+impl TokioListener for TcpListener {
+    type Stream = TcpStream;
+
+    async fn accept(&self) -> io::Result<TcpStream> {
+        TcpListener::accept(self).await.map(|(stream, _)| stream)
+    }
+}
+
+#[cfg(unix)]
+impl TokioListener for UnixListener {
+    type Stream = UnixStream;
+
+    async fn accept(&self) -> io::Result<UnixStream> {
+        UnixListener::accept(self).await.map(|(stream, _)| stream)
+    }
+}
+
+/// Options of [`serve`].
+pub struct TokioServeOptions {
+    tick_interval: u64,
+    handle: NewHandle,
+    notifications: Option<Box<dyn TimerNotificationSink + Sync>>,
+    broadcast: TimerEventBroadcast<usize>,
+}
+
+impl fmt::Debug for TokioServeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokioServeOptions")
+            .field("tick_interval", &self.tick_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for TokioServeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokioServeOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self {
+            tick_interval: DEFAULT_TICK_INTERVAL,
+            handle: Box::new(|_| TimerRequestHandle::new()),
+            notifications: None,
+            broadcast: TimerEventBroadcast::new(),
+        }
+    }
+
+    /// Ticks the timers every given number of seconds at most, see
+    /// [`TimerTick::with_interval`].
+    pub fn with_tick_interval(mut self, secs: u64) -> Self {
+        self.tick_interval = secs;
+        self
+    }
+
+    /// Creates the handle of each connection with the given function,
+    /// called with the id of the connection (for example to register
+    /// middlewares or an audit sink).
+    pub fn with_handle(
+        mut self,
+        handle: impl Fn(usize) -> TimerRequestHandle + Send + Sync + 'static,
+    ) -> Self {
+        self.handle = Box::new(handle);
+        self
+    }
+
+    /// Notifies the given sink each time the state of a timer
+    /// changes, see [`TimerNotification::StateChanged`].
+    ///
+    /// [`TimerNotification::StateChanged`]: crate::notify::TimerNotification::StateChanged
+    pub fn with_notifications(mut self, sink: impl TimerNotificationSink + Sync + 'static) -> Self {
+        self.notifications = Some(Box::new(sink));
+        self
+    }
+
+    /// Pushes the published events to subscribed connections with
+    /// the given broadcast, see [`StdServeOptions::with_broadcast`].
+    ///
+    /// [`StdServeOptions::with_broadcast`]: crate::server::std::StdServeOptions::with_broadcast
+    pub fn with_broadcast(mut self, broadcast: TimerEventBroadcast<usize>) -> Self {
+        self.broadcast = broadcast;
+        self
+    }
+}
+
+/// Serves the given timers to the connections of the given listener,
+/// until it fails to accept one.
+///
+/// The async counterpart of the std [`serve`]: each connection is
+/// served in its own task, while another task ticks the timers with a
+/// [`TimerTick`]. Events emitted by requests and ticks are pushed to
+/// every subscribed connection by a [`TimerEventBroadcast`], as soon
+/// as they are published:
+///
+/// ```rust,ignore
+/// use io_time::{server::tokio::{serve, TokioServeOptions}, timer::Timer};
+/// use tokio::net::TcpListener;
+///
+/// let listener = TcpListener::bind("localhost:1234").await?;
+/// serve(listener, Timer::new(config), TokioServeOptions::new()).await?;
+/// ```
+///
+/// Use a [`TokioServer`] to serve several listeners at once.
+///
+/// [`serve`]: crate::server::std::serve
+pub async fn serve<L, S>(listener: L, timers: S, options: TokioServeOptions) -> io::Result<()>
+where
+    L: TokioListener,
+    S: TimerStore + Send + 'static,
+{
+    TokioServer::new(timers, options).accept(listener).await
+}
+
+/// Asynchronous timer server, serving the same timers to the
+/// connections of several listeners.
+///
+/// The async counterpart of the [`StdServer`]:
+///
+/// ```rust,ignore
+/// let server = TokioServer::new(timer, TokioServeOptions::new());
+/// server.spawn(UnixListener::bind(path)?);
+/// server.accept(TcpListener::bind("0.0.0.0:1234").await?).await?;
+/// ```
+///
+/// Timers are ticked until every clone of the server is dropped,
+/// spawned listeners included.
+///
+/// [`StdServer`]: crate::server::std::StdServer
+pub struct TokioServer<S> {
+    shared: Arc<Mutex<Shared<NotifiedStore<S>, Arc<Notify>>>>,
+    handle: Arc<NewHandle>,
+    ticking: Arc<Ticking>,
+}
+
+impl<S> Clone for TokioServer<S> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            handle: self.handle.clone(),
+            ticking: self.ticking.clone(),
+        }
+    }
+}
+
+impl<S> fmt::Debug for TokioServer<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokioServer").finish_non_exhaustive()
+    }
+}
+
+impl<S: TimerStore + Send + 'static> TokioServer<S> {
+    /// Creates a new server, and starts ticking the given timers in
+    /// a dedicated task.
+    ///
+    /// Needs to be called from within a tokio runtime.
+    pub fn new(timers: S, options: TokioServeOptions) -> Self {
+        let TokioServeOptions {
+            tick_interval,
+            handle,
+            notifications,
+            broadcast,
+        } = options;
+
+        let sink = notifications.map(|sink| sink as Box<_>);
+        let timers = NotifiedStore::new(timers).with_boxed_sink(sink);
+        let shared = Arc::new(Mutex::new(Shared::new(timers, broadcast)));
+
+        let tick = TimerTick::new().with_interval(tick_interval);
+        let ticker = shared.clone();
+        let ticking = task::spawn(async move {
+            if let Err(err) = run_tick(&ticker, tick).await {
+                debug!("stop ticking timers: {err}");
+            }
+        });
+
+        Self {
+            shared,
+            handle: Arc::new(handle),
+            ticking: Arc::new(Ticking(ticking)),
+        }
+    }
+
+    /// Accepts the connections of the given listener, until it fails
+    /// to accept one.
+    pub async fn accept<L: TokioListener>(&self, listener: L) -> io::Result<()> {
+        loop {
+            let stream = listener.accept().await?;
+
+            let notify = Arc::new(Notify::new());
+            let connection = lock(&self.shared).insert(&*self.handle, notify.clone());
+            let id = connection.id;
+
+            let shared = self.shared.clone();
+            task::spawn(async move {
+                match run_connection(&shared, connection, &notify, stream).await {
+                    Ok(()) => debug!("connection {id} closed"),
+                    Err(err) => debug!("connection {id} closed: {err}"),
+                }
+                lock(&shared).remove(id);
+            });
+        }
+    }
+
+    /// Accepts the connections of the given listener in a dedicated
+    /// task, see [`accept`].
+    ///
+    /// [`accept`]: TokioServer::accept
+    pub fn spawn<L>(&self, listener: L) -> JoinHandle<io::Result<()>>
+    where
+        L: TokioListener + Send + 'static,
+    {
+        let server = self.clone();
+        task::spawn(async move { server.accept(listener).await })
+    }
+
+    /// Gives the history of the timer with the given name to the
+    /// given function, for example to tune its retention or to clear
+    /// it while serving:
+    ///
+    /// ```rust,ignore
+    /// server.with_history(None, |history| history.set_max_age(Some(3600)));
+    /// server.with_history(Some("work"), TimerHistory::clear);
+    /// ```
+    ///
+    /// Returns `None` if the timer does not exist.
+    pub fn with_history<T>(
+        &self,
+        timer: Option<&str>,
+        f: impl FnOnce(&mut TimerHistory) -> T,
+    ) -> Option<T> {
+        let mut shared = lock(&self.shared);
+        let timer = shared.timers.get_mut(timer)?;
+        Some(f(&mut timer.history))
+    }
+}
+
+/// The tick task of a server, aborted once dropped.
+struct Ticking(JoinHandle<()>);
+
+impl Drop for Ticking {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+async fn run_tick<S: TimerStore, W: TimerEventWaker>(
+    shared: &Mutex<Shared<S, W>>,
+    mut tick: TimerTick,
+) -> io::Result<()> {
+    let mut arg = None;
+
+    loop {
+        let result = {
+            let mut shared = lock(shared);
+            let result = tick.resume(&mut shared.timers, arg.take());
+            if let TimerTickResult::Ok { .. } = result {
+                shared.publish();
+            }
+            result
+        };
+
+        match result {
+            TimerTickResult::Ok { .. } => continue,
+            TimerTickResult::Io { input } => arg = Some(time_handle(input).await?),
+            TimerTickResult::Err { err } => return Err(io::Error::other(err)),
+        }
+    }
+}
+
+async fn run_connection<S: TimerStore>(
+    shared: &Mutex<Shared<S, Arc<Notify>>>,
+    mut connection: Connection,
+    notify: &Notify,
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+) -> io::Result<()> {
+    let write_timeout = lock(shared).write_timeout(connection.id);
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        let result = lock(shared).resume(&mut connection, arg.take());
+
+        match result {
+            TimerServeResult::Ok { .. } => continue,
+            // reads are abandoned as soon as events are published,
+            // so that they are pushed without waiting for a request
+            TimerServeResult::Io {
+                input: SocketInput::Read { mut buf },
+            } => {
+                tokio::select! {
+                    read = stream.read(&mut buf) => {
+                        let n = read?;
+                        arg = Some(SocketOutput::Read { buf, n }.into());
+                    }
+                    () = notify.notified() => (),
+                }
+            }
+            TimerServeResult::Io {
+                input: SocketInput::Write { buf },
+            } => {
+                let write = stream.write(&buf);
+                let n = match write_timeout {
+                    None => write.await?,
+                    Some(secs) => match time::timeout(Duration::from_secs(secs), write).await {
+                        Ok(n) => n?,
+                        Err(_) => {
+                            arg = Some(TimerRequestHandleArg::TimedOut);
+                            continue;
+                        }
+                    },
+                };
+                arg = Some(SocketOutput::Wrote { buf, n }.into());
+            }
+            TimerServeResult::TimeIo { input } => {
+                arg = Some(time_handle(input).await?.into());
+            }
+            TimerServeResult::Disconnected => return Ok(()),
+            TimerServeResult::Err { err } => return Err(io::Error::other(err)),
+        }
+    }
+}
+
+impl TimerEventWaker for Arc<Notify> {
+    fn wake(&self) {
+        // the permit is kept until the connection waits for it
+        self.notify_one();
+    }
+}
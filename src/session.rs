@@ -0,0 +1,419 @@
+//! Per-connection session negotiated during the handshake.
+//!
+//! A client willing to use non-default connection settings sends a
+//! [`ClientHello`] right after connecting, advertising what it
+//! supports. The server answers with a [`ServerHello`] containing
+//! its picks. Both sides then build the same [`Session`], to be
+//! given to the request coroutines of this connection.
+//!
+//! The handshake is optional: a connection without handshake uses
+//! the default session.
+//!
+//! The handshake is also the place where both sides agree on a
+//! [`Keepalive`], so that dead connections can be detected and
+//! closed, and on the [`Permission`] of the connection.
+//!
+//! Message-oriented transports (like UDP) have no connection to
+//! negotiate over: both sides use [`Session::datagram`] instead.
+
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "signing")]
+use crate::signing::SigningKey;
+use crate::{
+    auth::TimerAuthToken, checksum::Checksum, codec::Codec, compression::Compression,
+    frame::Framing, timer::PROTOCOL_VERSION,
+};
+
+/// The settings negotiated for a connection.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Session {
+    /// The codec used to encode requests and responses.
+    pub codec: Codec,
+    /// The compression applied to response frames.
+    pub compression: Compression,
+    /// The checksum appended to request and response frames.
+    pub checksum: Checksum,
+    /// The heartbeat settings of the connection, if any.
+    pub keepalive: Option<Keepalive>,
+    /// Whether frames are armored in printable ASCII, for text-only
+    /// transports.
+    pub armored: bool,
+    /// What the client is allowed to do on the connection.
+    pub permission: Permission,
+    /// Whether the transport preserves message boundaries, in which
+    /// case each request and response is sent as a single datagram.
+    pub datagram: bool,
+    /// The key signing requests, if any.
+    ///
+    /// Unlike the other settings, the key is never negotiated: it
+    /// needs to be set on both sides after the handshake. See the
+    /// [`signing`] module.
+    ///
+    /// [`signing`]: crate::signing
+    #[cfg(feature = "signing")]
+    pub signing_key: Option<SigningKey>,
+}
+
+impl Session {
+    /// Creates the session used over message-oriented transports,
+    /// where a request fits in one datagram and its response comes
+    /// back in one datagram.
+    pub fn datagram() -> Self {
+        Self {
+            datagram: true,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the framing used by both sides of the connection.
+    ///
+    /// Datagrams are already delimited by the transport. Armored
+    /// payloads are line-delimited whatever they contain. Otherwise,
+    /// binary, compressed and checksummed payloads may contain any
+    /// byte, therefore they cannot be line-delimited.
+    pub fn framing(&self) -> Framing {
+        if self.datagram {
+            Framing::Datagram
+        } else if self.armored {
+            Framing::Armored
+        } else if self.codec.is_binary()
+            || self.compression != Compression::None
+            || self.checksum != Checksum::None
+        {
+            Framing::LengthPrefixed
+        } else {
+            Framing::Line
+        }
+    }
+}
+
+impl From<ServerHello> for Session {
+    fn from(hello: ServerHello) -> Self {
+        Self {
+            codec: hello.codec,
+            compression: hello.compression,
+            checksum: hello.checksum,
+            keepalive: hello.keepalive,
+            armored: hello.armored,
+            permission: hello.permission,
+            datagram: false,
+            #[cfg(feature = "signing")]
+            signing_key: None,
+        }
+    }
+}
+
+/// What a client is allowed to do on a connection.
+///
+/// Permissions are ordered from the most restricted to the least
+/// restricted one.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
+pub enum Permission {
+    /// The client can only observe timers, using requests that do not
+    /// modify them (like [`TimerRequest::Get`],
+    /// [`TimerRequest::Status`] or [`TimerRequest::Subscribe`]).
+    ///
+    /// [`TimerRequest::Get`]: crate::timer::TimerRequest::Get
+    /// [`TimerRequest::Status`]: crate::timer::TimerRequest::Status
+    /// [`TimerRequest::Subscribe`]: crate::timer::TimerRequest::Subscribe
+    Observer,
+    /// The client can also control timers.
+    #[default]
+    Controller,
+}
+
+/// The heartbeat settings of a connection, in seconds.
+///
+/// The client sends a heartbeat (like [`TimerRequest::Ping`]) every
+/// `interval` seconds when it has nothing else to send. The server
+/// closes the connection when it receives nothing for `timeout`
+/// seconds.
+///
+/// Since I/O-free coroutines cannot observe time while waiting for a
+/// read, the timeout is enforced by the runtime, typically by using
+/// it as read timeout of the socket.
+///
+/// [`TimerRequest::Ping`]: crate::timer::TimerRequest::Ping
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Keepalive {
+    /// The interval between two heartbeats sent by the client.
+    pub interval: u64,
+    /// The idle time after which the server closes the connection.
+    pub timeout: u64,
+}
+
+impl Keepalive {
+    /// Creates keepalive settings from the given idle timeout,
+    /// sending three heartbeats per timeout.
+    pub fn from_timeout(timeout: u64) -> Self {
+        Self {
+            interval: (timeout / 3).max(1),
+            timeout,
+        }
+    }
+
+    /// Agrees on the settings proposed by the client, the idle
+    /// timeout being capped by the one of the server, if any.
+    pub fn negotiate(client: Option<Self>, max_timeout: Option<u64>) -> Option<Self> {
+        match (client, max_timeout) {
+            (None, None) => None,
+            (Some(client), None) => Some(client),
+            (None, Some(timeout)) => Some(Self::from_timeout(timeout)),
+            (Some(client), Some(timeout)) => {
+                let timeout = client.timeout.min(timeout);
+                let interval = client.interval.min(timeout);
+                Some(Self { interval, timeout })
+            }
+        }
+    }
+}
+
+/// The first message sent by a client, advertising the settings it
+/// supports.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ClientHello {
+    /// The revision of the wire protocol spoken by the client, see
+    /// [`PROTOCOL_VERSION`].
+    #[serde(default = "first_protocol")]
+    pub protocol: u32,
+    /// The supported codecs, by order of preference.
+    pub codecs: Vec<Codec>,
+    /// The supported compression algorithms, by order of
+    /// preference.
+    pub compressions: Vec<Compression>,
+    /// The supported checksums, by order of preference.
+    #[serde(default)]
+    pub checksums: Vec<Checksum>,
+    /// The proposed heartbeat settings, if any.
+    #[serde(default)]
+    pub keepalive: Option<Keepalive>,
+    /// Whether frames should be armored in printable ASCII.
+    #[serde(default)]
+    pub armored: bool,
+    /// The requested permission.
+    #[serde(default)]
+    pub permission: Permission,
+    /// The token authenticating the client, if any.
+    ///
+    /// See the [`auth`] module.
+    ///
+    /// [`auth`]: crate::auth
+    #[serde(default)]
+    pub token: Option<TimerAuthToken>,
+}
+
+impl Default for ClientHello {
+    /// Advertises everything supported by this build.
+    fn default() -> Self {
+        Self {
+            protocol: PROTOCOL_VERSION,
+            codecs: Codec::supported(),
+            compressions: Compression::supported(),
+            checksums: Checksum::supported(),
+            keepalive: None,
+            armored: false,
+            permission: Permission::default(),
+            token: None,
+        }
+    }
+}
+
+/// The answer of the server to a [`ClientHello`], containing the
+/// settings picked for the connection.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ServerHello {
+    /// The revision of the wire protocol spoken by the server, see
+    /// [`PROTOCOL_VERSION`].
+    #[serde(default = "first_protocol")]
+    pub protocol: u32,
+    /// The codec picked for requests and responses.
+    pub codec: Codec,
+    /// The compression picked for response frames.
+    pub compression: Compression,
+    /// The checksum picked for request and response frames.
+    #[serde(default)]
+    pub checksum: Checksum,
+    /// The agreed heartbeat settings, if any.
+    #[serde(default)]
+    pub keepalive: Option<Keepalive>,
+    /// Whether frames are armored in printable ASCII.
+    #[serde(default)]
+    pub armored: bool,
+    /// The granted permission.
+    #[serde(default)]
+    pub permission: Permission,
+}
+
+impl Default for ServerHello {
+    fn default() -> Self {
+        Self {
+            protocol: PROTOCOL_VERSION,
+            codec: Codec::default(),
+            compression: Compression::default(),
+            checksum: Checksum::default(),
+            keepalive: None,
+            armored: false,
+            permission: Permission::default(),
+        }
+    }
+}
+
+impl ServerHello {
+    /// Picks, for each setting, the first client preference that is
+    /// also part of the given server preferences.
+    ///
+    /// Falls back to the default setting when there is no match. The
+    /// protocol revision is always the one of this build. The
+    /// keepalive settings, the armor and the permission requested by
+    /// the client are accepted as is.
+    pub fn negotiate(
+        client: &ClientHello,
+        codecs: &[Codec],
+        compressions: &[Compression],
+        checksums: &[Checksum],
+    ) -> Self {
+        let codec = client
+            .codecs
+            .iter()
+            .find(|c| c.is_supported() && codecs.contains(c))
+            .copied()
+            .unwrap_or_default();
+
+        let compression = client
+            .compressions
+            .iter()
+            .find(|c| c.is_supported() && compressions.contains(c))
+            .copied()
+            .unwrap_or_default();
+
+        let checksum = client
+            .checksums
+            .iter()
+            .find(|c| checksums.contains(c))
+            .copied()
+            .unwrap_or_default();
+
+        Self {
+            protocol: PROTOCOL_VERSION,
+            codec,
+            compression,
+            checksum,
+            keepalive: client.keepalive,
+            armored: client.armored,
+            permission: client.permission,
+        }
+    }
+}
+
+/// The protocol revision of peers which do not advertise it, as
+/// hellos only started to carry it after the first revision.
+fn first_protocol() -> u32 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn negotiate_first_common_compression() {
+        let client = ClientHello {
+            protocol: PROTOCOL_VERSION,
+            codecs: vec![Codec::Json],
+            compressions: vec![Compression::Deflate, Compression::None],
+            checksums: vec![],
+            keepalive: None,
+            armored: false,
+            permission: Permission::Controller,
+            token: None,
+        };
+
+        let hello = ServerHello::negotiate(&client, &[Codec::Json], &[Compression::None], &[]);
+        assert_eq!(hello.compression, Compression::None);
+
+        let hello = ServerHello::negotiate(&client, &[Codec::Json], &Compression::supported(), &[]);
+        assert_eq!(hello.compression, Compression::supported()[0]);
+    }
+
+    #[test]
+    fn negotiate_codec_picks_json_framing() {
+        let client = ClientHello::default();
+
+        let hello = ServerHello::negotiate(&client, &[Codec::Json], &[Compression::None], &[]);
+        let session = Session::from(hello);
+        assert_eq!(session.codec, Codec::Json);
+        assert_eq!(session.framing(), Framing::Line);
+    }
+
+    #[test]
+    fn negotiate_falls_back_to_default() {
+        let client = ClientHello {
+            protocol: PROTOCOL_VERSION,
+            codecs: vec![],
+            compressions: vec![],
+            checksums: vec![],
+            keepalive: None,
+            armored: false,
+            permission: Permission::Controller,
+            token: None,
+        };
+
+        let hello = ServerHello::negotiate(
+            &client,
+            &Codec::supported(),
+            &Compression::supported(),
+            &Checksum::supported(),
+        );
+        assert_eq!(Session::from(hello), Session::default());
+    }
+
+    #[test]
+    fn negotiate_checksum_picks_binary_framing() {
+        let client = ClientHello {
+            checksums: vec![Checksum::Crc32, Checksum::None],
+            ..Default::default()
+        };
+
+        let hello = ServerHello::negotiate(
+            &client,
+            &[Codec::Json],
+            &[Compression::None],
+            &Checksum::supported(),
+        );
+        let session = Session::from(hello);
+        assert_eq!(session.checksum, Checksum::Crc32);
+        assert_eq!(session.framing(), Framing::LengthPrefixed);
+    }
+
+    #[test]
+    fn negotiate_keepalive_caps_client_timeout() {
+        let client = Keepalive {
+            interval: 30,
+            timeout: 90,
+        };
+
+        assert_eq!(Keepalive::negotiate(None, None), None);
+        assert_eq!(Keepalive::negotiate(Some(client), None), Some(client));
+        assert_eq!(
+            Keepalive::negotiate(None, Some(60)),
+            Some(Keepalive {
+                interval: 20,
+                timeout: 60
+            })
+        );
+        assert_eq!(
+            Keepalive::negotiate(Some(client), Some(20)),
+            Some(Keepalive {
+                interval: 20,
+                timeout: 20
+            })
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Signatures of request frames.
+//!
+//! Deployments where TLS is unavailable can still protect the
+//! integrity of requests with a key shared by clients and server,
+//! configured out of band in their [`Session`]. Each request payload
+//! is then followed by its HMAC-SHA256, verified by the server before
+//! decoding the request. Requests with a missing or invalid
+//! signature are answered with [`TimerError::MalformedRequest`], and
+//! never applied.
+//!
+//! Signatures do not encrypt requests, and do not prevent a recorded
+//! request from being sent again.
+//!
+//! [`Session`]: crate::session::Session
+//! [`TimerError::MalformedRequest`]: crate::timer::TimerError::MalformedRequest
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+/// The size of a HMAC-SHA256 signature.
+pub const SIGNATURE_SIZE: usize = 32;
+
+/// The size of a signing key.
+pub const SIGNING_KEY_SIZE: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Error emitted when verifying the signature of a frame.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum SigningError {
+    #[error("Frame is too short to contain a signature")]
+    Truncated,
+    #[error("Invalid frame signature")]
+    Mismatch,
+}
+
+/// The key shared by clients and server to sign requests.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct SigningKey([u8; SIGNING_KEY_SIZE]);
+
+impl SigningKey {
+    /// Creates a new key from the given secret bytes, which should
+    /// be randomly generated.
+    pub fn new(secret: [u8; SIGNING_KEY_SIZE]) -> Self {
+        Self(secret)
+    }
+
+    /// Appends the signature of the given payload to it.
+    pub fn sign(&self, mut payload: Vec<u8>) -> Vec<u8> {
+        let signature = self.mac(&payload).finalize().into_bytes();
+        payload.extend_from_slice(&signature);
+        payload
+    }
+
+    /// Verifies and removes the signature ending the given frame.
+    pub fn verify(&self, mut frame: Vec<u8>) -> Result<Vec<u8>, SigningError> {
+        let Some(len) = frame.len().checked_sub(SIGNATURE_SIZE) else {
+            return Err(SigningError::Truncated);
+        };
+
+        let signature = frame.split_off(len);
+
+        match self.mac(&frame).verify_slice(&signature) {
+            Ok(()) => Ok(frame),
+            Err(_) => Err(SigningError::Mismatch),
+        }
+    }
+
+    fn mac(&self, payload: &[u8]) -> HmacSha256 {
+        // HMAC accepts keys of any size
+        let mut mac = HmacSha256::new_from_slice(&self.0).unwrap();
+        mac.update(payload);
+        mac
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SigningKey(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> SigningKey {
+        let mut secret = [0; SIGNING_KEY_SIZE];
+        for (i, byte) in secret.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        SigningKey::new(secret)
+    }
+
+    #[test]
+    fn hmac_sha256_signature() {
+        let frame = key().sign(b"what do ya want for nothing?".to_vec());
+
+        assert_eq!(
+            frame[frame.len() - SIGNATURE_SIZE..],
+            [
+                0x09, 0x98, 0x05, 0xf4, 0xac, 0x31, 0x07, 0x86, 0x96, 0x85, 0x65, 0xc0, 0x98, 0xdb,
+                0x51, 0x5c, 0xc5, 0x08, 0x62, 0xb4, 0x20, 0xae, 0x31, 0xe2, 0x02, 0x38, 0x31, 0x23,
+                0x44, 0xbe, 0xd3, 0x6a,
+            ]
+        );
+    }
+
+    #[test]
+    fn forged_frame_rejected() {
+        let key = key();
+        let frame = key.sign(b"{\"request\":\"Get\"}".to_vec());
+        assert_eq!(
+            key.verify(frame.clone()),
+            Ok(b"{\"request\":\"Get\"}".to_vec())
+        );
+
+        let other = SigningKey::new([0; SIGNING_KEY_SIZE]);
+        assert_eq!(other.verify(frame.clone()), Err(SigningError::Mismatch));
+
+        let mut forged = frame;
+        forged[12] = b'S';
+        assert_eq!(key.verify(forged), Err(SigningError::Mismatch));
+
+        assert_eq!(key.verify(b"abc".to_vec()), Err(SigningError::Truncated));
+    }
+}
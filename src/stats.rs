@@ -0,0 +1,144 @@
+//! Statistics of a timer.
+//!
+//! The server accounts the events emitted by each timer into
+//! [`TimerStats`], so that clients can display how the session went
+//! (for example the time spent focusing) with
+//! [`TimerRequest::GetStats`].
+//!
+//! The statistics of every timer of the server can be exported at
+//! once with [`TimerRequest::ExportStats`], streamed as a sequence of
+//! [`TimerStatsPage`]s.
+//!
+//! Like the history, statistics are kept in memory only, and reset
+//! when the server restarts.
+//!
+//! [`TimerRequest::GetStats`]: crate::timer::TimerRequest::GetStats
+//! [`TimerRequest::ExportStats`]: crate::timer::TimerRequest::ExportStats
+
+use alloc::{collections::BTreeMap, string::String};
+
+use serde::{Deserialize, Serialize};
+
+use crate::timer::TimerEvent;
+
+/// The maximum number of timers returned in a single
+/// [`TimerStatsPage`].
+pub const MAX_EXPORT_PAGE_SIZE: usize = 16;
+
+/// The statistics of a timer, accounted from its events.
+///
+/// Durations are accounted at the resolution of the timer updates:
+/// cycles ending between two updates are accounted to the first one.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerStats {
+    /// The number of cycles which ran until their end.
+    pub completed_cycles: u64,
+    /// The number of times the timer was paused.
+    pub pauses: u64,
+    /// The seconds spent running, per cycle name.
+    pub durations: BTreeMap<String, u64>,
+    /// The running cycle, along with the Unix epoch seconds since
+    /// when it is accounted.
+    #[serde(skip)]
+    running: Option<(String, u64)>,
+}
+
+impl TimerStats {
+    /// Creates empty statistics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accounts the given event, emitted at `at`.
+    pub fn record(&mut self, at: u64, event: &TimerEvent) {
+        match event {
+            TimerEvent::Began(cycle) | TimerEvent::Resumed(cycle) => {
+                self.stop(at);
+                self.running = Some((cycle.name.clone(), at));
+            }
+            TimerEvent::Ended(cycle) => {
+                if cycle.duration == 0 {
+                    self.completed_cycles += 1;
+                }
+                self.stop(at);
+            }
+            TimerEvent::Paused { .. } => {
+                self.pauses += 1;
+                self.stop(at);
+            }
+            TimerEvent::Stopped => self.stop(at),
+            TimerEvent::Started
+            | TimerEvent::Running(_)
+            | TimerEvent::Set(_)
+            | TimerEvent::Configured(_)
+            | TimerEvent::Lagged { .. } => (),
+        }
+    }
+
+    /// Returns the seconds spent running the cycle of the given name.
+    pub fn duration(&self, cycle: &str) -> u64 {
+        self.durations.get(cycle).copied().unwrap_or_default()
+    }
+
+    /// Returns the seconds spent running, all cycles included.
+    pub fn total_duration(&self) -> u64 {
+        self.durations.values().sum()
+    }
+
+    /// Returns the statistics as of `now`, including the time spent
+    /// in the running cycle.
+    pub fn at(&self, now: u64) -> Self {
+        let mut stats = self.clone();
+        stats.stop(now);
+        stats
+    }
+
+    fn stop(&mut self, at: u64) {
+        if let Some((name, since)) = self.running.take() {
+            let secs = at.saturating_sub(since);
+            *self.durations.entry(name).or_default() += secs;
+        }
+    }
+}
+
+/// A page of the statistics of the timers of a server.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerStatsPage {
+    /// The statistics of the timers of the page, by name.
+    pub timers: BTreeMap<String, TimerStats>,
+    /// The name of the first timer of the next page, if any.
+    pub next: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timer::{PauseReason, TimerCycle};
+
+    use super::*;
+
+    #[test]
+    fn events_accounted_per_cycle() {
+        let mut stats = TimerStats::new();
+        stats.record(0, &TimerEvent::Started);
+        stats.record(0, &TimerEvent::Began(TimerCycle::new("Focus", 60)));
+        let cycle = TimerCycle::new("Focus", 40);
+        let reason = PauseReason::Request;
+        stats.record(20, &TimerEvent::Paused { cycle, reason });
+        stats.record(50, &TimerEvent::Resumed(TimerCycle::new("Focus", 40)));
+        stats.record(90, &TimerEvent::Ended(TimerCycle::new("Focus", 0)));
+        stats.record(90, &TimerEvent::Began(TimerCycle::new("Break", 30)));
+
+        let now = stats.at(100);
+        assert_eq!(now.completed_cycles, 1);
+        assert_eq!(now.pauses, 1);
+        assert_eq!(now.duration("Focus"), 60);
+        assert_eq!(now.duration("Break"), 10);
+        assert_eq!(now.total_duration(), 70);
+
+        stats.record(110, &TimerEvent::Ended(TimerCycle::new("Break", 10)));
+        stats.record(110, &TimerEvent::Stopped);
+        assert_eq!(stats.completed_cycles, 1);
+        assert_eq!(stats.duration("Break"), 20);
+        assert_eq!(stats.at(200), stats);
+    }
+}
@@ -8,12 +8,32 @@
 //! [`TimeNow`]: crate::coroutines::now::TimeNow
 
 use alloc::{
+    collections::BTreeMap,
+    format,
     string::{String, ToString},
     vec::Vec,
 };
 use core::ops::{Deref, DerefMut};
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    codec::Codec,
+    compression::Compression,
+    delta::TimerDelta,
+    history::{TimerHistory, TimerHistoryEntry, TimerHistoryPage},
+    idempotency::IdempotencyCache,
+    schema::TimerRecord,
+    stats::{TimerStats, TimerStatsPage},
+};
+
+/// The revision of the wire protocol spoken by this build.
+///
+/// Bumped on every incompatible change of the requests, responses or
+/// handshake.
+pub const PROTOCOL_VERSION: u32 = 1;
 
 /// Controls how many full loops the timer runs before stopping.
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
@@ -98,6 +118,17 @@ pub enum TimerState {
     Stopped,
 }
 
+/// The reason a timer was paused, see [`TimerEvent::Paused`].
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PauseReason {
+    /// The timer was paused by a request.
+    #[default]
+    Request,
+    /// No activity was reported for the idle timeout of the timer,
+    /// see [`Timer::with_idle_timeout`].
+    Idle,
+}
+
 /// An event emitted by a timer during its lifecycle.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum TimerEvent {
@@ -107,20 +138,66 @@ pub enum TimerEvent {
     Began(TimerCycle),
     /// The timer is running the given cycle (periodic tick).
     Running(TimerCycle),
-    /// The remaining duration was manually set.
+    /// The remaining duration of the current cycle was manually set.
     Set(TimerCycle),
-    /// The timer was paused at the given cycle.
-    Paused(TimerCycle),
+    /// The configured duration of the given cycle was changed.
+    Configured(TimerCycle),
+    /// The timer was paused at the given cycle, for the given
+    /// reason.
+    Paused {
+        cycle: TimerCycle,
+        reason: PauseReason,
+    },
     /// The timer was resumed at the given cycle.
     Resumed(TimerCycle),
     /// The timer ended the given cycle.
     Ended(TimerCycle),
     /// The timer stopped.
     Stopped,
+    /// The given number of events were dropped before being pushed,
+    /// because the subscriber was too slow to receive them (see
+    /// [`TimerEventBroadcast::with_capacity`]).
+    ///
+    /// Clients should resynchronize with [`TimerRequest::Get`].
+    ///
+    /// [`TimerEventBroadcast::with_capacity`]: crate::broadcast::TimerEventBroadcast::with_capacity
+    Lagged { missed: u64 },
+}
+
+impl TimerEvent {
+    /// Returns the name of the event, as encoded on the wire.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Started => "Started",
+            Self::Began(_) => "Began",
+            Self::Running(_) => "Running",
+            Self::Set(_) => "Set",
+            Self::Configured(_) => "Configured",
+            Self::Paused { .. } => "Paused",
+            Self::Resumed(_) => "Resumed",
+            Self::Ended(_) => "Ended",
+            Self::Stopped => "Stopped",
+            Self::Lagged { .. } => "Lagged",
+        }
+    }
+}
+
+/// A [`TimerEvent`] along with the wall-clock time it occurred at,
+/// as sent over the wire.
+///
+/// Clients can use the timestamp to display accurate times, or to
+/// detect delays introduced by the network.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerStampedEvent {
+    /// Unix epoch seconds at which the event occurred, according to
+    /// the server clock.
+    pub at: u64,
+    /// The event itself.
+    pub event: TimerEvent,
 }
 
 /// Timer configuration: cycle definitions and loop count.
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct TimerConfig {
     /// The ordered list of timer cycles.
     pub cycles: TimerCycles,
@@ -129,6 +206,26 @@ pub struct TimerConfig {
 }
 
 impl TimerConfig {
+    /// Checks that a timer can be created from the configuration.
+    pub fn validate(&self) -> Result<(), TimerError> {
+        if self.cycles.is_empty() {
+            let reason = "timer must have at least one cycle";
+            return Err(TimerError::InvalidConfig(reason.to_string()));
+        }
+
+        if let Some(cycle) = self.cycles.iter().find(|cycle| cycle.duration == 0) {
+            let reason = format!("duration of cycle {} must be positive", cycle.name);
+            return Err(TimerError::InvalidConfig(reason));
+        }
+
+        if self.cycles_count == TimerLoop::Fixed(0) {
+            let reason = "timer must run at least one loop";
+            return Err(TimerError::InvalidConfig(reason.to_string()));
+        }
+
+        Ok(())
+    }
+
     fn first_cycle(&self) -> TimerCycle {
         self.cycles
             .first()
@@ -144,9 +241,16 @@ impl TimerConfig {
 /// internally. Obtain `now` via the [`TimeNow`] coroutine or via the
 /// server-side [`TimerRequestHandle`] coroutine.
 ///
+/// Timers are serialized along with their schema version, so that
+/// they can be persisted and loaded back by a newer build (see
+/// [`schema`]). The idempotency cache, the history and the
+/// statistics are not serialized.
+///
 /// [`TimeNow`]: crate::coroutines::now::TimeNow
 /// [`TimerRequestHandle`]: crate::coroutines::server::TimerRequestHandle
+/// [`schema`]: crate::schema
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(try_from = "TimerRecord", into = "TimerRecord")]
 pub struct Timer {
     /// The timer configuration.
     pub config: TimerConfig,
@@ -154,6 +258,15 @@ pub struct Timer {
     pub state: TimerState,
     /// The current cycle (with remaining duration).
     pub cycle: TimerCycle,
+    /// The index of the current cycle in the configured cycles.
+    pub cycle_index: usize,
+    /// Elapsed seconds (as returned by [`Timer::elapsed`]) at which
+    /// the current cycle began.
+    pub cycle_offset: usize,
+    /// Seconds added to (or removed from) the configured duration of
+    /// the current cycle by [`Timer::set_remaining`]. Reset when the
+    /// cycle ends.
+    pub cycle_adjustment: isize,
     /// The configured loop count, decremented as loops complete.
     pub cycles_count: TimerLoop,
     /// Unix epoch seconds at which the timer was last started or
@@ -162,6 +275,21 @@ pub struct Timer {
     /// Accumulated elapsed seconds from previous runs (before the
     /// last pause or stop).
     pub elapsed: usize,
+    /// Keys of the recently applied mutating requests, used by the
+    /// server to avoid applying retried requests twice.
+    pub idempotency: IdempotencyCache,
+    /// The last events emitted by the timer as a result of requests
+    /// handled by the server.
+    pub history: TimerHistory,
+    /// The statistics accounted from the events of the
+    /// [`history`](Timer::history).
+    pub stats: TimerStats,
+    /// Seconds without activity after which the running timer is
+    /// paused, see [`Timer::with_idle_timeout`].
+    pub idle_timeout: Option<u64>,
+    /// Unix epoch seconds at which the user was last reported active,
+    /// see [`Timer::activity`].
+    pub last_activity: Option<u64>,
 }
 
 impl Timer {
@@ -181,6 +309,59 @@ impl Timer {
         }
     }
 
+    /// Pauses the running timer once no activity was reported for the
+    /// given number of seconds, so that the time the user was away is
+    /// not tracked.
+    ///
+    /// Activity is reported with [`Timer::activity`] (or
+    /// [`TimerRequest::Activity`]), starting or resuming the timer
+    /// counting as activity. The timer is then paused by
+    /// [`Timer::update`] as of the idle deadline, emitting a
+    /// [`TimerEvent::Paused`] event, with [`PauseReason::Idle`].
+    pub fn with_idle_timeout(mut self, secs: u64) -> Self {
+        self.idle_timeout = Some(secs);
+        self
+    }
+
+    /// Returns the time (Unix epoch seconds) at which the running
+    /// timer becomes idle, if it has an idle timeout.
+    pub fn idle_deadline(&self) -> Option<u64> {
+        if !matches!(self.state, TimerState::Running) {
+            return None;
+        }
+
+        let timeout = self.idle_timeout?;
+        let active = self.last_activity.max(self.started_at)?;
+        Some(active.saturating_add(timeout))
+    }
+
+    /// Returns the time (Unix epoch seconds) at which the current
+    /// cycle of the running timer ends.
+    pub fn cycle_deadline(&self) -> Option<u64> {
+        if !matches!(self.state, TimerState::Running) {
+            return None;
+        }
+
+        let started_at = self.started_at?;
+        let end = self.cycle_offset + self.cycle_total_duration();
+        Some(started_at.saturating_add(end.saturating_sub(self.elapsed) as u64))
+    }
+
+    /// Records that the user is active.
+    ///
+    /// Activity reported after the idle deadline comes too late: the
+    /// timer is first paused as of the deadline, and the emitted
+    /// events are returned.
+    pub fn activity(&mut self, now: u64) -> impl IntoIterator<Item = TimerEvent> {
+        let events = match self.idle_deadline() {
+            Some(deadline) if deadline <= now => self.update(now).into_iter().collect(),
+            _ => Vec::new(),
+        };
+
+        self.last_activity = Some(now);
+        events
+    }
+
     /// Returns the total elapsed seconds since the timer last started
     /// or resumed, plus any previously accumulated elapsed time.
     pub fn elapsed(&self, now: u64) -> usize {
@@ -191,57 +372,137 @@ impl Timer {
         running + self.elapsed
     }
 
+    /// Returns the total duration of the current cycle, taking into
+    /// account the adjustment made by [`Timer::set_remaining`].
+    fn cycle_total_duration(&self) -> usize {
+        let duration = self
+            .config
+            .cycles
+            .get(self.cycle_index)
+            .map(|cycle| cycle.duration)
+            .unwrap_or_default();
+
+        duration.saturating_add_signed(self.cycle_adjustment)
+    }
+
+    /// Resets the cycle progression to the first configured cycle.
+    fn rewind(&mut self) {
+        self.cycle = self.config.first_cycle();
+        self.cycle_index = 0;
+        self.cycle_offset = 0;
+        self.cycle_adjustment = 0;
+        self.cycles_count = self.config.cycles_count.clone();
+        self.started_at = None;
+        self.elapsed = 0;
+    }
+
     /// Advances the timer by one tick and returns any events that
     /// fired.
     ///
+    /// Cycles are walked one by one from the current one, so that
+    /// every cycle ending since the last tick emits its own
+    /// [`TimerEvent::Ended`] and [`TimerEvent::Began`] events. When
+    /// the last loop of a [`TimerLoop::Fixed`] timer ends, the timer
+    /// stops.
+    ///
     /// Has no effect when the timer is paused or stopped.
+    ///
+    /// A timer idle since `now` is advanced up to its idle deadline
+    /// only, then paused, see [`Timer::with_idle_timeout`].
     pub fn update(&mut self, now: u64) -> impl IntoIterator<Item = TimerEvent> {
+        let deadline = self.idle_deadline().filter(|deadline| *deadline <= now);
+
+        let Some(deadline) = deadline else {
+            return self.advance(now);
+        };
+
+        let mut events = self.advance(deadline);
+
+        // the last loop of the timer may have ended in the meantime
+        if matches!(self.state, TimerState::Running) {
+            self.elapsed = self.elapsed(deadline);
+            self.started_at = None;
+            self.state = TimerState::Paused;
+            events.push(TimerEvent::Paused {
+                cycle: self.cycle.clone(),
+                reason: PauseReason::Idle,
+            });
+        }
+
+        events
+    }
+
+    /// Walks the cycles of the running timer up to `now`, see
+    /// [`Timer::update`].
+    fn advance(&mut self, now: u64) -> Vec<TimerEvent> {
         let mut events = Vec::with_capacity(3);
 
-        if let TimerState::Running = self.state {
-            let mut elapsed = self.elapsed(now);
+        if !matches!(self.state, TimerState::Running) {
+            return events;
+        }
 
-            let (cycles, total_duration) = self.config.cycles.iter().cloned().fold(
-                (Vec::new(), 0),
-                |(mut cycles, mut sum), mut cycle| {
-                    cycle.duration += sum;
-                    sum = cycle.duration;
-                    cycles.push(cycle);
-                    (cycles, sum)
-                },
-            );
+        let loop_duration: usize = self.config.cycles.iter().map(|c| c.duration).sum();
+
+        if loop_duration == 0 {
+            return events;
+        }
+
+        let elapsed = self.elapsed(now);
+        let mut began = false;
 
-            if let TimerLoop::Fixed(cycles_count) = self.cycles_count {
-                if elapsed >= total_duration * cycles_count {
-                    self.state = TimerState::Stopped;
-                    return events;
+        events.push(TimerEvent::Running(self.cycle.clone()));
+
+        loop {
+            let end = self.cycle_offset + self.cycle_total_duration();
+
+            if elapsed < end {
+                self.cycle.duration = end - elapsed;
+                if began {
+                    events.push(TimerEvent::Began(self.cycle.clone()));
                 }
+                break;
+            }
+
+            if began {
+                events.push(TimerEvent::Began(self.cycle.clone()));
             }
 
-            elapsed %= total_duration;
+            let mut prev_cycle = self.cycle.clone();
+            prev_cycle.duration = 0;
+            events.push(TimerEvent::Ended(prev_cycle));
 
-            let last_cycle = cycles[cycles.len() - 1].clone();
-            let next_cycle = cycles
-                .into_iter()
-                .fold(None, |next_cycle, mut cycle| match next_cycle {
-                    None if elapsed < cycle.duration => {
-                        cycle.duration -= elapsed;
-                        Some(cycle)
-                    }
-                    _ => next_cycle,
-                })
-                .unwrap_or(last_cycle);
+            self.cycle_offset = end;
+            self.cycle_adjustment = 0;
+            self.cycle_index += 1;
 
-            events.push(TimerEvent::Running(self.cycle.clone()));
+            if self.cycle_index >= self.config.cycles.len() {
+                self.cycle_index = 0;
 
-            if self.cycle.name != next_cycle.name {
-                let mut prev_cycle = self.cycle.clone();
-                prev_cycle.duration = 0;
-                events.push(TimerEvent::Ended(prev_cycle));
-                events.push(TimerEvent::Began(next_cycle.clone()));
+                if let TimerLoop::Fixed(count) = &mut self.cycles_count {
+                    if *count <= 1 {
+                        self.state = TimerState::Stopped;
+                        self.rewind();
+                        events.push(TimerEvent::Stopped);
+                        return events;
+                    }
+                    *count -= 1;
+                }
+
+                // skip whole loops at once when far behind
+                let loops = (elapsed - self.cycle_offset) / loop_duration;
+                let loops = match &mut self.cycles_count {
+                    TimerLoop::Infinite => loops,
+                    TimerLoop::Fixed(count) => {
+                        let loops = loops.min(*count - 1);
+                        *count -= loops;
+                        loops
+                    }
+                };
+                self.cycle_offset += loops * loop_duration;
             }
 
-            self.cycle = next_cycle;
+            self.cycle = self.config.cycles[self.cycle_index].clone();
+            began = true;
         }
 
         events
@@ -255,10 +516,8 @@ impl Timer {
 
         if matches!(self.state, TimerState::Stopped) {
             self.state = TimerState::Running;
-            self.cycle = self.config.first_cycle();
-            self.cycles_count = self.config.cycles_count.clone();
+            self.rewind();
             self.started_at = Some(now);
-            self.elapsed = 0;
             events.push(TimerEvent::Started);
             events.push(TimerEvent::Began(self.cycle.clone()));
         }
@@ -266,13 +525,113 @@ impl Timer {
         events
     }
 
-    /// Sets the remaining duration of the current cycle to
-    /// `duration_secs`.
-    pub fn set(&mut self, duration_secs: usize) -> impl IntoIterator<Item = TimerEvent> {
-        self.cycle.duration = duration_secs;
+    /// Sets the remaining duration of the current cycle to `secs`.
+    ///
+    /// Only the current occurrence of the cycle is affected: the
+    /// next cycles, as well as the next loops, keep their configured
+    /// duration. The adjustment of a stopped timer is discarded by
+    /// [`Timer::start`].
+    pub fn set_remaining(&mut self, now: u64, secs: usize) -> impl IntoIterator<Item = TimerEvent> {
+        let cycle_elapsed = self.elapsed(now).saturating_sub(self.cycle_offset);
+        let configured = self.cycle_total_duration() as isize - self.cycle_adjustment;
+
+        // the current cycle now ends `secs` seconds from now
+        self.cycle_adjustment = (cycle_elapsed + secs) as isize - configured;
+        self.cycle.duration = secs;
         [TimerEvent::Set(self.cycle.clone())]
     }
 
+    /// Sets the configured duration of every cycle named `name` to
+    /// `secs`.
+    ///
+    /// The new duration applies to the next occurrences of the
+    /// cycle. If the current cycle is affected, its remaining
+    /// duration is shifted accordingly, without going below zero.
+    ///
+    /// Has no effect if no cycle matches the given name.
+    pub fn set_cycle_duration(
+        &mut self,
+        name: &str,
+        secs: usize,
+    ) -> impl IntoIterator<Item = TimerEvent> {
+        let mut events = Vec::new();
+
+        for (index, cycle) in self.config.cycles.iter_mut().enumerate() {
+            if cycle.name != name {
+                continue;
+            }
+
+            if index == self.cycle_index {
+                let delta = secs as isize - cycle.duration as isize;
+                self.cycle.duration = self.cycle.duration.saturating_add_signed(delta);
+            }
+
+            cycle.duration = secs;
+            events.push(TimerEvent::Configured(cycle.clone()));
+        }
+
+        events
+    }
+
+    /// Replaces the configuration of the timer, for example after its
+    /// configuration file changed.
+    ///
+    /// A stopped timer is reset to the first cycle of the new
+    /// configuration. Otherwise, the current cycle goes on if the new
+    /// configuration has a cycle of the same name at the same index,
+    /// its remaining duration being shifted like with
+    /// [`Timer::set_cycle_duration`]. If not, the first cycle of the
+    /// new configuration begins. In both cases, the loop count
+    /// restarts from the new configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config` has no cycles, see [`TimerConfig::validate`].
+    pub fn set_config(
+        &mut self,
+        now: u64,
+        config: TimerConfig,
+    ) -> impl IntoIterator<Item = TimerEvent> {
+        let mut events: Vec<TimerEvent> = config
+            .cycles
+            .iter()
+            .cloned()
+            .map(TimerEvent::Configured)
+            .collect();
+
+        let previous = self.cycle_total_duration() as isize - self.cycle_adjustment;
+        let current = config
+            .cycles
+            .get(self.cycle_index)
+            .filter(|cycle| cycle.name == self.cycle.name)
+            .map(|cycle| cycle.duration);
+
+        self.config = config;
+
+        if matches!(self.state, TimerState::Stopped) {
+            self.rewind();
+            return events;
+        }
+
+        self.cycles_count = self.config.cycles_count.clone();
+
+        match current {
+            Some(duration) => {
+                let delta = duration as isize - previous;
+                self.cycle.duration = self.cycle.duration.saturating_add_signed(delta);
+            }
+            None => {
+                self.cycle = self.config.first_cycle();
+                self.cycle_index = 0;
+                self.cycle_offset = self.elapsed(now);
+                self.cycle_adjustment = 0;
+                events.push(TimerEvent::Began(self.cycle.clone()));
+            }
+        }
+
+        events
+    }
+
     /// Pauses the timer, saving the elapsed time.
     ///
     /// Has no effect if the timer is not running.
@@ -281,7 +640,10 @@ impl Timer {
             self.elapsed = self.elapsed(now);
             self.started_at = None;
             self.state = TimerState::Paused;
-            Some(TimerEvent::Paused(self.cycle.clone()))
+            Some(TimerEvent::Paused {
+                cycle: self.cycle.clone(),
+                reason: PauseReason::Request,
+            })
         } else {
             None
         }
@@ -300,6 +662,18 @@ impl Timer {
         }
     }
 
+    /// Starts the timer if stopped, pauses it if running, or resumes
+    /// it if paused.
+    pub fn toggle(&mut self, now: u64) -> impl IntoIterator<Item = TimerEvent> {
+        let events: Vec<TimerEvent> = match self.state {
+            TimerState::Stopped => self.start(now).into_iter().collect(),
+            TimerState::Running => self.pause(now).into_iter().collect(),
+            TimerState::Paused => self.resume(now).into_iter().collect(),
+        };
+
+        events
+    }
+
     /// Stops the timer and resets it to the initial state.
     ///
     /// Has no effect if the timer is not running.
@@ -310,14 +684,33 @@ impl Timer {
             self.state = TimerState::Stopped;
             events.push(TimerEvent::Ended(self.cycle.clone()));
             events.push(TimerEvent::Stopped);
-            self.cycle = self.config.first_cycle();
-            self.cycles_count = self.config.cycles_count.clone();
-            self.started_at = None;
-            self.elapsed = 0;
+            self.rewind();
         }
 
         events
     }
+
+    /// Stops the timer and resets it to the initial state, whatever
+    /// its current state.
+    ///
+    /// Unlike [`Timer::stop`], paused timers are also stopped, and
+    /// the cycle progression of stopped timers is rewound.
+    pub fn reset(&mut self) -> impl IntoIterator<Item = TimerEvent> {
+        let mut events = Vec::with_capacity(2);
+
+        match self.state {
+            TimerState::Running => {
+                events.push(TimerEvent::Ended(self.cycle.clone()));
+                events.push(TimerEvent::Stopped);
+            }
+            TimerState::Paused => events.push(TimerEvent::Stopped),
+            TimerState::Stopped => (),
+        }
+
+        self.state = TimerState::Stopped;
+        self.rewind();
+        events
+    }
 }
 
 /// A command sent to a timer server.
@@ -335,17 +728,534 @@ pub enum TimerRequest {
     Resume,
     /// Advance the timer by one tick.
     Update,
-    /// Set the remaining duration of the current cycle.
-    Set(usize),
+    /// Set the remaining duration of the current cycle, in seconds.
+    SetRemaining(usize),
+    /// Set the configured duration of the named cycle, in seconds.
+    SetCycleDuration { cycle: String, secs: usize },
+    /// Start the timer if stopped, pause it if running, or resume it
+    /// if paused.
+    Toggle,
+    /// Return the features supported by the server.
+    Capabilities,
+    /// Return the version of the server.
+    Version,
+    /// Return the status of every timer managed by the server.
+    List,
+    /// Create a new timer with the given name and configuration.
+    Create { name: String, config: TimerConfig },
+    /// Delete the timer with the given name.
+    Delete { name: String },
+    /// Keep the connection alive, see [`Keepalive`].
+    ///
+    /// [`Keepalive`]: crate::session::Keepalive
+    Ping,
+    /// Return at most `limit` events of the timer history, starting
+    /// from the sequence number `cursor`.
+    GetHistory {
+        cursor: Option<u64>,
+        limit: Option<usize>,
+    },
+    /// Subscribe the connection to the events of the timer.
+    ///
+    /// Events are then pushed as [`TimerResponse::Event`] frames. With
+    /// an `id`, the subscription is durable: pushed events must be
+    /// acknowledged with [`TimerRequest::Ack`], and the events not
+    /// acknowledged yet are pushed again when subscribing again with
+    /// the same id (for example after a reconnection).
+    ///
+    /// Targeting the [`WILDCARD_TIMER`] subscribes to every timer of
+    /// the server, in which case events are pushed as
+    /// [`TimerResponse::TaggedEvent`] frames. Such subscriptions
+    /// cannot be durable.
+    ///
+    /// [`WILDCARD_TIMER`]: crate::registry::WILDCARD_TIMER
+    Subscribe {
+        #[serde(default)]
+        id: Option<String>,
+    },
+    /// Acknowledge the events pushed to the durable subscription of
+    /// the connection, up to the sequence number `seq` (included).
+    Ack { seq: u64 },
+    /// Return a compact, human-readable status of the timer, for
+    /// status bars.
+    Status,
+    /// An embedder-defined request, handled by
+    /// [`TimerStore::extension`].
+    ///
+    /// The payload is kept as a JSON value, therefore extensions
+    /// require a self-describing codec like [`Codec::Json`].
+    ///
+    /// [`TimerStore::extension`]: crate::registry::TimerStore::extension
+    Extension {
+        name: String,
+        #[serde(default)]
+        payload: Value,
+    },
+    /// Replace the configuration of the timer, see
+    /// [`Timer::set_config`].
+    SetConfig(TimerConfig),
+    /// Stop the timer whatever its state, see [`Timer::reset`].
+    Reset,
+    /// Return the statistics of the timer.
+    GetStats,
+    /// Return every event of the timer history, starting from the
+    /// sequence number `cursor`.
+    ///
+    /// Unlike [`TimerRequest::GetHistory`], the whole history is
+    /// returned at once, as a sequence of [`TimerResponse::History`]
+    /// frames of at most [`MAX_PAGE_SIZE`] events, the last one
+    /// without next cursor.
+    ///
+    /// [`MAX_PAGE_SIZE`]: crate::history::MAX_PAGE_SIZE
+    StreamHistory { cursor: Option<u64> },
+    /// Report that the user is active, see [`Timer::activity`].
+    ///
+    /// Clients send it periodically while the user is active, so that
+    /// timers with an idle timeout keep running.
+    Activity,
+    /// Return the statistics of every timer managed by the server,
+    /// starting from the timer named `cursor`.
+    ///
+    /// The statistics are returned as a sequence of
+    /// [`TimerResponse::StatsExport`] frames of at most
+    /// [`MAX_EXPORT_PAGE_SIZE`] timers, the last one without next
+    /// cursor.
+    ///
+    /// [`MAX_EXPORT_PAGE_SIZE`]: crate::stats::MAX_EXPORT_PAGE_SIZE
+    ExportStats { cursor: Option<String> },
+}
+
+impl TimerRequest {
+    /// Returns the name of the request, as encoded on the wire.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Get => "Get",
+            Self::Start => "Start",
+            Self::Stop => "Stop",
+            Self::Pause => "Pause",
+            Self::Resume => "Resume",
+            Self::Update => "Update",
+            Self::SetRemaining(_) => "SetRemaining",
+            Self::SetCycleDuration { .. } => "SetCycleDuration",
+            Self::Toggle => "Toggle",
+            Self::Capabilities => "Capabilities",
+            Self::Version => "Version",
+            Self::List => "List",
+            Self::Create { .. } => "Create",
+            Self::Delete { .. } => "Delete",
+            Self::Ping => "Ping",
+            Self::GetHistory { .. } => "GetHistory",
+            Self::Subscribe { .. } => "Subscribe",
+            Self::Ack { .. } => "Ack",
+            Self::Status => "Status",
+            Self::Extension { .. } => "Extension",
+            Self::SetConfig(_) => "SetConfig",
+            Self::Reset => "Reset",
+            Self::GetStats => "GetStats",
+            Self::StreamHistory { .. } => "StreamHistory",
+            Self::Activity => "Activity",
+            Self::ExportStats { .. } => "ExportStats",
+        }
+    }
+
+    /// Returns `true` if the request may modify the timer.
+    pub fn is_mutating(&self) -> bool {
+        !matches!(
+            self,
+            Self::Get
+                | Self::Capabilities
+                | Self::Version
+                | Self::List
+                | Self::Ping
+                | Self::GetHistory { .. }
+                | Self::Subscribe { .. }
+                | Self::Ack { .. }
+                | Self::Status
+                | Self::GetStats
+                | Self::StreamHistory { .. }
+                | Self::ExportStats { .. }
+        )
+    }
+
+    /// Checks that the request makes sense for the given timer,
+    /// before applying it.
+    ///
+    /// Requests repeating the current state of the timer (like
+    /// [`TimerRequest::Start`] on a running timer, or
+    /// [`TimerRequest::Pause`] on a paused one) are valid, and
+    /// applied as no-op returning no event. Requests which cannot
+    /// apply to the timer are rejected with
+    /// [`TimerError::InvalidRequest`]:
+    ///
+    /// - [`TimerRequest::SetRemaining`] with zero seconds,
+    /// - [`TimerRequest::SetCycleDuration`] with zero seconds or a
+    ///   cycle unknown to the timer,
+    /// - [`TimerRequest::Pause`], [`TimerRequest::Resume`] and
+    ///   [`TimerRequest::SetRemaining`] on a stopped timer, since
+    ///   starting the timer begins the first cycle from scratch.
+    ///
+    /// Configurations of [`TimerRequest::SetConfig`] and
+    /// [`TimerRequest::Create`] are checked by
+    /// [`TimerConfig::validate`], and rejected with
+    /// [`TimerError::InvalidConfig`].
+    pub fn validate(&self, timer: &Timer) -> Result<(), TimerError> {
+        let reason = match self {
+            Self::SetConfig(config) | Self::Create { config, .. } => return config.validate(),
+            Self::SetRemaining(0) => "remaining duration must be positive".to_string(),
+            Self::SetCycleDuration { secs: 0, .. } => "cycle duration must be positive".to_string(),
+            Self::SetCycleDuration { cycle, .. }
+                if !timer.config.cycles.iter().any(|c| c.name == *cycle) =>
+            {
+                format!("unknown cycle {cycle}")
+            }
+            Self::Pause | Self::Resume | Self::SetRemaining(_)
+                if timer.state == TimerState::Stopped =>
+            {
+                "timer is stopped".to_string()
+            }
+            _ => return Ok(()),
+        };
+
+        Err(TimerError::InvalidRequest(reason))
+    }
+}
+
+/// A [`TimerRequest`] along with its metadata, as sent on the wire.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerEnvelope {
+    /// The request itself.
+    pub request: TimerRequest,
+    /// The optional idempotency key of the request.
+    ///
+    /// A mutating request sent again with the same key is not
+    /// applied twice: the server replays the response of the first
+    /// request instead. See [`IdempotencyCache`].
+    #[serde(default)]
+    pub key: Option<String>,
+    /// The name of the timer targeted by the request.
+    ///
+    /// Targets the [`DEFAULT_TIMER`] when `None`.
+    ///
+    /// [`DEFAULT_TIMER`]: crate::registry::DEFAULT_TIMER
+    #[serde(default)]
+    pub timer: Option<String>,
+}
+
+impl TimerEnvelope {
+    /// Wraps the given request, without metadata.
+    ///
+    /// Metadata can then be attached with the `with_*` builders,
+    /// before passing the envelope to a client coroutine:
+    ///
+    /// ```rust,ignore
+    /// let envelope = TimerEnvelope::new(TimerRequest::Start)
+    ///     .with_key("start-1")
+    ///     .with_timer("tea");
+    /// let client = TimerRequestSend::new(envelope);
+    /// ```
+    pub fn new(request: TimerRequest) -> Self {
+        Self {
+            request,
+            key: None,
+            timer: None,
+        }
+    }
+
+    /// Attaches the given idempotency key, see [`TimerEnvelope::key`].
+    pub fn with_key(mut self, key: impl ToString) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Targets the timer with the given name, see
+    /// [`TimerEnvelope::timer`].
+    pub fn with_timer(mut self, name: impl ToString) -> Self {
+        self.timer = Some(name.to_string());
+        self
+    }
+}
+
+impl From<TimerRequest> for TimerEnvelope {
+    fn from(request: TimerRequest) -> Self {
+        Self::new(request)
+    }
 }
 
 /// A response from a timer server.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum TimerResponse {
     /// The current timer state (reply to [`TimerRequest::Get`]).
-    Timer(Timer),
+    Snapshot(TimerSnapshot),
     /// Events emitted by the timer as a result of a command.
-    Events(Vec<TimerEvent>),
+    Events(Vec<TimerStampedEvent>),
+    /// The timer state resulting from a command (reply to
+    /// [`TimerRequest::Toggle`]).
+    State(TimerState),
+    /// The features supported by the server (reply to
+    /// [`TimerRequest::Capabilities`]).
+    Capabilities(TimerCapabilities),
+    /// The version of the server (reply to
+    /// [`TimerRequest::Version`]).
+    Version(TimerVersion),
+    /// The redacted state of the timer (reply to
+    /// [`TimerRequest::Get`] when the server redacts responses).
+    Status(TimerStatus),
+    /// The compact status of the timer (reply to
+    /// [`TimerRequest::Status`]).
+    StatusLine(TimerStatusLine),
+    /// The status of every timer managed by the server, by name
+    /// (reply to [`TimerRequest::List`]).
+    List(BTreeMap<String, TimerStatus>),
+    /// A page of the timer history (reply to
+    /// [`TimerRequest::GetHistory`] and
+    /// [`TimerRequest::StreamHistory`]).
+    History(TimerHistoryPage),
+    /// The subscription succeeded (reply to
+    /// [`TimerRequest::Subscribe`]), along with the events of the
+    /// durable subscription not acknowledged yet.
+    Subscribed(Vec<TimerHistoryEntry>),
+    /// An event pushed to a subscribed connection.
+    Event(TimerHistoryEntry),
+    /// An event pushed to a connection subscribed to every timer,
+    /// tagged with the name of its timer.
+    TaggedEvent {
+        timer: String,
+        event: TimerHistoryEntry,
+    },
+    /// The changes of a timer pushed to a subscribed connection,
+    /// instead of its events (see
+    /// [`TimerEventBroadcast::with_deltas`]).
+    ///
+    /// [`TimerEventBroadcast::with_deltas`]: crate::broadcast::TimerEventBroadcast::with_deltas
+    Delta(TimerDelta),
+    /// The changes of a timer pushed to a connection subscribed to
+    /// every timer, tagged with the name of its timer.
+    TaggedDelta { timer: String, delta: TimerDelta },
+    /// The connection is alive (reply to [`TimerRequest::Ping`]).
+    Pong,
+    /// The request was applied, without further information (reply
+    /// to [`TimerRequest::Create`], [`TimerRequest::Delete`] and
+    /// [`TimerRequest::Ack`]).
+    Ok,
+    /// The server is too busy to apply the request right now, and the
+    /// client should retry after the given number of milliseconds.
+    ///
+    /// The request was not applied.
+    Busy { retry_after_ms: u64 },
+    /// The result of an embedder-defined request (reply to
+    /// [`TimerRequest::Extension`]).
+    Extension(Value),
+    /// The request was rejected by the server.
+    Err(TimerError),
+    /// The statistics of the timer (reply to
+    /// [`TimerRequest::GetStats`]).
+    Stats(TimerStats),
+    /// A page of the statistics of every timer (reply to
+    /// [`TimerRequest::ExportStats`]).
+    StatsExport(TimerStatsPage),
+}
+
+/// An error sent back by a timer server instead of applying a
+/// request.
+#[derive(Clone, Debug, Deserialize, Eq, Error, PartialEq, Serialize)]
+pub enum TimerError {
+    /// The client issued too many requests, and should wait the
+    /// given number of seconds before retrying.
+    #[error("Too many requests, retry after {retry_after} seconds")]
+    TooManyRequests { retry_after: u64 },
+    /// The request targets a timer unknown to the server.
+    #[error("Unknown timer {0}")]
+    UnknownTimer(String),
+    /// The request creates a timer whose name is already taken.
+    #[error("Timer {0} already exists")]
+    TimerExists(String),
+    /// The request contains an invalid timer configuration.
+    #[error("Invalid timer configuration: {0}")]
+    InvalidConfig(String),
+    /// The request modifies a timer, while the connection is only
+    /// allowed to observe timers.
+    #[error("Request not allowed to observer connections")]
+    Forbidden,
+    /// The acknowledgement was sent on a connection without durable
+    /// subscription.
+    #[error("Connection has no durable subscription")]
+    NotSubscribed,
+    /// The request frame exceeds the maximum frame size, in bytes.
+    #[error("Request frame exceeds the maximum size of {max} bytes")]
+    FrameTooLarge { max: usize },
+    /// The request is not supported by the server.
+    #[error("Request not supported by the server")]
+    Unsupported,
+    /// The request frame could not be decoded.
+    #[error("Malformed request")]
+    MalformedRequest,
+    /// The request cannot apply to the targeted timer, see
+    /// [`TimerRequest::validate`].
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+}
+
+/// The features supported by a timer server.
+///
+/// Clients can request them with [`TimerRequest::Capabilities`] in
+/// order to adapt their behaviour, instead of sending requests the
+/// server may not understand.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerCapabilities {
+    /// The version of the server crate.
+    pub version: String,
+    /// The codecs supported at handshake.
+    pub codecs: Vec<Codec>,
+    /// The compression algorithms supported at handshake.
+    pub compressions: Vec<Compression>,
+    /// Whether the server manages multiple named timers.
+    pub multi_timer: bool,
+    /// Whether clients can subscribe to timer events.
+    pub subscribe: bool,
+    /// Whether the server keeps an history of timer events.
+    pub history: bool,
+}
+
+impl Default for TimerCapabilities {
+    /// Returns the capabilities of this build.
+    fn default() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            codecs: Codec::supported(),
+            compressions: Compression::supported(),
+            multi_timer: false,
+            subscribe: true,
+            history: true,
+        }
+    }
+}
+
+/// The version of a timer server, to identify what is running behind
+/// a socket.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerVersion {
+    /// The version of the server crate.
+    pub crate_version: String,
+    /// The revision of the wire protocol, see [`PROTOCOL_VERSION`].
+    pub protocol: u32,
+}
+
+impl Default for TimerVersion {
+    /// Returns the version of this build.
+    fn default() -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol: PROTOCOL_VERSION,
+        }
+    }
+}
+
+/// The state of a timer, as sent on the wire.
+///
+/// Unlike [`Timer`], the snapshot does not expose the timer
+/// internals, so that they can evolve without breaking the protocol.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerSnapshot {
+    /// The current timer state.
+    pub state: TimerState,
+    /// The name of the current cycle.
+    pub cycle: String,
+    /// Remaining seconds in the current cycle.
+    pub remaining: usize,
+    /// The index of the current cycle in the configured cycles.
+    pub cycle_index: usize,
+    /// The remaining loop count.
+    pub cycles_count: TimerLoop,
+    /// Accumulated elapsed seconds from previous runs (before the
+    /// last pause or stop).
+    pub elapsed: usize,
+    /// Unix epoch seconds at which the timer was last started or
+    /// resumed. `None` when the timer is stopped or paused.
+    pub started_at: Option<u64>,
+}
+
+impl From<&Timer> for TimerSnapshot {
+    fn from(timer: &Timer) -> Self {
+        Self {
+            state: timer.state.clone(),
+            cycle: timer.cycle.name.clone(),
+            remaining: timer.cycle.duration,
+            cycle_index: timer.cycle_index,
+            cycles_count: timer.cycles_count.clone(),
+            elapsed: timer.elapsed,
+            started_at: timer.started_at,
+        }
+    }
+}
+
+/// The public status of a timer, without its configuration nor its
+/// internals.
+///
+/// Sent instead of the whole [`Timer`] by servers exposing the timer
+/// status to untrusted clients.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerStatus {
+    /// The current timer state.
+    pub state: TimerState,
+    /// The name of the current cycle.
+    pub cycle: String,
+    /// Remaining seconds in the current cycle.
+    pub remaining: usize,
+}
+
+impl From<&Timer> for TimerStatus {
+    fn from(timer: &Timer) -> Self {
+        Self {
+            state: timer.state.clone(),
+            cycle: timer.cycle.name.clone(),
+            remaining: timer.cycle.duration,
+        }
+    }
+}
+
+/// The compact status of a timer, assembled server-side so that
+/// trivial clients (like shell scripts or status bars) can display it
+/// as is.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimerStatusLine {
+    /// The status formatted as `<cycle> <remaining> <symbol>`, like
+    /// `Focus 12:34 ▶`.
+    pub text: String,
+    /// The current timer state.
+    pub state: TimerState,
+    /// The progression of the current cycle, from 0 to 100.
+    pub percent: u8,
+}
+
+impl From<&Timer> for TimerStatusLine {
+    fn from(timer: &Timer) -> Self {
+        let remaining = timer.cycle.duration;
+        let total = timer.cycle_total_duration().max(remaining);
+        let percent = match total {
+            0 => 0,
+            total => ((total - remaining) * 100 / total) as u8,
+        };
+
+        let symbol = match timer.state {
+            TimerState::Running => '▶',
+            TimerState::Paused => '⏸',
+            TimerState::Stopped => '■',
+        };
+
+        let (hours, mins, secs) = (remaining / 3600, remaining / 60 % 60, remaining % 60);
+        let name = &timer.cycle.name;
+        let text = if hours > 0 {
+            format!("{name} {hours}:{mins:02}:{secs:02} {symbol}")
+        } else {
+            format!("{name} {mins:02}:{secs:02} {symbol}")
+        };
+
+        Self {
+            text,
+            state: timer.state.clone(),
+            percent,
+        }
+    }
 }
 
 impl Eq for Timer {}
@@ -354,6 +1264,9 @@ impl PartialEq for Timer {
     fn eq(&self, other: &Self) -> bool {
         self.state == other.state
             && self.cycle == other.cycle
+            && self.cycle_index == other.cycle_index
+            && self.cycle_offset == other.cycle_offset
+            && self.cycle_adjustment == other.cycle_adjustment
             && self.started_at == other.started_at
             && self.elapsed == other.elapsed
     }
@@ -462,7 +1375,7 @@ mod tests {
         assert_eq!(timer.cycle, TimerCycle::new("a", 3));
 
         events.extend(timer.start(0));
-        events.extend(timer.set(21));
+        events.extend(timer.set_remaining(0, 21));
 
         assert_eq!(timer.state, TimerState::Running);
         assert_eq!(timer.cycle, TimerCycle::new("a", 21));
@@ -488,11 +1401,212 @@ mod tests {
                 TimerEvent::Started,
                 TimerEvent::Began(TimerCycle::new("a", 3)),
                 TimerEvent::Set(TimerCycle::new("a", 21)),
-                TimerEvent::Paused(TimerCycle::new("a", 21)),
+                TimerEvent::Paused {
+                    cycle: TimerCycle::new("a", 21),
+                    reason: PauseReason::Request,
+                },
                 TimerEvent::Resumed(TimerCycle::new("a", 21)),
                 TimerEvent::Ended(TimerCycle::new("a", 21)),
                 TimerEvent::Stopped,
             ]
         );
     }
+
+    #[test]
+    fn toggle_cycles_through_states() {
+        let mut timer = Timer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("a", 3)]),
+            ..Default::default()
+        });
+
+        let mut events = Vec::new();
+
+        events.extend(timer.toggle(0));
+        assert_eq!(timer.state, TimerState::Running);
+
+        events.extend(timer.toggle(1));
+        assert_eq!(timer.state, TimerState::Paused);
+
+        events.extend(timer.toggle(2));
+        assert_eq!(timer.state, TimerState::Running);
+
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::Started,
+                TimerEvent::Began(TimerCycle::new("a", 3)),
+                TimerEvent::Paused {
+                    cycle: TimerCycle::new("a", 3),
+                    reason: PauseReason::Request,
+                },
+                TimerEvent::Resumed(TimerCycle::new("a", 3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_remaining_survives_update() {
+        let mut timer = testing_timer();
+
+        timer.update(1);
+        timer.set_remaining(1, 10);
+        assert_eq!(timer.cycle, TimerCycle::new("a", 10));
+
+        timer.update(5);
+        assert_eq!(timer.cycle, TimerCycle::new("a", 6));
+
+        timer.update(11);
+        assert_eq!(timer.cycle, TimerCycle::new("b", 2));
+
+        // next loop uses the configured duration again
+        timer.update(14);
+        assert_eq!(timer.cycle, TimerCycle::new("a", 3));
+    }
+
+    #[test]
+    fn set_cycle_duration_shifts_current_cycle() {
+        let mut timer = testing_timer();
+
+        timer.update(1);
+        let events: Vec<_> = timer.set_cycle_duration("a", 5).into_iter().collect();
+        assert_eq!(
+            events,
+            vec![TimerEvent::Configured(TimerCycle::new("a", 5))]
+        );
+        assert_eq!(timer.cycle, TimerCycle::new("a", 4));
+
+        timer.update(5);
+        assert_eq!(timer.cycle, TimerCycle::new("b", 2));
+
+        let events: Vec<_> = timer.set_cycle_duration("z", 5).into_iter().collect();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn set_config_keeps_or_replaces_current_cycle() {
+        let mut timer = testing_timer();
+        timer.update(1);
+
+        let config = TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("a", 5), TimerCycle::new("b", 2)]),
+            ..Default::default()
+        };
+        let events: Vec<_> = timer.set_config(1, config).into_iter().collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(timer.cycle, TimerCycle::new("a", 4));
+
+        let config = TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("z", 10)]),
+            ..Default::default()
+        };
+        let events: Vec<_> = timer.set_config(2, config).into_iter().collect();
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::Configured(TimerCycle::new("z", 10)),
+                TimerEvent::Began(TimerCycle::new("z", 10)),
+            ]
+        );
+
+        timer.update(7);
+        assert_eq!(timer.cycle, TimerCycle::new("z", 5));
+        assert!(TimerConfig::default().validate().is_err());
+    }
+
+    #[test]
+    fn reset_stops_paused_timer() {
+        let mut timer = testing_timer();
+        timer.update(1);
+        timer.pause(1);
+
+        assert!(timer.stop().into_iter().next().is_none());
+        assert_eq!(timer.state, TimerState::Paused);
+
+        let events: Vec<_> = timer.reset().into_iter().collect();
+        assert_eq!(events, vec![TimerEvent::Stopped]);
+        assert_eq!(timer.state, TimerState::Stopped);
+        assert_eq!(timer.cycle, TimerCycle::new("a", 3));
+        assert!(timer.reset().into_iter().next().is_none());
+    }
+
+    #[test]
+    fn update_emits_events_for_every_ended_cycle() {
+        let mut timer = testing_timer();
+
+        let events: Vec<_> = timer.update(5).into_iter().collect();
+
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::Running(TimerCycle::new("a", 3)),
+                TimerEvent::Ended(TimerCycle::new("a", 0)),
+                TimerEvent::Began(TimerCycle::new("b", 2)),
+                TimerEvent::Ended(TimerCycle::new("b", 0)),
+                TimerEvent::Began(TimerCycle::new("c", 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn fixed_timer_stops_after_last_loop() {
+        let mut timer = testing_timer();
+        timer.cycles_count = TimerLoop::Fixed(2);
+
+        timer.update(7);
+        assert_eq!(timer.state, TimerState::Running);
+        assert_eq!(timer.cycle, TimerCycle::new("a", 2));
+        assert_eq!(timer.cycles_count, TimerLoop::Fixed(1));
+
+        let events: Vec<_> = timer.update(12).into_iter().collect();
+        assert_eq!(timer.state, TimerState::Stopped);
+        assert_eq!(events.last(), Some(&TimerEvent::Stopped));
+    }
+
+    #[test]
+    fn idle_timer_paused_at_idle_deadline() {
+        let mut timer = testing_timer().with_idle_timeout(2);
+        assert_eq!(timer.idle_deadline(), Some(2));
+
+        assert!(timer.activity(1).into_iter().next().is_none());
+        assert_eq!(timer.idle_deadline(), Some(3));
+
+        let events: Vec<_> = timer.update(5).into_iter().collect();
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::Running(TimerCycle::new("a", 3)),
+                TimerEvent::Ended(TimerCycle::new("a", 0)),
+                TimerEvent::Began(TimerCycle::new("b", 2)),
+                TimerEvent::Paused {
+                    cycle: TimerCycle::new("b", 2),
+                    reason: PauseReason::Idle,
+                },
+            ]
+        );
+
+        // the idle time is not accounted
+        assert_eq!(timer.state, TimerState::Paused);
+        assert_eq!(timer.elapsed(10), 3);
+        assert_eq!(timer.idle_deadline(), None);
+    }
+
+    #[test]
+    fn late_activity_does_not_resume_idle_timer() {
+        let mut timer = testing_timer().with_idle_timeout(2);
+
+        let events: Vec<_> = timer.activity(4).into_iter().collect();
+        assert_eq!(
+            events.last(),
+            Some(&TimerEvent::Paused {
+                cycle: TimerCycle::new("a", 1),
+                reason: PauseReason::Idle,
+            })
+        );
+        assert_eq!(timer.state, TimerState::Paused);
+        assert_eq!(timer.last_activity, Some(4));
+
+        // resuming counts as activity
+        timer.resume(10);
+        assert_eq!(timer.idle_deadline(), Some(12));
+    }
 }
@@ -0,0 +1,88 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use std::{
+    os::unix::net::UnixStream,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use io_time::{
+    audit::{TimerAuditLog, TimerAuditOutcome},
+    client::std::run,
+    coroutines::{
+        client::TimerRequestSend,
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    runtimes::std::handle as time_handle,
+    session::Permission,
+    timer::{Timer, TimerConfig, TimerCycle, TimerCycles, TimerError, TimerLoop, TimerResponse},
+};
+
+use common::socket_io;
+
+fn serve(mut stream: UnixStream, mut handle: TimerRequestHandle, timer: &mut Timer, n: usize) {
+    let mut arg: Option<TimerRequestHandleArg> = None;
+    let mut handled = 0;
+
+    while handled < n {
+        match handle.resume(timer, arg.take()) {
+            TimerRequestHandleResult::Ok { .. } => handled += 1,
+            TimerRequestHandleResult::Io { input } => {
+                arg = Some(socket_io(&mut stream, input).into());
+            }
+            TimerRequestHandleResult::TimeIo { input } => {
+                arg = Some(time_handle(input).unwrap().into());
+            }
+            TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+            TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+        }
+    }
+}
+
+#[test]
+fn requests_recorded_into_shared_audit_log() {
+    let log = Arc::new(Mutex::new(TimerAuditLog::with_capacity(2)));
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let (mut client, server) = UnixStream::pair().unwrap();
+    let handle = TimerRequestHandle::new().with_audit("alice", log.clone());
+    let thread = thread::spawn(move || {
+        serve(server, handle, &mut timer, 1);
+        timer
+    });
+    let started = run(&mut client, TimerRequestSend::start()).unwrap();
+    let mut timer = thread.join().unwrap();
+    assert!(matches!(started, TimerResponse::Events(_)));
+
+    let (mut client, server) = UnixStream::pair().unwrap();
+    let handle = TimerRequestHandle::new()
+        .with_permission(Permission::Observer)
+        .with_audit("bob", log.clone());
+    let thread = thread::spawn(move || serve(server, handle, &mut timer, 2));
+    run(&mut client, TimerRequestSend::get()).unwrap();
+    let stopped = run(&mut client, TimerRequestSend::stop().with_timer("tea")).unwrap();
+    thread.join().unwrap();
+    assert_eq!(stopped, TimerResponse::Err(TimerError::Forbidden));
+
+    // the oldest entry was evicted
+    let log = log.lock().unwrap();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log.session("alice").count(), 0);
+
+    let entries: Vec<_> = log.session("bob").collect();
+    assert_eq!(entries[0].request, "Get");
+    assert_eq!(entries[0].outcome, TimerAuditOutcome::Ok);
+    assert_eq!(entries[1].request, "Stop");
+    assert_eq!(entries[1].timer.as_deref(), Some("tea"));
+    assert_eq!(
+        entries[1].outcome,
+        TimerAuditOutcome::Rejected(TimerError::Forbidden)
+    );
+    assert!(entries[1].at > 0);
+    assert_eq!(log.since(entries[0].at).count(), 2);
+}
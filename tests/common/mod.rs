@@ -0,0 +1,19 @@
+//! Helpers shared by the integration tests.
+
+use std::io::{Read, Write};
+
+use io_socket::io::{SocketInput, SocketOutput};
+
+/// Performs the given socket I/O on a blocking stream.
+pub fn socket_io(stream: &mut (impl Read + Write), input: SocketInput) -> SocketOutput {
+    match input {
+        SocketInput::Read { mut buf } => {
+            let n = stream.read(&mut buf).unwrap();
+            SocketOutput::Read { buf, n }
+        }
+        SocketInput::Write { buf } => {
+            let n = stream.write(&buf).unwrap();
+            SocketOutput::Wrote { buf, n }
+        }
+    }
+}
@@ -0,0 +1,695 @@
+mod common;
+
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    thread,
+};
+
+use io_socket::io::SocketInput;
+use io_time::{
+    broadcast::{TimerEventBroadcast, TimerEventInterceptor},
+    client::std::run,
+    coroutines::{
+        client::TimerRequestSend,
+        push::TimerEventPushResult,
+        server::{
+            TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleError,
+            TimerRequestHandleResult, TimerSessionManager,
+        },
+        stream::{TimerEventStream, TimerEventStreamResult},
+    },
+    delta::TimerDelta,
+    history::TimerHistoryEntry,
+    mirror::TimerMirror,
+    registry::TimerStore,
+    runtimes::std::handle as time_handle,
+    session::{Keepalive, Session},
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerEvent, TimerLoop, TimerResponse,
+        TimerSnapshot, TimerState, TimerStatus,
+    },
+};
+
+use common::socket_io;
+
+/// Handles one request of the connection with the given id.
+fn handle(
+    connections: &mut TimerSessionManager<u32>,
+    id: u32,
+    stream: &mut UnixStream,
+    timer: &mut Timer,
+) -> bool {
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        match connections.resume(&id, timer, arg.take()) {
+            Some(TimerRequestHandleResult::Ok { .. }) => return true,
+            Some(TimerRequestHandleResult::Io { input }) => {
+                arg = Some(TimerRequestHandleArg::Socket(socket_io(stream, input)));
+            }
+            Some(TimerRequestHandleResult::TimeIo { input }) => {
+                arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+            }
+            Some(TimerRequestHandleResult::Disconnected | TimerRequestHandleResult::Err { .. })
+            | None => return false,
+        }
+    }
+}
+
+#[test]
+fn connections_share_timers() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let (mut a, mut server_a) = UnixStream::pair().unwrap();
+    let (mut b, mut server_b) = UnixStream::pair().unwrap();
+
+    let mut connections = TimerSessionManager::new();
+    connections.insert(1, TimerRequestHandle::new());
+    connections.insert(2, TimerRequestHandle::new());
+    assert_eq!(connections.len(), 2);
+
+    let client_a = thread::spawn(move || {
+        run(&mut a, TimerRequestSend::start()).unwrap();
+        run(&mut a, TimerRequestSend::subscribe(None)).unwrap();
+    });
+    assert!(handle(&mut connections, 1, &mut server_a, &mut timer));
+    assert!(handle(&mut connections, 1, &mut server_a, &mut timer));
+    client_a.join().unwrap();
+
+    let client_b = thread::spawn(move || run(&mut b, TimerRequestSend::get()).unwrap());
+    assert!(handle(&mut connections, 2, &mut server_b, &mut timer));
+    let TimerResponse::Snapshot(snapshot) = client_b.join().unwrap() else {
+        panic!("expected timer snapshot");
+    };
+    assert_eq!(snapshot.state, TimerState::Running);
+
+    let subscribers: Vec<_> = connections.subscribers("default").collect();
+    assert_eq!(subscribers, [&1]);
+
+    // both clients are gone, their connections are unregistered
+    assert!(!handle(&mut connections, 2, &mut server_b, &mut timer));
+    assert!(!connections.contains(&2));
+    assert!(!handle(&mut connections, 2, &mut server_b, &mut timer));
+    assert_eq!(connections.ids().collect::<Vec<_>>(), [&1]);
+}
+
+#[test]
+fn disconnected_clients_reaped_quietly() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let (mut a, mut server_a) = UnixStream::pair().unwrap();
+    let (mut b, mut server_b) = UnixStream::pair().unwrap();
+
+    let mut connections = TimerSessionManager::new();
+    connections.insert(1, TimerRequestHandle::new());
+    connections.insert(2, TimerRequestHandle::new());
+
+    // closed between two requests
+    let client_a = thread::spawn(move || run(&mut a, TimerRequestSend::get()).unwrap());
+    assert!(handle(&mut connections, 1, &mut server_a, &mut timer));
+    client_a.join().unwrap();
+
+    let input = match connections.resume(&1, &mut timer, None::<TimerRequestHandleArg>) {
+        Some(TimerRequestHandleResult::Io { input }) => input,
+        other => panic!("unexpected result: {other:?}"),
+    };
+    let output = socket_io(&mut server_a, input);
+    assert!(matches!(
+        connections.resume(&1, &mut timer, Some(output)),
+        Some(TimerRequestHandleResult::Disconnected)
+    ));
+    assert!(!connections.contains(&1));
+
+    // closed in the middle of a request
+    b.write_all(&[0, 0]).unwrap();
+    drop(b);
+
+    let mut arg: Option<TimerRequestHandleArg> = None;
+    let err = loop {
+        match connections.resume(&2, &mut timer, arg.take()) {
+            Some(TimerRequestHandleResult::Io { input }) => {
+                arg = Some(socket_io(&mut server_b, input).into());
+            }
+            Some(TimerRequestHandleResult::Err { err }) => break err,
+            other => panic!("unexpected result: {other:?}"),
+        }
+    };
+    assert!(matches!(err, TimerRequestHandleError::ReadEof));
+    assert!(connections.is_empty());
+}
+
+#[test]
+fn stalled_response_writes_close_the_session() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let client = thread::spawn(move || run(&mut client, TimerRequestSend::get()));
+
+    let mut connections = TimerSessionManager::new();
+    connections.insert(1, TimerRequestHandle::new().with_write_timeout(5));
+    assert_eq!(connections.get(&1).unwrap().write_timeout(), Some(5));
+
+    let mut arg: Option<TimerRequestHandleArg> = None;
+    let err = loop {
+        match connections.resume(&1, &mut timer, arg.take()) {
+            // the client never reads the response
+            Some(TimerRequestHandleResult::Io {
+                input: SocketInput::Write { .. },
+            }) => arg = Some(TimerRequestHandleArg::TimedOut),
+            Some(TimerRequestHandleResult::Io { input }) => {
+                arg = Some(socket_io(&mut server, input).into());
+            }
+            Some(TimerRequestHandleResult::TimeIo { input }) => {
+                arg = Some(time_handle(input).unwrap().into());
+            }
+            Some(TimerRequestHandleResult::Err { err }) => break err,
+            other => panic!("unexpected result: {other:?}"),
+        }
+    };
+
+    assert!(matches!(err, TimerRequestHandleError::WriteTimeout(5)));
+    assert!(connections.is_empty());
+
+    drop(server);
+    assert!(client.join().unwrap().is_err());
+}
+
+#[test]
+fn metrics_aggregated_across_connections() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let (mut a, mut server_a) = UnixStream::pair().unwrap();
+    let (mut b, mut server_b) = UnixStream::pair().unwrap();
+
+    let mut connections = TimerSessionManager::new();
+    connections.insert(1, TimerRequestHandle::new());
+    connections.insert(2, TimerRequestHandle::new());
+
+    let client_a = thread::spawn(move || {
+        run(&mut a, TimerRequestSend::start()).unwrap();
+        run(&mut a, TimerRequestSend::get()).unwrap();
+    });
+    assert!(handle(&mut connections, 1, &mut server_a, &mut timer));
+    assert!(handle(&mut connections, 1, &mut server_a, &mut timer));
+    client_a.join().unwrap();
+
+    b.write_all(b"{\"Garbage\":true}\n").unwrap();
+    assert!(handle(&mut connections, 2, &mut server_b, &mut timer));
+    let b_metrics = connections.get(&2).unwrap().metrics().clone();
+    assert_eq!(b_metrics.decode_failures, 1);
+    assert_eq!(b_metrics.total_requests(), 0);
+
+    // closed connections are still accounted
+    let n = b.read(&mut [0; 64]).unwrap();
+    assert!(n > 0);
+    drop(b);
+    assert!(!handle(&mut connections, 2, &mut server_b, &mut timer));
+
+    let metrics = connections.metrics();
+    assert_eq!(metrics.requests["Start"], 1);
+    assert_eq!(metrics.requests["Get"], 1);
+    assert_eq!(metrics.total_requests(), 2);
+    assert_eq!(metrics.decode_failures, 1);
+    assert_eq!(metrics.events, 2);
+    assert_eq!(metrics.bytes_read, {
+        let a = connections.get(&1).unwrap().metrics();
+        a.bytes_read + b_metrics.bytes_read
+    });
+    assert!(metrics.bytes_written > b_metrics.bytes_written);
+}
+
+#[test]
+fn idle_connections_reported() {
+    let keepalive = Session {
+        keepalive: Some(Keepalive::from_timeout(10)),
+        ..Default::default()
+    };
+
+    let mut connections = TimerSessionManager::new().with_idle_timeout(60);
+    connections.insert(1, TimerRequestHandle::new());
+    connections.insert(2, TimerRequestHandle::new());
+    connections.insert(3, TimerRequestHandle::new().with_session(keepalive));
+    connections.insert(4, TimerRequestHandle::new());
+
+    connections.touch(&1, 100);
+    connections.touch(&2, 130);
+    connections.touch(&3, 140);
+    connections.touch(&5, 140);
+
+    let idle = |connections: &TimerSessionManager<u32>, now| -> Vec<u32> {
+        connections.idle(now).copied().collect()
+    };
+
+    assert_eq!(idle(&connections, 150), [] as [u32; 0]);
+    // the keepalive of the session takes precedence
+    assert_eq!(idle(&connections, 151), [3]);
+    assert_eq!(idle(&connections, 161), [1, 3]);
+
+    connections.touch(&1, 161);
+    connections.remove(&3);
+    assert_eq!(idle(&connections, 191), [2]);
+}
+
+/// A timer counting the notifications of its changes.
+struct Persisted(Timer, usize);
+
+impl TimerStore for Persisted {
+    const MULTI_TIMER: bool = false;
+
+    fn get_mut(&mut self, name: Option<&str>) -> Option<&mut Timer> {
+        self.0.get_mut(name)
+    }
+
+    fn list(&self) -> BTreeMap<String, TimerStatus> {
+        self.0.list()
+    }
+
+    fn changed(&mut self, _name: &str, _snapshot: Option<&TimerSnapshot>) {
+        self.1 += 1;
+    }
+}
+
+#[test]
+fn shutdown_drains_connections() {
+    let timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut timers = Persisted(timer, 0);
+
+    let (mut b, mut server_b) = UnixStream::pair().unwrap();
+
+    let mut connections = TimerSessionManager::new();
+    connections.insert(1, TimerRequestHandle::new());
+    connections.insert(2, TimerRequestHandle::new());
+
+    let client_b = thread::spawn(move || run(&mut b, TimerRequestSend::start()).unwrap());
+
+    // the request of the second connection is in flight
+    let mut arg: Option<TimerRequestHandleArg> = None;
+    loop {
+        match connections.resume(&2, &mut timers, arg.take()) {
+            Some(TimerRequestHandleResult::Io { input }) => {
+                arg = Some(socket_io(&mut server_b, input).into());
+            }
+            Some(TimerRequestHandleResult::TimeIo { input }) => {
+                arg = Some(time_handle(input).unwrap().into());
+                break;
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    assert_eq!(connections.shutdown(&mut timers), [1]);
+    assert!(!connections.is_drained());
+
+    loop {
+        match connections.resume(&2, &mut timers, arg.take()) {
+            Some(TimerRequestHandleResult::Ok { .. }) => break,
+            Some(TimerRequestHandleResult::Io { input }) => {
+                arg = Some(socket_io(&mut server_b, input).into());
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    assert!(matches!(client_b.join().unwrap(), TimerResponse::Events(_)));
+    assert_eq!(timers.1, 1);
+
+    let result = connections.resume(&2, &mut timers, None::<TimerRequestHandleArg>);
+    assert!(matches!(
+        result,
+        Some(TimerRequestHandleResult::Err {
+            err: TimerRequestHandleError::Shutdown
+        })
+    ));
+    assert!(connections.is_drained());
+    // the final state of the timer was notified
+    assert_eq!(timers.1, 2);
+}
+
+#[test]
+fn final_state_notified_once() {
+    let timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut timers = Persisted(timer, 0);
+
+    let mut connections = TimerSessionManager::new();
+    connections.insert(1, TimerRequestHandle::new());
+
+    assert_eq!(connections.shutdown(&mut timers), [1]);
+    assert!(connections.is_drained());
+    assert_eq!(timers.1, 1);
+
+    assert!(connections.shutdown(&mut timers).is_empty());
+    assert_eq!(timers.1, 1);
+}
+
+#[test]
+fn events_broadcast_to_subscribers() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let (mut a, mut server_a) = UnixStream::pair().unwrap();
+    let (mut b, mut server_b) = UnixStream::pair().unwrap();
+
+    let mut connections = TimerSessionManager::new();
+    connections.insert(1, TimerRequestHandle::new());
+    connections.insert(2, TimerRequestHandle::new());
+    let mut broadcast = TimerEventBroadcast::new();
+
+    // the subscriber waits for two events
+    let subscriber = thread::spawn(move || {
+        let mut stream = TimerEventStream::new(None);
+        let mut events = Vec::new();
+        let mut arg = None;
+
+        while events.len() < 2 {
+            match stream.resume(arg.take()) {
+                TimerEventStreamResult::Event { event, .. } => events.push(event.event),
+                TimerEventStreamResult::Io { input } => arg = Some(socket_io(&mut a, input)),
+                other => panic!("unexpected stream result: {other:?}"),
+            }
+        }
+
+        events
+    });
+    assert!(handle(&mut connections, 1, &mut server_a, &mut timer));
+
+    let client_b = thread::spawn(move || run(&mut b, TimerRequestSend::start()).unwrap());
+    assert!(handle(&mut connections, 2, &mut server_b, &mut timer));
+    client_b.join().unwrap();
+
+    assert_eq!(broadcast.publish(&connections, "default", &timer), 2);
+    assert_eq!(broadcast.publish(&connections, "default", &timer), 0);
+    assert_eq!(broadcast.pending(&1), 2);
+    assert_eq!(broadcast.pending(&2), 0);
+
+    let mut push = broadcast.take(&connections, &1).unwrap();
+    let mut arg = None;
+
+    loop {
+        match push.resume(arg.take()) {
+            TimerEventPushResult::Ok => break,
+            TimerEventPushResult::Io { input } => arg = Some(socket_io(&mut server_a, input)),
+            TimerEventPushResult::Err { err } => panic!("push error: {err}"),
+        }
+    }
+
+    assert!(broadcast.take(&connections, &1).is_none());
+    assert_eq!(broadcast.ready().count(), 0);
+
+    let events = subscriber.join().unwrap();
+    assert_eq!(events[0], TimerEvent::Started);
+    assert!(matches!(events[1], TimerEvent::Began(_)));
+}
+
+/// Drops the `Started` events, and localizes the names of cycles.
+#[derive(Debug)]
+struct Localize;
+
+impl TimerEventInterceptor for Localize {
+    fn intercept(&mut self, _: &str, mut entry: TimerHistoryEntry) -> Option<TimerHistoryEntry> {
+        match &mut entry.event {
+            TimerEvent::Started => return None,
+            TimerEvent::Began(cycle) if cycle.name == "Focus" => cycle.name = "Fokus".into(),
+            _ => (),
+        }
+        Some(entry)
+    }
+}
+
+#[test]
+fn interceptors_rewrite_broadcast_events() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    for event in timer.start(0).into_iter().collect::<Vec<_>>() {
+        timer.history.push(0, event);
+    }
+
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let mut connections = TimerSessionManager::new();
+    connections.insert(1, TimerRequestHandle::new());
+    let mut broadcast = TimerEventBroadcast::new().with_interceptor(Localize);
+
+    let subscriber = thread::spawn(move || {
+        let mut stream = TimerEventStream::new(None);
+        let mut arg = None;
+
+        loop {
+            match stream.resume(arg.take()) {
+                TimerEventStreamResult::Event { event, .. } => return event.event,
+                TimerEventStreamResult::Io { input } => arg = Some(socket_io(&mut client, input)),
+                other => panic!("unexpected stream result: {other:?}"),
+            }
+        }
+    });
+    assert!(handle(&mut connections, 1, &mut server, &mut timer));
+
+    assert_eq!(broadcast.publish(&connections, "default", &timer), 1);
+    let mut push = broadcast.take(&connections, &1).unwrap();
+    let mut arg = None;
+
+    loop {
+        match push.resume(arg.take()) {
+            TimerEventPushResult::Ok => break,
+            TimerEventPushResult::Io { input } => arg = Some(socket_io(&mut server, input)),
+            TimerEventPushResult::Err { err } => panic!("push error: {err}"),
+        }
+    }
+
+    let event = subscriber.join().unwrap();
+    assert_eq!(event, TimerEvent::Began(TimerCycle::new("Fokus", 1500)));
+
+    // the history of the timer is left untouched
+    let history = timer.history.page(None, None).events;
+    assert_eq!(history[0].event, TimerEvent::Started);
+    assert_eq!(
+        history[1].event,
+        TimerEvent::Began(TimerCycle::new("Focus", 1500))
+    );
+}
+
+#[test]
+fn slow_subscribers_notified_of_lagged_events() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let mut connections = TimerSessionManager::new();
+    connections.insert(1, TimerRequestHandle::new());
+    let mut broadcast = TimerEventBroadcast::new().with_capacity(2);
+
+    let subscriber = thread::spawn(move || {
+        let mut stream = TimerEventStream::new(None);
+        let mut events = Vec::new();
+        let mut arg = None;
+
+        while events.len() < 3 {
+            match stream.resume(arg.take()) {
+                TimerEventStreamResult::Event { event, .. } => events.push(event),
+                TimerEventStreamResult::Io { input } => arg = Some(socket_io(&mut client, input)),
+                other => panic!("unexpected stream result: {other:?}"),
+            }
+        }
+
+        events
+    });
+    assert!(handle(&mut connections, 1, &mut server, &mut timer));
+
+    let entries = (0..5).map(|seq| TimerHistoryEntry {
+        seq,
+        at: seq,
+        event: TimerEvent::Running(TimerCycle::new("Focus", 1500 - seq as usize)),
+    });
+    assert_eq!(
+        broadcast.publish_entries(&connections, "default", entries),
+        5
+    );
+    assert_eq!(broadcast.pending(&1), 3);
+    assert_eq!(broadcast.missed(&1), 3);
+
+    let mut push = broadcast.take(&connections, &1).unwrap();
+    let mut arg = None;
+
+    loop {
+        match push.resume(arg.take()) {
+            TimerEventPushResult::Ok => break,
+            TimerEventPushResult::Io { input } => arg = Some(socket_io(&mut server, input)),
+            TimerEventPushResult::Err { err } => panic!("push error: {err}"),
+        }
+    }
+
+    let events = subscriber.join().unwrap();
+    assert_eq!(events[0].event, TimerEvent::Lagged { missed: 3 });
+    assert_eq!(events[0].seq, 2);
+    assert_eq!(events[1].seq, 3);
+    assert_eq!(events[2].seq, 4);
+    assert_eq!(broadcast.missed(&1), 0);
+}
+
+/// Records the given events in the history of the timer, as the
+/// server does for handled requests.
+fn record(timer: &mut Timer, at: u64, events: Vec<TimerEvent>) {
+    for event in events {
+        timer.history.push(at, event);
+    }
+}
+
+/// Pushes the pending events of the connection with the given id.
+fn push_pending(
+    broadcast: &mut TimerEventBroadcast<u32>,
+    connections: &TimerSessionManager<u32>,
+    stream: &mut UnixStream,
+) {
+    while let Some(mut push) = broadcast.take(connections, &1) {
+        let mut arg = None;
+
+        loop {
+            match push.resume(arg.take()) {
+                TimerEventPushResult::Ok => break,
+                TimerEventPushResult::Io { input } => arg = Some(socket_io(stream, input)),
+                TimerEventPushResult::Err { err } => panic!("push error: {err}"),
+            }
+        }
+    }
+}
+
+#[test]
+fn mirror_driven_by_pushed_deltas() {
+    let config = TimerConfig {
+        cycles: TimerCycles::from([
+            TimerCycle::new("Focus", 1500),
+            TimerCycle::new("Break", 300),
+        ]),
+        cycles_count: TimerLoop::Infinite,
+    };
+    let mut timer = Timer::new(config.clone());
+
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let mut connections = TimerSessionManager::new();
+    connections.insert(1, TimerRequestHandle::new());
+    let mut broadcast = TimerEventBroadcast::new().with_deltas();
+
+    let subscriber = thread::spawn(move || {
+        let mut stream = TimerEventStream::new(None);
+        let mut deltas: Vec<TimerDelta> = Vec::new();
+        let mut arg = None;
+
+        while deltas.len() < 2 {
+            match stream.resume(arg.take()) {
+                TimerEventStreamResult::Delta { timer, delta } => {
+                    assert_eq!(timer, None);
+                    deltas.push(delta);
+                }
+                TimerEventStreamResult::Io { input } => arg = Some(socket_io(&mut client, input)),
+                other => panic!("unexpected stream result: {other:?}"),
+            }
+        }
+
+        deltas
+    });
+    assert!(handle(&mut connections, 1, &mut server, &mut timer));
+
+    let events = timer.start(100).into_iter().collect();
+    record(&mut timer, 100, events);
+    assert_eq!(broadcast.publish(&connections, "default", &timer), 2);
+    push_pending(&mut broadcast, &connections, &mut server);
+
+    // changes not pushed yet are merged together
+    let events = timer.pause(130).into_iter().collect();
+    record(&mut timer, 130, events);
+    broadcast.publish(&connections, "default", &timer);
+    let events = timer.set_remaining(140, 600).into_iter().collect();
+    record(&mut timer, 140, events);
+    broadcast.publish(&connections, "default", &timer);
+    assert_eq!(broadcast.pending(&1), 1);
+    push_pending(&mut broadcast, &connections, &mut server);
+
+    let deltas = subscriber.join().unwrap();
+    assert!(deltas[0].keyframe);
+    assert!(!deltas[1].keyframe);
+    assert_eq!(deltas[1].base, 2);
+    assert_eq!(deltas[1].seq, 4);
+
+    let mut mirror = TimerMirror::new(config);
+    mirror.apply_delta(&deltas[0]).unwrap();
+    assert_eq!(mirror.timer().state, TimerState::Running);
+    assert_eq!(mirror.seq(), Some(1));
+
+    mirror.apply_delta(&deltas[1]).unwrap();
+    assert_eq!(mirror.seq(), Some(3));
+    assert_eq!(mirror.snapshot(), TimerSnapshot::from(&timer));
+    assert!(mirror.apply_delta(&deltas[1]).is_err());
+}
+
+#[test]
+fn recreated_timers_published_from_start() {
+    let config = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    };
+    let mut timer = Timer::new(config.clone());
+
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let mut connections = TimerSessionManager::new();
+    connections.insert(1, TimerRequestHandle::new());
+    let mut broadcast = TimerEventBroadcast::new();
+
+    let subscriber = thread::spawn(move || {
+        let mut stream = TimerEventStream::new(None);
+        let mut events = Vec::new();
+        let mut arg = None;
+
+        while events.len() < 5 {
+            match stream.resume(arg.take()) {
+                TimerEventStreamResult::Event { event, .. } => events.push(event),
+                TimerEventStreamResult::Io { input } => arg = Some(socket_io(&mut client, input)),
+                other => panic!("unexpected stream result: {other:?}"),
+            }
+        }
+
+        events
+    });
+    assert!(handle(&mut connections, 1, &mut server, &mut timer));
+
+    let events = timer.start(100).into_iter().collect();
+    record(&mut timer, 100, events);
+    let events = timer.pause(110).into_iter().collect();
+    record(&mut timer, 110, events);
+    assert_eq!(broadcast.publish(&connections, "default", &timer), 3);
+
+    // the timer is deleted, then created again
+    let mut timer = Timer::new(config);
+    let events = timer.start(120).into_iter().collect();
+    record(&mut timer, 120, events);
+    assert_eq!(broadcast.publish(&connections, "default", &timer), 2);
+    push_pending(&mut broadcast, &connections, &mut server);
+
+    let events = subscriber.join().unwrap();
+    assert_eq!(events[3].seq, 0);
+    assert_eq!(events[3].event, TimerEvent::Started);
+    assert_eq!(events[4].seq, 1);
+}
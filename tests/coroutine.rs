@@ -0,0 +1,135 @@
+mod common;
+
+use std::{fmt::Display, os::unix::net::UnixStream, thread};
+
+use io_socket::io::SocketInput;
+use io_time::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::TimerRequestSend,
+        now::TimeNow,
+        request::TimerGet,
+        server::{
+            TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleInput, TimerRequestHandled,
+        },
+    },
+    runtimes::std::handle as time_handle,
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerEvent, TimerLoop, TimerResponse,
+        TimerState,
+    },
+};
+
+use common::socket_io;
+
+fn run<C>(mut coroutine: C, mut io: impl FnMut(C::Input) -> C::Output) -> C::Ok
+where
+    C: Coroutine,
+    C::Error: Display,
+{
+    let mut arg = None;
+
+    loop {
+        match Coroutine::resume(&mut coroutine, arg.take()) {
+            CoroutineResult::Ok(ok) => return ok,
+            CoroutineResult::Io(input) => arg = Some(io(input)),
+            CoroutineResult::Err(err) => panic!("coroutine error: {err}"),
+        }
+    }
+}
+
+#[test]
+fn time_coroutine_driven_generically() {
+    let (secs, _) = run(TimeNow::new(), |input| time_handle(input).unwrap());
+    assert!(secs > 0);
+}
+
+#[test]
+fn client_and_server_driven_generically() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut timer = Timer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+            cycles_count: TimerLoop::Infinite,
+        });
+        let mut server = TimerRequestHandle::new();
+
+        run(server.bind(&mut timer), |input| match input {
+            TimerRequestHandleInput::Time(input) => {
+                TimerRequestHandleArg::Time(time_handle(input).unwrap())
+            }
+            TimerRequestHandleInput::Socket(input) => {
+                TimerRequestHandleArg::Socket(socket_io(&mut server_stream, input))
+            }
+        })
+    });
+
+    let response = run(TimerRequestSend::start(), |input| {
+        socket_io(&mut client_stream, input)
+    });
+    let TimerRequestHandled::Events(events) = server.join().unwrap() else {
+        panic!("client disconnected");
+    };
+
+    assert!(matches!(response, TimerResponse::Events(_)));
+    assert_eq!(events.first(), Some(&TimerEvent::Started));
+}
+
+#[test]
+fn clean_disconnection_driven_generically() {
+    let (client_stream, mut server_stream) = UnixStream::pair().unwrap();
+    drop(client_stream);
+
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut server = TimerRequestHandle::new();
+
+    let handled = run(server.bind(&mut timer), |input| match input {
+        TimerRequestHandleInput::Time(input) => {
+            TimerRequestHandleArg::Time(time_handle(input).unwrap())
+        }
+        TimerRequestHandleInput::Socket(input) => {
+            TimerRequestHandleArg::Socket(socket_io(&mut server_stream, input))
+        }
+    });
+
+    assert_eq!(handled, TimerRequestHandled::Disconnected);
+}
+
+#[test]
+fn client_reads_into_given_buffer() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut timer = Timer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+            cycles_count: TimerLoop::Infinite,
+        });
+        let mut server = TimerRequestHandle::new();
+
+        run(server.bind(&mut timer), |input| match input {
+            TimerRequestHandleInput::Time(input) => {
+                TimerRequestHandleArg::Time(time_handle(input).unwrap())
+            }
+            TimerRequestHandleInput::Socket(input) => {
+                TimerRequestHandleArg::Socket(socket_io(&mut server_stream, input))
+            }
+        })
+    });
+
+    let mut reads = 0;
+    let snapshot = run(TimerGet::new().with_read_buffer(vec![0; 16]), |input| {
+        if let SocketInput::Read { buf } = &input {
+            assert_eq!(buf.len(), 16);
+            reads += 1;
+        }
+        socket_io(&mut client_stream, input)
+    });
+    server.join().unwrap();
+
+    assert_eq!(snapshot.state, TimerState::Stopped);
+    assert!(reads > 1);
+}
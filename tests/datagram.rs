@@ -0,0 +1,96 @@
+use std::{net::UdpSocket, thread};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use io_time::{
+    coroutines::{
+        client::{TimerRequestSend, TimerRequestSendResult},
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    runtimes::std::handle as time_handle,
+    session::Session,
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerEvent, TimerLoop, TimerRequest,
+        TimerResponse,
+    },
+};
+
+fn datagram_io(socket: &UdpSocket, input: SocketInput) -> SocketOutput {
+    match input {
+        SocketInput::Read { mut buf } => {
+            let n = socket.recv(&mut buf).unwrap();
+            SocketOutput::Read { buf, n }
+        }
+        SocketInput::Write { buf } => {
+            let n = socket.send(&buf).unwrap();
+            SocketOutput::Wrote { buf, n }
+        }
+    }
+}
+
+fn pair() -> (UdpSocket, UdpSocket) {
+    let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    client.connect(server.local_addr().unwrap()).unwrap();
+    server.connect(client.local_addr().unwrap()).unwrap();
+    (client, server)
+}
+
+fn send(socket: &UdpSocket, request: TimerRequest) -> TimerResponse {
+    let mut client = TimerRequestSend::new(request).with_session(Session::datagram());
+    let mut arg = None;
+
+    loop {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { response } => return response,
+            TimerRequestSendResult::Io { input } => arg = Some(datagram_io(socket, input)),
+            TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
+        }
+    }
+}
+
+fn handle(socket: UdpSocket, mut timer: Timer, requests: usize) -> Vec<TimerEvent> {
+    let mut server = TimerRequestHandle::new().with_session(Session::datagram());
+    let mut arg: Option<TimerRequestHandleArg> = None;
+    let mut events = Vec::new();
+
+    for _ in 0..requests {
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { events: e } => {
+                    events.extend(e);
+                    break;
+                }
+                TimerRequestHandleResult::Io { input } => {
+                    let output = datagram_io(&socket, input);
+                    arg = Some(TimerRequestHandleArg::Socket(output));
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    let output = time_handle(input).unwrap();
+                    arg = Some(TimerRequestHandleArg::Time(output));
+                }
+                TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+            }
+        }
+    }
+
+    events
+}
+
+#[test]
+fn requests_and_responses_fit_in_one_datagram() {
+    let (client_socket, server_socket) = pair();
+    let timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let server = thread::spawn(move || handle(server_socket, timer, 2));
+    let started = send(&client_socket, TimerRequest::Start);
+    let got = send(&client_socket, TimerRequest::Get);
+    let events = server.join().unwrap();
+
+    assert!(matches!(started, TimerResponse::Events(e) if e.len() == 2));
+    assert!(matches!(got, TimerResponse::Snapshot(t) if t.started_at.is_some()));
+    assert_eq!(events.len(), 2);
+}
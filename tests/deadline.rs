@@ -0,0 +1,114 @@
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use io_time::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::TimerRequestSend,
+        deadline::{TimeDeadline, TimeDeadlineArg, TimeDeadlineError, TimeDeadlineInput},
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    io::{TimeInput, TimeOutput},
+    runtimes::std::{handle as time_handle, now},
+    timer::{Timer, TimerConfig, TimerCycle, TimerCycles, TimerLoop, TimerResponse},
+};
+
+use common::socket_io;
+
+fn handle(mut stream: UnixStream) {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut server = TimerRequestHandle::new();
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        match server.resume(&mut timer, arg.take()) {
+            TimerRequestHandleResult::Ok { .. } => return,
+            TimerRequestHandleResult::Io { input } => {
+                arg = Some(TimerRequestHandleArg::Socket(socket_io(&mut stream, input)));
+            }
+            TimerRequestHandleResult::TimeIo { input } => {
+                arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+            }
+            TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+            TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+        }
+    }
+}
+
+#[test]
+fn request_completed_before_deadline() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || handle(server_stream));
+
+    let TimeOutput::Now { secs, .. } = now().unwrap() else {
+        unreachable!()
+    };
+    let mut client = TimeDeadline::new(TimerRequestSend::get(), secs + 60);
+    let mut arg = None;
+
+    let response = loop {
+        match Coroutine::resume(&mut client, arg.take()) {
+            CoroutineResult::Ok(response) => break response,
+            CoroutineResult::Io(TimeDeadlineInput::Time(input)) => {
+                arg = Some(TimeDeadlineArg::Time(time_handle(input).unwrap()));
+            }
+            CoroutineResult::Io(TimeDeadlineInput::Socket { input, timeout }) => {
+                assert!(timeout <= 60);
+                arg = Some(TimeDeadlineArg::Socket(socket_io(
+                    &mut client_stream,
+                    input,
+                )));
+            }
+            CoroutineResult::Err(err) => panic!("client error: {err}"),
+        }
+    };
+    server.join().unwrap();
+
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+}
+
+#[test]
+fn deadline_reached_before_io() {
+    let mut client = TimeDeadline::new(TimerRequestSend::get(), 100);
+
+    let CoroutineResult::Io(TimeDeadlineInput::Time(TimeInput::Now)) =
+        Coroutine::resume(&mut client, None)
+    else {
+        panic!("expected time I/O");
+    };
+
+    let arg = TimeDeadlineArg::Time(TimeOutput::Now {
+        secs: 100,
+        nanos: 0,
+    });
+    let CoroutineResult::Err(err) = Coroutine::resume(&mut client, Some(arg)) else {
+        panic!("expected timeout");
+    };
+
+    assert!(matches!(err, TimeDeadlineError::Timeout(100)));
+}
+
+#[test]
+fn socket_io_timed_out() {
+    let mut client = TimeDeadline::new(TimerRequestSend::get(), 100);
+    Coroutine::resume(&mut client, None);
+
+    let arg = TimeDeadlineArg::Time(TimeOutput::Now { secs: 90, nanos: 0 });
+    let CoroutineResult::Io(TimeDeadlineInput::Socket { timeout, .. }) =
+        Coroutine::resume(&mut client, Some(arg))
+    else {
+        panic!("expected socket I/O");
+    };
+    assert_eq!(timeout, 10);
+
+    let CoroutineResult::Err(err) = Coroutine::resume(&mut client, Some(TimeDeadlineArg::TimedOut))
+    else {
+        panic!("expected timeout");
+    };
+
+    assert!(matches!(err, TimeDeadlineError::Timeout(100)));
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use io_socket::io::SocketInput;
+
+use io_time::{
+    coroutines::{
+        history::{TimerHistoryFetch, TimerHistoryFetchResult},
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    runtimes::std::handle as time_handle,
+    timer::{Timer, TimerConfig, TimerCycle, TimerCycles, TimerEvent, TimerLoop},
+};
+
+use common::socket_io;
+
+fn handle(stream: &mut UnixStream, server: &mut TimerRequestHandle, timer: &mut Timer) {
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        match server.resume(timer, arg.take()) {
+            TimerRequestHandleResult::Ok { .. } => return,
+            TimerRequestHandleResult::Io { input } => {
+                arg = Some(TimerRequestHandleArg::Socket(socket_io(stream, input)));
+            }
+            TimerRequestHandleResult::TimeIo { input } => {
+                arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+            }
+            TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+            TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+        }
+    }
+}
+
+#[test]
+fn fetch_follows_history_cursors() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut timer = Timer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+            cycles_count: TimerLoop::Infinite,
+        });
+        for at in 0..5 {
+            timer.history.push(at, TimerEvent::Started);
+        }
+        let mut server = TimerRequestHandle::new();
+
+        // events 1 to 4 fit in 2 pages of 2 events
+        handle(&mut server_stream, &mut server, &mut timer);
+        handle(&mut server_stream, &mut server, &mut timer);
+    });
+
+    let mut client = TimerHistoryFetch::new(Some(1)).with_page_size(2);
+    let mut arg = None;
+
+    let events = loop {
+        match client.resume(arg.take()) {
+            TimerHistoryFetchResult::Ok { events } => break events,
+            TimerHistoryFetchResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input));
+            }
+            TimerHistoryFetchResult::Err { err } => panic!("client error: {err}"),
+        }
+    };
+    server.join().unwrap();
+
+    let seqs: Vec<u64> = events.iter().map(|event| event.seq).collect();
+    assert_eq!(seqs, [1, 2, 3, 4]);
+}
+
+#[test]
+fn streamed_history_fetched_with_single_request() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut timer = Timer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+            cycles_count: TimerLoop::Infinite,
+        });
+        for at in 0..250 {
+            timer.history.push(at, TimerEvent::Started);
+        }
+        let mut server = TimerRequestHandle::new();
+
+        // the 3 pages are written before the request terminates
+        handle(&mut server_stream, &mut server, &mut timer);
+        server.metrics().total_requests()
+    });
+
+    let mut client = TimerHistoryFetch::new(Some(10)).streamed();
+    let mut arg = None;
+
+    let events = loop {
+        match client.resume(arg.take()) {
+            TimerHistoryFetchResult::Ok { events } => break events,
+            TimerHistoryFetchResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input));
+            }
+            TimerHistoryFetchResult::Err { err } => panic!("client error: {err}"),
+        }
+    };
+
+    assert_eq!(server.join().unwrap(), 1);
+    let seqs: Vec<u64> = events.iter().map(|event| event.seq).collect();
+    assert_eq!(seqs, (10..250).collect::<Vec<_>>());
+}
+
+#[test]
+fn streamed_history_pages_built_once_previous_written() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut timer = Timer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+            cycles_count: TimerLoop::Infinite,
+        });
+        for at in 0..250 {
+            timer.history.push(at, TimerEvent::Started);
+        }
+        let mut server = TimerRequestHandle::new();
+        let mut arg: Option<TimerRequestHandleArg> = None;
+        let mut written = false;
+
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => return,
+                TimerRequestHandleResult::Io { input } => {
+                    let write = matches!(input, SocketInput::Write { .. });
+                    arg = Some(TimerRequestHandleArg::Socket(socket_io(
+                        &mut server_stream,
+                        input,
+                    )));
+
+                    // events pushed after the first page is written
+                    // are streamed with the next pages
+                    if write && !written {
+                        written = true;
+                        for at in 250..300 {
+                            timer.history.push(at, TimerEvent::Started);
+                        }
+                    }
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+                }
+                TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+            }
+        }
+    });
+
+    let mut client = TimerHistoryFetch::new(Some(10)).streamed();
+    let mut arg = None;
+
+    let events = loop {
+        match client.resume(arg.take()) {
+            TimerHistoryFetchResult::Ok { events } => break events,
+            TimerHistoryFetchResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input));
+            }
+            TimerHistoryFetchResult::Err { err } => panic!("client error: {err}"),
+        }
+    };
+    server.join().unwrap();
+
+    let seqs: Vec<u64> = events.iter().map(|event| event.seq).collect();
+    assert_eq!(seqs, (10..300).collect::<Vec<_>>());
+}
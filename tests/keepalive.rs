@@ -0,0 +1,125 @@
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use io_time::{
+    coroutines::{
+        keepalive::{
+            TimerKeepalive, TimerKeepaliveArg, TimerKeepaliveError, TimerKeepaliveInput,
+            TimerKeepaliveResult,
+        },
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    io::{TimeInput, TimeOutput},
+    runtimes::std::handle as time_handle,
+    session::Keepalive,
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerEnvelope, TimerLoop, TimerRequest,
+        TimerResponse,
+    },
+};
+
+use common::socket_io;
+
+fn serve(mut stream: UnixStream, requests: usize) {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut server = TimerRequestHandle::new();
+
+    for _ in 0..requests {
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => break,
+                TimerRequestHandleResult::Io { input } => {
+                    arg = Some(TimerRequestHandleArg::Socket(socket_io(&mut stream, input)));
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+                }
+                TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+            }
+        }
+    }
+}
+
+#[test]
+fn heartbeats_interleaved_with_requests() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || serve(server_stream, 3));
+
+    let keepalive = Keepalive {
+        interval: 5,
+        timeout: 15,
+    };
+    let mut client = TimerKeepalive::new(keepalive);
+    let mut sleeps = 0;
+    let mut arg = None;
+
+    // the first two idle waits elapse, the third one is interrupted
+    let response = loop {
+        match client.resume(arg.take()) {
+            TimerKeepaliveResult::Ok { response } => break response,
+            TimerKeepaliveResult::Io {
+                input: TimerKeepaliveInput::Time(TimeInput::Sleep { secs: 5 }),
+            } if sleeps < 2 => {
+                sleeps += 1;
+                arg = Some(TimerKeepaliveArg::Time(TimeOutput::Slept));
+            }
+            TimerKeepaliveResult::Io {
+                input: TimerKeepaliveInput::Time(TimeInput::Sleep { secs: 5 }),
+            } => {
+                let envelope = TimerEnvelope::new(TimerRequest::Get);
+                arg = Some(TimerKeepaliveArg::Request(envelope));
+            }
+            TimerKeepaliveResult::Io {
+                input: TimerKeepaliveInput::Socket { input, timeout: 15 },
+            } => {
+                arg = Some(TimerKeepaliveArg::Socket(socket_io(
+                    &mut client_stream,
+                    input,
+                )))
+            }
+            other => panic!("unexpected keepalive result: {other:?}"),
+        }
+    };
+    server.join().unwrap();
+
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+    assert!(matches!(
+        client.resume(None),
+        TimerKeepaliveResult::Io {
+            input: TimerKeepaliveInput::Time(TimeInput::Sleep { secs: 5 })
+        }
+    ));
+}
+
+#[test]
+fn dead_server_detected_on_heartbeat() {
+    let keepalive = Keepalive::from_timeout(30);
+    let mut client = TimerKeepalive::new(keepalive);
+
+    let TimerKeepaliveResult::Io {
+        input: TimerKeepaliveInput::Time(TimeInput::Sleep { secs: 10 }),
+    } = client.resume(None)
+    else {
+        panic!("expected idle wait");
+    };
+
+    let TimerKeepaliveResult::Io {
+        input: TimerKeepaliveInput::Socket { timeout: 30, .. },
+    } = client.resume(Some(TimerKeepaliveArg::Time(TimeOutput::Slept)))
+    else {
+        panic!("expected heartbeat");
+    };
+
+    let TimerKeepaliveResult::Err { err } = client.resume(Some(TimerKeepaliveArg::TimedOut)) else {
+        panic!("expected timeout");
+    };
+
+    assert!(matches!(err, TimerKeepaliveError::Timeout(30)));
+}
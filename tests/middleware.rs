@@ -0,0 +1,110 @@
+mod common;
+
+use std::{
+    os::unix::net::UnixStream,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use io_time::{
+    client::std::run,
+    coroutines::{
+        client::TimerRequestSend,
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    middleware::TimerMiddleware,
+    runtimes::std::handle as time_handle,
+    session::Session,
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerEnvelope, TimerError, TimerLoop,
+        TimerRequest, TimerResponse, TimerState,
+    },
+};
+use serde_json::json;
+
+use common::socket_io;
+
+/// Records the name of every answered request.
+#[derive(Debug)]
+struct Log(Arc<Mutex<Vec<String>>>);
+
+impl TimerMiddleware for Log {
+    fn after(&mut self, _: &Session, envelope: &TimerEnvelope, response: &mut TimerResponse) {
+        let entry = format!("{:?} -> {response:?}", envelope.request);
+        self.0.lock().unwrap().push(entry);
+    }
+}
+
+/// Forbids stopping the timer, and answers the `echo` extension.
+#[derive(Debug)]
+struct Guard;
+
+impl TimerMiddleware for Guard {
+    fn before(&mut self, _: &Session, envelope: &TimerEnvelope) -> Option<TimerResponse> {
+        match &envelope.request {
+            TimerRequest::Stop => Some(TimerResponse::Err(TimerError::Forbidden)),
+            TimerRequest::Extension { name, payload } if name == "echo" => {
+                Some(TimerResponse::Extension(payload.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn middlewares_wrap_and_replace_handlers() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+
+    let middleware = Log(log.clone());
+    let server = thread::spawn(move || {
+        let mut timer = Timer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+            cycles_count: TimerLoop::Infinite,
+        });
+        let mut handle = TimerRequestHandle::new()
+            .with_middleware(middleware)
+            .with_middleware(Guard);
+        let mut arg: Option<TimerRequestHandleArg> = None;
+        let mut handled = 0;
+
+        while handled < 3 {
+            match handle.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => handled += 1,
+                TimerRequestHandleResult::Io { input } => {
+                    arg = Some(socket_io(&mut server, input).into());
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    arg = Some(time_handle(input).unwrap().into());
+                }
+                TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+            }
+        }
+
+        timer
+    });
+
+    let started = run(&mut client, TimerRequestSend::start()).unwrap();
+    let stopped = run(&mut client, TimerRequestSend::stop()).unwrap();
+    let echo = TimerRequestSend::new(TimerRequest::Extension {
+        name: "echo".to_string(),
+        payload: json!({ "hello": "world" }),
+    });
+    let echoed = run(&mut client, echo).unwrap();
+
+    let timer = server.join().unwrap();
+
+    assert!(matches!(started, TimerResponse::Events(_)));
+    assert_eq!(stopped, TimerResponse::Err(TimerError::Forbidden));
+    assert_eq!(
+        echoed,
+        TimerResponse::Extension(json!({ "hello": "world" }))
+    );
+    assert_eq!(timer.state, TimerState::Running);
+
+    // the outer middleware sees the responses of the inner one
+    let log = log.lock().unwrap();
+    assert_eq!(log.len(), 3);
+    assert_eq!(log[1], "Stop -> Err(Forbidden)");
+}
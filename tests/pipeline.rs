@@ -0,0 +1,92 @@
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use io_time::{
+    coroutines::{
+        pipeline::{TimerRequestPipeline, TimerRequestPipelineResult},
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    runtimes::std::handle as time_handle,
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerLoop, TimerRequest, TimerResponse,
+        TimerState,
+    },
+};
+
+use common::socket_io;
+
+fn handle(mut stream: UnixStream, requests: usize) {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut server = TimerRequestHandle::new();
+
+    for _ in 0..requests {
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => break,
+                TimerRequestHandleResult::Io { input } => {
+                    arg = Some(TimerRequestHandleArg::Socket(socket_io(&mut stream, input)));
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+                }
+                TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+            }
+        }
+    }
+}
+
+fn send(stream: &mut UnixStream, mut client: TimerRequestPipeline) -> Vec<TimerResponse> {
+    let mut arg = None;
+
+    loop {
+        match client.resume(arg.take()) {
+            TimerRequestPipelineResult::Ok { responses } => return responses,
+            TimerRequestPipelineResult::Io { input } => arg = Some(socket_io(stream, input)),
+            TimerRequestPipelineResult::Err { err } => panic!("client error: {err}"),
+        }
+    }
+}
+
+#[test]
+fn responses_received_in_order() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || handle(server_stream, 4));
+
+    let client = TimerRequestPipeline::new([
+        TimerRequest::Start,
+        TimerRequest::Pause,
+        TimerRequest::SetRemaining(60),
+        TimerRequest::Get,
+    ]);
+    let responses = send(&mut client_stream, client);
+    server.join().unwrap();
+
+    assert_eq!(responses.len(), 4);
+    assert!(matches!(responses[0], TimerResponse::Events(_)));
+    assert!(matches!(responses[1], TimerResponse::Events(_)));
+    assert!(matches!(responses[2], TimerResponse::Events(_)));
+    match &responses[3] {
+        TimerResponse::Snapshot(snapshot) => {
+            assert_eq!(snapshot.state, TimerState::Paused);
+            assert_eq!(snapshot.remaining, 60);
+        }
+        other => panic!("expected snapshot, got {other:?}"),
+    }
+}
+
+#[test]
+fn empty_pipeline() {
+    let (mut client_stream, _) = UnixStream::pair().unwrap();
+    let responses = send(
+        &mut client_stream,
+        TimerRequestPipeline::new(Vec::<TimerRequest>::new()),
+    );
+    assert!(responses.is_empty());
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use io_time::{
+    coroutines::{
+        poll::{TimerPoll, TimerPollArg, TimerPollInput, TimerPollResult},
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    io::{TimeInput, TimeOutput},
+    runtimes::std::handle as time_handle,
+    timer::{Timer, TimerConfig, TimerCycle, TimerCycles, TimerLoop, TimerState},
+};
+
+use common::socket_io;
+
+fn handle(stream: &mut UnixStream, server: &mut TimerRequestHandle, timer: &mut Timer) {
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        match server.resume(timer, arg.take()) {
+            TimerRequestHandleResult::Ok { .. } => return,
+            TimerRequestHandleResult::Io { input } => {
+                arg = Some(TimerRequestHandleArg::Socket(socket_io(stream, input)));
+            }
+            TimerRequestHandleResult::TimeIo { input } => {
+                arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+            }
+            TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+            TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+        }
+    }
+}
+
+#[test]
+fn poll_returns_once_timer_changed() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut timer = Timer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+            cycles_count: TimerLoop::Infinite,
+        });
+        let mut server = TimerRequestHandle::new();
+
+        // the timer starts after the second poll
+        handle(&mut server_stream, &mut server, &mut timer);
+        handle(&mut server_stream, &mut server, &mut timer);
+        timer.start(0).into_iter().for_each(drop);
+        handle(&mut server_stream, &mut server, &mut timer);
+    });
+
+    let mut client = TimerPoll::new().with_interval(5);
+    let mut sleeps = 0;
+    let mut arg = None;
+
+    let snapshot = loop {
+        match client.resume(arg.take()) {
+            TimerPollResult::Ok { snapshot } => break snapshot,
+            TimerPollResult::Io {
+                input: TimerPollInput::Socket(input),
+            } => arg = Some(TimerPollArg::Socket(socket_io(&mut client_stream, input))),
+            TimerPollResult::Io {
+                input: TimerPollInput::Time(TimeInput::Sleep { secs: 5 }),
+            } => {
+                sleeps += 1;
+                arg = Some(TimerPollArg::Time(TimeOutput::Slept));
+            }
+            other => panic!("unexpected poll result: {other:?}"),
+        }
+    };
+    server.join().unwrap();
+
+    assert_eq!(snapshot.state, TimerState::Running);
+    assert_eq!(sleeps, 2);
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use io_time::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        flush::TimerQueueFlush,
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    queue::TimerRequestQueue,
+    runtimes::std::handle as time_handle,
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerLoop, TimerRequest, TimerResponse,
+        TimerState,
+    },
+};
+
+use common::socket_io;
+
+fn serve(mut stream: UnixStream, requests: usize) -> Timer {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut server = TimerRequestHandle::new();
+
+    for _ in 0..requests {
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => break,
+                TimerRequestHandleResult::Io { input } => {
+                    arg = Some(TimerRequestHandleArg::Socket(socket_io(&mut stream, input)));
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+                }
+                TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+            }
+        }
+    }
+
+    timer
+}
+
+#[test]
+fn queued_requests_flushed_once_connected() {
+    let mut queue = TimerRequestQueue::new("gui");
+    queue.push(TimerRequest::Start).unwrap();
+    queue.push(TimerRequest::Pause).unwrap();
+
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || serve(server_stream, 2));
+
+    let mut flush = TimerQueueFlush::new();
+    let mut coroutine = flush.bind(&mut queue);
+    let mut arg = None;
+
+    let responses = loop {
+        match coroutine.resume(arg.take()) {
+            CoroutineResult::Ok(responses) => break responses,
+            CoroutineResult::Io(input) => arg = Some(socket_io(&mut client_stream, input)),
+            CoroutineResult::Err(err) => panic!("flush error: {err}"),
+        }
+    };
+    let timer = server.join().unwrap();
+
+    assert!(queue.is_empty());
+    assert_eq!(responses.len(), 2);
+    assert!(matches!(responses[0], TimerResponse::Events(_)));
+    assert_eq!(timer.state, TimerState::Paused);
+}
+
+#[test]
+fn requests_stay_queued_after_connection_failure() {
+    let mut queue = TimerRequestQueue::new("gui");
+    queue.push(TimerRequest::Start).unwrap();
+
+    // the server closes the connection without answering
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    drop(server_stream);
+
+    let mut flush = TimerQueueFlush::new();
+    let mut coroutine = flush.bind(&mut queue);
+    let mut arg = None;
+
+    let result = loop {
+        match coroutine.resume(arg.take()) {
+            CoroutineResult::Io(SocketInput::Write { buf }) => {
+                let n = buf.len();
+                arg = Some(SocketOutput::Wrote { buf, n });
+            }
+            CoroutineResult::Io(input) => arg = Some(socket_io(&mut client_stream, input)),
+            result => break result,
+        }
+    };
+
+    assert!(matches!(result, CoroutineResult::Err(_)));
+    assert_eq!(queue.len(), 1);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use io_time::{
+    coroutines::{
+        raw::{TimerFrameSend, TimerFrameSendResult},
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    runtimes::std::handle as time_handle,
+    session::Session,
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerEnvelope, TimerLoop, TimerRequest,
+        TimerResponse, TimerState,
+    },
+};
+
+use common::socket_io;
+
+fn handle(mut stream: UnixStream, requests: usize) {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut server = TimerRequestHandle::new();
+
+    for _ in 0..requests {
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => break,
+                TimerRequestHandleResult::Io { input } => {
+                    arg = Some(TimerRequestHandleArg::Socket(socket_io(&mut stream, input)));
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+                }
+                TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+            }
+        }
+    }
+}
+
+fn encode(session: &Session, request: TimerRequest) -> Vec<u8> {
+    let bytes = session.codec.encode(&TimerEnvelope::new(request)).unwrap();
+    let bytes = session.checksum.append(bytes);
+    session.framing().encode(bytes)
+}
+
+fn decode(session: &Session, frame: Vec<u8>) -> TimerResponse {
+    let bytes = session.checksum.verify(frame).unwrap();
+    let bytes = session.compression.decompress(bytes).unwrap();
+    session.codec.decode(&bytes).unwrap()
+}
+
+fn send(stream: &mut UnixStream, client: &mut TimerFrameSend) -> Vec<u8> {
+    let mut arg = None;
+
+    loop {
+        match client.resume(arg.take()) {
+            TimerFrameSendResult::Ok { frame } => return frame,
+            TimerFrameSendResult::Io { input } => arg = Some(socket_io(stream, input)),
+            TimerFrameSendResult::Err { err } => panic!("client error: {err}"),
+        }
+    }
+}
+
+#[test]
+fn raw_frames_forwarded() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || handle(server_stream, 2));
+    let session = Session::default();
+
+    let mut client = TimerFrameSend::new(encode(&session, TimerRequest::Start));
+    let frame = send(&mut client_stream, &mut client);
+    assert!(matches!(decode(&session, frame), TimerResponse::Events(_)));
+
+    client.send(encode(&session, TimerRequest::Get));
+    let frame = send(&mut client_stream, &mut client);
+    let TimerResponse::Snapshot(snapshot) = decode(&session, frame) else {
+        panic!("expected timer snapshot");
+    };
+    assert_eq!(snapshot.state, TimerState::Running);
+
+    server.join().unwrap();
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use std::{io::Write, os::unix::net::UnixStream, thread};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use io_time::{
+    coroutines::{
+        client::TimerRequestSend,
+        reconnect::{
+            TimerRequestReconnect, TimerRequestReconnectArg, TimerRequestReconnectError,
+            TimerRequestReconnectInput, TimerRequestReconnectResult,
+        },
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    io::{TimeInput, TimeOutput},
+    runtimes::std::handle as time_handle,
+    timer::{Timer, TimerConfig, TimerCycle, TimerCycles, TimerLoop, TimerResponse},
+};
+
+use common::socket_io;
+
+fn handle(mut stream: UnixStream) {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut server = TimerRequestHandle::new();
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        match server.resume(&mut timer, arg.take()) {
+            TimerRequestHandleResult::Ok { .. } => return,
+            TimerRequestHandleResult::Io { input } => {
+                arg = Some(TimerRequestHandleArg::Socket(socket_io(&mut stream, input)));
+            }
+            TimerRequestHandleResult::TimeIo { input } => {
+                arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+            }
+            TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+            TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+        }
+    }
+}
+
+/// Opens a new connection whose server side is either dropped right
+/// away, or handles one request.
+fn connect(dead: bool) -> UnixStream {
+    let (client_stream, server_stream) = UnixStream::pair().unwrap();
+
+    if dead {
+        drop(server_stream);
+    } else {
+        thread::spawn(move || handle(server_stream));
+    }
+
+    client_stream
+}
+
+/// Drives the coroutine over connections dropped by the server, until
+/// the given number of them, then over working connections.
+fn send(
+    client: &mut TimerRequestReconnect,
+    dead: usize,
+) -> Result<TimerResponse, TimerRequestReconnectError> {
+    let mut connections = 1;
+    let mut stream = connect(dead > 0);
+    let mut arg = None;
+
+    loop {
+        match client.resume(arg.take()) {
+            TimerRequestReconnectResult::Ok { response } => return Ok(response),
+            TimerRequestReconnectResult::Io { input } => {
+                arg = Some(match input {
+                    TimerRequestReconnectInput::Socket(SocketInput::Write { buf }) => {
+                        match stream.write(&buf) {
+                            Ok(n) => {
+                                TimerRequestReconnectArg::Socket(SocketOutput::Wrote { buf, n })
+                            }
+                            Err(_) => TimerRequestReconnectArg::SocketFailed,
+                        }
+                    }
+                    TimerRequestReconnectInput::Socket(input) => {
+                        TimerRequestReconnectArg::Socket(socket_io(&mut stream, input))
+                    }
+                    TimerRequestReconnectInput::Time(input) => {
+                        TimerRequestReconnectArg::Time(time_handle(input).unwrap())
+                    }
+                    TimerRequestReconnectInput::Reconnect => {
+                        stream = connect(connections < dead);
+                        connections += 1;
+                        TimerRequestReconnectArg::Reconnected
+                    }
+                });
+            }
+            TimerRequestReconnectResult::Err { err } => return Err(err),
+        }
+    }
+}
+
+#[test]
+fn request_replayed_after_reconnection() {
+    let mut client = TimerRequestReconnect::new(TimerRequestSend::start()).with_backoff(0, 0);
+    let response = send(&mut client, 2).unwrap();
+
+    assert!(matches!(response, TimerResponse::Events(_)));
+    assert_eq!(client.retries(), 2);
+}
+
+#[test]
+fn retries_exhausted() {
+    let mut client = TimerRequestReconnect::new(TimerRequestSend::get())
+        .with_max_retries(1)
+        .with_backoff(0, 0);
+    let err = send(&mut client, 3).unwrap_err();
+
+    assert!(matches!(
+        err,
+        TimerRequestReconnectError::RetriesExhausted(1)
+    ));
+}
+
+#[test]
+fn backoff_doubles_up_to_max() {
+    let mut client = TimerRequestReconnect::new(TimerRequestSend::get()).with_backoff(2, 5);
+    let mut sleeps = Vec::new();
+    let mut arg = None;
+
+    while sleeps.len() < 3 {
+        arg = Some(match client.resume(arg.take()) {
+            TimerRequestReconnectResult::Io {
+                input: TimerRequestReconnectInput::Socket(_),
+            } => TimerRequestReconnectArg::SocketFailed,
+            TimerRequestReconnectResult::Io {
+                input: TimerRequestReconnectInput::Time(TimeInput::Sleep { secs }),
+            } => {
+                sleeps.push(secs);
+                TimerRequestReconnectArg::Time(TimeOutput::Slept)
+            }
+            TimerRequestReconnectResult::Io {
+                input: TimerRequestReconnectInput::Reconnect,
+            } => TimerRequestReconnectArg::Reconnected,
+            other => panic!("unexpected result {other:?}"),
+        });
+    }
+
+    assert_eq!(sleeps, [2, 4, 5]);
+}
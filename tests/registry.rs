@@ -0,0 +1,409 @@
+mod common;
+
+use std::{
+    collections::BTreeMap,
+    os::unix::net::UnixStream,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use io_socket::io::{SocketInput, SocketOutput};
+use io_time::{
+    coroutines::{
+        Coroutine, CoroutineResult,
+        client::{TimerRequestSend, TimerRequestSendResult},
+        registry::{TimerCreate, TimerDelete, TimerList, TimerRegistryRequestError},
+        request::TimerStatsExport,
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    notify::{NotifiedStore, TimerNotification, TimerNotificationSink},
+    registry::{TimerRegistry, TimerStore, WILDCARD_TIMER},
+    runtimes::std::handle as time_handle,
+    stats::MAX_EXPORT_PAGE_SIZE,
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerError, TimerLoop, TimerResponse,
+        TimerSnapshot, TimerState, TimerStatus,
+    },
+};
+use serde_json::{Value, json};
+
+use common::socket_io;
+
+fn send(stream: &mut UnixStream, mut client: TimerRequestSend) -> TimerResponse {
+    let mut arg = None;
+
+    loop {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { response } => return response,
+            TimerRequestSendResult::Io { input } => arg = Some(socket_io(stream, input)),
+            TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
+        }
+    }
+}
+
+fn run<C>(stream: &mut UnixStream, mut coroutine: C) -> Result<C::Ok, C::Error>
+where
+    C: Coroutine<Input = SocketInput, Output = SocketOutput>,
+{
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            CoroutineResult::Ok(ok) => return Ok(ok),
+            CoroutineResult::Io(input) => arg = Some(socket_io(stream, input)),
+            CoroutineResult::Err(err) => return Err(err),
+        }
+    }
+}
+
+fn serve<S: TimerStore>(mut stream: UnixStream, mut timers: S, requests: usize) -> S {
+    let mut server = TimerRequestHandle::new();
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    for _ in 0..requests {
+        loop {
+            match server.resume(&mut timers, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => break,
+                TimerRequestHandleResult::Io { input } => {
+                    let output = socket_io(&mut stream, input);
+                    arg = Some(TimerRequestHandleArg::Socket(output));
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    let output = time_handle(input).unwrap();
+                    arg = Some(TimerRequestHandleArg::Time(output));
+                }
+                TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+            }
+        }
+    }
+
+    timers
+}
+
+fn test_registry() -> TimerRegistry {
+    let timer = |name: &str, duration| {
+        Timer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new(name, duration)]),
+            cycles_count: TimerLoop::Infinite,
+        })
+    };
+
+    TimerRegistry::from_iter([
+        ("default", timer("Focus", 1500)),
+        ("tea", timer("Steep", 180)),
+    ])
+}
+
+#[test]
+fn list_returns_every_timer_status() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || serve(server_stream, test_registry(), 1));
+    let response = send(&mut client_stream, TimerRequestSend::list());
+    server.join().unwrap();
+
+    let TimerResponse::List(timers) = response else {
+        panic!("expected List, got {response:?}");
+    };
+
+    assert_eq!(timers.len(), 2);
+    assert_eq!(timers["default"].cycle, "Focus");
+    assert_eq!(timers["tea"].cycle, "Steep");
+    assert_eq!(timers["tea"].remaining, 180);
+    assert_eq!(timers["tea"].state, TimerState::Stopped);
+}
+
+#[test]
+fn requests_target_named_timer() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || serve(server_stream, test_registry(), 2));
+    let started = send(
+        &mut client_stream,
+        TimerRequestSend::start().with_timer("tea"),
+    );
+    let unknown = send(
+        &mut client_stream,
+        TimerRequestSend::get().with_timer("coffee"),
+    );
+    let registry = server.join().unwrap();
+
+    assert!(matches!(started, TimerResponse::Events(_)));
+    assert_eq!(
+        unknown,
+        TimerResponse::Err(TimerError::UnknownTimer("coffee".into()))
+    );
+    assert_eq!(registry.get("tea").unwrap().state, TimerState::Running);
+    assert_eq!(registry.get("default").unwrap().state, TimerState::Stopped);
+}
+
+#[test]
+fn stats_exported_across_pages() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+
+    let mut registry = test_registry();
+    for i in 0..MAX_EXPORT_PAGE_SIZE {
+        let timer = registry.get("tea").unwrap().clone();
+        registry.insert(format!("tea-{i:02}"), timer);
+    }
+
+    // the 2 pages are written before the request terminates
+    let server = thread::spawn(move || serve(server_stream, registry, 1));
+    let stats = run(&mut client_stream, TimerStatsExport::new()).unwrap();
+    server.join().unwrap();
+
+    assert_eq!(stats.len(), MAX_EXPORT_PAGE_SIZE + 2);
+    assert!(stats.contains_key("default"));
+    assert!(stats.contains_key("tea"));
+    assert!(stats.contains_key("tea-15"));
+}
+
+#[test]
+fn create_and_delete_timers() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let config = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Boil", 300)]),
+        cycles_count: TimerLoop::Fixed(1),
+    };
+
+    let server = thread::spawn(move || serve(server_stream, test_registry(), 4));
+    let created = send(
+        &mut client_stream,
+        TimerRequestSend::create("egg", config.clone()),
+    );
+    let duplicate = send(&mut client_stream, TimerRequestSend::create("tea", config));
+    let deleted = send(&mut client_stream, TimerRequestSend::delete("tea"));
+    let unknown = send(&mut client_stream, TimerRequestSend::delete("tea"));
+    let registry = server.join().unwrap();
+
+    assert_eq!(created, TimerResponse::Ok);
+    assert_eq!(
+        duplicate,
+        TimerResponse::Err(TimerError::TimerExists("tea".into()))
+    );
+    assert_eq!(deleted, TimerResponse::Ok);
+    assert_eq!(
+        unknown,
+        TimerResponse::Err(TimerError::UnknownTimer("tea".into()))
+    );
+    assert_eq!(registry.get("egg").unwrap().cycle.name, "Boil");
+    assert!(!registry.contains("tea"));
+}
+
+#[test]
+fn wildcard_timer_name_rejected() {
+    let mut registry = test_registry();
+    let config = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Boil", 300)]),
+        cycles_count: TimerLoop::Fixed(1),
+    };
+
+    let err = registry.create(WILDCARD_TIMER, config).unwrap_err();
+
+    assert!(matches!(err, TimerError::InvalidConfig(_)));
+    assert!(!registry.contains(WILDCARD_TIMER));
+}
+
+/// A store creating timers without checking their configuration.
+struct TrustingStore(TimerRegistry);
+
+impl TimerStore for TrustingStore {
+    const MULTI_TIMER: bool = true;
+
+    fn get_mut(&mut self, name: Option<&str>) -> Option<&mut Timer> {
+        self.0.get_mut(name)
+    }
+
+    fn list(&self) -> BTreeMap<String, TimerStatus> {
+        self.0.list()
+    }
+
+    fn create(&mut self, name: &str, config: TimerConfig) -> Result<(), TimerError> {
+        self.0.insert(name, Timer::new(config));
+        Ok(())
+    }
+}
+
+#[test]
+fn invalid_configs_rejected() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let empty = TimerConfig::default();
+    let instant = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Boil", 0)]),
+        cycles_count: TimerLoop::Infinite,
+    };
+    let never = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Boil", 300)]),
+        cycles_count: TimerLoop::Fixed(0),
+    };
+
+    let store = TrustingStore(test_registry());
+    let server = thread::spawn(move || serve(server_stream, store, 3));
+    let responses = [
+        send(&mut client_stream, TimerRequestSend::create("egg", empty)),
+        send(&mut client_stream, TimerRequestSend::create("egg", instant)),
+        send(&mut client_stream, TimerRequestSend::set_config(never)),
+    ];
+    let store = server.join().unwrap();
+
+    for response in responses {
+        assert!(matches!(
+            response,
+            TimerResponse::Err(TimerError::InvalidConfig(_))
+        ));
+    }
+    assert!(!store.0.contains("egg"));
+}
+
+#[test]
+fn registry_coroutines_manage_timers() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let config = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Boil", 300)]),
+        cycles_count: TimerLoop::Fixed(1),
+    };
+
+    let server = thread::spawn(move || serve(server_stream, test_registry(), 4));
+    run(&mut client_stream, TimerCreate::new("egg", config)).unwrap();
+    run(&mut client_stream, TimerDelete::new("tea")).unwrap();
+    let unknown = run(&mut client_stream, TimerDelete::new("tea")).unwrap_err();
+    let timers = run(&mut client_stream, TimerList::new()).unwrap();
+    server.join().unwrap();
+
+    assert!(matches!(
+        unknown,
+        TimerRegistryRequestError::Rejected(TimerError::UnknownTimer(_))
+    ));
+    assert_eq!(timers.keys().collect::<Vec<_>>(), ["default", "egg"]);
+    assert_eq!(timers["egg"].cycle, "Boil");
+}
+
+/// A store extending the protocol with a `count` request, returning
+/// the number of timers.
+struct CountingStore(TimerRegistry);
+
+impl TimerStore for CountingStore {
+    const MULTI_TIMER: bool = true;
+
+    fn get_mut(&mut self, name: Option<&str>) -> Option<&mut Timer> {
+        self.0.get_mut(name)
+    }
+
+    fn list(&self) -> BTreeMap<String, TimerStatus> {
+        self.0.list()
+    }
+
+    fn extension(
+        &mut self,
+        _timer: Option<&str>,
+        name: &str,
+        _payload: &Value,
+    ) -> Result<Value, TimerError> {
+        match name {
+            "count" => Ok(json!(self.0.len())),
+            _ => Err(TimerError::Unsupported),
+        }
+    }
+}
+
+#[test]
+fn extension_requests_handled_by_store() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let store = CountingStore(test_registry());
+
+    let server = thread::spawn(move || serve(server_stream, store, 2));
+    let count = send(
+        &mut client_stream,
+        TimerRequestSend::extension("count", Value::Null),
+    );
+    let unknown = send(
+        &mut client_stream,
+        TimerRequestSend::extension("reset", Value::Null),
+    );
+    server.join().unwrap();
+
+    assert_eq!(count, TimerResponse::Extension(json!(2)));
+    assert_eq!(unknown, TimerResponse::Err(TimerError::Unsupported));
+}
+
+/// A store recording the timers to persist, with their new state.
+struct PersistedStore(TimerRegistry, Vec<(String, Option<TimerState>)>);
+
+impl TimerStore for PersistedStore {
+    const MULTI_TIMER: bool = true;
+
+    fn get_mut(&mut self, name: Option<&str>) -> Option<&mut Timer> {
+        self.0.get_mut(name)
+    }
+
+    fn list(&self) -> BTreeMap<String, TimerStatus> {
+        self.0.list()
+    }
+
+    fn changed(&mut self, name: &str, snapshot: Option<&TimerSnapshot>) {
+        let state = snapshot.map(|snapshot| snapshot.state.clone());
+        self.1.push((name.to_string(), state));
+    }
+}
+
+#[test]
+fn store_notified_of_changed_timers() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let store = PersistedStore(test_registry(), Vec::new());
+
+    let server = thread::spawn(move || serve(server_stream, store, 4));
+    send(&mut client_stream, TimerRequestSend::start());
+    send(&mut client_stream, TimerRequestSend::get());
+    send(
+        &mut client_stream,
+        TimerRequestSend::start().with_timer("tea"),
+    );
+    // already running, nothing changes
+    send(&mut client_stream, TimerRequestSend::start());
+    let store = server.join().unwrap();
+
+    assert_eq!(
+        store.1,
+        [
+            ("default".to_string(), Some(TimerState::Running)),
+            ("tea".to_string(), Some(TimerState::Running)),
+        ]
+    );
+}
+
+/// A sink recording the notified timers, with their new state.
+#[derive(Debug, Default)]
+struct Notifications(Vec<(String, TimerState)>);
+
+impl TimerNotificationSink for Notifications {
+    fn notify(&mut self, timer: &str, notification: TimerNotification) {
+        let TimerNotification::StateChanged(snapshot) = notification;
+        self.0.push((timer.to_string(), snapshot.state));
+    }
+}
+
+#[test]
+fn notified_store_forwards_changes_to_sink() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let notifications = Arc::new(Mutex::new(Notifications::default()));
+    let store = PersistedStore(test_registry(), Vec::new());
+    let store = NotifiedStore::new(store).with_sink(notifications.clone());
+
+    let server = thread::spawn(move || serve(server_stream, store, 3));
+    send(&mut client_stream, TimerRequestSend::start());
+    send(&mut client_stream, TimerRequestSend::get());
+    send(
+        &mut client_stream,
+        TimerRequestSend::pause().with_timer("tea"),
+    );
+    let store = server.join().unwrap();
+
+    // both the store and the sink are notified of the start
+    let changes = [("default".to_string(), TimerState::Running)];
+    assert_eq!(notifications.lock().unwrap().0, changes);
+    assert_eq!(
+        store.into_inner().1,
+        [("default".to_string(), Some(TimerState::Running))]
+    );
+}
@@ -0,0 +1,157 @@
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use io_socket::io::SocketInput;
+use io_time::{
+    coroutines::{
+        serve::{TimerServe, TimerServeResult},
+        server::TimerRequestHandleArg,
+        stream::{TimerEventStream, TimerEventStreamResult},
+    },
+    history::TimerHistoryEntry,
+    runtimes::std::handle,
+    timer::{Timer, TimerConfig, TimerCycle, TimerCycles, TimerEvent, TimerLoop},
+};
+
+use common::socket_io;
+
+#[test]
+fn events_pushed_between_responses() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+
+    let subscriber = thread::spawn(move || {
+        let mut stream = TimerEventStream::new(None);
+        let mut events = Vec::new();
+        let mut arg = None;
+
+        while events.len() < 2 {
+            match stream.resume(arg.take()) {
+                TimerEventStreamResult::Event { event, .. } => events.push(event.event),
+                TimerEventStreamResult::Io { input } => arg = Some(socket_io(&mut client, input)),
+                other => panic!("unexpected stream result: {other:?}"),
+            }
+        }
+
+        events
+    });
+
+    let mut serve = TimerServe::default();
+    let entries = [
+        TimerHistoryEntry {
+            seq: 0,
+            at: 1,
+            event: TimerEvent::Started,
+        },
+        TimerHistoryEntry {
+            seq: 1,
+            at: 1,
+            event: TimerEvent::Began(TimerCycle::new("Focus", 1500)),
+        },
+    ];
+
+    // events are not queued before the subscription
+    assert_eq!(serve.publish("default", entries.clone()), 0);
+
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        match serve.resume(&mut timer, arg.take()) {
+            TimerServeResult::Ok { .. } => break,
+            TimerServeResult::Io { input } => arg = Some(socket_io(&mut server, input).into()),
+            TimerServeResult::TimeIo { input } => arg = Some(handle(input).unwrap().into()),
+            TimerServeResult::Disconnected => panic!("client disconnected"),
+            TimerServeResult::Err { err } => panic!("serve error: {err}"),
+        }
+    }
+
+    assert_eq!(serve.publish("other", entries.clone()), 0);
+    assert_eq!(serve.publish("default", entries), 2);
+
+    // queued events are pushed before the next request is read
+    loop {
+        match serve.resume(&mut timer, arg.take()) {
+            TimerServeResult::Io {
+                input: SocketInput::Read { .. },
+            } => break,
+            TimerServeResult::Io { input } => arg = Some(socket_io(&mut server, input).into()),
+            other => panic!("unexpected serve result: {other:?}"),
+        }
+    }
+
+    assert_eq!(serve.pending(), 0);
+
+    let events = subscriber.join().unwrap();
+    assert_eq!(events[0], TimerEvent::Started);
+    assert!(matches!(events[1], TimerEvent::Began(_)));
+}
+
+#[test]
+fn slow_subscriber_lagged() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+
+    let subscriber = thread::spawn(move || {
+        let mut stream = TimerEventStream::new(None);
+        let mut events = Vec::new();
+        let mut arg = None;
+
+        while events.len() < 2 {
+            match stream.resume(arg.take()) {
+                TimerEventStreamResult::Event { event, .. } => events.push(event.event),
+                TimerEventStreamResult::Io { input } => arg = Some(socket_io(&mut client, input)),
+                other => panic!("unexpected stream result: {other:?}"),
+            }
+        }
+
+        events
+    });
+
+    let mut serve = TimerServe::default().with_capacity(1);
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        match serve.resume(&mut timer, arg.take()) {
+            TimerServeResult::Ok { .. } => break,
+            TimerServeResult::Io { input } => arg = Some(socket_io(&mut server, input).into()),
+            TimerServeResult::TimeIo { input } => arg = Some(handle(input).unwrap().into()),
+            TimerServeResult::Disconnected => panic!("client disconnected"),
+            TimerServeResult::Err { err } => panic!("serve error: {err}"),
+        }
+    }
+
+    let entries = (0..3).map(|seq| TimerHistoryEntry {
+        seq,
+        at: 1,
+        event: TimerEvent::Started,
+    });
+    assert_eq!(serve.publish("default", entries), 3);
+
+    // the marker replaces the dropped events
+    assert_eq!(serve.pending(), 2);
+
+    loop {
+        match serve.resume(&mut timer, arg.take()) {
+            TimerServeResult::Io {
+                input: SocketInput::Read { .. },
+            } => break,
+            TimerServeResult::Io { input } => arg = Some(socket_io(&mut server, input).into()),
+            other => panic!("unexpected serve result: {other:?}"),
+        }
+    }
+
+    let events = subscriber.join().unwrap();
+    assert_eq!(
+        events,
+        [TimerEvent::Lagged { missed: 2 }, TimerEvent::Started]
+    );
+}
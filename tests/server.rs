@@ -0,0 +1,290 @@
+#![cfg(feature = "std-runtime")]
+
+mod common;
+
+use std::{
+    env,
+    net::{TcpListener, TcpStream},
+    os::{
+        fd::{FromRawFd, IntoRawFd},
+        unix::net::{UnixListener, UnixStream},
+    },
+    sync::mpsc::{self, Sender},
+    thread,
+    time::Duration,
+};
+
+use io_time::{
+    broadcast::TimerEventBroadcast,
+    client::std::{StdClient, run},
+    coroutines::{
+        client::TimerRequestSend,
+        stream::{TimerEventStream, TimerEventStreamResult},
+    },
+    history::TimerHistory,
+    mirror::TimerMirror,
+    notify::{TimerNotification, TimerNotificationSink},
+    registry::{DEFAULT_TIMER, TimerRegistry},
+    server::{
+        activation::ActivatedListener,
+        std::{StdServeOptions, StdServer, serve},
+    },
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerEvent, TimerLoop, TimerRequest,
+        TimerState,
+    },
+};
+
+use common::socket_io;
+
+#[test]
+fn std_server_pushes_events_to_subscribers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    thread::spawn(move || serve(listener, timer, StdServeOptions::new()));
+
+    let mut subscriber = TcpStream::connect(addr).unwrap();
+    run(&mut subscriber, TimerRequestSend::subscribe(None)).unwrap();
+
+    let mut client = StdClient::connect_tcp(addr).unwrap();
+    client.start().unwrap();
+
+    let mut stream = TimerEventStream::new(None);
+    let mut events = Vec::new();
+    let mut arg = None;
+
+    while events.len() < 2 {
+        match stream.resume(arg.take()) {
+            TimerEventStreamResult::Event { event, .. } => events.push(event.event),
+            TimerEventStreamResult::Io { input } => arg = Some(socket_io(&mut subscriber, input)),
+            other => panic!("unexpected stream result: {other:?}"),
+        }
+    }
+
+    assert_eq!(events[0], TimerEvent::Started);
+    assert!(matches!(events[1], TimerEvent::Began(_)));
+}
+
+#[derive(Debug)]
+struct Notifications(Sender<(String, TimerNotification)>);
+
+impl TimerNotificationSink for Notifications {
+    fn notify(&mut self, timer: &str, notification: TimerNotification) {
+        self.0.send((timer.to_string(), notification)).unwrap();
+    }
+}
+
+#[test]
+fn std_server_notifies_state_changes() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let (sender, receiver) = mpsc::channel();
+    let options = StdServeOptions::new().with_notifications(Notifications(sender));
+    thread::spawn(move || serve(listener, timer, options));
+
+    let mut client = StdClient::connect_tcp(addr).unwrap();
+    client.get().unwrap();
+    client.start().unwrap();
+    client.pause().unwrap();
+
+    let timeout = Duration::from_secs(5);
+    let mut states = Vec::new();
+
+    for _ in 0..2 {
+        let (name, notification) = receiver.recv_timeout(timeout).unwrap();
+        let TimerNotification::StateChanged(snapshot) = notification;
+        assert_eq!(name, DEFAULT_TIMER);
+        states.push(snapshot.state);
+    }
+
+    // reading the timer does not change its state
+    assert_eq!(states, [TimerState::Running, TimerState::Paused]);
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn std_server_shares_timers_between_listeners() {
+    let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let path = env::temp_dir().join(format!("io-time-server-{}.sock", std::process::id()));
+    let unix = UnixListener::bind(&path).unwrap();
+    let timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let server = StdServer::new(timer, StdServeOptions::new());
+    server.spawn(tcp);
+    server.spawn(unix);
+
+    // subscribed over the Unix socket, started over TCP
+    let mut subscriber = UnixStream::connect(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    run(&mut subscriber, TimerRequestSend::subscribe(None)).unwrap();
+
+    let mut client = StdClient::connect_tcp(addr).unwrap();
+    client.start().unwrap();
+
+    let mut stream = TimerEventStream::new(None);
+    let mut arg = None;
+
+    let event = loop {
+        match stream.resume(arg.take()) {
+            TimerEventStreamResult::Event { event, .. } => break event.event,
+            TimerEventStreamResult::Io { input } => arg = Some(socket_io(&mut subscriber, input)),
+            other => panic!("unexpected stream result: {other:?}"),
+        }
+    };
+
+    assert_eq!(event, TimerEvent::Started);
+}
+
+#[test]
+fn std_server_history_retention_tuned_while_serving() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    timer.history = TimerHistory::new(8);
+
+    let server = StdServer::new(timer, StdServeOptions::new());
+    server.spawn(listener);
+
+    let mut client = StdClient::connect_tcp(addr).unwrap();
+    client.start().unwrap();
+    client.pause().unwrap();
+
+    assert_eq!(server.with_history(None, |history| history.len()), Some(3));
+    assert_eq!(
+        server.with_history(Some("missing"), |history| history.len()),
+        None
+    );
+
+    server.with_history(None, |history| history.set_capacity(1));
+    assert_eq!(server.with_history(None, |history| history.len()), Some(1));
+
+    server.with_history(None, TimerHistory::clear);
+    client.resume().unwrap();
+
+    let entries = server.with_history(None, |history| history.page(None, None).events);
+    let entries = entries.unwrap();
+    assert_eq!(entries.len(), 1);
+    assert!(matches!(entries[0].event, TimerEvent::Resumed(_)));
+}
+
+#[test]
+fn std_server_publishes_recreated_timers_from_start() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    };
+    let timers = TimerRegistry::from_iter([("work", Timer::new(config.clone()))]);
+
+    thread::spawn(move || serve(listener, timers, StdServeOptions::new()));
+
+    let mut subscriber = TcpStream::connect(addr).unwrap();
+    run(
+        &mut subscriber,
+        TimerRequestSend::subscribe(None).with_timer("work"),
+    )
+    .unwrap();
+
+    let mut client = StdClient::connect_tcp(addr).unwrap().with_timer("work");
+    client.start().unwrap();
+    client.pause().unwrap();
+
+    // the timer is deleted, then created again
+    let name = String::from("work");
+    client
+        .send(TimerRequest::Delete { name: name.clone() })
+        .unwrap();
+    client.send(TimerRequest::Create { name, config }).unwrap();
+    client.start().unwrap();
+
+    let mut stream = TimerEventStream::new(None).with_timer("work");
+    let mut events = Vec::new();
+    let mut arg = None;
+
+    while events.len() < 5 {
+        match stream.resume(arg.take()) {
+            TimerEventStreamResult::Event { event, .. } => events.push(event),
+            TimerEventStreamResult::Io { input } => arg = Some(socket_io(&mut subscriber, input)),
+            other => panic!("unexpected stream result: {other:?}"),
+        }
+    }
+
+    assert_eq!(events[3].seq, 0);
+    assert_eq!(events[3].event, TimerEvent::Started);
+    assert_eq!(events[4].seq, 1);
+}
+
+#[test]
+fn std_server_pushes_deltas_with_broadcast() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    };
+    let timer = Timer::new(config.clone());
+
+    let broadcast = TimerEventBroadcast::new().with_deltas();
+    let options = StdServeOptions::new().with_broadcast(broadcast);
+    thread::spawn(move || serve(listener, timer, options));
+
+    let mut subscriber = TcpStream::connect(addr).unwrap();
+    run(&mut subscriber, TimerRequestSend::subscribe(None)).unwrap();
+
+    let mut client = StdClient::connect_tcp(addr).unwrap();
+    client.start().unwrap();
+
+    let mut stream = TimerEventStream::new(None);
+    let mut mirror = TimerMirror::new(config);
+    let mut arg = None;
+
+    let delta = loop {
+        match stream.resume(arg.take()) {
+            TimerEventStreamResult::Delta { delta, .. } => break delta,
+            TimerEventStreamResult::Io { input } => arg = Some(socket_io(&mut subscriber, input)),
+            other => panic!("unexpected stream result: {other:?}"),
+        }
+    };
+
+    assert!(delta.keyframe);
+    mirror.apply_delta(&delta).unwrap();
+    assert_eq!(mirror.timer().state, TimerState::Running);
+}
+
+#[test]
+fn activated_listeners_detected_from_fd() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let fd = listener.into_raw_fd();
+
+    match unsafe { ActivatedListener::from_raw_fd(fd) } {
+        ActivatedListener::Tcp(listener) => assert_eq!(listener.local_addr().unwrap(), addr),
+        other => panic!("unexpected listener: {other:?}"),
+    }
+
+    let path = env::temp_dir().join(format!("io-time-activation-{}.sock", std::process::id()));
+    let listener = UnixListener::bind(&path).unwrap();
+    let fd = listener.into_raw_fd();
+    let listener = unsafe { ActivatedListener::from_raw_fd(fd) };
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(listener, ActivatedListener::Unix(_)));
+}
@@ -0,0 +1,432 @@
+mod common;
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    os::unix::net::UnixStream,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use io_time::{
+    auth::{TimerAuthToken, TimerAuthenticator},
+    checksum::Checksum,
+    compression::Compression,
+    coroutines::{
+        accept::{TimerAccept, TimerAcceptError, TimerAcceptResult},
+        client::{TimerRequestSend, TimerRequestSendResult},
+        connect::{TimerConnect, TimerConnectError, TimerConnectResult},
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    frame::Framing,
+    runtimes::std::handle as time_handle,
+    session::{ClientHello, Keepalive, Permission, ServerHello, Session},
+    timer::{
+        PROTOCOL_VERSION, Timer, TimerConfig, TimerCycle, TimerCycles, TimerEnvelope, TimerError,
+        TimerLoop, TimerRequest, TimerResponse,
+    },
+};
+
+use common::socket_io;
+
+fn connect(stream: &mut UnixStream, hello: ClientHello) -> Session {
+    let mut client = TimerConnect::new(hello);
+    let mut arg = None;
+
+    loop {
+        match client.resume(arg.take()) {
+            TimerConnectResult::Ok { session } => return session,
+            TimerConnectResult::Io { input } => arg = Some(socket_io(stream, input)),
+            TimerConnectResult::Err { err } => panic!("connect error: {err}"),
+        }
+    }
+}
+
+fn accept(stream: &mut UnixStream, mut server: TimerAccept) -> Session {
+    let mut arg = None;
+
+    loop {
+        match server.resume(arg.take()) {
+            TimerAcceptResult::Ok { session } => return session,
+            TimerAcceptResult::Io { input } => arg = Some(socket_io(stream, input)),
+            TimerAcceptResult::Err { err } => panic!("accept error: {err}"),
+        }
+    }
+}
+
+fn serve(mut stream: UnixStream, server: TimerAccept) -> Session {
+    let session = accept(&mut stream, server);
+    handle(&mut stream, session, 1);
+    session
+}
+
+/// Handles the given number of requests using the given session.
+fn handle(stream: &mut UnixStream, session: Session, requests: usize) {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    let mut handler = TimerRequestHandle::new().with_session(session);
+
+    for _ in 0..requests {
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        loop {
+            match handler.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => break,
+                TimerRequestHandleResult::Io { input } => {
+                    arg = Some(TimerRequestHandleArg::Socket(socket_io(stream, input)));
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+                }
+                other => panic!("unexpected server result: {other:?}"),
+            }
+        }
+    }
+}
+
+/// Encodes the given response as sent by the server.
+fn frame(session: Session, response: &TimerResponse) -> Vec<u8> {
+    let bytes = session.codec.encode(response).unwrap();
+    let bytes = session.checksum.append(bytes);
+    session.framing().encode(bytes)
+}
+
+fn get(stream: &mut UnixStream, session: Session) -> TimerResponse {
+    let mut client = TimerRequestSend::new(TimerRequest::Get).with_session(session);
+    let mut arg = None;
+
+    loop {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { response } => return response,
+            TimerRequestSendResult::Io { input } => arg = Some(socket_io(stream, input)),
+            TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
+        }
+    }
+}
+
+#[test]
+fn handshake_negotiates_supported_compression() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || serve(server_stream, TimerAccept::new()));
+    let session = connect(&mut client_stream, ClientHello::default());
+    let response = get(&mut client_stream, session);
+
+    assert_eq!(session, server.join().unwrap());
+    assert_eq!(session.compression, Compression::supported()[0]);
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+}
+
+#[test]
+fn handshake_respects_server_preferences() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let accept = TimerAccept::new().with_compressions([Compression::None]);
+
+    let server = thread::spawn(move || serve(server_stream, accept));
+    let session = connect(&mut client_stream, ClientHello::default());
+    let response = get(&mut client_stream, session);
+
+    assert_eq!(session, server.join().unwrap());
+    assert_eq!(session, Session::default());
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+fn handshake_negotiates_postcard_codec() {
+    use io_time::codec::Codec;
+
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let hello = ClientHello {
+        protocol: PROTOCOL_VERSION,
+        codecs: vec![Codec::Postcard, Codec::Json],
+        compressions: vec![Compression::None],
+        checksums: vec![],
+        keepalive: None,
+        armored: false,
+        permission: Default::default(),
+        token: None,
+    };
+
+    let server = thread::spawn(move || serve(server_stream, TimerAccept::new()));
+    let session = connect(&mut client_stream, hello);
+    let response = get(&mut client_stream, session);
+
+    assert_eq!(session, server.join().unwrap());
+    assert_eq!(session.codec, Codec::Postcard);
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+}
+
+#[test]
+fn handshake_negotiates_crc32_checksum() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let hello = ClientHello {
+        checksums: vec![Checksum::Crc32],
+        ..Default::default()
+    };
+
+    let server = thread::spawn(move || serve(server_stream, TimerAccept::new()));
+    let session = connect(&mut client_stream, hello);
+    let response = get(&mut client_stream, session);
+
+    assert_eq!(session, server.join().unwrap());
+    assert_eq!(session.checksum, Checksum::Crc32);
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+}
+
+#[test]
+fn corrupted_frame_is_answered() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+    let session = Session {
+        checksum: Checksum::Crc32,
+        ..Default::default()
+    };
+
+    let server = thread::spawn(move || handle(&mut server_stream, session, 2));
+
+    let envelope = TimerEnvelope::from(TimerRequest::Get);
+    let mut bytes = session.codec.encode(&envelope).unwrap();
+    bytes = session.checksum.append(bytes);
+    // flip one bit of the payload
+    bytes[1] ^= 1;
+    client_stream
+        .write_all(&session.framing().encode(bytes))
+        .unwrap();
+
+    let mut buf = [0; 512];
+    let n = client_stream.read(&mut buf).unwrap();
+    let malformed = TimerResponse::Err(TimerError::MalformedRequest);
+    assert_eq!(&buf[..n], frame(session, &malformed));
+
+    // the connection is still usable
+    let response = get(&mut client_stream, session);
+    server.join().unwrap();
+
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+}
+
+#[test]
+fn handshake_negotiates_armored_framing() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let hello = ClientHello {
+        checksums: vec![Checksum::Crc32],
+        armored: true,
+        ..Default::default()
+    };
+
+    let server = thread::spawn(move || serve(server_stream, TimerAccept::new()));
+    let session = connect(&mut client_stream, hello);
+    let response = get(&mut client_stream, session);
+
+    assert_eq!(session, server.join().unwrap());
+    assert!(session.armored);
+    assert_eq!(session.framing(), Framing::Armored);
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+}
+
+#[test]
+fn mangled_armored_frame_is_answered() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+    let session = Session {
+        armored: true,
+        ..Default::default()
+    };
+
+    let server = thread::spawn(move || handle(&mut server_stream, session, 2));
+
+    client_stream.write_all(b"not*base64\n").unwrap();
+    let mut buf = [0; 512];
+    let n = client_stream.read(&mut buf).unwrap();
+    let malformed = TimerResponse::Err(TimerError::MalformedRequest);
+    assert_eq!(&buf[..n], frame(session, &malformed));
+
+    // the connection is still usable
+    let response = get(&mut client_stream, session);
+    server.join().unwrap();
+
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+}
+
+#[test]
+fn handshake_rejects_other_protocol_revision() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut hello = [0; 1024];
+        let n = server_stream.read(&mut hello).unwrap();
+        assert!(n > 0);
+
+        let hello = ServerHello {
+            protocol: PROTOCOL_VERSION + 1,
+            ..Default::default()
+        };
+        let mut bytes = serde_json::to_vec(&hello).unwrap();
+        bytes.push(b'\n');
+        server_stream.write_all(&bytes).unwrap();
+    });
+
+    let mut client = TimerConnect::new(ClientHello::default());
+    let mut arg = None;
+
+    let err = loop {
+        match client.resume(arg.take()) {
+            TimerConnectResult::Ok { session } => panic!("unexpected session {session:?}"),
+            TimerConnectResult::Io { input } => arg = Some(socket_io(&mut client_stream, input)),
+            TimerConnectResult::Err { err } => break err,
+        }
+    };
+
+    server.join().unwrap();
+    assert!(matches!(
+        err,
+        TimerConnectError::UnsupportedProtocol(v) if v == PROTOCOL_VERSION + 1
+    ));
+}
+
+#[test]
+fn observer_connection_cannot_control_timer() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let accept = TimerAccept::new().with_permission(Permission::Observer);
+
+    let server = thread::spawn(move || serve(server_stream, accept));
+    let session = connect(&mut client_stream, ClientHello::default());
+    let mut client = TimerRequestSend::start().with_session(session);
+    let mut arg = None;
+
+    let response = loop {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { response } => break response,
+            TimerRequestSendResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input));
+            }
+            TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
+        }
+    };
+
+    assert_eq!(session, server.join().unwrap());
+    assert_eq!(session.permission, Permission::Observer);
+    assert_eq!(response, TimerResponse::Err(TimerError::Forbidden));
+}
+
+#[derive(Debug)]
+struct Tokens;
+
+impl TimerAuthenticator for Tokens {
+    fn authenticate(&self, token: Option<&TimerAuthToken>) -> Option<Permission> {
+        match token?.expose() {
+            "admin" => Some(Permission::Controller),
+            "guest" => Some(Permission::Observer),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn authenticator_grants_permission() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let accept = TimerAccept::new().with_authenticator(Arc::new(Tokens));
+    let hello = ClientHello {
+        token: Some(TimerAuthToken::new("guest")),
+        ..Default::default()
+    };
+
+    let server = thread::spawn(move || serve(server_stream, accept));
+    let session = connect(&mut client_stream, hello);
+    get(&mut client_stream, session);
+
+    assert_eq!(session, server.join().unwrap());
+    assert_eq!(session.permission, Permission::Observer);
+}
+
+#[test]
+fn authenticator_rejects_unknown_clients() {
+    for token in [None, Some(TimerAuthToken::new("intruder"))] {
+        let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+        let accept = TimerAccept::new().with_authenticator(Arc::new(Tokens));
+        let hello = ClientHello {
+            token,
+            ..Default::default()
+        };
+
+        let server = thread::spawn(move || {
+            let mut accept = accept;
+            let mut arg = None;
+
+            loop {
+                match accept.resume(arg.take()) {
+                    TimerAcceptResult::Io { input } => {
+                        arg = Some(socket_io(&mut server_stream, input));
+                    }
+                    TimerAcceptResult::Err { err } => break err,
+                    other => panic!("unexpected accept result: {other:?}"),
+                }
+            }
+        });
+
+        let mut client = TimerConnect::new(hello);
+        let mut arg = None;
+
+        // the server closes the connection without answering
+        let err = loop {
+            match client.resume(arg.take()) {
+                TimerConnectResult::Io { input } => {
+                    arg = Some(socket_io(&mut client_stream, input));
+                }
+                TimerConnectResult::Err { err } => break err,
+                other => panic!("unexpected connect result: {other:?}"),
+            }
+        };
+
+        let rejected = server.join().unwrap();
+        assert!(matches!(rejected, TimerAcceptError::Unauthenticated));
+        assert!(matches!(err, TimerConnectError::ReadEof));
+    }
+}
+
+#[test]
+fn auth_token_never_printed() {
+    let token = TimerAuthToken::new("secret");
+    assert!(!format!("{token:?}").contains("secret"));
+}
+
+#[test]
+fn idle_connection_closed_after_keepalive_timeout() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+    let hello = ClientHello {
+        keepalive: Some(Keepalive {
+            interval: 10,
+            timeout: 60,
+        }),
+        ..Default::default()
+    };
+
+    let server = thread::spawn(move || {
+        let session = accept(&mut server_stream, TimerAccept::new().with_idle_timeout(1));
+        let timeout = session.keepalive.unwrap().timeout;
+        server_stream
+            .set_read_timeout(Some(Duration::from_secs(timeout)))
+            .unwrap();
+
+        // the client sends nothing: the read times out
+        let err = server_stream.read(&mut [0; 16]).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::WouldBlock | ErrorKind::TimedOut
+        ));
+        session
+    });
+
+    let session = connect(&mut client_stream, hello);
+    assert_eq!(session, server.join().unwrap());
+    assert_eq!(
+        session.keepalive,
+        Some(Keepalive {
+            interval: 1,
+            timeout: 1
+        })
+    );
+    assert_eq!(client_stream.read(&mut [0; 16]).unwrap(), 0);
+}
@@ -0,0 +1,120 @@
+#![cfg(feature = "signing")]
+
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use io_time::{
+    coroutines::{
+        client::{TimerRequestSend, TimerRequestSendResult},
+        server::{
+            TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleError,
+            TimerRequestHandleResult,
+        },
+    },
+    runtimes::std::handle as time_handle,
+    session::Session,
+    signing::SigningKey,
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerError, TimerLoop, TimerRequest,
+        TimerResponse,
+    },
+};
+
+use common::socket_io;
+
+fn session(secret: u8) -> Session {
+    Session {
+        signing_key: Some(SigningKey::new([secret; 32])),
+        ..Default::default()
+    }
+}
+
+/// Handles the given number of requests, returning the server error
+/// if any.
+fn handle(
+    mut stream: UnixStream,
+    session: Session,
+    requests: usize,
+) -> Option<TimerRequestHandleError> {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut server = TimerRequestHandle::new().with_session(session);
+
+    for _ in 0..requests {
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => break,
+                TimerRequestHandleResult::Io { input } => {
+                    arg = Some(TimerRequestHandleArg::Socket(socket_io(&mut stream, input)));
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+                }
+                TimerRequestHandleResult::Disconnected => return None,
+                TimerRequestHandleResult::Err { err } => return Some(err),
+            }
+        }
+    }
+
+    None
+}
+
+fn send(stream: &mut UnixStream, session: Session) -> Option<TimerResponse> {
+    let mut client = TimerRequestSend::new(TimerRequest::Ping).with_session(session);
+    let mut arg = None;
+
+    loop {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { response } => return Some(response),
+            TimerRequestSendResult::Io { input } => arg = Some(socket_io(stream, input)),
+            TimerRequestSendResult::Err { .. } => return None,
+        }
+    }
+}
+
+#[test]
+fn signed_request_accepted() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || handle(server_stream, session(1), 1));
+
+    assert_eq!(
+        send(&mut client_stream, session(1)),
+        Some(TimerResponse::Pong)
+    );
+    assert!(server.join().unwrap().is_none());
+}
+
+#[test]
+fn forged_request_rejected() {
+    for client_session in [session(2), Session::default()] {
+        let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || handle(server_stream, session(1), 1));
+
+        assert_eq!(
+            send(&mut client_stream, client_session),
+            Some(TimerResponse::Err(TimerError::MalformedRequest))
+        );
+        assert!(server.join().unwrap().is_none());
+    }
+}
+
+#[test]
+fn session_survives_forged_request() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || handle(server_stream, session(1), 2));
+
+    assert_eq!(
+        send(&mut client_stream, session(2)),
+        Some(TimerResponse::Err(TimerError::MalformedRequest))
+    );
+    assert_eq!(
+        send(&mut client_stream, session(1)),
+        Some(TimerResponse::Pong)
+    );
+    assert!(server.join().unwrap().is_none());
+}
@@ -0,0 +1,216 @@
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use io_time::{
+    client::{
+        ClientError,
+        std::{StdClient, run},
+    },
+    coroutines::request::{TimerCommand, TimerConfigSet, TimerGet, TimerGroupControl, TimerResync},
+    coroutines::server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    runtimes::std::handle as time_handle,
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerError, TimerLoop, TimerRequest,
+        TimerState,
+    },
+};
+
+use common::socket_io;
+
+/// Serves requests until the client closes the connection.
+fn serve(mut stream: UnixStream) {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut server = TimerRequestHandle::new();
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        match server.resume(&mut timer, arg.take()) {
+            TimerRequestHandleResult::Ok { .. } => (),
+            TimerRequestHandleResult::Io { input } => {
+                arg = Some(TimerRequestHandleArg::Socket(socket_io(&mut stream, input)));
+            }
+            TimerRequestHandleResult::TimeIo { input } => {
+                arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+            }
+            TimerRequestHandleResult::Disconnected | TimerRequestHandleResult::Err { .. } => return,
+        }
+    }
+}
+
+#[test]
+fn blocking_client_methods() {
+    let (client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || serve(server_stream));
+
+    let mut client = StdClient::new(client_stream);
+    client.ping().unwrap();
+
+    let events = client.start().unwrap();
+    assert!(!events.is_empty());
+    assert_eq!(client.get().unwrap().state, TimerState::Running);
+
+    assert!(client.activity().unwrap().is_empty());
+    assert_eq!(client.toggle().unwrap(), TimerState::Paused);
+    client.set_remaining(60).unwrap();
+    assert_eq!(client.status().unwrap().text, "Focus 01:00 ⏸");
+
+    let stats = client.stats().unwrap();
+    assert_eq!(stats.pauses, 1);
+    assert_eq!(stats.completed_cycles, 0);
+
+    assert!(!client.reset().unwrap().is_empty());
+    assert_eq!(client.get().unwrap().state, TimerState::Stopped);
+
+    let mut client = client.with_timer("tea");
+    let err = client.get().unwrap_err();
+    assert!(matches!(
+        err,
+        ClientError::Rejected(TimerError::UnknownTimer(_))
+    ));
+
+    drop(client);
+    server.join().unwrap();
+}
+
+#[test]
+fn run_drives_coroutines() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || serve(server_stream));
+
+    let events = run(&mut client_stream, TimerCommand::start()).unwrap();
+    assert!(!events.is_empty());
+
+    let snapshot = run(&mut client_stream, TimerGet::new()).unwrap();
+    assert_eq!(snapshot.state, TimerState::Running);
+
+    let err = run(&mut client_stream, TimerGet::new().with_timer("tea")).unwrap_err();
+    assert!(matches!(
+        err,
+        ClientError::Rejected(TimerError::UnknownTimer(_))
+    ));
+
+    drop(client_stream);
+    server.join().unwrap();
+}
+
+#[test]
+fn resync_returns_fresh_timer_after_mutation() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || serve(server_stream));
+
+    let snapshot = run(&mut client_stream, TimerResync::new(TimerRequest::Start)).unwrap();
+    assert_eq!(snapshot.state, TimerState::Running);
+
+    let snapshot = run(&mut client_stream, TimerResync::new(TimerRequest::Pause)).unwrap();
+    assert_eq!(snapshot.state, TimerState::Paused);
+
+    let resync = TimerResync::new(TimerRequest::Resume).with_timer("tea");
+    let err = run(&mut client_stream, resync).unwrap_err();
+    assert!(matches!(
+        err,
+        ClientError::Rejected(TimerError::UnknownTimer(_))
+    ));
+
+    drop(client_stream);
+    server.join().unwrap();
+}
+
+#[test]
+fn config_set_validated_then_applied() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || serve(server_stream));
+
+    let err = run(
+        &mut client_stream,
+        TimerConfigSet::new(TimerConfig::default()),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ClientError::Rejected(TimerError::InvalidConfig(_))
+    ));
+
+    let config = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Tea", 180)]),
+        cycles_count: TimerLoop::Fixed(1),
+    };
+    run(&mut client_stream, TimerConfigSet::new(config)).unwrap();
+
+    let snapshot = run(&mut client_stream, TimerGet::new()).unwrap();
+    assert_eq!(snapshot.cycle, "Tea");
+    assert_eq!(snapshot.remaining, 180);
+
+    drop(client_stream);
+    server.join().unwrap();
+}
+
+#[test]
+fn group_control_aggregates_results() {
+    let (mut client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || serve(server_stream));
+
+    let results = run(
+        &mut client_stream,
+        TimerGroupControl::start(["default", "tea"]),
+    )
+    .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(!results["default"].as_ref().unwrap().is_empty());
+    assert!(matches!(results["tea"], Err(TimerError::UnknownTimer(_))));
+
+    let results = run(&mut client_stream, TimerGroupControl::pause(["default"])).unwrap();
+    assert!(results["default"].is_ok());
+    assert_eq!(
+        run(&mut client_stream, TimerGet::new()).unwrap().state,
+        TimerState::Paused
+    );
+
+    let empty: [&str; 0] = [];
+    let results = run(&mut client_stream, TimerGroupControl::stop(empty)).unwrap();
+    assert!(results.is_empty());
+
+    drop(client_stream);
+    server.join().unwrap();
+}
+
+#[test]
+fn batch_committed_at_once() {
+    let (client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || serve(server_stream));
+
+    let mut client = StdClient::new(client_stream);
+    let config = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Tea", 180)]),
+        cycles_count: TimerLoop::Fixed(1),
+    };
+
+    let responses = client
+        .batch()
+        .stop()
+        .set_config(config)
+        .start()
+        .commit()
+        .unwrap();
+    assert_eq!(responses.len(), 3);
+    assert_eq!(client.get().unwrap().cycle, "Tea");
+
+    let err = client
+        .batch()
+        .pause()
+        .get()
+        .with_timer("tea")
+        .get()
+        .commit();
+    assert!(matches!(
+        err,
+        Err(ClientError::Rejected(TimerError::UnknownTimer(_)))
+    ));
+    assert_eq!(client.get().unwrap().state, TimerState::Paused);
+
+    drop(client);
+    server.join().unwrap();
+}
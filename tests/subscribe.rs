@@ -0,0 +1,341 @@
+mod common;
+
+use std::{io::Read, os::unix::net::UnixStream, sync::mpsc, thread};
+
+use io_time::{
+    codec::Codec,
+    coroutines::{
+        client::{TimerRequestSend, TimerRequestSendResult},
+        push::{TimerEventPush, TimerEventPushResult},
+        server::{
+            TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult, TimerSubscription,
+        },
+        stream::{TimerEventStream, TimerEventStreamResult},
+    },
+    frame::FrameDecoder,
+    history::TimerHistoryEntry,
+    registry::{TimerRegistry, TimerStore, WILDCARD_TIMER},
+    runtimes::std::handle as time_handle,
+    timer::{
+        Timer, TimerConfig, TimerCycle, TimerCycles, TimerError, TimerEvent, TimerLoop,
+        TimerResponse,
+    },
+};
+
+use common::socket_io;
+
+fn send(stream: &mut UnixStream, mut client: TimerRequestSend) -> TimerResponse {
+    let mut arg = None;
+
+    loop {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { response } => return response,
+            TimerRequestSendResult::Io { input } => arg = Some(socket_io(stream, input)),
+            TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
+        }
+    }
+}
+
+fn handle(stream: &mut UnixStream, server: &mut TimerRequestHandle, timers: &mut impl TimerStore) {
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        match server.resume(timers, arg.take()) {
+            TimerRequestHandleResult::Ok { .. } => return,
+            TimerRequestHandleResult::Io { input } => {
+                arg = Some(TimerRequestHandleArg::Socket(socket_io(stream, input)));
+            }
+            TimerRequestHandleResult::TimeIo { input } => {
+                arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+            }
+            TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+            TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+        }
+    }
+}
+
+fn push(stream: &mut UnixStream, pusher: &mut TimerEventPush) {
+    let mut arg = None;
+
+    loop {
+        match pusher.resume(arg.take()) {
+            TimerEventPushResult::Ok => return,
+            TimerEventPushResult::Io { input } => arg = Some(socket_io(stream, input)),
+            TimerEventPushResult::Err { err } => panic!("push error: {err}"),
+        }
+    }
+}
+
+fn receive(stream: &mut UnixStream, n: usize) -> Vec<TimerResponse> {
+    let mut decoder = FrameDecoder::new();
+    let mut responses = Vec::new();
+    let mut buf = [0; 512];
+
+    while responses.len() < n {
+        match decoder.next_frame().unwrap() {
+            Some(frame) => responses.push(Codec::Json.decode(&frame).unwrap()),
+            None => {
+                let read = stream.read(&mut buf).unwrap();
+                decoder.push(&buf[..read]);
+            }
+        }
+    }
+
+    responses
+}
+
+fn test_timer() -> Timer {
+    Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    })
+}
+
+#[test]
+fn unacked_events_pushed_again_on_resubscribe() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+    let (started_tx, started_rx) = mpsc::channel();
+
+    let server = thread::spawn(move || {
+        let mut timer = test_timer();
+        let mut server = TimerRequestHandle::new();
+
+        // subscribe, then start the timer
+        handle(&mut server_stream, &mut server, &mut timer);
+        handle(&mut server_stream, &mut server, &mut timer);
+
+        let subscription = TimerSubscription {
+            timer: None,
+            id: Some("billing".into()),
+        };
+        assert_eq!(server.subscription(), Some(&subscription));
+
+        // events are pushed once the client received the response,
+        // so that they do not end up in the same read
+        started_rx.recv().unwrap();
+        let events = timer.history.page(None, None).events;
+        push(&mut server_stream, &mut TimerEventPush::new(events));
+
+        // acknowledge the first pushed event only
+        handle(&mut server_stream, &mut server, &mut timer);
+        timer
+    });
+
+    let subscribe = TimerRequestSend::subscribe(Some("billing".into()));
+    let response = send(&mut client_stream, subscribe);
+    assert_eq!(response, TimerResponse::Subscribed(vec![]));
+
+    let response = send(&mut client_stream, TimerRequestSend::start());
+    assert!(matches!(response, TimerResponse::Events(_)));
+    started_tx.send(()).unwrap();
+
+    let events: Vec<_> = receive(&mut client_stream, 2)
+        .into_iter()
+        .map(|response| match response {
+            TimerResponse::Event(event) => event,
+            other => panic!("expected pushed event, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(events[0].event, TimerEvent::Started);
+    assert_eq!(events[1].seq, 1);
+
+    let response = send(&mut client_stream, TimerRequestSend::ack(events[0].seq));
+    assert_eq!(response, TimerResponse::Ok);
+
+    let mut timer = server.join().unwrap();
+
+    // the client reconnects
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut server = TimerRequestHandle::new();
+        handle(&mut server_stream, &mut server, &mut timer);
+    });
+
+    let subscribe = TimerRequestSend::subscribe(Some("billing".into()));
+    let response = send(&mut client_stream, subscribe);
+    server.join().unwrap();
+
+    assert_eq!(response, TimerResponse::Subscribed(vec![events[1].clone()]));
+}
+
+#[test]
+fn ack_without_durable_subscription_is_rejected() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut timer = test_timer();
+        let mut server = TimerRequestHandle::new();
+        handle(&mut server_stream, &mut server, &mut timer);
+        handle(&mut server_stream, &mut server, &mut timer);
+    });
+
+    let response = send(&mut client_stream, TimerRequestSend::subscribe(None));
+    assert_eq!(response, TimerResponse::Subscribed(vec![]));
+
+    let response = send(&mut client_stream, TimerRequestSend::ack(0));
+    server.join().unwrap();
+
+    assert_eq!(response, TimerResponse::Err(TimerError::NotSubscribed));
+}
+
+#[test]
+fn subscription_to_unknown_timer_is_rejected() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut registry = TimerRegistry::from_iter([("a", test_timer()), ("b", test_timer())]);
+        let mut server = TimerRequestHandle::new();
+        for _ in 0..4 {
+            handle(&mut server_stream, &mut server, &mut registry);
+        }
+    });
+
+    let subscribe = TimerRequestSend::subscribe(Some("billing".into()));
+    let response = send(&mut client_stream, subscribe.clone().with_timer("c"));
+    assert_eq!(
+        response,
+        TimerResponse::Err(TimerError::UnknownTimer("c".into()))
+    );
+
+    let response = send(&mut client_stream, subscribe.with_timer("a"));
+    assert_eq!(response, TimerResponse::Subscribed(vec![]));
+
+    // the subscribed timer is deleted before the acknowledgement
+    let response = send(&mut client_stream, TimerRequestSend::delete("a"));
+    assert_eq!(response, TimerResponse::Ok);
+
+    let response = send(&mut client_stream, TimerRequestSend::ack(0));
+    server.join().unwrap();
+
+    assert_eq!(
+        response,
+        TimerResponse::Err(TimerError::UnknownTimer("a".into()))
+    );
+}
+
+#[test]
+fn wildcard_subscription_receives_tagged_events() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+    let (subscribed_tx, subscribed_rx) = mpsc::channel();
+
+    let server = thread::spawn(move || {
+        let mut registry = TimerRegistry::from_iter([("a", test_timer()), ("b", test_timer())]);
+        let mut server = TimerRequestHandle::new();
+        handle(&mut server_stream, &mut server, &mut registry);
+
+        let subscription = server.subscription().unwrap().clone();
+        assert!(subscription.is_wildcard());
+        subscribed_rx.recv().unwrap();
+
+        for name in ["a", "b"] {
+            assert!(subscription.matches(name));
+            let event = TimerHistoryEntry {
+                seq: 0,
+                at: 0,
+                event: TimerEvent::Started,
+            };
+            let mut pusher = TimerEventPush::new([event]).with_timer(name);
+            push(&mut server_stream, &mut pusher);
+        }
+    });
+
+    let subscribe = TimerRequestSend::subscribe(None).with_timer(WILDCARD_TIMER);
+    let response = send(&mut client_stream, subscribe);
+    assert_eq!(response, TimerResponse::Subscribed(vec![]));
+    subscribed_tx.send(()).unwrap();
+
+    let responses = receive(&mut client_stream, 2);
+    server.join().unwrap();
+
+    let timers: Vec<_> = responses
+        .into_iter()
+        .map(|response| match response {
+            TimerResponse::TaggedEvent { timer, .. } => timer,
+            other => panic!("expected tagged event, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(timers, ["a", "b"]);
+}
+
+#[test]
+fn events_pushed_as_server_sent_events() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+    let events = [
+        TimerHistoryEntry {
+            seq: 3,
+            at: 10,
+            event: TimerEvent::Started,
+        },
+        TimerHistoryEntry {
+            seq: 4,
+            at: 10,
+            event: TimerEvent::Stopped,
+        },
+    ];
+
+    let mut pusher = TimerEventPush::new(events).with_timer("tea").with_sse();
+    push(&mut server_stream, &mut pusher);
+    drop(server_stream);
+
+    let mut stream = String::new();
+    client_stream.read_to_string(&mut stream).unwrap();
+
+    assert_eq!(
+        stream,
+        concat!(
+            "id: 3\nevent: Started\n",
+            "data: {\"TaggedEvent\":{\"timer\":\"tea\",\"event\":{\"seq\":3,\"at\":10,\"event\":\"Started\"}}}\n\n",
+            "id: 4\nevent: Stopped\n",
+            "data: {\"TaggedEvent\":{\"timer\":\"tea\",\"event\":{\"seq\":4,\"at\":10,\"event\":\"Stopped\"}}}\n\n",
+        )
+    );
+}
+
+#[test]
+fn event_stream_yields_pushed_events() {
+    let (mut client_stream, mut server_stream) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut timer = test_timer();
+        let mut server = TimerRequestHandle::new();
+        handle(&mut server_stream, &mut server, &mut timer);
+
+        let events = [TimerEvent::Started, TimerEvent::Stopped]
+            .map(|event| timer.history.push(10, event))
+            .map(|seq| timer.history.page(Some(seq), Some(1)).events.remove(0));
+        push(&mut server_stream, &mut TimerEventPush::new(events));
+
+        // acknowledgement of the first event
+        handle(&mut server_stream, &mut server, &mut timer);
+        timer.history.subscribe("billing")
+    });
+
+    let mut stream = TimerEventStream::new(Some("billing".into()));
+    let mut events = Vec::new();
+    let mut arg = None;
+
+    loop {
+        match stream.resume(arg.take()) {
+            TimerEventStreamResult::Ok => break,
+            TimerEventStreamResult::Event { timer, event } => {
+                assert_eq!(timer, None);
+                if events.is_empty() {
+                    stream.ack(event.seq);
+                }
+                events.push(event.event);
+            }
+            TimerEventStreamResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input));
+            }
+            TimerEventStreamResult::Err { err } => panic!("stream error: {err}"),
+            other => panic!("unexpected stream result: {other:?}"),
+        }
+    }
+
+    let unacked = server.join().unwrap();
+
+    assert_eq!(events, [TimerEvent::Started, TimerEvent::Stopped]);
+    assert_eq!(unacked.len(), 1);
+    assert_eq!(unacked[0].event, TimerEvent::Stopped);
+}
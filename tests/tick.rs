@@ -0,0 +1,264 @@
+use std::sync::mpsc;
+
+use io_time::{
+    coroutines::tick::{TimerTick, TimerTickResult},
+    io::{TimeInput, TimeOutput},
+    notify::Notifiers,
+    registry::{TimerRegistry, TimerStore},
+    timer::{
+        PauseReason, Timer, TimerConfig, TimerCycle, TimerCycles, TimerEvent, TimerLoop, TimerState,
+    },
+};
+
+fn now(secs: u64) -> Option<TimeOutput> {
+    Some(TimeOutput::Now { secs, nanos: 0 })
+}
+
+#[test]
+fn ticks_until_cycle_end() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 3), TimerCycle::new("Break", 2)]),
+        cycles_count: TimerLoop::Fixed(1),
+    });
+    timer.start(100);
+
+    let mut tick = TimerTick::new().with_interval(10);
+
+    match tick.resume(&mut timer, None) {
+        TimerTickResult::Io {
+            input: TimeInput::Now,
+        } => {}
+        other => panic!("expected Io {{ TimeInput::Now }}, got {other:?}"),
+    }
+
+    match tick.resume(&mut timer, now(101)) {
+        TimerTickResult::Ok { at: 101, events } => {
+            let events = &events["default"];
+            assert!(matches!(events[..], [TimerEvent::Running(_)]));
+        }
+        other => panic!("expected Ok, got {other:?}"),
+    }
+
+    // sleeps until the end of the focus cycle, not the interval
+    match tick.resume(&mut timer, None) {
+        TimerTickResult::Io {
+            input: TimeInput::SleepUntil { timestamp: 103 },
+        } => {}
+        other => panic!("expected Io {{ TimeInput::SleepUntil }}, got {other:?}"),
+    }
+
+    tick.resume(&mut timer, Some(TimeOutput::Slept));
+
+    match tick.resume(&mut timer, now(103)) {
+        TimerTickResult::Ok { at: 103, events } => {
+            let events = &events["default"];
+            assert!(matches!(events[1], TimerEvent::Ended(_)));
+            assert!(matches!(events[2], TimerEvent::Began(_)));
+        }
+        other => panic!("expected Ok, got {other:?}"),
+    }
+
+    tick.resume(&mut timer, None);
+    tick.resume(&mut timer, Some(TimeOutput::Slept));
+
+    match tick.resume(&mut timer, now(105)) {
+        TimerTickResult::Ok { events, .. } => {
+            assert_eq!(events["default"].last(), Some(&TimerEvent::Stopped));
+        }
+        other => panic!("expected Ok, got {other:?}"),
+    }
+    assert_eq!(timer.state, TimerState::Stopped);
+    assert_eq!(timer.history.page(None, None).events.len(), 7);
+
+    // stopped timers are ticked silently, every interval
+    match tick.resume(&mut timer, None) {
+        TimerTickResult::Io {
+            input: TimeInput::SleepUntil { timestamp: 115 },
+        } => {}
+        other => panic!("expected Io {{ TimeInput::SleepUntil }}, got {other:?}"),
+    }
+
+    tick.resume(&mut timer, Some(TimeOutput::Slept));
+
+    match tick.resume(&mut timer, now(115)) {
+        TimerTickResult::Io {
+            input: TimeInput::SleepUntil { timestamp: 125 },
+        } => {}
+        other => panic!("expected Io {{ TimeInput::SleepUntil }}, got {other:?}"),
+    }
+}
+
+#[test]
+fn timers_ticked_at_their_own_interval() {
+    let config = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    };
+
+    let mut timers = TimerRegistry::new();
+    for name in ["fast", "slow"] {
+        let mut timer = Timer::new(config.clone());
+        timer.start(100);
+        timers.insert(name, timer);
+    }
+    timers.set_tick_interval("slow", 5);
+
+    let mut tick = TimerTick::new().with_interval(2);
+    tick.resume(&mut timers, None);
+
+    match tick.resume(&mut timers, now(100)) {
+        TimerTickResult::Ok { events, .. } => {
+            assert_eq!(events.keys().collect::<Vec<_>>(), ["fast", "slow"]);
+        }
+        other => panic!("expected Ok, got {other:?}"),
+    }
+
+    // the slow timer is not due yet
+    for at in [102, 104] {
+        match tick.resume(&mut timers, None) {
+            TimerTickResult::Io {
+                input: TimeInput::SleepUntil { timestamp },
+            } => assert_eq!(timestamp, at),
+            other => panic!("expected Io {{ TimeInput::SleepUntil }}, got {other:?}"),
+        }
+
+        tick.resume(&mut timers, Some(TimeOutput::Slept));
+
+        match tick.resume(&mut timers, now(at)) {
+            TimerTickResult::Ok { events, .. } => {
+                assert_eq!(events.keys().collect::<Vec<_>>(), ["fast"]);
+            }
+            other => panic!("expected Ok, got {other:?}"),
+        }
+    }
+
+    match tick.resume(&mut timers, None) {
+        TimerTickResult::Io {
+            input: TimeInput::SleepUntil { timestamp: 105 },
+        } => {}
+        other => panic!("expected Io {{ TimeInput::SleepUntil }}, got {other:?}"),
+    }
+
+    tick.resume(&mut timers, Some(TimeOutput::Slept));
+
+    match tick.resume(&mut timers, now(105)) {
+        TimerTickResult::Ok { events, .. } => {
+            assert_eq!(events.keys().collect::<Vec<_>>(), ["slow"]);
+        }
+        other => panic!("expected Ok, got {other:?}"),
+    }
+}
+
+#[test]
+fn cycle_shortened_between_ticks() {
+    let config = TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    };
+
+    let mut timers = TimerRegistry::new();
+    for name in ["fast", "slow"] {
+        timers.insert(name, Timer::new(config.clone()));
+    }
+    timers.get_mut(Some("slow")).unwrap().start(100);
+    timers.set_tick_interval("slow", 60);
+
+    let mut tick = TimerTick::new().with_interval(2);
+    tick.resume(&mut timers, None);
+    tick.resume(&mut timers, now(100));
+
+    match tick.resume(&mut timers, None) {
+        TimerTickResult::Io {
+            input: TimeInput::SleepUntil { timestamp: 102 },
+        } => {}
+        other => panic!("expected Io {{ TimeInput::SleepUntil }}, got {other:?}"),
+    }
+
+    // the running cycle now ends at 103, before the next tick of
+    // the slow timer
+    let slow = timers.get_mut(Some("slow")).unwrap();
+    slow.set_remaining(101, 2);
+
+    tick.resume(&mut timers, Some(TimeOutput::Slept));
+
+    match tick.resume(&mut timers, now(102)) {
+        TimerTickResult::Io {
+            input: TimeInput::SleepUntil { timestamp: 103 },
+        } => {}
+        other => panic!("expected Io {{ TimeInput::SleepUntil }}, got {other:?}"),
+    }
+
+    tick.resume(&mut timers, Some(TimeOutput::Slept));
+
+    match tick.resume(&mut timers, now(103)) {
+        TimerTickResult::Ok { events, .. } => {
+            let events = &events["slow"];
+            assert!(matches!(events[1], TimerEvent::Ended(_)));
+        }
+        other => panic!("expected Ok, got {other:?}"),
+    }
+}
+
+#[test]
+fn emitted_events_notified_to_every_sink() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1), TimerCycle::new("Break", 2)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    timer.start(100);
+
+    let (a, events_a) = mpsc::channel();
+    let (b, events_b) = mpsc::channel();
+    let notifiers = Notifiers::new().with(a).with(b);
+    assert_eq!(notifiers.len(), 2);
+
+    let mut tick = TimerTick::new().with_notifier(notifiers);
+    tick.resume(&mut timer, None);
+
+    let events = match tick.resume(&mut timer, now(101)) {
+        TimerTickResult::Ok { mut events, .. } => events.remove("default").unwrap(),
+        other => panic!("expected Ok, got {other:?}"),
+    };
+
+    assert_eq!(events_a.try_iter().collect::<Vec<_>>(), events);
+    assert_eq!(events_b.try_iter().collect::<Vec<_>>(), events);
+}
+
+#[test]
+fn idle_timers_ticked_at_their_idle_deadline() {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    })
+    .with_idle_timeout(30);
+    timer.start(100);
+
+    let mut tick = TimerTick::new().with_interval(60);
+    tick.resume(&mut timer, None);
+    tick.resume(&mut timer, now(101));
+
+    match tick.resume(&mut timer, None) {
+        TimerTickResult::Io {
+            input: TimeInput::SleepUntil { timestamp: 130 },
+        } => {}
+        other => panic!("expected Io {{ TimeInput::SleepUntil }}, got {other:?}"),
+    }
+
+    tick.resume(&mut timer, Some(TimeOutput::Slept));
+
+    match tick.resume(&mut timer, now(131)) {
+        TimerTickResult::Ok { events, .. } => {
+            let events = &events["default"];
+            assert!(matches!(
+                events.last(),
+                Some(TimerEvent::Paused {
+                    reason: PauseReason::Idle,
+                    ..
+                })
+            ));
+        }
+        other => panic!("expected Ok, got {other:?}"),
+    }
+    assert_eq!(timer.state, TimerState::Paused);
+    assert_eq!(timer.elapsed(131), 30);
+}
@@ -1,37 +1,45 @@
+mod common;
+
 use std::{
     io::{Read, Write},
     os::unix::net::UnixStream,
+    sync::{Arc, Mutex},
     thread,
 };
 
 use io_socket::io::{SocketInput, SocketOutput};
 use io_time::{
+    codec::Codec,
     coroutines::{
-        client::{TimerRequestSend, TimerRequestSendResult},
-        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+        client::{
+            TimerRequestProgress, TimerRequestSend, TimerRequestSendError, TimerRequestSendResult,
+        },
+        request::{TimerCommand, TimerGet, TimerRequestError, TimerRequestResult},
+        server::{
+            TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleError,
+            TimerRequestHandleIoResult, TimerRequestHandleResult,
+        },
+        toggle::{TimerToggle, TimerToggleResult},
     },
+    metrics::{TimerRequestEvent, TimerRequestMetrics},
+    rate_limit::RateLimit,
     runtimes::std::handle as time_handle,
+    session::Permission,
     timer::{
-        Timer, TimerConfig, TimerCycle, TimerCycles, TimerEvent, TimerLoop, TimerRequest,
-        TimerResponse, TimerState,
+        Timer, TimerCapabilities, TimerConfig, TimerCycle, TimerCycles, TimerEnvelope, TimerError,
+        TimerEvent, TimerLoop, TimerRequest, TimerResponse, TimerStampedEvent, TimerState,
+        TimerStatus, TimerStatusLine, TimerVersion,
     },
 };
 
-fn socket_io(stream: &mut (impl Read + Write), input: SocketInput) -> SocketOutput {
-    match input {
-        SocketInput::Read { mut buf } => {
-            let n = stream.read(&mut buf).unwrap();
-            SocketOutput::Read { buf, n }
-        }
-        SocketInput::Write { buf } => {
-            let n = stream.write(&buf).unwrap();
-            SocketOutput::Wrote { buf, n }
-        }
-    }
+use common::socket_io;
+
+fn send(stream: UnixStream, request: TimerRequest) -> TimerResponse {
+    send_envelope(stream, TimerEnvelope::from(request))
 }
 
-fn send(mut stream: UnixStream, request: TimerRequest) -> TimerResponse {
-    let mut client = TimerRequestSend::new(request);
+fn send_envelope(mut stream: UnixStream, envelope: TimerEnvelope) -> TimerResponse {
+    let mut client = TimerRequestSend::new(envelope);
     let mut arg = None;
 
     loop {
@@ -43,8 +51,15 @@ fn send(mut stream: UnixStream, request: TimerRequest) -> TimerResponse {
     }
 }
 
-fn handle(mut stream: UnixStream, mut timer: Timer) -> (Timer, Vec<TimerEvent>) {
-    let mut server = TimerRequestHandle::new();
+fn handle(stream: UnixStream, timer: Timer) -> (Timer, Vec<TimerEvent>) {
+    handle_with(TimerRequestHandle::new(), stream, timer)
+}
+
+fn handle_with(
+    mut server: TimerRequestHandle,
+    mut stream: UnixStream,
+    mut timer: Timer,
+) -> (Timer, Vec<TimerEvent>) {
     let mut arg: Option<TimerRequestHandleArg> = None;
 
     loop {
@@ -56,11 +71,16 @@ fn handle(mut stream: UnixStream, mut timer: Timer) -> (Timer, Vec<TimerEvent>)
             TimerRequestHandleResult::TimeIo { input } => {
                 arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
             }
+            TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
             TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
         }
     }
 }
 
+fn unstamp(events: Vec<TimerStampedEvent>) -> Vec<TimerEvent> {
+    events.into_iter().map(|e| e.event).collect()
+}
+
 fn test_timer() -> Timer {
     Timer::new(TimerConfig {
         cycles: TimerCycles::from([
@@ -84,14 +104,589 @@ fn get_returns_stopped_timer() {
     let response = send(client_stream, TimerRequest::Get);
     let (_, events) = server.join().unwrap();
 
-    assert!(matches!(response, TimerResponse::Timer(_)));
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
     assert!(events.is_empty());
 
-    if let TimerResponse::Timer(t) = response {
+    if let TimerResponse::Snapshot(t) = response {
         assert_eq!(t.state, TimerState::Stopped);
     }
 }
 
+#[test]
+fn redacted_get_returns_status_only() {
+    let (mut client_stream, mut server_stream) = pair();
+    let mut timer = test_timer();
+    timer.start(0).into_iter().for_each(drop);
+
+    let server = thread::spawn(move || {
+        let mut server = TimerRequestHandle::new().with_redacted(true);
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => break,
+                TimerRequestHandleResult::Io { input } => {
+                    let output = socket_io(&mut server_stream, input);
+                    arg = Some(TimerRequestHandleArg::Socket(output));
+                }
+                other => panic!("unexpected server result: {other:?}"),
+            }
+        }
+    });
+
+    let mut client = TimerRequestSend::get();
+    let mut arg = None;
+
+    let response = loop {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { response } => break response,
+            TimerRequestSendResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input))
+            }
+            TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
+        }
+    };
+
+    server.join().unwrap();
+
+    let expected = TimerStatus {
+        state: TimerState::Running,
+        cycle: "Focus".into(),
+        remaining: 1500,
+    };
+    assert_eq!(response, TimerResponse::Status(expected));
+}
+
+#[test]
+fn status_returns_compact_line() {
+    let (client_stream, server_stream) = pair();
+    let mut timer = test_timer();
+    timer.start(0).into_iter().for_each(drop);
+    timer.update(300).into_iter().for_each(drop);
+
+    let server = thread::spawn(move || handle(server_stream, timer));
+    let response = send(client_stream, TimerRequest::Status);
+    server.join().unwrap();
+
+    let expected = TimerStatusLine {
+        text: "Focus 20:00 ▶".into(),
+        state: TimerState::Running,
+        percent: 20,
+    };
+    assert_eq!(response, TimerResponse::StatusLine(expected));
+}
+
+#[test]
+fn oversized_request_frame_is_rejected() {
+    let (mut client_stream, mut server_stream) = pair();
+    let mut timer = test_timer();
+
+    let server = thread::spawn(move || {
+        let mut server = TimerRequestHandle::new().with_max_frame_size(16);
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Io { input } => {
+                    let output = socket_io(&mut server_stream, input);
+                    arg = Some(TimerRequestHandleArg::Socket(output));
+                }
+                TimerRequestHandleResult::Err { err } => break err,
+                other => panic!("unexpected server result: {other:?}"),
+            }
+        }
+    });
+
+    let mut client = TimerRequestSend::get().with_timer("a-timer-with-a-very-long-name");
+    let mut arg = None;
+
+    let response = loop {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { response } => break response,
+            TimerRequestSendResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input))
+            }
+            TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
+        }
+    };
+
+    let err = server.join().unwrap();
+
+    assert_eq!(
+        response,
+        TimerResponse::Err(TimerError::FrameTooLarge { max: 16 })
+    );
+    assert!(matches!(err, TimerRequestHandleError::Frame(_)));
+}
+
+#[test]
+fn unterminated_request_is_rejected() {
+    let (mut client_stream, mut server_stream) = pair();
+    let mut timer = test_timer();
+
+    let server = thread::spawn(move || {
+        let mut server = TimerRequestHandle::new().with_max_frame_size(16);
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Io { input } => {
+                    let output = socket_io(&mut server_stream, input);
+                    arg = Some(TimerRequestHandleArg::Socket(output));
+                }
+                TimerRequestHandleResult::Err { err } => break err,
+                other => panic!("unexpected server result: {other:?}"),
+            }
+        }
+    });
+
+    // the request is rejected before its end is received
+    client_stream.write_all(&[b'a'; 64]).unwrap();
+    let mut buf = [0; 512];
+    let n = client_stream.read(&mut buf).unwrap();
+
+    let err = server.join().unwrap();
+
+    assert_eq!(&buf[..n], b"{\"Err\":{\"FrameTooLarge\":{\"max\":16}}}\n");
+    assert!(matches!(err, TimerRequestHandleError::Frame(_)));
+}
+
+#[test]
+fn read_only_connection_cannot_control_timer() {
+    let (mut client_stream, mut server_stream) = pair();
+
+    let server = thread::spawn(move || {
+        let mut timer = test_timer();
+        let mut server = TimerRequestHandle::new().with_permission(Permission::Observer);
+        let mut arg: Option<TimerRequestHandleArg> = None;
+        let mut handled = 0;
+
+        while handled < 3 {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => {
+                    handled += 1;
+                    // the client authenticated with its second request
+                    if handled == 2 {
+                        server.set_permission(Permission::Controller);
+                    }
+                }
+                TimerRequestHandleResult::Io { input } => {
+                    let output = socket_io(&mut server_stream, input);
+                    arg = Some(TimerRequestHandleArg::Socket(output));
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+                }
+                TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+            }
+        }
+    });
+
+    let mut client = TimerRequestSend::start();
+    let mut responses = Vec::new();
+    let mut arg = None;
+
+    while responses.len() < 3 {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { response } => {
+                responses.push(response);
+                let request = match responses.len() {
+                    1 => TimerRequest::Get,
+                    _ => TimerRequest::Start,
+                };
+                client.send(request);
+            }
+            TimerRequestSendResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input))
+            }
+            TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
+        }
+    }
+
+    server.join().unwrap();
+
+    assert_eq!(responses[0], TimerResponse::Err(TimerError::Forbidden));
+    assert!(matches!(responses[1], TimerResponse::Snapshot(_)));
+    assert!(matches!(responses[2], TimerResponse::Events(_)));
+}
+
+#[test]
+fn malformed_response_is_recoverable() {
+    let (mut client_stream, mut server_stream) = pair();
+
+    let server = thread::spawn(move || {
+        // answers the first request with garbage
+        let mut buf = [0; 512];
+        let n = server_stream.read(&mut buf).unwrap();
+        assert!(buf[..n].ends_with(b"\n"));
+        server_stream.write_all(b"{\"Garbage\":true}\n").unwrap();
+
+        handle(server_stream, test_timer());
+    });
+
+    let mut client = TimerRequestSend::get();
+    let mut errors = Vec::new();
+    let mut arg = None;
+
+    let response = loop {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { response } => break response,
+            TimerRequestSendResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input))
+            }
+            TimerRequestSendResult::Err { err } => errors.push(err),
+        }
+    };
+
+    server.join().unwrap();
+
+    assert!(matches!(
+        errors[..],
+        [TimerRequestSendError::Deserialize(_)]
+    ));
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+}
+
+#[test]
+fn malformed_request_is_answered() {
+    let (mut client_stream, server_stream) = pair();
+
+    let server = thread::spawn(move || {
+        let (timer, events) = handle(server_stream.try_clone().unwrap(), test_timer());
+        assert!(events.is_empty());
+        handle(server_stream, timer)
+    });
+
+    client_stream.write_all(b"{\"Garbage\":true}\n").unwrap();
+    let mut buf = [0; 512];
+    let n = client_stream.read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"{\"Err\":\"MalformedRequest\"}\n");
+
+    // the connection is still usable
+    let response = send(client_stream, TimerRequest::Get);
+    server.join().unwrap();
+
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+}
+
+#[test]
+fn send_reuses_coroutine_for_next_request() {
+    let (mut client_stream, server_stream) = pair();
+
+    let server = thread::spawn(move || {
+        let (timer, _) = handle(server_stream.try_clone().unwrap(), test_timer());
+        handle(server_stream, timer)
+    });
+
+    let mut client = TimerRequestSend::start();
+    let mut responses = Vec::new();
+    let mut arg = None;
+
+    while responses.len() < 2 {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { response } => {
+                responses.push(response);
+                client.send(TimerRequest::Get);
+            }
+            TimerRequestSendResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input))
+            }
+            TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
+        }
+    }
+
+    server.join().unwrap();
+
+    assert!(matches!(responses[0], TimerResponse::Events(_)));
+    let TimerResponse::Snapshot(snapshot) = &responses[1] else {
+        panic!("expected Snapshot, got {:?}", responses[1]);
+    };
+    assert_eq!(snapshot.state, TimerState::Running);
+}
+
+#[test]
+fn handle_reuses_read_buffer_across_requests() {
+    let (client_stream, mut server_stream) = pair();
+
+    let client = thread::spawn(move || {
+        send(client_stream.try_clone().unwrap(), TimerRequest::Start);
+        send(client_stream, TimerRequest::Get)
+    });
+
+    let mut server = TimerRequestHandle::new().with_read_buffer_size(8);
+    let mut timer = test_timer();
+    let mut handled = 0;
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    while handled < 2 {
+        match server.resume(&mut timer, arg.take()) {
+            TimerRequestHandleResult::Ok { .. } => handled += 1,
+            TimerRequestHandleResult::Io { input } => {
+                if let SocketInput::Read { buf } = &input {
+                    assert_eq!(buf.len(), 8);
+                }
+                let output = socket_io(&mut server_stream, input);
+                arg = Some(TimerRequestHandleArg::Socket(output));
+            }
+            TimerRequestHandleResult::TimeIo { input } => {
+                arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+            }
+            TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+            TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+        }
+    }
+
+    let TimerResponse::Snapshot(snapshot) = client.join().unwrap() else {
+        panic!("expected Snapshot");
+    };
+    assert_eq!(snapshot.state, TimerState::Running);
+    assert_eq!(server.take_read_buffer().len(), 8);
+}
+
+#[test]
+fn timer_only_borrowed_to_apply_request() {
+    let (client_stream, mut server_stream) = pair();
+    let timer = Arc::new(Mutex::new(test_timer()));
+
+    let client = thread::spawn(move || send(client_stream, TimerRequest::Start));
+
+    let mut server = TimerRequestHandle::new();
+    let mut applied = 0;
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    let events = loop {
+        match server.resume_io(arg.take()) {
+            TimerRequestHandleIoResult::Apply => {
+                server.apply(&mut *timer.lock().unwrap()).unwrap();
+                applied += 1;
+            }
+            TimerRequestHandleIoResult::Ok { events } => break events,
+            TimerRequestHandleIoResult::Io { input } => {
+                // the timer can be used by other connections meanwhile
+                assert!(timer.try_lock().is_ok());
+                let output = socket_io(&mut server_stream, input);
+                arg = Some(TimerRequestHandleArg::Socket(output));
+            }
+            TimerRequestHandleIoResult::TimeIo { input } => {
+                arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+            }
+            TimerRequestHandleIoResult::Disconnected => panic!("client disconnected"),
+            TimerRequestHandleIoResult::Err { err } => panic!("server error: {err}"),
+        }
+    };
+
+    assert!(matches!(client.join().unwrap(), TimerResponse::Events(_)));
+    assert_eq!(applied, 1);
+    assert_eq!(
+        events,
+        [TimerEvent::Started, TimerEvent::Began(test_timer().cycle)]
+    );
+    assert_eq!(timer.lock().unwrap().state, TimerState::Running);
+}
+
+#[test]
+fn typed_requests_return_natural_values() {
+    fn run<T>(
+        stream: &mut UnixStream,
+        mut resume: impl FnMut(Option<SocketOutput>) -> TimerRequestResult<T>,
+    ) -> Result<T, TimerRequestError> {
+        let mut arg = None;
+
+        loop {
+            match resume(arg.take()) {
+                TimerRequestResult::Ok { value } => return Ok(value),
+                TimerRequestResult::Io { input } => arg = Some(socket_io(stream, input)),
+                TimerRequestResult::Err { err } => return Err(err),
+            }
+        }
+    }
+
+    let (mut client_stream, server_stream) = pair();
+    let server = thread::spawn(move || handle(server_stream, test_timer()));
+    let mut start = TimerCommand::start();
+    let events = run(&mut client_stream, |arg| start.resume(arg)).unwrap();
+    let (timer, _) = server.join().unwrap();
+
+    let (mut client_stream, server_stream) = pair();
+    let server = thread::spawn(move || handle(server_stream, timer));
+    let mut get = TimerGet::new();
+    let snapshot = run(&mut client_stream, |arg| get.resume(arg)).unwrap();
+    let (timer, _) = server.join().unwrap();
+
+    let (mut client_stream, server_stream) = pair();
+    let server = thread::spawn(move || handle(server_stream, timer));
+    let mut get = TimerGet::new().with_timer("tea");
+    let err = run(&mut client_stream, |arg| get.resume(arg)).unwrap_err();
+    server.join().unwrap();
+
+    assert_eq!(unstamp(events)[0], TimerEvent::Started);
+    assert_eq!(snapshot.state, TimerState::Running);
+    assert!(matches!(
+        err,
+        TimerRequestError::Rejected(TimerError::UnknownTimer(_))
+    ));
+}
+
+#[test]
+fn progress_tells_whether_request_may_be_applied() {
+    let (mut client_stream, server_stream) = pair();
+    let server = thread::spawn(move || handle(server_stream, test_timer()));
+
+    let mut client = TimerRequestSend::start();
+    assert_eq!(client.progress(), TimerRequestProgress::Pending);
+    assert!(!client.may_be_applied());
+
+    let TimerRequestSendResult::Io { input } = client.resume(None) else {
+        panic!("expected socket I/O");
+    };
+    assert_eq!(client.progress(), TimerRequestProgress::Sent);
+    assert!(client.may_be_applied());
+
+    let mut arg = Some(socket_io(&mut client_stream, input));
+    loop {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { .. } => break,
+            TimerRequestSendResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input))
+            }
+            TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
+        }
+    }
+    server.join().unwrap();
+
+    assert_eq!(client.progress(), TimerRequestProgress::Received);
+    assert!(!client.may_be_applied());
+
+    client.send(TimerRequest::Get);
+    assert_eq!(client.progress(), TimerRequestProgress::Pending);
+}
+
+#[test]
+fn metrics_count_requests_and_bytes() {
+    static EVENTS: std::sync::Mutex<Vec<TimerRequestEvent>> = std::sync::Mutex::new(Vec::new());
+
+    let (mut client_stream, mut server_stream) = pair();
+
+    let server = thread::spawn(move || {
+        // answers the first request with garbage
+        let mut buf = [0; 512];
+        let n = server_stream.read(&mut buf).unwrap();
+        server_stream.write_all(b"{\"Garbage\":true}\n").unwrap();
+        assert_eq!(server_stream.read(&mut buf).unwrap(), n);
+        server_stream.write_all(b"\"Pong\"\n").unwrap();
+        n
+    });
+
+    let mut client = TimerRequestSend::ping().with_hook(|event| EVENTS.lock().unwrap().push(event));
+    let mut arg = None;
+
+    loop {
+        match client.resume(arg.take()) {
+            TimerRequestSendResult::Ok { .. } => break,
+            TimerRequestSendResult::Io { input } => {
+                arg = Some(socket_io(&mut client_stream, input))
+            }
+            TimerRequestSendResult::Err { .. } => (),
+        }
+    }
+    let n = server.join().unwrap();
+
+    assert_eq!(
+        *client.metrics(),
+        TimerRequestMetrics {
+            requests: 2,
+            responses: 1,
+            failures: 1,
+            decode_failures: 1,
+            bytes_written: 2 * n as u64,
+            bytes_read: 24,
+        }
+    );
+    assert_eq!(
+        EVENTS.lock().unwrap()[..],
+        [
+            TimerRequestEvent::Started,
+            TimerRequestEvent::Wrote(n),
+            TimerRequestEvent::Read(17),
+            TimerRequestEvent::DecodeFailed,
+            TimerRequestEvent::Started,
+            TimerRequestEvent::Wrote(n),
+            TimerRequestEvent::Read(7),
+            TimerRequestEvent::Received,
+        ]
+    );
+}
+
+#[test]
+fn capabilities_returns_server_features() {
+    let (client_stream, server_stream) = pair();
+    let timer = test_timer();
+
+    let server = thread::spawn(move || handle(server_stream, timer));
+    let response = send(client_stream, TimerRequest::Capabilities);
+    let (_, events) = server.join().unwrap();
+
+    let capabilities = match response {
+        TimerResponse::Capabilities(c) => c,
+        other => panic!("expected Capabilities, got {other:?}"),
+    };
+
+    assert_eq!(capabilities.version, env!("CARGO_PKG_VERSION"));
+    assert!(capabilities.codecs.contains(&Codec::Json));
+    assert!(events.is_empty());
+}
+
+#[test]
+fn disabled_subscriptions_are_not_advertised() {
+    let (client_stream, server_stream) = pair();
+    let server = TimerRequestHandle::new().with_subscriptions(false);
+
+    let server = thread::spawn(move || handle_with(server, server_stream, test_timer()));
+    let response = send(client_stream, TimerRequest::Capabilities);
+    server.join().unwrap();
+
+    let (client_stream, server_stream) = pair();
+    let server = TimerRequestHandle::new().with_subscriptions(false);
+
+    let server = thread::spawn(move || handle_with(server, server_stream, test_timer()));
+    let subscribed = send(client_stream, TimerRequest::Subscribe { id: None });
+    server.join().unwrap();
+
+    assert!(matches!(
+        response,
+        TimerResponse::Capabilities(TimerCapabilities {
+            subscribe: false,
+            ..
+        })
+    ));
+    assert_eq!(subscribed, TimerResponse::Err(TimerError::Unsupported));
+}
+
+#[test]
+fn version_returns_crate_and_protocol_versions() {
+    let (client_stream, server_stream) = pair();
+
+    let server = thread::spawn(move || handle(server_stream, test_timer()));
+    let response = send(client_stream, TimerRequest::Version);
+    let (_, events) = server.join().unwrap();
+
+    assert_eq!(response, TimerResponse::Version(TimerVersion::default()));
+    assert!(events.is_empty());
+}
+
+#[test]
+fn ping_returns_pong() {
+    let (client_stream, server_stream) = pair();
+
+    let server = thread::spawn(move || handle(server_stream, test_timer()));
+    let response = send(client_stream, TimerRequest::Ping);
+    let (_, events) = server.join().unwrap();
+
+    assert_eq!(response, TimerResponse::Pong);
+    assert!(events.is_empty());
+}
+
 #[test]
 fn start_returns_started_and_began_events() {
     let (client_stream, server_stream) = pair();
@@ -102,7 +697,7 @@ fn start_returns_started_and_began_events() {
     let (_, events) = server.join().unwrap();
 
     let resp_events = match response {
-        TimerResponse::Events(e) => e,
+        TimerResponse::Events(e) => unstamp(e),
         other => panic!("expected Events, got {other:?}"),
     };
 
@@ -112,6 +707,103 @@ fn start_returns_started_and_began_events() {
     assert_eq!(resp_events, events);
 }
 
+#[test]
+fn events_are_stamped_with_server_time() {
+    let (client_stream, server_stream) = pair();
+    let timer = test_timer();
+
+    let server = thread::spawn(move || handle(server_stream, timer));
+    let response = send(client_stream, TimerRequest::Start);
+    let (updated_timer, _) = server.join().unwrap();
+
+    let resp_events = match response {
+        TimerResponse::Events(e) => e,
+        other => panic!("expected Events, got {other:?}"),
+    };
+
+    let started_at = updated_timer.started_at.unwrap();
+    assert!(resp_events.iter().all(|e| e.at == started_at));
+}
+
+#[test]
+fn mutating_requests_emit_events() {
+    let requests = [
+        TimerRequest::Start,
+        TimerRequest::Pause,
+        TimerRequest::Resume,
+        TimerRequest::Toggle,
+        TimerRequest::Toggle,
+        TimerRequest::SetRemaining(60),
+        TimerRequest::SetCycleDuration {
+            cycle: "Break".to_string(),
+            secs: 600,
+        },
+        TimerRequest::Stop,
+    ];
+    let mut timer = test_timer();
+    let mut count = 0;
+
+    for request in requests {
+        let (client_stream, server_stream) = pair();
+        let server = thread::spawn(move || handle(server_stream, timer));
+        let response = send(client_stream, request.clone());
+        let events;
+        (timer, events) = server.join().unwrap();
+
+        assert!(!events.is_empty(), "{request:?} emitted no event");
+        if let TimerResponse::Events(stamped) = response {
+            assert_eq!(unstamp(stamped), events);
+        }
+        count += events.len();
+    }
+
+    let history = timer.history.page(None, Some(count + 1));
+    assert_eq!(history.events.len(), count);
+    assert_eq!(timer.stats.pauses, 2);
+}
+
+#[test]
+fn invalid_requests_are_rejected() {
+    let requests = [
+        (TimerRequest::Pause, "timer is stopped"),
+        (TimerRequest::Resume, "timer is stopped"),
+        (TimerRequest::SetRemaining(300), "timer is stopped"),
+        (
+            TimerRequest::SetRemaining(0),
+            "remaining duration must be positive",
+        ),
+        (
+            TimerRequest::SetCycleDuration {
+                cycle: "Lunch".to_string(),
+                secs: 600,
+            },
+            "unknown cycle Lunch",
+        ),
+        (
+            TimerRequest::SetCycleDuration {
+                cycle: "Break".to_string(),
+                secs: 0,
+            },
+            "cycle duration must be positive",
+        ),
+    ];
+    let mut timer = test_timer();
+
+    for (request, reason) in requests {
+        let (client_stream, server_stream) = pair();
+        let server = thread::spawn(move || handle(server_stream, timer));
+        let response = send(client_stream, request);
+        let events;
+        (timer, events) = server.join().unwrap();
+
+        let err = TimerError::InvalidRequest(reason.to_string());
+        assert_eq!(response, TimerResponse::Err(err));
+        assert!(events.is_empty());
+    }
+
+    assert_eq!(timer, test_timer());
+}
+
 #[test]
 fn start_on_already_running_timer_is_noop() {
     let (client_stream, server_stream) = pair();
@@ -123,7 +815,7 @@ fn start_on_already_running_timer_is_noop() {
     let (_, events) = server.join().unwrap();
 
     let resp_events = match response {
-        TimerResponse::Events(e) => e,
+        TimerResponse::Events(e) => unstamp(e),
         other => panic!("expected Events, got {other:?}"),
     };
 
@@ -142,7 +834,7 @@ fn stop_running_timer_returns_ended_and_stopped_events() {
     let (_, events) = server.join().unwrap();
 
     let resp_events = match response {
-        TimerResponse::Events(e) => e,
+        TimerResponse::Events(e) => unstamp(e),
         other => panic!("expected Events, got {other:?}"),
     };
 
@@ -162,7 +854,7 @@ fn stop_on_stopped_timer_is_noop() {
     let (_, events) = server.join().unwrap();
 
     let resp_events = match response {
-        TimerResponse::Events(e) => e,
+        TimerResponse::Events(e) => unstamp(e),
         other => panic!("expected Events, got {other:?}"),
     };
 
@@ -181,12 +873,12 @@ fn pause_running_timer_returns_paused_event() {
     let (_, events) = server.join().unwrap();
 
     let resp_events = match response {
-        TimerResponse::Events(e) => e,
+        TimerResponse::Events(e) => unstamp(e),
         other => panic!("expected Events, got {other:?}"),
     };
 
     assert_eq!(resp_events.len(), 1);
-    assert!(matches!(resp_events[0], TimerEvent::Paused(_)));
+    assert!(matches!(resp_events[0], TimerEvent::Paused { .. }));
     assert_eq!(resp_events, events);
 }
 
@@ -202,7 +894,7 @@ fn resume_paused_timer_returns_resumed_event() {
     let (_, events) = server.join().unwrap();
 
     let resp_events = match response {
-        TimerResponse::Events(e) => e,
+        TimerResponse::Events(e) => unstamp(e),
         other => panic!("expected Events, got {other:?}"),
     };
 
@@ -212,16 +904,17 @@ fn resume_paused_timer_returns_resumed_event() {
 }
 
 #[test]
-fn set_updates_cycle_duration() {
+fn set_remaining_updates_cycle_duration() {
     let (client_stream, server_stream) = pair();
-    let timer = test_timer();
+    let mut timer = test_timer();
+    timer.start(0).into_iter().for_each(drop);
 
     let server = thread::spawn(move || handle(server_stream, timer));
-    let response = send(client_stream, TimerRequest::Set(60));
+    let response = send(client_stream, TimerRequest::SetRemaining(60));
     let (updated_timer, events) = server.join().unwrap();
 
     let resp_events = match response {
-        TimerResponse::Events(e) => e,
+        TimerResponse::Events(e) => unstamp(e),
         other => panic!("expected Events, got {other:?}"),
     };
 
@@ -242,7 +935,7 @@ fn update_on_running_timer_returns_running_event() {
     let (_, events) = server.join().unwrap();
 
     let resp_events = match response {
-        TimerResponse::Events(e) => e,
+        TimerResponse::Events(e) => unstamp(e),
         other => panic!("expected Events, got {other:?}"),
     };
 
@@ -251,3 +944,291 @@ fn update_on_running_timer_returns_running_event() {
     assert!(matches!(resp_events[0], TimerEvent::Running(_)));
     assert_eq!(resp_events, events);
 }
+
+#[test]
+fn set_cycle_duration_updates_config() {
+    let (client_stream, server_stream) = pair();
+    let timer = test_timer();
+
+    let server = thread::spawn(move || handle(server_stream, timer));
+    let request = TimerRequest::SetCycleDuration {
+        cycle: "Break".into(),
+        secs: 600,
+    };
+    let response = send(client_stream, request);
+    let (updated_timer, events) = server.join().unwrap();
+
+    let resp_events = match response {
+        TimerResponse::Events(e) => unstamp(e),
+        other => panic!("expected Events, got {other:?}"),
+    };
+
+    let expected = vec![TimerEvent::Configured(TimerCycle::new("Break", 600))];
+    assert_eq!(resp_events, expected);
+    assert_eq!(updated_timer.config.cycles[1].duration, 600);
+    assert_eq!(updated_timer.cycle, TimerCycle::new("Focus", 1500));
+    assert_eq!(events, expected);
+}
+
+#[test]
+fn busy_server_does_not_apply_request() {
+    let (client_stream, mut server_stream) = pair();
+    let mut timer = test_timer();
+
+    let server = thread::spawn(move || {
+        let mut server = TimerRequestHandle::new();
+        server.set_busy(Some(500));
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { events } => return (timer, events),
+                TimerRequestHandleResult::Io { input } => {
+                    let output = socket_io(&mut server_stream, input);
+                    arg = Some(TimerRequestHandleArg::Socket(output));
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+                }
+                TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+            }
+        }
+    });
+
+    let response = send(client_stream, TimerRequest::Start);
+    let (timer, events) = server.join().unwrap();
+
+    assert_eq!(
+        response,
+        TimerResponse::Busy {
+            retry_after_ms: 500
+        }
+    );
+    assert!(events.is_empty());
+    assert_eq!(timer.state, TimerState::Stopped);
+}
+
+#[test]
+fn retried_request_with_same_key_is_applied_once() {
+    let mut timer = test_timer();
+    let envelope = TimerEnvelope::new(TimerRequest::Start).with_key("start-1");
+
+    let (client_stream, server_stream) = pair();
+    let server = thread::spawn(move || handle(server_stream, timer));
+    let first = send_envelope(client_stream, envelope.clone());
+    let (updated_timer, events) = server.join().unwrap();
+    assert_eq!(events.len(), 2);
+    timer = updated_timer;
+
+    timer.stop().into_iter().for_each(drop);
+
+    let (client_stream, server_stream) = pair();
+    let server = thread::spawn(move || handle(server_stream, timer));
+    let retry = send_envelope(client_stream, envelope);
+    let (updated_timer, events) = server.join().unwrap();
+
+    assert_eq!(first, retry);
+    assert!(events.is_empty());
+    assert_eq!(updated_timer.state, TimerState::Stopped);
+}
+
+#[test]
+fn toggle_paused_timer_returns_running_state() {
+    let (client_stream, server_stream) = pair();
+    let mut timer = test_timer();
+    timer.start(0).into_iter().for_each(drop);
+    timer.pause(0).into_iter().for_each(drop);
+
+    let server = thread::spawn(move || handle(server_stream, timer));
+    let response = send(client_stream, TimerRequest::Toggle);
+    let (updated_timer, events) = server.join().unwrap();
+
+    assert_eq!(response, TimerResponse::State(TimerState::Running));
+    assert_eq!(updated_timer.state, TimerState::Running);
+    assert_eq!(events.len(), 1);
+    assert!(matches!(events[0], TimerEvent::Resumed(_)));
+}
+
+#[test]
+fn toggle_coroutine_returns_resulting_state() {
+    let (mut client_stream, server_stream) = pair();
+    let server = thread::spawn(move || handle(server_stream, test_timer()));
+
+    let mut client = TimerToggle::new();
+    let mut arg = None;
+
+    let state = loop {
+        match client.resume(arg.take()) {
+            TimerToggleResult::Ok { state } => break state,
+            TimerToggleResult::Io { input } => arg = Some(socket_io(&mut client_stream, input)),
+            TimerToggleResult::Err { err } => panic!("client error: {err}"),
+        }
+    };
+    let (timer, _) = server.join().unwrap();
+
+    assert_eq!(state, TimerState::Running);
+    assert_eq!(timer.state, TimerState::Running);
+}
+
+#[test]
+fn flooding_client_receives_too_many_requests() {
+    let (mut client_stream, mut server_stream) = pair();
+    let mut timer = test_timer();
+
+    let server = thread::spawn(move || {
+        let mut server = TimerRequestHandle::new().with_rate_limit(RateLimit::new(1, 60));
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        for _ in 0..2 {
+            loop {
+                match server.resume(&mut timer, arg.take()) {
+                    TimerRequestHandleResult::Ok { .. } => break,
+                    TimerRequestHandleResult::Io { input } => {
+                        let output = socket_io(&mut server_stream, input);
+                        arg = Some(TimerRequestHandleArg::Socket(output));
+                    }
+                    TimerRequestHandleResult::TimeIo { input } => {
+                        let output = time_handle(input).unwrap();
+                        arg = Some(TimerRequestHandleArg::Time(output));
+                    }
+                    TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                    TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+                }
+            }
+        }
+    });
+
+    let mut responses = Vec::new();
+
+    for _ in 0..2 {
+        let mut client = TimerRequestSend::get();
+        let mut arg = None;
+
+        let response = loop {
+            match client.resume(arg.take()) {
+                TimerRequestSendResult::Ok { response } => break response,
+                TimerRequestSendResult::Io { input } => {
+                    arg = Some(socket_io(&mut client_stream, input))
+                }
+                TimerRequestSendResult::Err { err } => panic!("client error: {err}"),
+            }
+        };
+
+        responses.push(response);
+    }
+
+    server.join().unwrap();
+
+    assert!(matches!(responses[0], TimerResponse::Snapshot(_)));
+    assert!(matches!(
+        responses[1],
+        TimerResponse::Err(TimerError::TooManyRequests { retry_after }) if retry_after > 0
+    ));
+}
+
+#[test]
+fn request_split_across_reads_is_decoded() {
+    let mut timer = test_timer();
+    let mut server = TimerRequestHandle::new();
+    let mut arg: Option<TimerRequestHandleArg> = None;
+    let mut chunks = vec![b"{\"request\":\"Ge".to_vec(), b"t\"}\n".to_vec()].into_iter();
+
+    let response = loop {
+        match server.resume(&mut timer, arg.take()) {
+            TimerRequestHandleResult::Io {
+                input: SocketInput::Read { .. },
+            } => {
+                let buf = chunks.next().expect("unexpected extra read");
+                let n = buf.len();
+                arg = Some(TimerRequestHandleArg::Socket(SocketOutput::Read { buf, n }));
+            }
+            TimerRequestHandleResult::Io {
+                input: SocketInput::Write { buf },
+            } => break buf,
+            other => panic!("expected socket I/O, got {other:?}"),
+        }
+    };
+
+    let response: TimerResponse = serde_json::from_slice(&response).unwrap();
+    assert!(matches!(response, TimerResponse::Snapshot(_)));
+}
+
+#[test]
+fn pipelined_requests_in_one_read_are_decoded() {
+    let mut timer = test_timer();
+    let mut server = TimerRequestHandle::new();
+    let mut arg: Option<TimerRequestHandleArg> = None;
+    let mut reads =
+        vec![b"{\"request\":\"Get\"}\n{\"request\":\"Version\"}\n".to_vec()].into_iter();
+    let mut responses = Vec::new();
+
+    while responses.len() < 2 {
+        match server.resume(&mut timer, arg.take()) {
+            TimerRequestHandleResult::Ok { .. } => (),
+            TimerRequestHandleResult::Io {
+                input: SocketInput::Read { .. },
+            } => {
+                let buf = reads.next().expect("unexpected extra read");
+                let n = buf.len();
+                arg = Some(TimerRequestHandleArg::Socket(SocketOutput::Read { buf, n }));
+            }
+            TimerRequestHandleResult::Io {
+                input: SocketInput::Write { buf },
+            } => {
+                let response: TimerResponse = serde_json::from_slice(&buf).unwrap();
+                responses.push(response);
+                let n = buf.len();
+                arg = Some(TimerRequestHandleArg::Socket(SocketOutput::Wrote {
+                    buf,
+                    n,
+                }));
+            }
+            other => panic!("expected socket I/O, got {other:?}"),
+        }
+    }
+
+    assert!(matches!(responses[0], TimerResponse::Snapshot(_)));
+    assert!(matches!(responses[1], TimerResponse::Version(_)));
+}
+
+#[test]
+fn history_returns_events_page_by_page() {
+    let (client_stream, server_stream) = pair();
+    let server = thread::spawn(move || handle(server_stream, test_timer()));
+    send(client_stream, TimerRequest::Start);
+    let (timer, _) = server.join().unwrap();
+
+    let (client_stream, server_stream) = pair();
+    let server = thread::spawn(move || handle(server_stream, timer));
+    let request = TimerRequest::GetHistory {
+        cursor: None,
+        limit: Some(1),
+    };
+    let response = send(client_stream, request);
+    let (timer, _) = server.join().unwrap();
+
+    let TimerResponse::History(page) = response else {
+        panic!("expected History, got {response:?}");
+    };
+
+    assert_eq!(page.events.len(), 1);
+    assert_eq!(page.events[0].event, TimerEvent::Started);
+    assert_eq!(page.next, Some(1));
+
+    let (client_stream, server_stream) = pair();
+    let server = thread::spawn(move || handle(server_stream, timer));
+    let request = TimerRequest::GetHistory {
+        cursor: page.next,
+        limit: Some(1),
+    };
+    let response = send(client_stream, request);
+    server.join().unwrap();
+
+    let TimerResponse::History(page) = response else {
+        panic!("expected History, got {response:?}");
+    };
+
+    assert!(matches!(page.events[0].event, TimerEvent::Began(_)));
+    assert_eq!(page.next, None);
+}
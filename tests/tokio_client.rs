@@ -0,0 +1,129 @@
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use io_time::{
+    client::{
+        ClientError,
+        tokio::{CoroutineFuture, TokioClient},
+    },
+    coroutines::{
+        client::TimerRequestSend,
+        push::{TimerEventPush, TimerEventPushResult},
+        request::{TimerCommand, TimerGet},
+        server::{TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult},
+    },
+    runtimes::std::handle as time_handle,
+    timer::{Timer, TimerConfig, TimerCycle, TimerCycles, TimerEvent, TimerLoop, TimerState},
+};
+
+use common::socket_io;
+
+/// Serves requests, pushing the timer history to the connection once
+/// subscribed, then closes it.
+fn serve(mut stream: UnixStream) {
+    let mut timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+    let mut server = TimerRequestHandle::new();
+
+    while server.subscription().is_none() {
+        let mut arg: Option<TimerRequestHandleArg> = None;
+
+        loop {
+            match server.resume(&mut timer, arg.take()) {
+                TimerRequestHandleResult::Ok { .. } => break,
+                TimerRequestHandleResult::Io { input } => {
+                    arg = Some(TimerRequestHandleArg::Socket(socket_io(&mut stream, input)));
+                }
+                TimerRequestHandleResult::TimeIo { input } => {
+                    arg = Some(TimerRequestHandleArg::Time(time_handle(input).unwrap()));
+                }
+                TimerRequestHandleResult::Disconnected => panic!("client disconnected"),
+                TimerRequestHandleResult::Err { err } => panic!("server error: {err}"),
+            }
+        }
+    }
+
+    let events = timer.history.page(None, None).events;
+    let mut pusher = TimerEventPush::new(events);
+    let mut arg = None;
+
+    loop {
+        match pusher.resume(arg.take()) {
+            TimerEventPushResult::Ok => return,
+            TimerEventPushResult::Io { input } => arg = Some(socket_io(&mut stream, input)),
+            TimerEventPushResult::Err { err } => panic!("push error: {err}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn async_client_methods() {
+    let (client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || serve(server_stream));
+
+    client_stream.set_nonblocking(true).unwrap();
+    let client_stream = tokio::net::UnixStream::from_std(client_stream).unwrap();
+    let mut client = TokioClient::new(client_stream);
+
+    client.ping().await.unwrap();
+    client.start().await.unwrap();
+    assert_eq!(client.toggle().await.unwrap(), TimerState::Paused);
+    assert_eq!(client.get().await.unwrap().state, TimerState::Paused);
+
+    let mut events = client.subscribe(None).await.unwrap();
+    let (timer, event) = events.next().await.unwrap().unwrap();
+    assert_eq!(timer, None);
+    assert_eq!(event.event, TimerEvent::Started);
+
+    server.join().unwrap();
+}
+
+#[tokio::test]
+async fn cancelled_request_poisons_connection() {
+    // the server never answers
+    let (client_stream, _server_stream) = UnixStream::pair().unwrap();
+
+    client_stream.set_nonblocking(true).unwrap();
+    let client_stream = tokio::net::UnixStream::from_std(client_stream).unwrap();
+    let mut client = TokioClient::new(client_stream);
+
+    tokio::select! {
+        biased;
+        _ = client.start() => panic!("request should be pending"),
+        _ = std::future::ready(()) => (),
+    }
+
+    assert!(client.is_cancelled());
+    assert!(matches!(client.get().await, Err(ClientError::Cancelled)));
+}
+
+#[tokio::test]
+async fn coroutines_awaited_directly() {
+    let (client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || serve(server_stream));
+
+    client_stream.set_nonblocking(true).unwrap();
+    let mut stream = tokio::net::UnixStream::from_std(client_stream).unwrap();
+
+    let events = CoroutineFuture::new(&mut stream, TimerCommand::start())
+        .await
+        .unwrap();
+    assert!(!events.is_empty());
+
+    let snapshot = CoroutineFuture::new(&mut stream, TimerGet::new())
+        .await
+        .unwrap();
+    assert_eq!(snapshot.state, TimerState::Running);
+
+    // lets the server push the events, then return
+    CoroutineFuture::new(&mut stream, TimerRequestSend::subscribe(None))
+        .await
+        .unwrap();
+
+    server.join().unwrap();
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "tokio")]
+
+use io_time::{
+    client::tokio::TokioClient,
+    server::tokio::{TokioServeOptions, serve},
+    timer::{Timer, TimerConfig, TimerCycle, TimerCycles, TimerEvent, TimerLoop, TimerRequest},
+};
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn tokio_server_pushes_events_to_subscribers() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let timer = Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    });
+
+    tokio::spawn(serve(listener, timer, TokioServeOptions::new()));
+
+    // subscribed before the timer starts
+    let mut subscriber = TokioClient::connect_tcp(addr).await.unwrap();
+    let request = TimerRequest::Subscribe { id: None };
+    subscriber.send(request).await.unwrap();
+    let mut events = subscriber.subscribe(None).await.unwrap();
+
+    let mut client = TokioClient::connect_tcp(addr).await.unwrap();
+    client.start().await.unwrap();
+
+    let (_, event) = events.next().await.unwrap().unwrap();
+    assert_eq!(event.event, TimerEvent::Started);
+    let (_, event) = events.next().await.unwrap().unwrap();
+    assert!(matches!(event.event, TimerEvent::Began(_)));
+}
@@ -0,0 +1,147 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    os::unix::net::UnixStream,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use io_time::{
+    client::std::run,
+    coroutines::{
+        client::TimerRequestSend,
+        server::{
+            TimerRequestHandle, TimerRequestHandleArg, TimerRequestHandleResult,
+            TimerSessionManager,
+        },
+        tick::{TimerTick, TimerTickResult},
+    },
+    io::TimeOutput,
+    runtimes::std::handle as time_handle,
+    timer::{Timer, TimerConfig, TimerCycle, TimerCycles, TimerLoop},
+};
+use tracing::{
+    Event, Id, Metadata, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Record},
+};
+
+use common::socket_io;
+
+#[derive(Clone, Debug, Default)]
+struct Fields(BTreeMap<&'static str, String>);
+
+/// Records the name and the fields of every span.
+#[derive(Clone, Default)]
+struct Spans(Arc<Mutex<Vec<(&'static str, Fields)>>>);
+
+impl Spans {
+    fn get(&self, name: &str) -> BTreeMap<&'static str, String> {
+        let spans = self.0.lock().unwrap();
+        let span = spans.iter().find(|(n, _)| *n == name);
+        span.unwrap_or_else(|| panic!("missing span {name}"))
+            .1
+            .0
+            .clone()
+    }
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_string());
+    }
+}
+
+impl Subscriber for Spans {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        let mut spans = self.0.lock().unwrap();
+        spans.push((span.metadata().name(), fields));
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut spans = self.0.lock().unwrap();
+        values.record(&mut spans[span.into_u64() as usize - 1].1);
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+fn test_timer() -> Timer {
+    Timer::new(TimerConfig {
+        cycles: TimerCycles::from([TimerCycle::new("Focus", 1500)]),
+        cycles_count: TimerLoop::Infinite,
+    })
+}
+
+#[test]
+fn server_coroutines_instrumented_with_spans() {
+    let spans = Spans::default();
+    let _guard = tracing::subscriber::set_default(spans.clone());
+
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let client = thread::spawn(move || run(&mut client, TimerRequestSend::start()).unwrap());
+
+    let mut timer = test_timer();
+    let mut connections = TimerSessionManager::new();
+    connections.insert(7, TimerRequestHandle::new());
+    let mut arg: Option<TimerRequestHandleArg> = None;
+
+    loop {
+        match connections.resume(&7, &mut timer, arg.take()) {
+            Some(TimerRequestHandleResult::Ok { .. }) => break,
+            Some(TimerRequestHandleResult::Io { input }) => {
+                arg = Some(socket_io(&mut server, input).into());
+            }
+            Some(TimerRequestHandleResult::TimeIo { input }) => {
+                arg = Some(time_handle(input).unwrap().into());
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    client.join().unwrap();
+
+    let mut tick = TimerTick::new();
+    tick.resume(&mut timer, None);
+    let now = timer.started_at.unwrap() + 1;
+    let now = Some(TimeOutput::Now {
+        secs: now,
+        nanos: 0,
+    });
+    assert!(matches!(
+        tick.resume(&mut timer, now),
+        TimerTickResult::Ok { .. }
+    ));
+
+    assert_eq!(connections.serial(&7), Some(1));
+    assert_eq!(spans.get("timer_connection")["id"], "1");
+
+    let request = spans.get("timer_request");
+    assert_eq!(request["request"], "Start");
+    assert_eq!(request["timer"], "default");
+    assert_eq!(request["events"], "2");
+
+    let tick = spans.get("timer_tick");
+    assert_eq!(tick["events"], "1");
+}